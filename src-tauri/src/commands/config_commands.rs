@@ -186,7 +186,7 @@ pub async fn generate_api_key_for_tool(tool: String) -> Result<GenerateApiKeyRes
     if let Some(mut data) = api_response.data {
         if !data.is_empty() {
             // 按id降序排序，取第一个（id最大的）
-            data.sort_by(|a, b| b.id.cmp(&a.id));
+            data.sort_by_key(|item| std::cmp::Reverse(item.id));
             let token = &data[0];
             let api_key = format!("sk-{}", token.key);
             return Ok(GenerateApiKeyResult {
@@ -3,6 +3,7 @@ pub mod config_commands;
 pub mod error; // 错误处理统一模块
 pub mod log_commands;
 pub mod onboarding;
+pub mod pricing_commands;
 pub mod profile_commands; // Profile 管理命令（v2.0）
 pub mod proxy_commands;
 pub mod session_commands;
@@ -19,6 +20,7 @@ pub use balance_commands::*;
 pub use config_commands::*;
 pub use log_commands::*;
 pub use onboarding::*;
+pub use pricing_commands::*;
 pub use profile_commands::*; // Profile 管理命令（v2.0）
 pub use proxy_commands::*;
 pub use session_commands::*;
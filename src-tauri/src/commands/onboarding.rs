@@ -1,6 +1,8 @@
 // filepath: e:\DuckCoding\src-tauri\src\commands\onboarding.rs
 
-use duckcoding::models::config::{GlobalConfig, LogConfig, OnboardingStatus};
+use duckcoding::models::config::{
+    GlobalConfig, LogConfig, OnboardingStatus, SessionRetentionConfig,
+};
 use duckcoding::utils::config::{read_global_config, write_global_config};
 use std::collections::HashMap;
 use tracing::{error, info};
@@ -23,6 +25,7 @@ fn create_minimal_config() -> GlobalConfig {
         hide_transparent_proxy_tip: false,
         hide_session_config_hint: false,
         log_config: LogConfig::default(),
+        session_retention: SessionRetentionConfig::default(),
         onboarding_status: None,
         external_watch_enabled: true,
         external_poll_interval_ms: 5000,
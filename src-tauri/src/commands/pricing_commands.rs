@@ -0,0 +1,82 @@
+// 费用估算相关命令
+//
+// 模型价格表管理，以及基于本地代理用量记录的费用统计
+
+use ::duckcoding::models::{ModelPrice, PricingStore};
+use ::duckcoding::services::pricing::{build_cost_stats, CostGroupBy, CostStats, PricingManager};
+use ::duckcoding::services::session::SESSION_MANAGER;
+
+/// 加载价格表（文件不存在时返回内置价格表）
+#[tauri::command]
+pub async fn get_pricing_table() -> Result<PricingStore, String> {
+    let manager = PricingManager::new().map_err(|e| e.to_string())?;
+    manager.load_store().map_err(|e| e.to_string())
+}
+
+/// 保存完整价格表
+#[tauri::command]
+pub async fn save_pricing_table(store: PricingStore) -> Result<(), String> {
+    let manager = PricingManager::new().map_err(|e| e.to_string())?;
+    manager.save_store(&store).map_err(|e| e.to_string())
+}
+
+/// 设置模型价格（单位：货币 / 百万 Token）
+#[tauri::command]
+pub async fn set_model_price(model: String, price: ModelPrice) -> Result<(), String> {
+    let manager = PricingManager::new().map_err(|e| e.to_string())?;
+    manager
+        .set_model_price(&model, price)
+        .map_err(|e| e.to_string())
+}
+
+/// 删除模型价格
+#[tauri::command]
+pub async fn delete_model_price(model: String) -> Result<(), String> {
+    let manager = PricingManager::new().map_err(|e| e.to_string())?;
+    manager
+        .delete_model_price(&model)
+        .map_err(|e| e.to_string())
+}
+
+/// 设置 Profile 价格倍率（multiplier 为空时恢复为 1.0）
+#[tauri::command]
+pub async fn set_profile_price_multiplier(
+    tool_id: String,
+    profile_name: String,
+    multiplier: Option<f64>,
+) -> Result<(), String> {
+    let manager = PricingManager::new().map_err(|e| e.to_string())?;
+    manager
+        .set_profile_multiplier(&tool_id, &profile_name, multiplier)
+        .map_err(|e| e.to_string())
+}
+
+/// 恢复内置默认模型价格
+#[tauri::command]
+pub async fn reset_model_prices() -> Result<PricingStore, String> {
+    let manager = PricingManager::new().map_err(|e| e.to_string())?;
+    manager.reset_model_prices().map_err(|e| e.to_string())
+}
+
+/// 获取本地代理用量的费用统计
+///
+/// # 参数
+/// - `tool_id`: 工具 ID，为空时统计全部工具
+/// - `start_time` / `end_time`: 时间范围（Unix 时间戳，秒）
+/// - `group_by`: 分组维度（request / session / profile / model / day）
+#[tauri::command]
+pub async fn get_cost_stats(
+    tool_id: Option<String>,
+    start_time: i64,
+    end_time: i64,
+    group_by: CostGroupBy,
+) -> Result<CostStats, String> {
+    let manager = PricingManager::new().map_err(|e| e.to_string())?;
+    let store = manager.load_store().map_err(|e| e.to_string())?;
+
+    let records = SESSION_MANAGER
+        .get_usage_records(tool_id.as_deref(), start_time, end_time)
+        .map_err(|e| e.to_string())?;
+
+    Ok(build_cost_stats(&store, &records, group_by))
+}
@@ -1,6 +1,7 @@
 //! Profile 管理 Tauri 命令（v2.1 - 简化版）
 
 use super::error::AppResult;
+use ::duckcoding::models::ToolDescriptor;
 use ::duckcoding::services::profile_manager::ProfileDescriptor;
 use serde::Deserialize;
 use std::sync::Arc;
@@ -30,6 +31,9 @@ pub enum ProfileInput {
         #[serde(default)]
         model: Option<String>,
     },
+    /// 自定义工具（由工具描述文件声明）
+    #[serde(rename = "custom")]
+    Custom { api_key: String, base_url: String },
 }
 
 /// 列出所有 Profile 描述符
@@ -73,6 +77,10 @@ pub async fn pm_get_profile(
             let profile = manager.get_gemini_profile(&name)?;
             serde_json::to_value(&profile)?
         }
+        _ if ToolDescriptor::find(&tool_id).is_some() => {
+            let profile = manager.get_custom_profile(&tool_id, &name)?;
+            serde_json::to_value(&profile)?
+        }
         _ => return Err(super::error::AppError::ToolNotFound { tool: tool_id }),
     };
 
@@ -147,6 +155,16 @@ pub async fn pm_save_profile(
                 })
             }
         }
+        _ if ToolDescriptor::find(&tool_id).is_some() => {
+            if let ProfileInput::Custom { api_key, base_url } = input {
+                Ok(manager.save_custom_profile(&tool_id, &name, api_key, base_url)?)
+            } else {
+                Err(super::error::AppError::ValidationError {
+                    field: "input".to_string(),
+                    reason: format!("{} 需要自定义工具 Profile 数据", tool_id),
+                })
+            }
+        }
         _ => Err(super::error::AppError::ToolNotFound { tool: tool_id }),
     }
 }
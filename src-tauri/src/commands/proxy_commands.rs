@@ -5,6 +5,7 @@ use std::sync::Arc;
 use tauri::State;
 
 use crate::commands::profile_commands::ProfileManagerState;
+use ::duckcoding::models::proxy_config::ToolProxyConfig;
 use ::duckcoding::services::proxy::ProxyManager;
 use ::duckcoding::services::proxy_config_manager::ProxyConfigManager;
 use ::duckcoding::utils::config::read_global_config;
@@ -161,8 +162,8 @@ async fn try_start_proxy_internal(
     if tool_config.local_api_key.is_none() {
         return Err("透明代理保护密钥未设置".to_string());
     }
-    if tool_config.real_api_key.is_none() || tool_config.real_base_url.is_none() {
-        return Err("真实 API Key 或 Base URL 未设置".to_string());
+    if !tool_config.has_upstream() {
+        return Err("真实 API Key 或 Base URL 未设置，且未配置上游池".to_string());
     }
 
     // ========== Profile 切换逻辑 ==========
@@ -185,7 +186,9 @@ async fn try_start_proxy_internal(
         "claude-code" => profile_mgr.get_claude_profile(&proxy_profile_name).is_ok(),
         "codex" => profile_mgr.get_codex_profile(&proxy_profile_name).is_ok(),
         "gemini-cli" => profile_mgr.get_gemini_profile(&proxy_profile_name).is_ok(),
-        _ => false,
+        _ => profile_mgr
+            .get_custom_profile(tool_id, &proxy_profile_name)
+            .is_ok(),
     };
 
     if !profile_exists {
@@ -345,16 +348,12 @@ pub async fn get_all_proxy_status(
 
     let mut status_map = HashMap::new();
 
-    for tool_id in &["claude-code", "codex", "gemini-cli"] {
+    for tool in ::duckcoding::models::Tool::all() {
+        let tool_id = tool.id.as_str();
         let port = proxy_store
             .get_config(tool_id)
             .map(|tc| tc.port)
-            .unwrap_or_else(|| match *tool_id {
-                "claude-code" => 8787,
-                "codex" => 8788,
-                "gemini-cli" => 8789,
-                _ => 8790,
-            });
+            .unwrap_or_else(|| ToolProxyConfig::default_port(tool_id));
 
         let running = manager_state.manager.is_running(tool_id).await;
 
@@ -367,6 +366,32 @@ pub async fn get_all_proxy_status(
     Ok(status_map)
 }
 
+/// 获取运行中透明代理的实时指标（请求数、错误分类、耗时分布、流量）
+#[tauri::command]
+pub async fn get_proxy_metrics(
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<HashMap<String, ::duckcoding::services::proxy::metrics::ProxyMetricsSnapshot>, String> {
+    Ok(manager_state.manager.get_all_metrics().await)
+}
+
+/// 导出透明代理 HTTPS 监听使用的本地 CA 证书（PEM），返回导出路径
+///
+/// CLI 可通过 `NODE_EXTRA_CA_CERTS` 等方式信任该证书
+#[tauri::command]
+pub async fn export_proxy_ca_certificate(target_path: String) -> Result<String, String> {
+    use ::duckcoding::services::proxy::tls::CertStore;
+
+    let extra_hosts = ProxyConfigManager::new()
+        .and_then(|manager| manager.get_all_configs())
+        .map(|store| store.tls_extra_hosts())
+        .map_err(|e| format!("读取代理配置失败: {e}"))?;
+    let target = std::path::PathBuf::from(&target_path);
+    CertStore::open()
+        .and_then(|store| store.export_ca(&target, &extra_hosts))
+        .map_err(|e| format!("导出 CA 证书失败: {e:#}"))?;
+    Ok(target.to_string_lossy().to_string())
+}
+
 /// 从 Profile 更新代理配置（不激活 Profile）
 #[tauri::command]
 pub async fn update_proxy_from_profile(
@@ -400,17 +425,16 @@ pub async fn update_proxy_from_profile(
                 .map_err(|e| e.to_string())?;
             (profile.api_key, profile.base_url)
         }
-        _ => return Err(format!("不支持的工具: {}", tool_id)),
+        _ => profile_mgr
+            .get_profile_credentials(&tool_id, &profile_name)
+            .map_err(|e| e.to_string())?,
     };
 
     // 更新代理配置的 real_* 字段
     let mut proxy_config = proxy_config_mgr
         .get_config(&tool_id)
         .map_err(|e| e.to_string())?
-        .unwrap_or_else(|| {
-            use ::duckcoding::models::proxy_config::ToolProxyConfig;
-            ToolProxyConfig::new(ToolProxyConfig::default_port(&tool_id))
-        });
+        .unwrap_or_else(|| ToolProxyConfig::new(ToolProxyConfig::default_port(&tool_id)));
 
     proxy_config.real_api_key = Some(api_key);
     proxy_config.real_base_url = Some(base_url);
@@ -435,9 +459,7 @@ pub async fn update_proxy_from_profile(
 
 /// 获取指定工具的代理配置
 #[tauri::command]
-pub async fn get_proxy_config(
-    tool_id: String,
-) -> Result<Option<::duckcoding::models::proxy_config::ToolProxyConfig>, String> {
+pub async fn get_proxy_config(tool_id: String) -> Result<Option<ToolProxyConfig>, String> {
     let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
     proxy_mgr.get_config(&tool_id).map_err(|e| e.to_string())
 }
@@ -446,17 +468,40 @@ pub async fn get_proxy_config(
 #[tauri::command]
 pub async fn update_proxy_config(
     tool_id: String,
-    config: ::duckcoding::models::proxy_config::ToolProxyConfig,
+    config: ToolProxyConfig,
     manager_state: State<'_, ProxyManagerState>,
     profile_state: State<'_, ProfileManagerState>,
 ) -> Result<(), String> {
-    // ========== 运行时保护检查 ==========
-    if manager_state.manager.is_running(&tool_id).await {
-        return Err(format!("{} 代理正在运行，请先停止代理再修改配置", tool_id));
+    // 校验上游协议是否受支持
+    ::duckcoding::services::proxy::headers::create_request_processor_for(
+        &tool_id,
+        config.upstream_protocol,
+    )
+    .map_err(|e| e.to_string())?;
+    ::duckcoding::services::proxy::ip_filter::IpFilter::new(&config.ip_filter)
+        .map_err(|e| format!("来源地址过滤配置无效: {e}"))?;
+
+    let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
+
+    // ========== 运行中的代理直接应用新配置 ==========
+    // 先应用到运行中的实例（端口无法绑定时保持原监听并返回错误），成功后再持久化
+    let running = manager_state.manager.is_running(&tool_id).await;
+    let mut listener_changed = false;
+    if running {
+        if !config.enabled {
+            return Err(format!("{} 代理正在运行，请先停止代理再禁用", tool_id));
+        }
+        let previous = proxy_mgr.get_config(&tool_id).map_err(|e| e.to_string())?;
+        listener_changed = previous.is_some_and(|p| p.port != config.port);
+
+        manager_state
+            .manager
+            .update_config(&tool_id, config.clone())
+            .await
+            .map_err(|e| format!("{e:#}"))?;
     }
 
     // ========== 更新配置到全局配置文件 ==========
-    let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
     proxy_mgr
         .update_config(&tool_id, config.clone())
         .map_err(|e| e.to_string())?;
@@ -464,11 +509,7 @@ pub async fn update_proxy_config(
     // ========== 同步创建/更新内置 Profile ==========
 
     // 只有在配置完整时才创建内置 Profile
-    if config.enabled
-        && config.local_api_key.is_some()
-        && config.real_api_key.is_some()
-        && config.real_base_url.is_some()
-    {
+    if config.enabled && config.local_api_key.is_some() && config.has_upstream() {
         let profile_mgr = profile_state.manager.write().await;
         let proxy_profile_name = format!("dc_proxy_{}", tool_id.replace("-", "_"));
         let proxy_endpoint = format!("http://127.0.0.1:{}", config.port);
@@ -506,7 +547,16 @@ pub async fn update_proxy_config(
                     )
                     .map_err(|e| format!("同步内置 Profile 失败: {}", e))?;
             }
-            _ => return Err(format!("不支持的工具: {}", tool_id)),
+            _ => {
+                profile_mgr
+                    .save_custom_profile_internal(
+                        &tool_id,
+                        &proxy_profile_name,
+                        proxy_key,
+                        proxy_endpoint,
+                    )
+                    .map_err(|e| format!("同步内置 Profile 失败: {}", e))?;
+            }
         }
 
         tracing::info!(
@@ -515,6 +565,13 @@ pub async fn update_proxy_config(
             port = config.port,
             "已同步更新内置 Profile"
         );
+
+        // 代理运行中切换了端口：重新激活内置 Profile，使工具配置指向新端口
+        if listener_changed {
+            profile_mgr
+                .activate_profile(&tool_id, &proxy_profile_name)
+                .map_err(|e| format!("激活内置 Profile 失败: {}", e))?;
+        }
     }
 
     Ok(())
@@ -527,3 +584,226 @@ pub async fn get_all_proxy_configs(
     let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
     proxy_mgr.get_all_configs().map_err(|e| e.to_string())
 }
+
+// ==================== 统一网关 ====================
+
+/// 启动统一网关（使用已保存的网关配置）
+#[tauri::command]
+pub async fn start_proxy_gateway(
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<::duckcoding::services::proxy::gateway::GatewayStatus, String> {
+    let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
+    let config = proxy_mgr.get_gateway_config().map_err(|e| e.to_string())?;
+
+    manager_state
+        .manager
+        .update_gateway_config(config)
+        .await
+        .map_err(|e| format!("{e:#}"))?;
+    manager_state
+        .manager
+        .start_gateway()
+        .await
+        .map_err(|e| format!("{e:#}"))?;
+    Ok(manager_state.manager.gateway_status().await)
+}
+
+/// 停止统一网关（各工具代理继续运行）
+#[tauri::command]
+pub async fn stop_proxy_gateway(manager_state: State<'_, ProxyManagerState>) -> Result<(), String> {
+    manager_state
+        .manager
+        .stop_gateway()
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// 获取统一网关状态
+#[tauri::command]
+pub async fn get_proxy_gateway_status(
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<::duckcoding::services::proxy::gateway::GatewayStatus, String> {
+    Ok(manager_state.manager.gateway_status().await)
+}
+
+/// 获取统一网关配置
+#[tauri::command]
+pub async fn get_proxy_gateway_config(
+) -> Result<::duckcoding::models::proxy_config::GatewayConfig, String> {
+    let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
+    proxy_mgr.get_gateway_config().map_err(|e| e.to_string())
+}
+
+/// 更新统一网关配置（网关运行中时立即生效，失败时不保存）
+#[tauri::command]
+pub async fn update_proxy_gateway_config(
+    config: ::duckcoding::models::proxy_config::GatewayConfig,
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<(), String> {
+    manager_state
+        .manager
+        .update_gateway_config(config.clone())
+        .await
+        .map_err(|e| format!("{e:#}"))?;
+
+    let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
+    proxy_mgr
+        .update_gateway_config(config)
+        .map_err(|e| e.to_string())
+}
+
+// ==================== 本地 Key 签发 ====================
+
+/// 修改指定工具的代理配置：运行中的代理立即生效，随后持久化
+async fn modify_proxy_config<T>(
+    tool_id: &str,
+    manager_state: &ProxyManagerState,
+    modify: impl FnOnce(&mut ToolProxyConfig) -> Result<T, String>,
+) -> Result<T, String> {
+    let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
+    let mut config = proxy_mgr
+        .get_config(tool_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("工具 {} 的代理配置不存在", tool_id))?;
+    let result = modify(&mut config)?;
+
+    if manager_state.manager.is_running(tool_id).await {
+        manager_state
+            .manager
+            .update_config(tool_id, config.clone())
+            .await
+            .map_err(|e| format!("{e:#}"))?;
+    }
+    proxy_mgr
+        .update_config(tool_id, config)
+        .map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+/// 签发本地 API Key（返回包含明文 Key 的记录）
+#[tauri::command]
+pub async fn issue_proxy_api_key(
+    tool_id: String,
+    label: String,
+    expires_at: Option<i64>,
+    token_quota: Option<u64>,
+    allowed_profiles: Option<Vec<String>>,
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<::duckcoding::models::proxy_config::LocalApiKey, String> {
+    let label = label.trim().to_string();
+    if label.is_empty() {
+        return Err("Key 标签不能为空".to_string());
+    }
+    let key = ::duckcoding::models::proxy_config::LocalApiKey {
+        key: ::duckcoding::services::proxy::api_keys::generate_key().map_err(|e| e.to_string())?,
+        label,
+        expires_at,
+        token_quota,
+        allowed_profiles: allowed_profiles.unwrap_or_default(),
+        revoked: false,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+
+    modify_proxy_config(&tool_id, &manager_state, |config| {
+        // 标签用于区分用量归属，已吊销的 Key 也保留其标签
+        if config.local_api_keys.iter().any(|k| k.label == key.label) {
+            return Err(format!("Key 标签 {} 已存在", key.label));
+        }
+        config.local_api_keys.push(key.clone());
+        Ok(())
+    })
+    .await?;
+
+    tracing::info!(tool_id = %tool_id, label = %key.label, "已签发本地 Key");
+    Ok(key)
+}
+
+/// 吊销本地 API Key（运行中的代理立即拒绝该 Key）
+#[tauri::command]
+pub async fn revoke_proxy_api_key(
+    tool_id: String,
+    label: String,
+    manager_state: State<'_, ProxyManagerState>,
+) -> Result<(), String> {
+    modify_proxy_config(&tool_id, &manager_state, |config| {
+        let key = config
+            .local_api_keys
+            .iter_mut()
+            .find(|k| k.label == label)
+            .ok_or_else(|| format!("Key {} 不存在", label))?;
+        key.revoked = true;
+        Ok(())
+    })
+    .await?;
+
+    tracing::info!(tool_id = %tool_id, label = %label, "已吊销本地 Key");
+    Ok(())
+}
+
+/// 按本地 Key 标签汇总用量
+#[tauri::command]
+pub async fn get_proxy_api_key_usage(
+    tool_id: String,
+) -> Result<std::collections::BTreeMap<String, ::duckcoding::services::session::UsageSummary>, String>
+{
+    ::duckcoding::services::session::SESSION_MANAGER
+        .get_usage_summary_by_key(&tool_id)
+        .map_err(|e| e.to_string())
+}
+
+// ==================== 抓包与重放 ====================
+
+/// 打开指定工具的抓包存储（使用工具当前的抓包配置）
+fn open_capture_store(
+    tool_id: &str,
+) -> Result<::duckcoding::services::proxy::capture::CaptureStore, String> {
+    let proxy_mgr = ProxyConfigManager::new().map_err(|e| e.to_string())?;
+    let capture_config = proxy_mgr
+        .get_config(tool_id)
+        .map_err(|e| e.to_string())?
+        .map(|c| c.capture)
+        .unwrap_or_default();
+    ::duckcoding::services::proxy::capture::CaptureStore::for_tool(tool_id, &capture_config)
+        .map_err(|e| e.to_string())
+}
+
+/// 列出抓包记录摘要（最新的在前）
+#[tauri::command]
+pub async fn list_proxy_captures(
+    tool_id: String,
+    limit: Option<usize>,
+) -> Result<Vec<::duckcoding::services::proxy::capture::CaptureSummary>, String> {
+    open_capture_store(&tool_id)?
+        .list(limit.unwrap_or(200))
+        .map_err(|e| e.to_string())
+}
+
+/// 获取单条抓包记录详情
+#[tauri::command]
+pub async fn get_proxy_capture(
+    tool_id: String,
+    capture_id: String,
+) -> Result<Option<::duckcoding::services::proxy::capture::CaptureEntry>, String> {
+    open_capture_store(&tool_id)?
+        .get(&capture_id)
+        .map_err(|e| e.to_string())
+}
+
+/// 清空指定工具的抓包记录
+#[tauri::command]
+pub async fn clear_proxy_captures(tool_id: String) -> Result<(), String> {
+    open_capture_store(&tool_id)?
+        .clear()
+        .map_err(|e| e.to_string())
+}
+
+/// 使用当前上游重放抓包记录
+#[tauri::command]
+pub async fn replay_proxy_capture(
+    tool_id: String,
+    capture_id: String,
+) -> Result<::duckcoding::services::proxy::capture::ReplayResult, String> {
+    ::duckcoding::services::proxy::capture::replay_capture(&tool_id, &capture_id)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -1,7 +1,13 @@
 // 会话管理 Tauri 命令
 
-use crate::commands::error::AppResult;
-use duckcoding::services::session::{SessionListResponse, SESSION_MANAGER};
+use crate::commands::error::{AppError, AppResult};
+use crate::commands::profile_commands::ProfileManagerState;
+use duckcoding::models::config::SessionRetentionConfig;
+use duckcoding::services::session::manager::SessionManager;
+use duckcoding::services::session::{
+    ExportFormat, ImportSummary, RetentionReport, SessionBundle, SessionListResponse,
+    SessionUsageResponse, TranscriptSearchHit, TranscriptTurn, UsageRecord, SESSION_MANAGER,
+};
 
 /// 获取会话列表
 #[tauri::command]
@@ -25,21 +31,43 @@ pub async fn clear_all_sessions(tool_id: String) -> AppResult<()> {
     Ok(SESSION_MANAGER.clear_sessions(&tool_id)?)
 }
 
-/// 更新会话配置
+/// 更新会话配置（custom 时按名称绑定 Profile，请求时解析凭证）
 #[tauri::command]
 pub async fn update_session_config(
+    state: tauri::State<'_, ProfileManagerState>,
     session_id: String,
     config_name: String,
     custom_profile_name: Option<String>,
-    url: String,
-    api_key: String,
 ) -> AppResult<()> {
+    if config_name == "custom" {
+        let profile = custom_profile_name
+            .as_deref()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| AppError::ValidationError {
+                field: "custom_profile_name".to_string(),
+                reason: "自定义配置需要指定 Profile".to_string(),
+            })?;
+        let session = SESSION_MANAGER
+            .get_session(&session_id)?
+            .ok_or_else(|| AppError::Custom(format!("会话不存在: {}", session_id)))?;
+
+        let manager = state.manager.read().await;
+        if manager
+            .get_profile_credentials(&session.tool_id, profile)
+            .is_err()
+        {
+            return Err(AppError::ProfileNotFound {
+                profile: profile.to_string(),
+            });
+        }
+    }
+
     Ok(SESSION_MANAGER.update_session_config(
         &session_id,
         &config_name,
-        custom_profile_name.as_deref(),
-        &url,
-        &api_key,
+        custom_profile_name
+            .as_deref()
+            .filter(|_| config_name == "custom"),
     )?)
 }
 
@@ -48,3 +76,79 @@ pub async fn update_session_config(
 pub async fn update_session_note(session_id: String, note: Option<String>) -> AppResult<()> {
     Ok(SESSION_MANAGER.update_session_note(&session_id, note.as_deref())?)
 }
+
+/// 获取会话的用量明细与汇总
+#[tauri::command]
+pub async fn get_session_usage(session_id: String) -> AppResult<SessionUsageResponse> {
+    Ok(SessionUsageResponse {
+        records: SESSION_MANAGER.get_session_usage_records(&session_id)?,
+        summary: SESSION_MANAGER.get_session_usage_summary(&session_id)?,
+    })
+}
+
+/// 按工具和时间范围查询用量记录（tool_id 为空时查询全部工具）
+#[tauri::command]
+pub async fn get_usage_records(
+    tool_id: Option<String>,
+    start_time: i64,
+    end_time: i64,
+) -> AppResult<Vec<UsageRecord>> {
+    Ok(SESSION_MANAGER.get_usage_records(tool_id.as_deref(), start_time, end_time)?)
+}
+
+/// 获取会话的对话记录（需在代理配置中开启对话记录）
+#[tauri::command]
+pub async fn get_session_transcript(session_id: String) -> AppResult<Vec<TranscriptTurn>> {
+    Ok(SESSION_MANAGER.get_session_transcript(&session_id)?)
+}
+
+/// 全文搜索对话记录（tool_id 为空时搜索全部工具，默认最多返回 50 条）
+#[tauri::command]
+pub async fn search_session_transcripts(
+    query: String,
+    tool_id: Option<String>,
+    limit: Option<usize>,
+) -> AppResult<Vec<TranscriptSearchHit>> {
+    Ok(SESSION_MANAGER.search_transcripts(&query, tool_id.as_deref(), limit.unwrap_or(50))?)
+}
+
+/// 导出会话（含用量明细与对话记录），返回导出路径
+///
+/// 指定 session_ids 时只导出这些会话，否则按 tool_id 批量导出（均为空时导出全部）
+#[tauri::command]
+pub async fn export_sessions(
+    target_path: String,
+    format: ExportFormat,
+    session_ids: Option<Vec<String>>,
+    tool_id: Option<String>,
+) -> AppResult<String> {
+    let bundle = SESSION_MANAGER.export_bundle(session_ids.as_deref(), tool_id.as_deref())?;
+    let target = std::path::PathBuf::from(&target_path);
+    bundle.save(&target, format)?;
+    Ok(target.to_string_lossy().to_string())
+}
+
+/// 导入 JSON Lines 会话包并合并到本机会话数据库
+#[tauri::command]
+pub async fn import_sessions(source_path: String) -> AppResult<ImportSummary> {
+    let bundle = SessionBundle::load(std::path::Path::new(&source_path))?;
+    Ok(SESSION_MANAGER.import_bundle(&bundle)?)
+}
+
+/// 预览保留策略将清理的会话（不删除）
+///
+/// 未传入 policy 时使用全局配置中已保存的策略，便于在保存前预览修改后的效果
+#[tauri::command]
+pub async fn preview_session_retention(
+    policy: Option<SessionRetentionConfig>,
+) -> AppResult<RetentionReport> {
+    let policy = policy.unwrap_or_else(SessionManager::retention_policy);
+    Ok(SESSION_MANAGER.apply_retention(policy, true).await?)
+}
+
+/// 立即按已保存的保留策略清理会话
+#[tauri::command]
+pub async fn apply_session_retention() -> AppResult<RetentionReport> {
+    let policy = SessionManager::retention_policy();
+    Ok(SESSION_MANAGER.apply_retention(policy, false).await?)
+}
@@ -1,7 +1,7 @@
 use crate::commands::error::{AppError, AppResult};
 use crate::commands::tool_management::ToolRegistryState;
 use crate::commands::types::ToolStatus;
-use ::duckcoding::models::InstallMethod;
+use ::duckcoding::models::{InstallMethod, ToolDescriptor};
 
 /// 手动添加工具实例（保存用户指定的路径）
 ///
@@ -38,3 +38,11 @@ pub async fn add_manual_tool_instance(
         .add_tool_instance(&tool_id, &path, parsed_method, installer_path)
         .await?)
 }
+
+/// 列出自定义工具描述（~/.duckcoding/tools/*.json，无效的描述文件会被跳过）
+///
+/// 工具注册表在启动时加载描述，新增描述文件后需重启应用才能检测与安装
+#[tauri::command]
+pub async fn list_custom_tools() -> AppResult<Vec<ToolDescriptor>> {
+    Ok(ToolDescriptor::load_all())
+}
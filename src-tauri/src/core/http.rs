@@ -109,6 +109,7 @@ mod tests {
             hide_transparent_proxy_tip: false,
             hide_session_config_hint: false,
             log_config: crate::models::config::LogConfig::default(),
+            session_retention: crate::models::config::SessionRetentionConfig::default(),
             onboarding_status: None,
             external_watch_enabled: true,
             external_poll_interval_ms: 5000,
@@ -137,6 +138,7 @@ mod tests {
             hide_transparent_proxy_tip: false,
             hide_session_config_hint: false,
             log_config: crate::models::config::LogConfig::default(),
+            session_retention: crate::models::config::SessionRetentionConfig::default(),
             onboarding_status: None,
             external_watch_enabled: true,
             external_poll_interval_ms: 5000,
@@ -184,6 +184,35 @@ impl SqliteManager {
         Ok(rows)
     }
 
+    /// 执行查询并逐行映射（不经过缓存）
+    ///
+    /// 用于需要读取 BLOB 等无法用通用 JSON 行表示的列
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// let blobs: Vec<Vec<u8>> =
+    ///     manager.query_map("SELECT data FROM files WHERE id = ?", &["1"], |row| row.get(0))?;
+    /// ```
+    pub fn query_map<T, F>(&self, sql: &str, params: &[&str], f: F) -> Result<Vec<T>>
+    where
+        F: FnMut(&Row) -> rusqlite::Result<T>,
+    {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| DataError::Concurrency(e.to_string()))?;
+
+        let mut stmt = conn.prepare(sql).map_err(DataError::Database)?;
+        let rows = stmt
+            .query_map(params_from_iter(params.iter()), f)
+            .map_err(DataError::Database)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(DataError::Database)?;
+
+        Ok(rows)
+    }
+
     /// 将 rusqlite::Row 转换为 QueryRow
     fn row_to_query_row(
         row: &Row,
@@ -593,6 +622,30 @@ mod tests {
         assert_eq!(rows.len(), 3);
     }
 
+    #[test]
+    fn test_query_map_reads_blob() {
+        let (_temp_dir, manager) = create_test_db();
+        manager
+            .execute_raw("CREATE TABLE files (id INTEGER PRIMARY KEY, data BLOB)")
+            .unwrap();
+        manager
+            .transaction(|tx| {
+                tx.execute(
+                    "INSERT INTO files (id, data) VALUES (1, ?)",
+                    [vec![0u8, 159, 146, 150]],
+                )?;
+                Ok(())
+            })
+            .unwrap();
+
+        let blobs: Vec<Vec<u8>> = manager
+            .query_map("SELECT data FROM files WHERE id = ?", &["1"], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(blobs, vec![vec![0u8, 159, 146, 150]]);
+    }
+
     #[test]
     fn test_table_exists() {
         let (_temp_dir, manager) = create_test_db();
@@ -91,7 +91,8 @@ pub async fn auto_start_proxies(manager: &ProxyManager) {
     let mut started_count = 0;
     let mut failed_count = 0;
 
-    for tool_id in &["claude-code", "codex", "gemini-cli"] {
+    for tool in models::Tool::all() {
+        let tool_id = &tool.id;
         let tool_config = match proxy_store.get_config(tool_id) {
             Some(cfg) => cfg.clone(),
             None => continue,
@@ -120,6 +121,17 @@ pub async fn auto_start_proxies(manager: &ProxyManager) {
         }
     }
 
+    if proxy_store.gateway.auto_start {
+        tracing::info!(port = proxy_store.gateway.port, "自动启动统一网关");
+        match manager.start_gateway().await {
+            Ok(_) => started_count += 1,
+            Err(e) => {
+                failed_count += 1;
+                tracing::error!(error = ?e, "统一网关启动失败");
+            }
+        }
+    }
+
     if started_count > 0 || failed_count > 0 {
         tracing::info!(
             started = started_count,
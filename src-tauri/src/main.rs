@@ -262,6 +262,7 @@ fn main() {
         update_tool_instance,
         validate_tool_path,
         add_manual_tool_instance,
+        list_custom_tools,
         scan_installer_for_tool_path,
         scan_all_tool_candidates,
         detect_single_tool,
@@ -284,6 +285,14 @@ fn main() {
         update_balance_config,
         delete_balance_config,
         migrate_balance_from_localstorage,
+        // 费用估算
+        get_pricing_table,
+        save_pricing_table,
+        set_model_price,
+        delete_model_price,
+        set_profile_price_multiplier,
+        reset_model_prices,
+        get_cost_stats,
         // 窗口管理
         handle_close_action,
         // 代理调试
@@ -306,16 +315,38 @@ fn main() {
         start_tool_proxy,
         stop_tool_proxy,
         get_all_proxy_status,
+        get_proxy_metrics,
+        export_proxy_ca_certificate,
         update_proxy_from_profile,
         get_proxy_config,
         update_proxy_config,
         get_all_proxy_configs,
+        issue_proxy_api_key,
+        revoke_proxy_api_key,
+        get_proxy_api_key_usage,
+        start_proxy_gateway,
+        stop_proxy_gateway,
+        get_proxy_gateway_status,
+        get_proxy_gateway_config,
+        update_proxy_gateway_config,
+        list_proxy_captures,
+        get_proxy_capture,
+        clear_proxy_captures,
+        replay_proxy_capture,
         // 会话管理命令
         get_session_list,
         delete_session,
         clear_all_sessions,
         update_session_config,
         update_session_note,
+        get_session_usage,
+        get_usage_records,
+        get_session_transcript,
+        search_session_transcripts,
+        export_sessions,
+        import_sessions,
+        preview_session_retention,
+        apply_session_retention,
         // 配置监听控制
         get_watcher_status,
         start_watcher_if_needed,
@@ -69,6 +69,50 @@ pub struct LogConfig {
     pub file_path: Option<String>,
 }
 
+/// 会话保留策略（后台定期清理与手动清理共用）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionRetentionConfig {
+    /// 最长保留天数（按最后活跃时间计算，0 表示不限）
+    #[serde(default = "default_retention_max_age_days")]
+    pub max_age_days: u32,
+    /// 每个工具最多保留的会话数（保留最近活跃的会话，0 表示不限）
+    #[serde(default = "default_retention_max_sessions")]
+    pub max_sessions_per_tool: u32,
+    /// 保留有备注或自定义端点配置的会话（不计入数量上限）
+    #[serde(default = "default_true")]
+    pub keep_annotated: bool,
+    /// 用量明细最长保留天数（按记录时间计算，0 表示不限；本地 Key 的用量用于额度统计，不清理）
+    #[serde(default = "default_usage_max_age_days")]
+    pub usage_max_age_days: u32,
+}
+
+fn default_retention_max_age_days() -> u32 {
+    30
+}
+
+fn default_retention_max_sessions() -> u32 {
+    1000
+}
+
+fn default_usage_max_age_days() -> u32 {
+    180
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for SessionRetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: default_retention_max_age_days(),
+            max_sessions_per_tool: default_retention_max_sessions(),
+            keep_annotated: true,
+            usage_max_age_days: default_usage_max_age_days(),
+        }
+    }
+}
+
 /// 新用户引导状态
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OnboardingStatus {
@@ -167,6 +211,9 @@ pub struct GlobalConfig {
     // 日志系统配置
     #[serde(default)]
     pub log_config: LogConfig,
+    // 会话保留策略
+    #[serde(default)]
+    pub session_retention: SessionRetentionConfig,
     // 新用户引导状态
     #[serde(default)]
     pub onboarding_status: Option<OnboardingStatus>,
@@ -1,12 +1,16 @@
 pub mod balance;
 pub mod config;
+pub mod pricing;
 pub mod proxy_config;
 pub mod tool;
+pub mod tool_descriptor;
 pub mod update;
 
 pub use balance::*;
 pub use config::*;
+pub use pricing::*;
 // 只导出新的 proxy_config 类型，避免与 config.rs 中的旧类型冲突
 pub use proxy_config::{ProxyMetadata, ProxyStore};
 pub use tool::*;
+pub use tool_descriptor::ToolDescriptor;
 pub use update::*;
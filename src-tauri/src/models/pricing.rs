@@ -0,0 +1,211 @@
+// Pricing 数据模型
+//
+// 模型价格表与 Profile 价格倍率的持久化存储结构（pricing.json）
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 单个模型的价格（单位：货币 / 百万 Token）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct ModelPrice {
+    /// 输入价格
+    pub input: f64,
+    /// 输出价格
+    pub output: f64,
+    /// 缓存读取价格
+    #[serde(default)]
+    pub cache_read: f64,
+    /// 缓存写入价格
+    #[serde(default)]
+    pub cache_write: f64,
+}
+
+impl ModelPrice {
+    pub const fn new(input: f64, output: f64, cache_read: f64, cache_write: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read,
+            cache_write,
+        }
+    }
+}
+
+/// 价格表存储结构
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PricingStore {
+    /// 存储格式版本
+    pub version: u32,
+    /// 货币单位（仅用于展示）
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// 模型价格表（键为模型名称或模型名前缀）
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelPrice>,
+    /// Profile 价格倍率：tool_id -> profile_name -> 倍率
+    ///
+    /// 中转站按官方价格的一定比例计费时使用，未配置时倍率为 1.0
+    #[serde(default)]
+    pub profile_multipliers: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+/// 内置价格表（USD / 百万 Token，官方标准价格）
+const BUILTIN_PRICES: &[(&str, ModelPrice)] = &[
+    ("claude-opus-4-5", ModelPrice::new(5.0, 25.0, 0.5, 6.25)),
+    ("claude-opus-4-1", ModelPrice::new(15.0, 75.0, 1.5, 18.75)),
+    ("claude-opus-4", ModelPrice::new(15.0, 75.0, 1.5, 18.75)),
+    ("claude-sonnet-4-5", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-sonnet-4", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-3-7-sonnet", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-haiku-4-5", ModelPrice::new(1.0, 5.0, 0.1, 1.25)),
+    ("claude-3-5-haiku", ModelPrice::new(0.8, 4.0, 0.08, 1.0)),
+    ("gpt-5", ModelPrice::new(1.25, 10.0, 0.125, 0.0)),
+    ("gpt-5-codex", ModelPrice::new(1.25, 10.0, 0.125, 0.0)),
+    ("gpt-5-mini", ModelPrice::new(0.25, 2.0, 0.025, 0.0)),
+    ("gpt-5-nano", ModelPrice::new(0.05, 0.4, 0.005, 0.0)),
+    ("gpt-4.1", ModelPrice::new(2.0, 8.0, 0.5, 0.0)),
+    ("gpt-4o", ModelPrice::new(2.5, 10.0, 1.25, 0.0)),
+    ("gemini-3-pro", ModelPrice::new(2.0, 12.0, 0.2, 0.0)),
+    ("gemini-2.5-pro", ModelPrice::new(1.25, 10.0, 0.31, 0.0)),
+    ("gemini-2.5-flash", ModelPrice::new(0.3, 2.5, 0.075, 0.0)),
+    (
+        "gemini-2.5-flash-lite",
+        ModelPrice::new(0.1, 0.4, 0.025, 0.0),
+    ),
+];
+
+impl Default for PricingStore {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            currency: default_currency(),
+            models: BUILTIN_PRICES
+                .iter()
+                .map(|(name, price)| (name.to_string(), *price))
+                .collect(),
+            profile_multipliers: BTreeMap::new(),
+        }
+    }
+}
+
+impl PricingStore {
+    /// 查找模型价格
+    ///
+    /// 先精确匹配，再按最长前缀匹配（如 `claude-sonnet-4-5-20250929` 匹配 `claude-sonnet-4-5`）；
+    /// 带供应商前缀的名称（如 `anthropic/claude-sonnet-4-5`）会去掉前缀后再匹配
+    pub fn find_price(&self, model: &str) -> Option<&ModelPrice> {
+        let model = model.rsplit('/').next().unwrap_or(model);
+
+        if let Some(price) = self.models.get(model) {
+            return Some(price);
+        }
+
+        self.models
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| price)
+    }
+
+    /// 获取 Profile 价格倍率（未配置时为 1.0）
+    pub fn multiplier(&self, tool_id: &str, profile_name: Option<&str>) -> f64 {
+        profile_name
+            .and_then(|name| self.profile_multipliers.get(tool_id)?.get(name))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// 估算单次请求费用（模型未定价时返回 None）
+    pub fn estimate_cost(
+        &self,
+        tool_id: &str,
+        profile_name: Option<&str>,
+        model: Option<&str>,
+        tokens: &TokenCounts,
+    ) -> Option<f64> {
+        let price = self.find_price(model?)?;
+        let base = (tokens.input as f64 * price.input
+            + tokens.output as f64 * price.output
+            + tokens.cache_read as f64 * price.cache_read
+            + tokens.cache_creation as f64 * price.cache_write)
+            / 1_000_000.0;
+        Some(base * self.multiplier(tool_id, profile_name))
+    }
+}
+
+/// 计费所需的 Token 数量
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenCounts {
+    pub input: i64,
+    pub output: i64,
+    pub cache_read: i64,
+    pub cache_creation: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_price_prefix_match() {
+        let store = PricingStore::default();
+
+        let price = store.find_price("claude-sonnet-4-5-20250929").unwrap();
+        assert_eq!(price.input, 3.0);
+
+        // 最长前缀优先：gpt-5-mini 不应匹配到 gpt-5
+        let price = store.find_price("gpt-5-mini-2025-08-07").unwrap();
+        assert_eq!(price.input, 0.25);
+
+        assert!(store.find_price("anthropic/claude-haiku-4-5").is_some());
+        assert!(store.find_price("unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_estimate_cost_with_multiplier() {
+        let mut store = PricingStore::default();
+        store
+            .profile_multipliers
+            .entry("claude-code".to_string())
+            .or_default()
+            .insert("relay".to_string(), 0.5);
+
+        let tokens = TokenCounts {
+            input: 1_000_000,
+            output: 100_000,
+            cache_read: 2_000_000,
+            cache_creation: 0,
+        };
+
+        // 3.0 + 1.5 + 0.6 = 5.1
+        let cost = store
+            .estimate_cost("claude-code", None, Some("claude-sonnet-4-5"), &tokens)
+            .unwrap();
+        assert!((cost - 5.1).abs() < 1e-9);
+
+        let cost = store
+            .estimate_cost(
+                "claude-code",
+                Some("relay"),
+                Some("claude-sonnet-4-5"),
+                &tokens,
+            )
+            .unwrap();
+        assert!((cost - 2.55).abs() < 1e-9);
+
+        assert!(store
+            .estimate_cost("claude-code", None, None, &tokens)
+            .is_none());
+    }
+
+    #[test]
+    fn test_pricing_store_missing_fields() {
+        let store: PricingStore = serde_json::from_str(r#"{"version":1}"#).unwrap();
+        assert_eq!(store.currency, "USD");
+        assert!(store.models.is_empty());
+    }
+}
@@ -2,6 +2,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// 单个工具的透明代理配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +11,9 @@ pub struct ToolProxyConfig {
     pub port: u16,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub local_api_key: Option<String>,
+    /// 签发给团队成员的本地 Key（与 `local_api_key` 同时有效）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_api_keys: Vec<LocalApiKey>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub real_api_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -18,6 +22,9 @@ pub struct ToolProxyConfig {
     pub real_profile_name: Option<String>,
     #[serde(default)]
     pub allow_public: bool,
+    /// 连接来源地址过滤（CIDR 允许/拒绝列表）
+    #[serde(default)]
+    pub ip_filter: IpFilterConfig,
     #[serde(default)]
     pub session_endpoint_config_enabled: bool,
     #[serde(default)]
@@ -25,6 +32,391 @@ pub struct ToolProxyConfig {
     /// 启动代理前激活的 Profile 名称（用于关闭时还原）
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub original_active_profile: Option<String>,
+    /// 上游池（按顺序排列，每个上游引用一个 Profile）
+    ///
+    /// 为空时使用 `real_base_url` / `real_api_key` 作为唯一上游
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upstreams: Vec<UpstreamConfig>,
+    /// 上游选择策略
+    #[serde(default)]
+    pub load_balance: LoadBalanceStrategy,
+    /// 请求/响应抓包配置（默认关闭）
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    /// 瞬时错误重试配置
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// 本地限流配置（默认不限制）
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// 在代理端口暴露 Prometheus 格式的 `/__duckcoding/metrics`（默认关闭）
+    #[serde(default)]
+    pub metrics_endpoint: bool,
+    /// 停止或重启代理时等待进行中请求完成的最长时间（秒），超时后强制关闭连接
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+    /// HTTPS 监听配置（默认关闭）
+    #[serde(default)]
+    pub tls: TlsListenerConfig,
+    /// 上游 API 协议（与工具原生协议不同时进行协议转换）
+    #[serde(default)]
+    pub upstream_protocol: UpstreamProtocol,
+    /// 模型映射与路由规则（按顺序匹配，第一条命中的规则生效）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_rules: Vec<ModelRule>,
+    /// 会话对话记录配置（默认关闭）
+    #[serde(default)]
+    pub transcript: TranscriptConfig,
+}
+
+fn default_drain_timeout_secs() -> u64 {
+    30
+}
+
+/// 连接来源地址过滤配置
+///
+/// 条目为 CIDR（如 `192.168.1.0/24`）或单个 IP；拒绝列表优先，
+/// 允许列表非空时仅允许命中的地址（本机回环地址始终允许）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IpFilterConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+/// HTTPS 监听配置
+///
+/// 开启后使用本地 CA 签发的证书额外监听 HTTPS 端口；同时开启 `allow_public` 时，
+/// 仅 HTTPS 端口对外开放，HTTP 端口只监听本机
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TlsListenerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// HTTPS 端口（未设置时为 HTTP 端口 + 1000）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// 证书额外包含的主机名或 IP（如局域网地址），localhost 与本机主机名始终包含
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_hosts: Vec<String>,
+}
+
+impl TlsListenerConfig {
+    /// HTTPS 监听端口（未开启 HTTPS 时为 None）
+    pub fn resolve_port(&self, http_port: u16) -> Option<u16> {
+        self.enabled.then(|| {
+            self.port
+                .unwrap_or_else(|| http_port.checked_add(1000).unwrap_or(http_port - 1000))
+        })
+    }
+}
+
+/// 统一网关配置
+///
+/// 网关在单一端口上接收所有工具的请求，按路径前缀（如 `/claude-code/`）或请求特征
+/// 分发到对应工具的代理，鉴权、上游、会话仍使用各工具自身的配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GatewayConfig {
+    #[serde(default = "default_gateway_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub allow_public: bool,
+    #[serde(default)]
+    pub ip_filter: IpFilterConfig,
+    #[serde(default)]
+    pub tls: TlsListenerConfig,
+    /// 应用启动时自动启动网关
+    #[serde(default)]
+    pub auto_start: bool,
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+}
+
+fn default_gateway_port() -> u16 {
+    8790
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            port: default_gateway_port(),
+            allow_public: false,
+            ip_filter: IpFilterConfig::default(),
+            tls: TlsListenerConfig::default(),
+            auto_start: false,
+            drain_timeout_secs: default_drain_timeout_secs(),
+        }
+    }
+}
+
+/// 上游 API 协议
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamProtocol {
+    /// 与工具原生协议一致（透明转发）
+    #[default]
+    Native,
+    /// OpenAI Chat Completions（`/v1/chat/completions`）
+    OpenaiChat,
+    /// OpenAI Responses API（`/v1/responses`）
+    OpenaiResponses,
+    /// Anthropic Messages（`/v1/messages`）
+    AnthropicMessages,
+}
+
+/// 模型映射与路由规则
+///
+/// 命中规则后可改写请求中的模型名称，并可将请求路由到指定 Profile 或 Base URL
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ModelRule {
+    /// 匹配的模型名称，支持 `*` 通配符（如 `claude-haiku-*`、`*-mini`）
+    pub pattern: String,
+    /// 改写后的模型名称（为空时不改写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rewrite_to: Option<String>,
+    /// 路由到的 Profile（优先于 base_url）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_name: Option<String>,
+    /// 路由到的 Base URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// 路由到 Base URL 时使用的 API Key（必填，未填写时跳过该路由）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default = "default_model_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_model_rule_enabled() -> bool {
+    true
+}
+
+/// 请求/响应抓包配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CaptureConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 单个请求体/响应体最多记录的字节数（超出部分截断）
+    #[serde(default = "default_capture_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// 单个抓包文件的大小上限（超出后轮转）
+    #[serde(default = "default_capture_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// 保留的抓包文件数量（含当前文件）
+    #[serde(default = "default_capture_max_files")]
+    pub max_files: u32,
+}
+
+fn default_capture_max_body_bytes() -> usize {
+    256 * 1024
+}
+
+fn default_capture_max_file_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_capture_max_files() -> u32 {
+    5
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_body_bytes: default_capture_max_body_bytes(),
+            max_file_bytes: default_capture_max_file_bytes(),
+            max_files: default_capture_max_files(),
+        }
+    }
+}
+
+/// 会话对话记录配置
+///
+/// 开启后按会话保存用户输入、助手回复与工具调用（压缩存储，支持全文搜索）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TranscriptConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 单条记录最多保存的字节数（超出部分截断）
+    #[serde(default = "default_transcript_max_turn_bytes")]
+    pub max_turn_bytes: usize,
+    /// 该工具对话记录的存储上限（压缩后字节数，超出后删除最旧的记录）
+    #[serde(default = "default_transcript_max_total_bytes")]
+    pub max_total_bytes: u64,
+}
+
+fn default_transcript_max_turn_bytes() -> usize {
+    64 * 1024
+}
+
+fn default_transcript_max_total_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+
+impl Default for TranscriptConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_turn_bytes: default_transcript_max_turn_bytes(),
+            max_total_bytes: default_transcript_max_total_bytes(),
+        }
+    }
+}
+
+/// 瞬时错误重试配置
+///
+/// 连接重置、502/503/504、429 时对同一上游退避重试（指数退避 + 随机抖动），
+/// 重试次数用尽后再切换到下一个上游
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// 单个上游的最大重试次数（0 表示不重试）
+    #[serde(default = "default_retry_max_retries")]
+    pub max_retries: u32,
+    /// 首次重试的基础等待时间（毫秒），之后每次翻倍
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub base_delay_ms: u64,
+    /// 单次等待时间上限（毫秒），同时限制 429 的 Retry-After（超出则不重试）
+    #[serde(default = "default_retry_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+fn default_retry_max_retries() -> u32 {
+    2
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_retry_max_delay_ms() -> u64 {
+    10_000
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_retry_max_retries(),
+            base_delay_ms: default_retry_base_delay_ms(),
+            max_delay_ms: default_retry_max_delay_ms(),
+        }
+    }
+}
+
+/// 限流阈值（未设置的项不限制）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimits {
+    /// 每分钟请求数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    /// 每分钟 Token 数（输入 + 输出，按响应中的用量统计）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_minute: Option<u64>,
+    /// 最大并发请求数（流式响应在传输结束前都计入）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<u32>,
+}
+
+impl RateLimits {
+    /// 是否设置了任一阈值
+    pub fn is_limited(&self) -> bool {
+        self.requests_per_minute.is_some()
+            || self.tokens_per_minute.is_some()
+            || self.max_concurrent.is_some()
+    }
+}
+
+/// 本地限流配置
+///
+/// 超出阈值的请求进入队列等待，超过 `queue_timeout_secs` 仍无法发送时返回 429
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// 工具级阈值（所有上游共享）
+    #[serde(flatten)]
+    pub limits: RateLimits,
+    /// Profile 级阈值（Profile 名称 -> 阈值，与工具级阈值同时生效）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, RateLimits>,
+    /// 排队等待超时（秒）
+    #[serde(default = "default_rate_limit_queue_timeout_secs")]
+    pub queue_timeout_secs: u64,
+}
+
+fn default_rate_limit_queue_timeout_secs() -> u64 {
+    60
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            limits: RateLimits::default(),
+            profiles: BTreeMap::new(),
+            queue_timeout_secs: default_rate_limit_queue_timeout_secs(),
+        }
+    }
+}
+
+/// 签发的本地 API Key
+///
+/// 以 `label` 标识使用者，会话与用量记录中记录该标签
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LocalApiKey {
+    pub key: String,
+    /// 使用者标签（同一工具内唯一）
+    pub label: String,
+    /// 过期时间（Unix 时间戳，秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Token 配额（累计输入 + 输出 + 缓存 Token，超出后拒绝请求）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_quota: Option<u64>,
+    /// 允许使用的 Profile（为空时不限制）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_profiles: Vec<String>,
+    /// 是否已吊销
+    #[serde(default)]
+    pub revoked: bool,
+    /// 签发时间（Unix 时间戳，秒）
+    #[serde(default)]
+    pub created_at: i64,
+}
+
+impl LocalApiKey {
+    /// 是否允许使用指定 Profile（未知 Profile 仅在不限制时允许）
+    pub fn allows_profile(&self, profile_name: Option<&str>) -> bool {
+        self.allowed_profiles.is_empty()
+            || profile_name.is_some_and(|name| self.allowed_profiles.iter().any(|p| p == name))
+    }
+}
+
+/// 上游池中的单个上游
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UpstreamConfig {
+    /// 引用的 Profile 名称（请求时通过 ProfileManager 解析 URL 和 Key）
+    pub profile_name: String,
+    /// 加权轮询权重（0 表示仅作为故障转移备用）
+    #[serde(default = "default_upstream_weight")]
+    pub weight: u32,
+    #[serde(default = "default_upstream_enabled")]
+    pub enabled: bool,
+}
+
+fn default_upstream_weight() -> u32 {
+    1
+}
+
+fn default_upstream_enabled() -> bool {
+    true
+}
+
+/// 上游选择策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadBalanceStrategy {
+    /// 按列表顺序优先使用第一个健康上游，失败时依次切换
+    #[default]
+    Failover,
+    /// 按权重平滑轮询，失败时切换到其余上游
+    WeightedRoundRobin,
 }
 
 impl ToolProxyConfig {
@@ -34,23 +426,49 @@ impl ToolProxyConfig {
             enabled: false,
             port,
             local_api_key: None,
+            local_api_keys: Vec::new(),
             real_api_key: None,
             real_base_url: None,
             real_profile_name: None,
             allow_public: false,
+            ip_filter: IpFilterConfig::default(),
             session_endpoint_config_enabled: false,
             auto_start: false,
             original_active_profile: None,
+            upstreams: Vec::new(),
+            load_balance: LoadBalanceStrategy::default(),
+            capture: CaptureConfig::default(),
+            retry: RetryConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            metrics_endpoint: false,
+            drain_timeout_secs: default_drain_timeout_secs(),
+            tls: TlsListenerConfig::default(),
+            upstream_protocol: UpstreamProtocol::default(),
+            model_rules: Vec::new(),
+            transcript: TranscriptConfig::default(),
         }
     }
 
+    /// 是否配置了可用的上游（上游池或单一 real_* 配置）
+    pub fn has_upstream(&self) -> bool {
+        self.upstreams.iter().any(|u| u.enabled)
+            || (self.real_api_key.is_some() && self.real_base_url.is_some())
+    }
+
+    /// HTTPS 监听端口（未开启 HTTPS 时为 None）
+    pub fn tls_port(&self) -> Option<u16> {
+        self.tls.resolve_port(self.port)
+    }
+
     /// 默认端口配置
     pub fn default_port(tool_id: &str) -> u16 {
         match tool_id {
             "claude-code" => 8787,
             "codex" => 8788,
             "gemini-cli" => 8789,
-            _ => 8787,
+            _ => super::ToolDescriptor::find(tool_id)
+                .map(|d| d.default_port)
+                .unwrap_or(8787),
         }
     }
 }
@@ -64,6 +482,11 @@ pub struct ProxyStore {
     pub codex: ToolProxyConfig,
     #[serde(rename = "gemini-cli")]
     pub gemini_cli: ToolProxyConfig,
+    /// 自定义工具的代理配置（工具 ID -> 配置）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, ToolProxyConfig>,
+    #[serde(default)]
+    pub gateway: GatewayConfig,
     pub metadata: ProxyMetadata,
 }
 
@@ -74,6 +497,8 @@ impl ProxyStore {
             claude_code: ToolProxyConfig::new(8787),
             codex: ToolProxyConfig::new(8788),
             gemini_cli: ToolProxyConfig::new(8789),
+            custom: BTreeMap::new(),
+            gateway: GatewayConfig::default(),
             metadata: ProxyMetadata {
                 last_updated: Utc::now(),
             },
@@ -86,7 +511,7 @@ impl ProxyStore {
             "claude-code" => Some(&self.claude_code),
             "codex" => Some(&self.codex),
             "gemini-cli" => Some(&self.gemini_cli),
-            _ => None,
+            _ => self.custom.get(tool_id),
         }
     }
 
@@ -96,7 +521,7 @@ impl ProxyStore {
             "claude-code" => Some(&mut self.claude_code),
             "codex" => Some(&mut self.codex),
             "gemini-cli" => Some(&mut self.gemini_cli),
-            _ => None,
+            _ => self.custom.get_mut(tool_id),
         }
     }
 
@@ -106,10 +531,23 @@ impl ProxyStore {
             "claude-code" => self.claude_code = config,
             "codex" => self.codex = config,
             "gemini-cli" => self.gemini_cli = config,
-            _ => {}
+            _ => {
+                self.custom.insert(tool_id.to_string(), config);
+            }
         }
         self.metadata.last_updated = Utc::now();
     }
+
+    /// 所有监听（各工具与统一网关）配置的 HTTPS 附加主机
+    pub fn tls_extra_hosts(&self) -> Vec<String> {
+        [&self.claude_code, &self.codex, &self.gemini_cli]
+            .into_iter()
+            .chain(self.custom.values())
+            .map(|config| &config.tls)
+            .chain(std::iter::once(&self.gateway.tls))
+            .flat_map(|tls| tls.extra_hosts.iter().cloned())
+            .collect()
+    }
 }
 
 impl Default for ProxyStore {
@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use super::tool_descriptor::ToolDescriptor;
+
+/// 内置工具 ID
+pub const BUILTIN_TOOL_IDS: [&str; 3] = ["claude-code", "codex", "gemini-cli"];
+
 /// 工具状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolStatus {
@@ -42,9 +47,20 @@ pub enum InstallMethod {
 }
 
 impl Tool {
-    /// 获取所有工具
+    /// 获取所有工具（内置工具 + 自定义工具描述）
     pub fn all() -> Vec<Tool> {
-        vec![Tool::claude_code(), Tool::codex(), Tool::gemini_cli()]
+        let mut tools = vec![Tool::claude_code(), Tool::codex(), Tool::gemini_cli()];
+        tools.extend(
+            ToolDescriptor::load_all()
+                .iter()
+                .map(ToolDescriptor::to_tool),
+        );
+        tools
+    }
+
+    /// 是否为内置工具
+    pub fn is_builtin(&self) -> bool {
+        BUILTIN_TOOL_IDS.contains(&self.id.as_str())
     }
 
     /// 根据 ID 获取工具
@@ -131,6 +147,10 @@ impl Tool {
             "gemini-cli" => {
                 methods.push(InstallMethod::Npm);
             }
+            // 自定义工具仅支持 npm 安装
+            _ if !self.npm_package.is_empty() => {
+                methods.push(InstallMethod::Npm);
+            }
             _ => {}
         }
 
@@ -155,7 +175,8 @@ impl Tool {
                 }
             }
             "gemini-cli" => InstallMethod::Npm,
-            _ => InstallMethod::Official,
+            _ if !self.npm_package.is_empty() => InstallMethod::Npm,
+            _ => InstallMethod::Other,
         }
     }
 
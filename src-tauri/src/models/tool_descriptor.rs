@@ -0,0 +1,499 @@
+//! 自定义工具描述文件
+//!
+//! 内置工具（Claude Code、CodeX、Gemini CLI）之外的工具通过 `~/.duckcoding/tools/*.json`
+//! 声明，工具注册表、Profile 管理、透明代理与配置监听都会加载这些描述，无需发版即可接入
+//! Qwen Code、OpenCode 等工具。
+//!
+//! ```json
+//! {
+//!   "id": "qwen-code",
+//!   "name": "Qwen Code",
+//!   "command": "qwen",
+//!   "npm_package": "@qwen-code/qwen-code",
+//!   "config_dir": "~/.qwen",
+//!   "config_files": [
+//!     { "path": ".env", "format": "env", "api_key_field": "OPENAI_API_KEY", "base_url_field": "OPENAI_BASE_URL" }
+//!   ],
+//!   "auth": { "style": "bearer" },
+//!   "url": { "base_path": "/v1", "strip_prefix": "/v1" },
+//!   "default_port": 8791
+//! }
+//! ```
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::tool::{EnvVars, Tool, BUILTIN_TOOL_IDS};
+
+/// 描述文件所在目录（位于 DuckCoding 配置目录下）
+pub const DESCRIPTORS_DIR: &str = "tools";
+
+/// 已解析描述的缓存，描述文件变化（增删、修改时间或大小变化）时重新加载
+static DESCRIPTOR_CACHE: Mutex<Option<DescriptorCache>> = Mutex::new(None);
+
+struct DescriptorCache {
+    dir: PathBuf,
+    signature: Vec<FileSignature>,
+    descriptors: Vec<ToolDescriptor>,
+}
+
+type FileSignature = (PathBuf, Option<SystemTime>, u64);
+
+/// 自定义工具描述
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolDescriptor {
+    /// 工具 ID（小写字母、数字与 `-`，不能与内置工具重复）
+    pub id: String,
+    pub name: String,
+    /// 可执行命令名称（如 `qwen`）
+    pub command: String,
+    /// 版本检查参数
+    #[serde(default = "default_version_args")]
+    pub version_args: String,
+    /// npm 包名（为空时不支持 APP 内安装与更新）
+    #[serde(default)]
+    pub npm_package: String,
+    /// 配置目录（支持 `~/` 开头）
+    pub config_dir: String,
+    /// 写入 API Key 与 Base URL 的配置文件（第一个为主配置文件）
+    #[serde(default)]
+    pub config_files: Vec<ConfigFileDescriptor>,
+    /// 上游认证头格式
+    #[serde(default)]
+    pub auth: AuthStyle,
+    /// 上游 URL 拼接规则
+    #[serde(default)]
+    pub url: UrlRules,
+    /// 透明代理默认端口
+    pub default_port: u16,
+    /// 版本检查是否使用代理
+    #[serde(default = "default_use_proxy")]
+    pub use_proxy_for_version_check: bool,
+}
+
+fn default_version_args() -> String {
+    "--version".to_string()
+}
+
+fn default_use_proxy() -> bool {
+    true
+}
+
+/// 配置文件描述
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConfigFileDescriptor {
+    /// 相对于配置目录的路径
+    pub path: String,
+    pub format: ConfigFormat,
+    /// API Key 所在的键（JSON / TOML 使用 `.` 分隔的路径，ENV 为变量名）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_field: Option<String>,
+    /// Base URL 所在的键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url_field: Option<String>,
+}
+
+/// 配置文件格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Env,
+}
+
+/// 上游认证头格式
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum AuthStyle {
+    /// `Authorization: Bearer <key>`
+    #[default]
+    Bearer,
+    /// `x-api-key: <key>`
+    XApiKey,
+    /// `x-goog-api-key: <key>`
+    XGoogApiKey,
+    /// 自定义请求头（`prefix` 拼接在 Key 之前，如 `Token `）
+    Header {
+        name: String,
+        #[serde(default)]
+        prefix: String,
+    },
+}
+
+impl AuthStyle {
+    /// 认证头名称（小写）
+    pub fn header_name(&self) -> String {
+        match self {
+            AuthStyle::Bearer => "authorization".to_string(),
+            AuthStyle::XApiKey => "x-api-key".to_string(),
+            AuthStyle::XGoogApiKey => "x-goog-api-key".to_string(),
+            AuthStyle::Header { name, .. } => name.to_ascii_lowercase(),
+        }
+    }
+
+    /// 认证头的值
+    pub fn header_value(&self, api_key: &str) -> String {
+        match self {
+            AuthStyle::Bearer => format!("Bearer {api_key}"),
+            AuthStyle::XApiKey | AuthStyle::XGoogApiKey => api_key.to_string(),
+            AuthStyle::Header { prefix, .. } => format!("{prefix}{api_key}"),
+        }
+    }
+}
+
+/// 上游 URL 拼接规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct UrlRules {
+    /// Base URL 缺少时追加的路径（如 `/v1`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_path: Option<String>,
+    /// 转发前从请求路径去掉的前缀（如客户端请求 `/v1/...` 而 Base URL 已包含 `/v1`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_prefix: Option<String>,
+}
+
+impl UrlRules {
+    /// 拼接上游 URL
+    pub fn build(&self, base_url: &str, path: &str, query: Option<&str>) -> String {
+        let mut base = base_url.trim_end_matches('/').to_string();
+        if let Some(base_path) = self.base_path.as_deref().map(|p| p.trim_end_matches('/')) {
+            if !base_path.is_empty() && !base.ends_with(base_path) {
+                base.push_str(base_path);
+            }
+        }
+
+        let path = self
+            .strip_prefix
+            .as_deref()
+            .filter(|prefix| !prefix.is_empty())
+            .and_then(|prefix| path.strip_prefix(prefix))
+            .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+            .unwrap_or(path);
+        let query_str = query.map(|q| format!("?{q}")).unwrap_or_default();
+        format!("{base}{path}{query_str}")
+    }
+}
+
+impl ToolDescriptor {
+    /// 校验描述内容
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty()
+            || !self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            bail!("工具 ID 只能包含小写字母、数字与 '-': {}", self.id);
+        }
+        if BUILTIN_TOOL_IDS.contains(&self.id.as_str()) {
+            bail!("工具 ID 与内置工具重复: {}", self.id);
+        }
+        if self.name.trim().is_empty() || self.command.trim().is_empty() {
+            bail!("工具 {} 缺少名称或命令", self.id);
+        }
+        if self.config_dir.trim().is_empty() {
+            bail!("工具 {} 缺少配置目录", self.id);
+        }
+        if has_parent_component(&self.config_dir) {
+            bail!(
+                "工具 {} 的配置目录不能包含 '..': {}",
+                self.id,
+                self.config_dir
+            );
+        }
+        if self.default_port == 0 {
+            bail!("工具 {} 的默认端口无效", self.id);
+        }
+        if let Some(file) = self.config_files.iter().find(|f| {
+            f.path.is_empty() || Path::new(&f.path).is_absolute() || has_parent_component(&f.path)
+        }) {
+            bail!("工具 {} 的配置文件路径无效: {}", self.id, file.path);
+        }
+        if let AuthStyle::Header { name, .. } = &self.auth {
+            hyper::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow!("工具 {} 的认证头名称无效: {}", self.id, name))?;
+        }
+        Ok(())
+    }
+
+    /// 配置目录（展开 `~/`）
+    pub fn config_dir_path(&self) -> PathBuf {
+        match self.config_dir.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None => PathBuf::from(&self.config_dir),
+        }
+    }
+
+    /// 所有配置文件的完整路径
+    pub fn config_paths(&self) -> Vec<PathBuf> {
+        let dir = self.config_dir_path();
+        self.config_files
+            .iter()
+            .map(|f| dir.join(&f.path))
+            .collect()
+    }
+
+    pub fn check_command(&self) -> String {
+        format!("{} {}", self.command, self.version_args)
+    }
+
+    /// 转换为工具定义
+    pub fn to_tool(&self) -> Tool {
+        let primary = self.config_files.first();
+        Tool {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            group_name: format!("{} 专用分组", self.name),
+            npm_package: self.npm_package.clone(),
+            check_command: self.check_command(),
+            config_dir: self.config_dir_path(),
+            config_file: primary.map(|f| f.path.clone()).unwrap_or_default(),
+            env_vars: EnvVars {
+                api_key: self
+                    .config_files
+                    .iter()
+                    .find_map(|f| f.api_key_field.clone())
+                    .unwrap_or_default(),
+                base_url: self
+                    .config_files
+                    .iter()
+                    .find_map(|f| f.base_url_field.clone())
+                    .unwrap_or_default(),
+            },
+            use_proxy_for_version_check: self.use_proxy_for_version_check,
+        }
+    }
+
+    /// 描述文件目录
+    pub fn descriptors_dir() -> Result<PathBuf> {
+        let dir = crate::utils::config::config_dir().map_err(|e| anyhow!(e))?;
+        Ok(dir.join(DESCRIPTORS_DIR))
+    }
+
+    /// 加载所有自定义工具描述（无效的描述文件记录警告后跳过）
+    pub fn load_all() -> Vec<ToolDescriptor> {
+        match Self::descriptors_dir() {
+            Ok(dir) => Self::load_cached(&dir),
+            Err(e) => {
+                tracing::warn!(error = ?e, "获取工具描述目录失败");
+                Vec::new()
+            }
+        }
+    }
+
+    /// 按 ID 查找自定义工具描述
+    pub fn find(tool_id: &str) -> Option<ToolDescriptor> {
+        if BUILTIN_TOOL_IDS.contains(&tool_id) {
+            return None;
+        }
+        Self::load_all().into_iter().find(|d| d.id == tool_id)
+    }
+
+    /// 从缓存加载描述，目录中的描述文件有变化时重新解析
+    pub fn load_cached(dir: &Path) -> Vec<ToolDescriptor> {
+        let signature: Vec<FileSignature> = Self::descriptor_files(dir)
+            .into_iter()
+            .map(|path| {
+                let meta = std::fs::metadata(&path).ok();
+                let modified = meta.as_ref().and_then(|m| m.modified().ok());
+                let len = meta.map(|m| m.len()).unwrap_or(0);
+                (path, modified, len)
+            })
+            .collect();
+
+        let mut cache = DESCRIPTOR_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache
+            .as_ref()
+            .filter(|c| c.dir == dir && c.signature == signature)
+        {
+            return cached.descriptors.clone();
+        }
+
+        let descriptors = Self::load_from(dir);
+        *cache = Some(DescriptorCache {
+            dir: dir.to_path_buf(),
+            signature,
+            descriptors: descriptors.clone(),
+        });
+        descriptors
+    }
+
+    /// 目录中的描述文件（按文件名排序）
+    fn descriptor_files(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// 从指定目录加载描述文件（按文件名排序，重复 ID 以先加载的为准）
+    pub fn load_from(dir: &Path) -> Vec<ToolDescriptor> {
+        let mut descriptors: Vec<ToolDescriptor> = Vec::new();
+        for path in Self::descriptor_files(dir) {
+            match Self::load_file(&path) {
+                Ok(descriptor) if descriptors.iter().any(|d| d.id == descriptor.id) => {
+                    tracing::warn!(path = %path.display(), tool_id = %descriptor.id, "工具 ID 重复，已忽略");
+                }
+                Ok(descriptor) => descriptors.push(descriptor),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = ?e, "加载工具描述失败");
+                }
+            }
+        }
+        descriptors
+    }
+
+    fn load_file(path: &Path) -> Result<ToolDescriptor> {
+        let content = std::fs::read_to_string(path).context("读取工具描述失败")?;
+        let descriptor: ToolDescriptor =
+            serde_json::from_str(&content).context("解析工具描述失败")?;
+        descriptor.validate()?;
+        Ok(descriptor)
+    }
+}
+
+/// 路径是否包含 `..`
+fn has_parent_component(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .any(|c| matches!(c, Component::ParentDir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QWEN: &str = r#"{
+        "id": "qwen-code",
+        "name": "Qwen Code",
+        "command": "qwen",
+        "npm_package": "@qwen-code/qwen-code",
+        "config_dir": "~/.qwen",
+        "config_files": [
+            { "path": ".env", "format": "env", "api_key_field": "OPENAI_API_KEY", "base_url_field": "OPENAI_BASE_URL" }
+        ],
+        "url": { "base_path": "/v1", "strip_prefix": "/v1" },
+        "default_port": 8791
+    }"#;
+
+    #[test]
+    fn test_parse_descriptor() {
+        let descriptor: ToolDescriptor = serde_json::from_str(QWEN).unwrap();
+        descriptor.validate().unwrap();
+        assert_eq!(descriptor.auth, AuthStyle::Bearer);
+        assert_eq!(descriptor.check_command(), "qwen --version");
+        assert!(descriptor.use_proxy_for_version_check);
+
+        let tool = descriptor.to_tool();
+        assert_eq!(tool.id, "qwen-code");
+        assert_eq!(tool.config_file, ".env");
+        assert_eq!(tool.env_vars.api_key, "OPENAI_API_KEY");
+        assert!(tool.config_dir.ends_with(".qwen"));
+
+        let header: AuthStyle =
+            serde_json::from_str(r#"{ "style": "header", "name": "X-Token", "prefix": "Token " }"#)
+                .unwrap();
+        assert_eq!(header.header_name(), "x-token");
+        assert_eq!(header.header_value("abc"), "Token abc");
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_descriptors() {
+        let base: ToolDescriptor = serde_json::from_str(QWEN).unwrap();
+
+        let mut builtin = base.clone();
+        builtin.id = "codex".to_string();
+        assert!(builtin.validate().is_err());
+
+        let mut bad_id = base.clone();
+        bad_id.id = "Qwen Code".to_string();
+        assert!(bad_id.validate().is_err());
+
+        let mut bad_port = base.clone();
+        bad_port.default_port = 0;
+        assert!(bad_port.validate().is_err());
+
+        let mut parent_dir = base.clone();
+        parent_dir.config_dir = "~/.qwen/../../etc".to_string();
+        assert!(parent_dir.validate().is_err());
+
+        let mut parent_file = base.clone();
+        parent_file.config_files[0].path = "../.bashrc".to_string();
+        assert!(parent_file.validate().is_err());
+
+        let mut bad_header = base;
+        bad_header.auth = AuthStyle::Header {
+            name: "bad header".to_string(),
+            prefix: String::new(),
+        };
+        assert!(bad_header.validate().is_err());
+    }
+
+    #[test]
+    fn test_url_rules() {
+        let rules = UrlRules {
+            base_path: Some("/v1".to_string()),
+            strip_prefix: Some("/v1".to_string()),
+        };
+        assert_eq!(
+            rules.build("https://relay.example.com", "/v1/chat/completions", None),
+            "https://relay.example.com/v1/chat/completions"
+        );
+        assert_eq!(
+            rules.build("https://relay.example.com/v1/", "/v1/models", Some("a=1")),
+            "https://relay.example.com/v1/models?a=1"
+        );
+        // 前缀须为完整路径段
+        assert_eq!(
+            rules.build("https://relay.example.com/v1", "/v1beta/models", None),
+            "https://relay.example.com/v1/v1beta/models"
+        );
+        assert_eq!(
+            UrlRules::default().build("https://api.example.com/", "/chat", None),
+            "https://api.example.com/chat"
+        );
+    }
+
+    #[test]
+    fn test_load_from_dir_skips_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a-qwen.json"), QWEN).unwrap();
+        // 重复 ID
+        std::fs::write(dir.path().join("b-qwen.json"), QWEN).unwrap();
+        std::fs::write(dir.path().join("broken.json"), "{").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let descriptors = ToolDescriptor::load_from(dir.path());
+        assert_eq!(descriptors.len(), 1);
+        assert_eq!(descriptors[0].id, "qwen-code");
+
+        assert!(ToolDescriptor::load_from(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_load_cached_reloads_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a-qwen.json"), QWEN).unwrap();
+        assert_eq!(ToolDescriptor::load_cached(dir.path()).len(), 1);
+
+        let other = QWEN.replace("qwen-code", "open-code");
+        std::fs::write(dir.path().join("b-open.json"), other).unwrap();
+        let ids: Vec<String> = ToolDescriptor::load_cached(dir.path())
+            .into_iter()
+            .map(|d| d.id)
+            .collect();
+        assert_eq!(ids, vec!["qwen-code", "open-code"]);
+
+        std::fs::remove_file(dir.path().join("a-qwen.json")).unwrap();
+        assert_eq!(ToolDescriptor::load_cached(dir.path()).len(), 1);
+    }
+}
@@ -130,7 +130,7 @@ impl BalanceManager {
         let mut store = self.load_store()?;
         store
             .configs
-            .sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+            .sort_by_key(|c| std::cmp::Reverse(c.updated_at));
         Ok(store.configs)
     }
 
@@ -5,7 +5,7 @@
 //! - `NotifyWatcherManager`: 基于 OS 通知的实时监听（性能更优）
 
 use super::types::{ExternalConfigChange, ImportExternalChangeResult};
-use crate::models::Tool;
+use crate::models::{Tool, ToolDescriptor};
 use crate::services::profile_manager::ProfileManager;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
@@ -53,7 +53,12 @@ pub(crate) fn config_paths(tool: &Tool) -> Vec<PathBuf> {
         "claude-code" => {
             paths.push(tool.config_dir.join("config.json"));
         }
-        _ => {}
+        // 自定义工具：描述文件声明的全部配置文件
+        _ => {
+            if let Some(descriptor) = ToolDescriptor::find(&tool.id) {
+                paths = descriptor.config_paths();
+            }
+        }
     }
     paths
 }
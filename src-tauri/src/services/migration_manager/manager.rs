@@ -186,6 +186,7 @@ impl MigrationManager {
                 hide_transparent_proxy_tip: false,
                 hide_session_config_hint: false,
                 log_config: crate::models::LogConfig::default(),
+                session_retention: crate::models::SessionRetentionConfig::default(),
                 onboarding_status: None,
                 external_watch_enabled: true,
                 external_poll_interval_ms: 5000,
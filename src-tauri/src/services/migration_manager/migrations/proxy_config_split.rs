@@ -282,5 +282,6 @@ fn parse_old_config(value: &Value) -> Result<ToolProxyConfig> {
             .get("original_active_profile")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string()),
+        ..ToolProxyConfig::new(8787)
     })
 }
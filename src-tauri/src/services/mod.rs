@@ -8,10 +8,12 @@
 // - session: 会话管理（透明代理请求追踪）
 // - migration_manager: 统一迁移管理（新）
 // - balance: 余额监控配置管理
+// - pricing: 模型价格表与本地费用估算
 
 pub mod balance;
 pub mod config;
 pub mod migration_manager;
+pub mod pricing;
 pub mod profile_manager; // Profile管理（v2.1）
 pub mod proxy;
 pub mod proxy_config_manager; // 透明代理配置管理（v2.1）
@@ -23,6 +25,7 @@ pub mod update;
 pub use balance::*;
 pub use config::types::*; // 仅导出类型
 pub use migration_manager::{create_migration_manager, MigrationManager};
+pub use pricing::*;
 pub use profile_manager::{
     ActiveStore, ClaudeProfile, CodexProfile, GeminiProfile, ProfileDescriptor, ProfileManager,
     ProfilesStore,
@@ -0,0 +1,161 @@
+// Pricing Manager - 模型价格表管理服务
+//
+// 价格表存储在 config_dir/pricing.json，使用 DataManager 统一文件管理
+
+use crate::data::DataManager;
+use crate::models::{ModelPrice, PricingStore};
+use crate::utils::config::config_dir;
+use anyhow::{anyhow, Context, Result};
+use std::path::PathBuf;
+
+/// 价格表管理器
+pub struct PricingManager {
+    data_manager: DataManager,
+    file_path: PathBuf,
+}
+
+impl PricingManager {
+    /// 创建新的 PricingManager 实例
+    pub fn new() -> Result<Self> {
+        let file_path = config_dir().map_err(|e| anyhow!(e))?.join("pricing.json");
+
+        Ok(Self {
+            data_manager: DataManager::new(),
+            file_path,
+        })
+    }
+
+    /// 加载价格表
+    ///
+    /// 如果文件不存在，返回内置默认价格表
+    pub fn load_store(&self) -> Result<PricingStore> {
+        if !self.file_path.exists() {
+            tracing::debug!("pricing.json 不存在，使用内置价格表");
+            return Ok(PricingStore::default());
+        }
+
+        let value = self
+            .data_manager
+            .json()
+            .read(&self.file_path)
+            .context("读取 pricing.json 失败")?;
+
+        serde_json::from_value(value).context("解析 pricing.json 失败")
+    }
+
+    /// 保存价格表
+    pub fn save_store(&self, store: &PricingStore) -> Result<()> {
+        validate_store(store)?;
+
+        let value = serde_json::to_value(store).context("序列化 PricingStore 失败")?;
+
+        self.data_manager
+            .json()
+            .write(&self.file_path, &value)
+            .context("保存 pricing.json 失败")
+    }
+
+    /// 设置（新增或覆盖）模型价格
+    pub fn set_model_price(&self, model: &str, price: ModelPrice) -> Result<()> {
+        let model = model.trim();
+        if model.is_empty() {
+            anyhow::bail!("模型名称不能为空");
+        }
+
+        let mut store = self.load_store()?;
+        store.models.insert(model.to_string(), price);
+        self.save_store(&store)?;
+
+        tracing::debug!("已设置模型价格: {}", model);
+        Ok(())
+    }
+
+    /// 删除模型价格
+    pub fn delete_model_price(&self, model: &str) -> Result<()> {
+        let mut store = self.load_store()?;
+
+        if store.models.remove(model).is_none() {
+            anyhow::bail!("未找到模型价格: {}", model);
+        }
+
+        self.save_store(&store)?;
+
+        tracing::debug!("已删除模型价格: {}", model);
+        Ok(())
+    }
+
+    /// 设置 Profile 价格倍率（None 表示恢复为 1.0）
+    pub fn set_profile_multiplier(
+        &self,
+        tool_id: &str,
+        profile_name: &str,
+        multiplier: Option<f64>,
+    ) -> Result<()> {
+        let mut store = self.load_store()?;
+
+        match multiplier {
+            Some(value) => {
+                store
+                    .profile_multipliers
+                    .entry(tool_id.to_string())
+                    .or_default()
+                    .insert(profile_name.to_string(), value);
+            }
+            None => {
+                if let Some(profiles) = store.profile_multipliers.get_mut(tool_id) {
+                    profiles.remove(profile_name);
+                    if profiles.is_empty() {
+                        store.profile_multipliers.remove(tool_id);
+                    }
+                }
+            }
+        }
+
+        self.save_store(&store)?;
+
+        tracing::debug!(
+            "已设置 Profile 价格倍率: {}/{} = {:?}",
+            tool_id,
+            profile_name,
+            multiplier
+        );
+        Ok(())
+    }
+
+    /// 恢复内置默认价格表（保留 Profile 倍率）
+    pub fn reset_model_prices(&self) -> Result<PricingStore> {
+        let mut store = self.load_store()?;
+        store.models = PricingStore::default().models;
+        self.save_store(&store)?;
+        Ok(store)
+    }
+}
+
+/// 校验价格表（价格与倍率必须为非负有限数）
+fn validate_store(store: &PricingStore) -> Result<()> {
+    let valid = |v: f64| v.is_finite() && v >= 0.0;
+
+    for (model, price) in &store.models {
+        if ![
+            price.input,
+            price.output,
+            price.cache_read,
+            price.cache_write,
+        ]
+        .into_iter()
+        .all(valid)
+        {
+            anyhow::bail!("模型 {} 的价格无效", model);
+        }
+    }
+
+    for (tool_id, profiles) in &store.profile_multipliers {
+        for (profile_name, multiplier) in profiles {
+            if !valid(*multiplier) {
+                anyhow::bail!("Profile {}/{} 的价格倍率无效", tool_id, profile_name);
+            }
+        }
+    }
+
+    Ok(())
+}
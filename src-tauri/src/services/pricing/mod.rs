@@ -0,0 +1,9 @@
+// Pricing Service Module
+//
+// 本地费用估算：模型价格表管理与用量费用统计
+
+mod manager;
+mod stats;
+
+pub use manager::PricingManager;
+pub use stats::{build_cost_stats, CostGroupBy, CostStats, CostSummary};
@@ -0,0 +1,196 @@
+// 费用统计
+//
+// 将用量记录按请求 / 会话 / Profile / 模型 / 日期分组并估算费用
+
+use crate::models::{PricingStore, TokenCounts};
+use crate::services::session::UsageRecord;
+use chrono::TimeZone;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 分组维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostGroupBy {
+    /// 按单次请求（键为记录 ID）
+    Request,
+    /// 按会话
+    Session,
+    /// 按 Profile
+    Profile,
+    /// 按模型
+    Model,
+    /// 按本地日期（YYYY-MM-DD）
+    Day,
+}
+
+/// 单个分组的费用汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CostSummary {
+    /// 分组键（会话 / Profile / 模型缺失时为空字符串）
+    pub key: String,
+    pub request_count: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    /// 估算费用（不含未定价请求）
+    pub cost: f64,
+    /// 模型未定价、未计入费用的请求数
+    pub unpriced_count: i64,
+}
+
+impl CostSummary {
+    fn add(&mut self, record: &UsageRecord, cost: Option<f64>) {
+        self.request_count += 1;
+        self.input_tokens += record.input_tokens;
+        self.output_tokens += record.output_tokens;
+        self.cache_read_tokens += record.cache_read_tokens;
+        self.cache_creation_tokens += record.cache_creation_tokens;
+        match cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_count += 1,
+        }
+    }
+}
+
+/// 费用统计结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostStats {
+    /// 货币单位
+    pub currency: String,
+    pub group_by: CostGroupBy,
+    /// 全部记录的汇总
+    pub total: CostSummary,
+    /// 分组明细
+    pub groups: Vec<CostSummary>,
+}
+
+/// 估算用量记录费用并按维度分组
+///
+/// 日期与请求分组按时间升序，其余分组按费用降序
+pub fn build_cost_stats(
+    store: &PricingStore,
+    records: &[UsageRecord],
+    group_by: CostGroupBy,
+) -> CostStats {
+    let mut total = CostSummary::default();
+    let mut groups: Vec<CostSummary> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    // 查询结果通常按时间降序，请求分组需显式按时间升序排列
+    let mut ordered: Vec<&UsageRecord> = records.iter().collect();
+    if group_by == CostGroupBy::Request {
+        ordered.sort_by_key(|r| (r.created_at, r.id));
+    }
+
+    for record in ordered {
+        let cost = store.estimate_cost(
+            &record.tool_id,
+            record.profile_name.as_deref(),
+            record.model.as_deref(),
+            &TokenCounts {
+                input: record.input_tokens,
+                output: record.output_tokens,
+                cache_read: record.cache_read_tokens,
+                cache_creation: record.cache_creation_tokens,
+            },
+        );
+
+        total.add(record, cost);
+
+        let key = group_key(record, group_by);
+        let idx = *index.entry(key.clone()).or_insert_with(|| {
+            groups.push(CostSummary {
+                key,
+                ..Default::default()
+            });
+            groups.len() - 1
+        });
+        groups[idx].add(record, cost);
+    }
+
+    match group_by {
+        CostGroupBy::Day => groups.sort_by(|a, b| a.key.cmp(&b.key)),
+        CostGroupBy::Request => {} // 已按时间升序
+        _ => groups.sort_by(|a, b| b.cost.total_cmp(&a.cost)),
+    }
+
+    CostStats {
+        currency: store.currency.clone(),
+        group_by,
+        total,
+        groups,
+    }
+}
+
+fn group_key(record: &UsageRecord, group_by: CostGroupBy) -> String {
+    match group_by {
+        CostGroupBy::Request => record.id.to_string(),
+        CostGroupBy::Session => record.session_id.clone().unwrap_or_default(),
+        CostGroupBy::Profile => record.profile_name.clone().unwrap_or_default(),
+        CostGroupBy::Model => record.model.clone().unwrap_or_default(),
+        CostGroupBy::Day => chrono::Local
+            .timestamp_opt(record.created_at, 0)
+            .single()
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: i64, session: &str, model: &str, input: i64, output: i64) -> UsageRecord {
+        UsageRecord {
+            id,
+            session_id: Some(session.to_string()),
+            tool_id: "claude-code".to_string(),
+            profile_name: Some("default".to_string()),
+            model: Some(model.to_string()),
+            input_tokens: input,
+            output_tokens: output,
+            status_code: 200,
+            created_at: 1_700_000_000 + id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_group_by_session() {
+        let store = PricingStore::default();
+        let records = vec![
+            record(1, "a", "claude-sonnet-4-5", 1_000_000, 0),
+            record(2, "b", "claude-opus-4-1", 1_000_000, 0),
+            record(3, "a", "unknown-model", 500, 500),
+        ];
+
+        let stats = build_cost_stats(&store, &records, CostGroupBy::Session);
+        assert_eq!(stats.total.request_count, 3);
+        assert_eq!(stats.total.unpriced_count, 1);
+        assert!((stats.total.cost - 18.0).abs() < 1e-9);
+
+        // 按费用降序：b (15.0) 在 a (3.0) 之前
+        assert_eq!(stats.groups.len(), 2);
+        assert_eq!(stats.groups[0].key, "b");
+        assert_eq!(stats.groups[1].key, "a");
+        assert_eq!(stats.groups[1].request_count, 2);
+        assert_eq!(stats.groups[1].unpriced_count, 1);
+    }
+
+    #[test]
+    fn test_group_by_request_ascending() {
+        let store = PricingStore::default();
+        // 与查询结果一致的降序输入
+        let records = vec![
+            record(8, "a", "gpt-5", 0, 0),
+            record(7, "a", "gpt-5", 0, 1_000_000),
+        ];
+
+        let stats = build_cost_stats(&store, &records, CostGroupBy::Request);
+        let keys: Vec<&str> = stats.groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["7", "8"]);
+        assert!((stats.groups[0].cost - 10.0).abs() < 1e-9);
+    }
+}
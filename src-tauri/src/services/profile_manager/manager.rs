@@ -2,6 +2,8 @@
 
 use super::types::*;
 use crate::data::DataManager;
+use crate::models::proxy_config::UpstreamProtocol;
+use crate::models::ToolDescriptor;
 use anyhow::{anyhow, Context, Result};
 use chrono::Utc;
 use fs2::FileExt;
@@ -22,6 +24,11 @@ fn validate_profile_name(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// 是否为已声明的自定义工具
+fn is_custom_tool(tool_id: &str) -> bool {
+    ToolDescriptor::find(tool_id).is_some()
+}
+
 pub struct ProfileManager {
     data_manager: DataManager,
     profiles_path: PathBuf,
@@ -41,6 +48,16 @@ impl ProfileManager {
         })
     }
 
+    /// 使用指定目录创建（测试用）
+    #[cfg(test)]
+    pub(crate) fn in_dir(dir: &std::path::Path) -> Self {
+        Self {
+            data_manager: DataManager::new(),
+            profiles_path: dir.join("profiles.json"),
+            active_path: dir.join("active.json"),
+        }
+    }
+
     fn load_profiles_store(&self) -> Result<ProfilesStore> {
         if !self.profiles_path.exists() {
             return Ok(ProfilesStore::new());
@@ -338,6 +355,69 @@ impl ProfileManager {
             .collect())
     }
 
+    // ==================== 自定义工具 ====================
+
+    pub fn save_custom_profile(
+        &self,
+        tool_id: &str,
+        name: &str,
+        api_key: String,
+        base_url: String,
+    ) -> Result<()> {
+        // 保留字校验
+        validate_profile_name(name)?;
+
+        self.save_custom_profile_internal(tool_id, name, api_key, base_url)?;
+
+        // 如果当前 profile 已激活，自动重新应用配置
+        let active_store = self.load_active_store()?;
+        if let Some(active) = active_store.get_active(tool_id) {
+            if active.profile == name {
+                tracing::info!("Profile {} 处于激活状态，自动重新应用配置", name);
+                self.apply_to_native(tool_id, name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_custom_profile(&self, tool_id: &str, name: &str) -> Result<CustomProfile> {
+        let store = self.load_profiles_store()?;
+        store
+            .custom
+            .get(tool_id)
+            .and_then(|profiles| profiles.get(name))
+            .cloned()
+            .ok_or_else(|| anyhow!("{} Profile 不存在: {}", tool_id, name))
+    }
+
+    pub fn delete_custom_profile(&self, tool_id: &str, name: &str) -> Result<()> {
+        let mut store = self.load_profiles_store()?;
+        if let Some(profiles) = store.custom.get_mut(tool_id) {
+            profiles.remove(name);
+            if profiles.is_empty() {
+                store.custom.remove(tool_id);
+            }
+        }
+        store.metadata.last_updated = Utc::now();
+        self.save_profiles_store(&store)
+    }
+
+    pub fn list_custom_profiles(&self, tool_id: &str) -> Result<Vec<String>> {
+        let store = self.load_profiles_store()?;
+        Ok(store
+            .custom
+            .get(tool_id)
+            .map(|profiles| {
+                profiles
+                    .keys()
+                    .filter(|name| !name.starts_with(RESERVED_PREFIX))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
     // ==================== 通用列表 ====================
 
     pub fn list_all_descriptors(&self) -> Result<Vec<ProfileDescriptor>> {
@@ -372,6 +452,25 @@ impl ProfileManager {
             descriptors.push(ProfileDescriptor::from_gemini(name, profile, active_gemini));
         }
 
+        // 自定义工具（仅列出仍有描述文件的工具）
+        for descriptor in ToolDescriptor::load_all() {
+            let Some(profiles) = profiles_store.custom.get(&descriptor.id) else {
+                continue;
+            };
+            let active = active_store.get_active(&descriptor.id);
+            for (name, profile) in profiles {
+                if name.starts_with(RESERVED_PREFIX) {
+                    continue; // 跳过内置 Profile
+                }
+                descriptors.push(ProfileDescriptor::from_custom(
+                    &descriptor.id,
+                    name,
+                    profile,
+                    active,
+                ));
+            }
+        }
+
         Ok(descriptors)
     }
 
@@ -380,10 +479,54 @@ impl ProfileManager {
             "claude-code" => self.list_claude_profiles(),
             "codex" => self.list_codex_profiles(),
             "gemini-cli" => self.list_gemini_profiles(),
+            _ if is_custom_tool(tool_id) => self.list_custom_profiles(tool_id),
             _ => Err(anyhow!("不支持的工具 ID: {}", tool_id)),
         }
     }
 
+    /// 获取指定 Profile 的连接信息
+    ///
+    /// 返回 (api_key, base_url)，供透明代理在请求时解析上游
+    pub fn get_profile_credentials(&self, tool_id: &str, name: &str) -> Result<(String, String)> {
+        match tool_id {
+            "claude-code" => {
+                let profile = self.get_claude_profile(name)?;
+                Ok((profile.api_key, profile.base_url))
+            }
+            "codex" => {
+                let profile = self.get_codex_profile(name)?;
+                Ok((profile.api_key, profile.base_url))
+            }
+            "gemini-cli" => {
+                let profile = self.get_gemini_profile(name)?;
+                Ok((profile.api_key, profile.base_url))
+            }
+            _ if is_custom_tool(tool_id) => {
+                let profile = self.get_custom_profile(tool_id, name)?;
+                Ok((profile.api_key, profile.base_url))
+            }
+            _ => Err(anyhow!("不支持的工具 ID: {}", tool_id)),
+        }
+    }
+
+    /// 获取 Profile 声明的上游 API 协议
+    ///
+    /// 目前仅 Codex Profile 通过 `wire_api` 声明（"chat" / "responses"），其他工具返回 None
+    pub fn get_profile_protocol(
+        &self,
+        tool_id: &str,
+        name: &str,
+    ) -> Result<Option<UpstreamProtocol>> {
+        if tool_id != "codex" {
+            return Ok(None);
+        }
+        let profile = self.get_codex_profile(name)?;
+        Ok(Some(match profile.wire_api.as_str() {
+            "chat" => UpstreamProtocol::OpenaiChat,
+            _ => UpstreamProtocol::OpenaiResponses,
+        }))
+    }
+
     // ==================== 激活管理 ====================
 
     pub fn activate_profile(&self, tool_id: &str, profile_name: &str) -> Result<()> {
@@ -393,6 +536,10 @@ impl ProfileManager {
             "claude-code" => store.claude_code.contains_key(profile_name),
             "codex" => store.codex.contains_key(profile_name),
             "gemini-cli" => store.gemini_cli.contains_key(profile_name),
+            _ if is_custom_tool(tool_id) => store
+                .custom
+                .get(tool_id)
+                .is_some_and(|profiles| profiles.contains_key(profile_name)),
             _ => return Err(anyhow!("不支持的工具 ID: {}", tool_id)),
         };
 
@@ -593,6 +740,52 @@ impl ProfileManager {
         Ok(())
     }
 
+    /// 内部方法：保存自定义工具 Profile（跳过保留字校验，用于系统内置 Profile）
+    pub fn save_custom_profile_internal(
+        &self,
+        tool_id: &str,
+        name: &str,
+        api_key: String,
+        base_url: String,
+    ) -> Result<()> {
+        if !is_custom_tool(tool_id) {
+            return Err(anyhow!("不支持的工具 ID: {}", tool_id));
+        }
+
+        let mut store = self.load_profiles_store()?;
+        let profiles = store.custom.entry(tool_id.to_string()).or_default();
+
+        let profile = if let Some(existing) = profiles.get_mut(name) {
+            // 更新模式：只更新非空字段
+            if !api_key.is_empty() {
+                existing.api_key = api_key;
+            }
+            if !base_url.is_empty() {
+                existing.base_url = base_url;
+            }
+            existing.updated_at = Utc::now();
+            existing.clone()
+        } else {
+            // 创建模式：必须有完整数据
+            if api_key.is_empty() || base_url.is_empty() {
+                return Err(anyhow!("创建 Profile 时 API Key 和 Base URL 不能为空"));
+            }
+            CustomProfile {
+                api_key,
+                base_url,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            }
+        };
+
+        profiles.insert(name.to_string(), profile);
+        store.metadata.last_updated = Utc::now();
+        self.save_profiles_store(&store)?;
+
+        tracing::debug!("已创建/更新 {} Profile: {}", tool_id, name);
+        Ok(())
+    }
+
     // ==================== 删除 ====================
 
     pub fn delete_profile(&self, tool_id: &str, name: &str) -> Result<()> {
@@ -600,6 +793,7 @@ impl ProfileManager {
             "claude-code" => self.delete_claude_profile(name),
             "codex" => self.delete_codex_profile(name),
             "gemini-cli" => self.delete_gemini_profile(name),
+            _ if is_custom_tool(tool_id) => self.delete_custom_profile(tool_id, name),
             _ => Err(anyhow!("不支持的工具 ID: {}", tool_id)),
         }
     }
@@ -10,6 +10,6 @@ mod types;
 
 pub use manager::ProfileManager;
 pub use types::{
-    ActiveMetadata, ActiveProfile, ActiveStore, ClaudeProfile, CodexProfile, GeminiProfile,
-    ProfileDescriptor, ProfilesMetadata, ProfilesStore,
+    ActiveMetadata, ActiveProfile, ActiveStore, ClaudeProfile, CodexProfile, CustomProfile,
+    GeminiProfile, ProfileDescriptor, ProfilesMetadata, ProfilesStore,
 };
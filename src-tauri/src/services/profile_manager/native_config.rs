@@ -3,6 +3,7 @@
 use super::types::*;
 use crate::data::DataManager;
 use crate::models::tool::Tool;
+use crate::models::tool_descriptor::{ConfigFormat, ToolDescriptor};
 use anyhow::{anyhow, Result};
 use serde_json::{Map, Value};
 use toml_edit;
@@ -26,7 +27,12 @@ impl super::manager::ProfileManager {
                 let profile = self.get_gemini_profile(profile_name)?;
                 apply_gemini_native(&tool, &profile)?;
             }
-            _ => return Err(anyhow!("不支持的工具: {}", tool_id)),
+            _ => {
+                let descriptor = ToolDescriptor::find(tool_id)
+                    .ok_or_else(|| anyhow!("不支持的工具: {}", tool_id))?;
+                let profile = self.get_custom_profile(tool_id, profile_name)?;
+                apply_custom_native(&descriptor, &profile)?;
+            }
         }
 
         tracing::info!("已应用 Profile: {} / {}", tool_id, profile_name);
@@ -50,7 +56,12 @@ impl super::manager::ProfileManager {
                 let (api_key, base_url, model) = capture_gemini_config(&tool)?;
                 self.save_gemini_profile(profile_name, api_key, base_url, Some(model))?;
             }
-            _ => return Err(anyhow!("不支持的工具: {}", tool_id)),
+            _ => {
+                let descriptor = ToolDescriptor::find(tool_id)
+                    .ok_or_else(|| anyhow!("不支持的工具: {}", tool_id))?;
+                let (api_key, base_url) = capture_custom_config(&descriptor)?;
+                self.save_custom_profile(tool_id, profile_name, api_key, base_url)?;
+            }
         }
 
         tracing::info!("已捕获 Profile: {} / {}", tool_id, profile_name);
@@ -308,3 +319,85 @@ fn capture_gemini_config(tool: &Tool) -> Result<(String, String, String)> {
 
     Ok((api_key, base_url, model))
 }
+
+// ==================== 自定义工具 ====================
+
+/// 按描述文件将 API Key 与 Base URL 写入各配置文件
+fn apply_custom_native(descriptor: &ToolDescriptor, profile: &CustomProfile) -> Result<()> {
+    let manager = DataManager::new();
+    let dir = descriptor.config_dir_path();
+
+    for file in &descriptor.config_files {
+        let path = dir.join(&file.path);
+        let fields = [
+            (file.api_key_field.as_deref(), profile.api_key.as_str()),
+            (file.base_url_field.as_deref(), profile.base_url.as_str()),
+        ];
+        for (key, value) in fields {
+            let Some(key) = key else { continue };
+            match file.format {
+                ConfigFormat::Json => {
+                    manager
+                        .json_uncached()
+                        .set(&path, key, Value::String(value.to_string()))?
+                }
+                ConfigFormat::Toml => {
+                    manager
+                        .toml()
+                        .set(&path, key, toml::Value::String(value.to_string()))?
+                }
+                ConfigFormat::Env => manager.env().set(&path, key, value)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 按描述文件从配置文件读取 API Key 与 Base URL（取第一个存在的值）
+fn capture_custom_config(descriptor: &ToolDescriptor) -> Result<(String, String)> {
+    let manager = DataManager::new();
+    let dir = descriptor.config_dir_path();
+
+    let read = |format: ConfigFormat, path: &std::path::Path, key: &str| -> Option<String> {
+        match format {
+            ConfigFormat::Json => manager
+                .json_uncached()
+                .get(path, key)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string)),
+            ConfigFormat::Toml => manager
+                .toml()
+                .get(path, key)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string)),
+            ConfigFormat::Env => manager.env().get(path, key).ok(),
+        }
+    };
+
+    let mut api_key = String::new();
+    let mut base_url = String::new();
+    for file in &descriptor.config_files {
+        let path = dir.join(&file.path);
+        if api_key.is_empty() {
+            if let Some(value) = file
+                .api_key_field
+                .as_deref()
+                .and_then(|key| read(file.format, &path, key))
+            {
+                api_key = value;
+            }
+        }
+        if base_url.is_empty() {
+            if let Some(value) = file
+                .base_url_field
+                .as_deref()
+                .and_then(|key| read(file.format, &path, key))
+            {
+                base_url = value;
+            }
+        }
+    }
+
+    Ok((api_key, base_url))
+}
@@ -55,6 +55,15 @@ pub struct GeminiProfile {
     pub raw_env: Option<String>,
 }
 
+/// 自定义工具 Profile（工具由 `~/.duckcoding/tools/*.json` 描述）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomProfile {
+    pub api_key: String,
+    pub base_url: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 // ==================== profiles.json 结构 ====================
 
 /// profiles.json 顶层结构
@@ -66,6 +75,9 @@ pub struct ProfilesStore {
     pub codex: HashMap<String, CodexProfile>,
     #[serde(rename = "gemini-cli")]
     pub gemini_cli: HashMap<String, GeminiProfile>,
+    /// 自定义工具 Profile（工具 ID -> Profile 名称 -> Profile）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub custom: HashMap<String, HashMap<String, CustomProfile>>,
     pub metadata: ProfilesMetadata,
 }
 
@@ -77,6 +89,7 @@ impl ProfilesStore {
             claude_code: HashMap::new(),
             codex: HashMap::new(),
             gemini_cli: HashMap::new(),
+            custom: HashMap::new(),
             metadata: ProfilesMetadata {
                 last_updated: Utc::now(),
             },
@@ -104,7 +117,12 @@ impl ProfilesStore {
                     .map(|(name, p)| (name.clone(), p.api_key.clone(), p.base_url.clone()))
                     .collect(),
             ),
-            _ => None,
+            _ => self.custom.get(tool_id).map(|profiles| {
+                profiles
+                    .iter()
+                    .map(|(name, p)| (name.clone(), p.api_key.clone(), p.base_url.clone()))
+                    .collect()
+            }),
         }
     }
 }
@@ -131,6 +149,9 @@ pub struct ActiveStore {
     pub codex: Option<ActiveProfile>,
     #[serde(rename = "gemini-cli")]
     pub gemini_cli: Option<ActiveProfile>,
+    /// 自定义工具的激活状态
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub custom: HashMap<String, ActiveProfile>,
     pub metadata: ActiveMetadata,
 }
 
@@ -141,6 +162,7 @@ impl ActiveStore {
             claude_code: None,
             codex: None,
             gemini_cli: None,
+            custom: HashMap::new(),
             metadata: ActiveMetadata {
                 last_updated: Utc::now(),
             },
@@ -152,7 +174,7 @@ impl ActiveStore {
             "claude-code" => self.claude_code.as_ref(),
             "codex" => self.codex.as_ref(),
             "gemini-cli" => self.gemini_cli.as_ref(),
-            _ => None,
+            _ => self.custom.get(tool_id),
         }
     }
 
@@ -161,7 +183,7 @@ impl ActiveStore {
             "claude-code" => self.claude_code.as_mut(),
             "codex" => self.codex.as_mut(),
             "gemini-cli" => self.gemini_cli.as_mut(),
-            _ => None,
+            _ => self.custom.get_mut(tool_id),
         }
     }
 
@@ -177,7 +199,9 @@ impl ActiveStore {
             "claude-code" => self.claude_code = Some(active),
             "codex" => self.codex = Some(active),
             "gemini-cli" => self.gemini_cli = Some(active),
-            _ => {}
+            _ => {
+                self.custom.insert(tool_id.to_string(), active);
+            }
         }
 
         self.metadata.last_updated = Utc::now();
@@ -188,7 +212,9 @@ impl ActiveStore {
             "claude-code" => self.claude_code = None,
             "codex" => self.codex = None,
             "gemini-cli" => self.gemini_cli = None,
-            _ => {}
+            _ => {
+                self.custom.remove(tool_id);
+            }
         }
         self.metadata.last_updated = Utc::now();
     }
@@ -315,6 +341,35 @@ impl ProfileDescriptor {
     }
 }
 
+impl ProfileDescriptor {
+    pub fn from_custom(
+        tool_id: &str,
+        name: &str,
+        profile: &CustomProfile,
+        active_profile: Option<&ActiveProfile>,
+    ) -> Self {
+        let is_active = active_profile.map(|ap| ap.profile == name).unwrap_or(false);
+        let switched_at = if is_active {
+            active_profile.map(|ap| ap.switched_at)
+        } else {
+            None
+        };
+
+        Self {
+            tool_id: tool_id.to_string(),
+            name: name.to_string(),
+            api_key_preview: mask_api_key(&profile.api_key),
+            base_url: profile.base_url.clone(),
+            created_at: profile.created_at,
+            updated_at: profile.updated_at,
+            is_active,
+            switched_at,
+            provider: None,
+            model: None,
+        }
+    }
+}
+
 // ==================== 辅助函数 ====================
 
 fn mask_api_key(key: &str) -> String {
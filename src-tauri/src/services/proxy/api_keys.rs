@@ -0,0 +1,255 @@
+//! 本地 API Key 校验
+//!
+//! 代理同时接受 `local_api_key`（管理员自用，无标签）与签发的 `local_api_keys`。
+//! 签发的 Key 可设置过期时间、Token 配额和允许使用的 Profile，吊销后立即失效。
+//!
+//! 配额按内存中的累计用量校验：每个 Key 首次校验时从数据库读取一次本机用量（不含导入的记录），
+//! 之后由用量记录同步累加，请求路径上不再查询数据库。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::models::proxy_config::{LocalApiKey, ToolProxyConfig};
+use crate::services::session::SESSION_MANAGER;
+
+/// 全局签发 Key 用量计数
+pub static KEY_USAGE: Lazy<KeyUsage> = Lazy::new(KeyUsage::default);
+
+/// 签发 Key 的前缀
+const KEY_PREFIX: &str = "dc-";
+
+/// 校验失败原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// 未提供 Key 或 Key 不匹配
+    Invalid,
+    /// Key 已吊销
+    Revoked(String),
+    /// Key 已过期
+    Expired(String),
+}
+
+/// 从请求头提取客户端提供的 Key（`Authorization: Bearer` 或 `x-api-key`）
+pub fn provided_key(headers: &hyper::HeaderMap) -> &str {
+    let auth_header = headers
+        .get("authorization")
+        .or_else(|| headers.get("x-api-key"))
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if let Some(stripped) = auth_header.strip_prefix("Bearer ") {
+        stripped
+    } else if let Some(stripped) = auth_header.strip_prefix("x-api-key ") {
+        stripped
+    } else {
+        auth_header
+    }
+}
+
+/// 校验请求使用的本地 Key
+///
+/// 返回匹配到的签发 Key；使用 `local_api_key` 或未配置任何 Key 时返回 `Ok(None)`
+pub fn authenticate<'a>(
+    headers: &hyper::HeaderMap,
+    config: &'a ToolProxyConfig,
+    now: i64,
+) -> Result<Option<&'a LocalApiKey>, AuthError> {
+    if config.local_api_key.is_none() && config.local_api_keys.is_empty() {
+        return Ok(None);
+    }
+
+    let provided = provided_key(headers);
+    if provided.is_empty() {
+        return Err(AuthError::Invalid);
+    }
+    if config.local_api_key.as_deref() == Some(provided) {
+        return Ok(None);
+    }
+
+    let key = config
+        .local_api_keys
+        .iter()
+        .find(|k| k.key == provided)
+        .ok_or(AuthError::Invalid)?;
+    if key.revoked {
+        return Err(AuthError::Revoked(key.label.clone()));
+    }
+    if key.expires_at.is_some_and(|t| t <= now) {
+        return Err(AuthError::Expired(key.label.clone()));
+    }
+    Ok(Some(key))
+}
+
+/// 签发 Key 的累计 Token 用量（按工具与标签区分）
+#[derive(Debug, Default)]
+pub struct KeyUsage {
+    used: Mutex<HashMap<(String, String), u64>>,
+}
+
+impl KeyUsage {
+    /// 累计用量（首次查询时在阻塞线程池中从数据库读取）
+    pub async fn used(&self, tool_id: &str, label: &str) -> u64 {
+        let (tool, key) = (tool_id.to_string(), label.to_string());
+        self.used_or_seed(tool_id, label, move || {
+            SESSION_MANAGER.get_key_token_usage(&tool, &key)
+        })
+        .await
+    }
+
+    async fn used_or_seed<F>(&self, tool_id: &str, label: &str, seed: F) -> u64
+    where
+        F: FnOnce() -> Result<u64> + Send + 'static,
+    {
+        let id = (tool_id.to_string(), label.to_string());
+        if let Some(&used) = self.lock().get(&id) {
+            return used;
+        }
+
+        let seeded = tokio::task::spawn_blocking(seed)
+            .await
+            .unwrap_or_else(|e| Err(anyhow!(e)));
+        match seeded {
+            // 读取期间其他请求已完成初始化时以已有值为准
+            Ok(used) => *self.lock().entry(id).or_insert(used),
+            Err(e) => {
+                tracing::warn!(tool_id = %tool_id, key = %label, error = ?e, "查询本地 Key 用量失败");
+                0
+            }
+        }
+    }
+
+    /// 记入一次请求的用量（尚未初始化的 Key 在首次查询时从数据库读取，这里无需记录）
+    pub fn add(&self, tool_id: &str, label: &str, tokens: u64) {
+        if let Some(used) = self
+            .lock()
+            .get_mut(&(tool_id.to_string(), label.to_string()))
+        {
+            *used += tokens;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), u64>> {
+        self.used.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 生成新的本地 Key（`dc-` + 48 位十六进制）
+pub fn generate_key() -> Result<String> {
+    let mut bytes = [0u8; 24];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow!("生成随机 Key 失败"))?;
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    Ok(format!("{KEY_PREFIX}{hex}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issued(label: &str, key: &str) -> LocalApiKey {
+        LocalApiKey {
+            key: key.to_string(),
+            label: label.to_string(),
+            expires_at: None,
+            token_quota: None,
+            allowed_profiles: Vec::new(),
+            revoked: false,
+            created_at: 0,
+        }
+    }
+
+    fn headers(key: &str) -> hyper::HeaderMap {
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {key}").parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_authenticate_issued_keys() {
+        let mut config = ToolProxyConfig::new(8787);
+        config.local_api_key = Some("admin".to_string());
+        config.local_api_keys = vec![issued("alice", "dc-alice"), issued("bob", "dc-bob")];
+        config.local_api_keys[1].revoked = true;
+
+        assert_eq!(authenticate(&headers("admin"), &config, 0), Ok(None));
+        assert_eq!(
+            authenticate(&headers("dc-alice"), &config, 0).map(|k| k.map(|k| k.label.as_str())),
+            Ok(Some("alice"))
+        );
+        assert_eq!(
+            authenticate(&headers("dc-bob"), &config, 0),
+            Err(AuthError::Revoked("bob".to_string()))
+        );
+        assert_eq!(
+            authenticate(&hyper::HeaderMap::new(), &config, 0),
+            Err(AuthError::Invalid)
+        );
+
+        config.local_api_keys[0].expires_at = Some(100);
+        assert!(authenticate(&headers("dc-alice"), &config, 99).is_ok());
+        assert_eq!(
+            authenticate(&headers("dc-alice"), &config, 100),
+            Err(AuthError::Expired("alice".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_authenticate_without_keys_allows_all() {
+        let config = ToolProxyConfig::new(8787);
+        assert_eq!(authenticate(&hyper::HeaderMap::new(), &config, 0), Ok(None));
+    }
+
+    #[test]
+    fn test_allowed_profiles() {
+        let mut key = issued("alice", "dc-alice");
+        assert!(key.allows_profile(None));
+
+        key.allowed_profiles = vec!["relay-a".to_string()];
+        assert!(key.allows_profile(Some("relay-a")));
+        assert!(!key.allows_profile(Some("relay-b")));
+        assert!(!key.allows_profile(None));
+    }
+
+    #[tokio::test]
+    async fn test_key_usage_seeds_once_and_accumulates() {
+        let usage = KeyUsage::default();
+
+        // 未初始化时不记录，首次查询从数据库读取
+        usage.add("codex", "alice", 10);
+        assert_eq!(usage.used_or_seed("codex", "alice", || Ok(100)).await, 100);
+
+        usage.add("codex", "alice", 25);
+        assert_eq!(
+            usage
+                .used_or_seed("codex", "alice", || panic!("只读取一次"))
+                .await,
+            125
+        );
+
+        // 读取失败时不缓存，下次重试
+        assert_eq!(
+            usage
+                .used_or_seed("codex", "bob", || Err(anyhow!("db locked")))
+                .await,
+            0
+        );
+        assert_eq!(usage.used_or_seed("codex", "bob", || Ok(7)).await, 7);
+        assert_eq!(
+            usage.used_or_seed("claude-code", "alice", || Ok(0)).await,
+            0
+        );
+    }
+
+    #[test]
+    fn test_generate_key() {
+        let key = generate_key().unwrap();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + 48);
+        assert_ne!(key, generate_key().unwrap());
+    }
+}
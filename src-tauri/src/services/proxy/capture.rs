@@ -0,0 +1,721 @@
+//! 请求/响应抓包与重放
+//!
+//! 开启抓包后，代理将每次上游请求记录到 `config_dir/captures/<tool_id>/`：
+//! - 当前文件为 `capture.jsonl`，超过大小上限后轮转为 `capture.1.jsonl`、`capture.2.jsonl` ...
+//! - 每个抓包文件旁有同名索引 `capture[.N].idx.jsonl`，记录摘要与行偏移，列表和查询不解析请求/响应体
+//! - 认证相关 headers 与 URL 中的 `key` 参数会被脱敏
+//! - 请求体/响应体超过上限时截断，截断的请求无法重放
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use super::headers::create_request_processor_for;
+use super::upstream::UpstreamPool;
+use crate::models::proxy_config::CaptureConfig;
+use crate::services::proxy_config_manager::ProxyConfigManager;
+use crate::utils::config::config_dir;
+
+/// 需要脱敏的 headers
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "x-goog-api-key",
+    "api-key",
+    "cookie",
+    "set-cookie",
+];
+
+/// 需要脱敏的 URL 查询参数
+const SENSITIVE_QUERY_PARAMS: &[&str] = &["key", "api_key"];
+
+const REDACTED: &str = "[REDACTED]";
+
+/// 串行化抓包文件写入与轮转
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 同一毫秒内的序号，用于生成唯一 ID
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// 单条抓包记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CaptureEntry {
+    pub id: String,
+    pub tool_id: String,
+    /// 请求时间（Unix 时间戳，毫秒）
+    pub timestamp: i64,
+    pub method: String,
+    /// 客户端请求路径（重放时使用）
+    pub path: String,
+    #[serde(default)]
+    pub query: Option<String>,
+    /// 实际转发的目标 URL（已脱敏）
+    pub target_url: String,
+    #[serde(default)]
+    pub profile_name: Option<String>,
+    /// 转发给上游的 headers（已脱敏）
+    pub request_headers: BTreeMap<String, String>,
+    /// 客户端原始请求体（协议转换前，重放时由处理器重新转换）
+    pub request_body: String,
+    #[serde(default)]
+    pub request_body_truncated: bool,
+    /// 上游响应状态码（连接失败时为空）
+    #[serde(default)]
+    pub response_status: Option<u16>,
+    #[serde(default)]
+    pub response_headers: BTreeMap<String, String>,
+    /// 普通响应体
+    #[serde(default)]
+    pub response_body: Option<String>,
+    /// SSE 响应的原始分块
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sse_chunks: Vec<String>,
+    #[serde(default)]
+    pub response_truncated: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// 请求耗时（毫秒，SSE 为整个流的耗时）
+    pub duration_ms: u64,
+}
+
+/// 抓包记录摘要（列表展示用）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaptureSummary {
+    pub id: String,
+    pub tool_id: String,
+    pub timestamp: i64,
+    pub method: String,
+    pub path: String,
+    pub profile_name: Option<String>,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl From<&CaptureEntry> for CaptureSummary {
+    fn from(entry: &CaptureEntry) -> Self {
+        Self {
+            id: entry.id.clone(),
+            tool_id: entry.tool_id.clone(),
+            timestamp: entry.timestamp,
+            method: entry.method.clone(),
+            path: entry.path.clone(),
+            profile_name: entry.profile_name.clone(),
+            response_status: entry.response_status,
+            error: entry.error.clone(),
+            duration_ms: entry.duration_ms,
+        }
+    }
+}
+
+/// 索引记录：摘要 + 记录在抓包文件中的字节偏移
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexRecord {
+    offset: u64,
+    #[serde(flatten)]
+    summary: CaptureSummary,
+}
+
+/// 重放结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+    pub target_url: String,
+    pub profile_name: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// 单个工具的抓包存储
+#[derive(Debug, Clone)]
+pub struct CaptureStore {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    max_files: u32,
+}
+
+impl CaptureStore {
+    /// 打开工具的抓包存储（`config_dir/captures/<tool_id>`）
+    pub fn for_tool(tool_id: &str, config: &CaptureConfig) -> Result<Self> {
+        let dir = config_dir()
+            .map_err(|e| anyhow!(e))?
+            .join("captures")
+            .join(tool_id);
+        Ok(Self::with_dir(dir, config))
+    }
+
+    pub fn with_dir(dir: PathBuf, config: &CaptureConfig) -> Self {
+        Self {
+            dir,
+            max_file_bytes: config.max_file_bytes,
+            max_files: config.max_files.max(1),
+        }
+    }
+
+    fn file_path(&self, index: u32) -> PathBuf {
+        if index == 0 {
+            self.dir.join("capture.jsonl")
+        } else {
+            self.dir.join(format!("capture.{index}.jsonl"))
+        }
+    }
+
+    fn index_path(&self, index: u32) -> PathBuf {
+        if index == 0 {
+            self.dir.join("capture.idx.jsonl")
+        } else {
+            self.dir.join(format!("capture.{index}.idx.jsonl"))
+        }
+    }
+
+    /// 追加一条记录（必要时先轮转），同时写入索引
+    pub fn append(&self, entry: &CaptureEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry).context("序列化抓包记录失败")?;
+        line.push(b'\n');
+
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        fs::create_dir_all(&self.dir).context("创建抓包目录失败")?;
+
+        let current = self.file_path(0);
+        let mut offset = fs::metadata(&current).map(|m| m.len()).unwrap_or(0);
+        if offset > 0 && offset + line.len() as u64 > self.max_file_bytes {
+            self.rotate()?;
+            offset = 0;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&current)
+            .context("打开抓包文件失败")?;
+        file.write_all(&line).context("写入抓包记录失败")?;
+
+        let record = IndexRecord {
+            offset,
+            summary: CaptureSummary::from(entry),
+        };
+        let mut index_line = serde_json::to_vec(&record).context("序列化抓包索引失败")?;
+        index_line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.index_path(0))
+            .and_then(|mut f| f.write_all(&index_line))
+            .context("写入抓包索引失败")?;
+        Ok(())
+    }
+
+    /// 轮转：capture.jsonl -> capture.1.jsonl -> ...，超出数量的最旧文件被删除（索引同步轮转）
+    fn rotate(&self) -> Result<()> {
+        for path in [
+            self.file_path(self.max_files - 1),
+            self.index_path(self.max_files - 1),
+        ] {
+            if path.exists() {
+                fs::remove_file(&path).context("删除旧抓包文件失败")?;
+            }
+        }
+        for index in (0..self.max_files - 1).rev() {
+            for (from, to) in [
+                (self.file_path(index), self.file_path(index + 1)),
+                (self.index_path(index), self.index_path(index + 1)),
+            ] {
+                if from.exists() {
+                    fs::rename(&from, to).context("轮转抓包文件失败")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取单个抓包文件的索引（按写入顺序）
+    ///
+    /// 没有索引的旧文件按行扫描，只解析摘要字段
+    fn read_index(&self, index: u32) -> Result<Vec<IndexRecord>> {
+        let index_path = self.index_path(index);
+        if index_path.exists() {
+            let file = File::open(&index_path).context("打开抓包索引失败")?;
+            let mut records = Vec::new();
+            for line in BufReader::new(file).lines() {
+                let line = line.context("读取抓包索引失败")?;
+                match serde_json::from_str::<IndexRecord>(&line) {
+                    Ok(record) => records.push(record),
+                    Err(e) => tracing::warn!("跳过无法解析的抓包索引: {}", e),
+                }
+            }
+            return Ok(records);
+        }
+
+        let path = self.file_path(index);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut reader = BufReader::new(File::open(&path).context("打开抓包文件失败")?);
+        let mut records = Vec::new();
+        let mut offset = 0u64;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line).context("读取抓包文件失败")?;
+            if read == 0 {
+                break;
+            }
+            match serde_json::from_str::<CaptureSummary>(line.trim_end()) {
+                Ok(summary) => records.push(IndexRecord { offset, summary }),
+                Err(e) => tracing::warn!("跳过无法解析的抓包记录: {}", e),
+            }
+            offset += read as u64;
+        }
+        Ok(records)
+    }
+
+    /// 列出记录摘要（最新的在前）
+    pub fn list(&self, limit: usize) -> Result<Vec<CaptureSummary>> {
+        let mut summaries = Vec::new();
+        for index in 0..self.max_files {
+            if summaries.len() >= limit {
+                break;
+            }
+            let remaining = limit - summaries.len();
+            summaries.extend(
+                self.read_index(index)?
+                    .into_iter()
+                    .rev()
+                    .take(remaining)
+                    .map(|r| r.summary),
+            );
+        }
+        Ok(summaries)
+    }
+
+    /// 获取单条记录（通过索引定位，只解析命中的一行）
+    pub fn get(&self, id: &str) -> Result<Option<CaptureEntry>> {
+        for index in 0..self.max_files {
+            let Some(record) = self
+                .read_index(index)?
+                .into_iter()
+                .find(|r| r.summary.id == id)
+            else {
+                continue;
+            };
+
+            let mut file = File::open(self.file_path(index)).context("打开抓包文件失败")?;
+            file.seek(SeekFrom::Start(record.offset))
+                .context("定位抓包记录失败")?;
+            let mut line = String::new();
+            BufReader::new(file)
+                .read_line(&mut line)
+                .context("读取抓包文件失败")?;
+            let entry = serde_json::from_str::<CaptureEntry>(line.trim_end())
+                .context("解析抓包记录失败")?;
+            return Ok(Some(entry));
+        }
+        Ok(None)
+    }
+
+    /// 删除所有抓包文件
+    pub fn clear(&self) -> Result<()> {
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir).context("删除抓包目录失败")?;
+        }
+        Ok(())
+    }
+}
+
+/// 进行中的抓包记录
+///
+/// 在请求发出前创建，响应结束（或流被丢弃）时写入存储
+pub struct CaptureRecorder {
+    store: CaptureStore,
+    entry: CaptureEntry,
+    max_body_bytes: usize,
+    response_bytes: usize,
+    started: Instant,
+    finished: bool,
+}
+
+impl CaptureRecorder {
+    /// 记录出站请求
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        store: CaptureStore,
+        config: &CaptureConfig,
+        tool_id: &str,
+        method: &str,
+        path: &str,
+        query: Option<&str>,
+        target_url: &str,
+        profile_name: Option<&str>,
+        headers: &reqwest::header::HeaderMap,
+        body: &[u8],
+    ) -> Self {
+        let (request_body, request_body_truncated) = truncate_body(body, config.max_body_bytes);
+
+        let entry = CaptureEntry {
+            id: next_capture_id(),
+            tool_id: tool_id.to_string(),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.map(redact_query),
+            target_url: redact_url(target_url),
+            profile_name: profile_name.map(|s| s.to_string()),
+            request_headers: redact_headers(headers),
+            request_body,
+            request_body_truncated,
+            ..Default::default()
+        };
+
+        Self {
+            store,
+            entry,
+            max_body_bytes: config.max_body_bytes,
+            response_bytes: 0,
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    /// 记录上游响应状态与 headers
+    pub fn set_response(&mut self, status: u16, headers: &reqwest::header::HeaderMap) {
+        self.entry.response_status = Some(status);
+        self.entry.response_headers = redact_headers(headers);
+    }
+
+    /// 记录普通响应体
+    pub fn set_response_body(&mut self, body: &[u8]) {
+        let (body, truncated) = truncate_body(body, self.max_body_bytes);
+        self.entry.response_body = Some(body);
+        self.entry.response_truncated = truncated;
+    }
+
+    /// 追加 SSE 分块（累计超过上限后只标记截断）
+    pub fn push_sse_chunk(&mut self, chunk: &[u8]) {
+        if self.entry.response_truncated {
+            return;
+        }
+        let remaining = self.max_body_bytes.saturating_sub(self.response_bytes);
+        let (text, truncated) = truncate_body(chunk, remaining);
+        self.response_bytes += chunk.len().min(remaining);
+        if !text.is_empty() {
+            self.entry.sse_chunks.push(text);
+        }
+        self.entry.response_truncated = truncated;
+    }
+
+    /// 记录错误（连接失败等）
+    pub fn set_error(&mut self, error: &str) {
+        self.entry.error = Some(error.to_string());
+    }
+
+    /// 写入存储
+    pub fn finish(mut self) {
+        self.write();
+    }
+
+    fn write(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.entry.duration_ms = self.started.elapsed().as_millis() as u64;
+
+        let store = self.store.clone();
+        let entry = std::mem::take(&mut self.entry);
+        let write = move || {
+            if let Err(e) = store.append(&entry) {
+                tracing::warn!(tool_id = %entry.tool_id, error = ?e, "写入抓包记录失败");
+            }
+        };
+
+        // 在 tokio 运行时中避免阻塞工作线程
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+}
+
+impl Drop for CaptureRecorder {
+    fn drop(&mut self) {
+        self.write();
+    }
+}
+
+/// 重放抓包记录：使用工具当前的代理配置和上游重新发送请求
+///
+/// 重放请求直接发往上游，不经过代理实例的处理链路，
+/// 不会记录会话、用量和对话内容，也不会再次被抓包；返回上游的原始响应（不做协议转换）
+pub async fn replay_capture(tool_id: &str, capture_id: &str) -> Result<ReplayResult> {
+    let config = ProxyConfigManager::new()?
+        .get_config(tool_id)?
+        .ok_or_else(|| anyhow!("未找到 {} 的代理配置", tool_id))?;
+
+    let store = CaptureStore::for_tool(tool_id, &config.capture)?;
+    let entry = store
+        .get(capture_id)?
+        .ok_or_else(|| anyhow!("未找到抓包记录: {}", capture_id))?;
+
+    if entry.request_body_truncated {
+        anyhow::bail!("该记录的请求体已被截断，无法重放");
+    }
+
+    let upstream = UpstreamPool::from_config(tool_id, &config)
+        .candidates()
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("{} 没有可用的上游", tool_id))?;
+
+    // 记录中的认证信息已脱敏，由处理器替换为当前上游的 Key
+    let mut original_headers = hyper::HeaderMap::new();
+    for (name, value) in &entry.request_headers {
+        if let (Ok(name), Ok(value)) = (
+            hyper::header::HeaderName::from_bytes(name.as_bytes()),
+            hyper::header::HeaderValue::from_str(value),
+        ) {
+            original_headers.insert(name, value);
+        }
+    }
+
+    // 与代理一致：按上游协议选择处理器（记录的是客户端原始请求体）
+    let protocol = upstream.protocol.unwrap_or(config.upstream_protocol);
+    let processor = create_request_processor_for(tool_id, protocol)?;
+    let processed = processor
+        .process_outgoing_request(
+            upstream.base_url.trim_end_matches('/'),
+            &upstream.api_key,
+            &entry.path,
+            entry.query.as_deref(),
+            &original_headers,
+            entry.request_body.as_bytes(),
+        )
+        .await
+        .context("处理重放请求失败")?;
+
+    let method = reqwest::Method::from_bytes(entry.method.as_bytes()).context("无效的请求方法")?;
+    let mut builder = super::client_pool::UPSTREAM_CLIENTS
+        .get(&processed.target_url)?
+        .request(method, &processed.target_url);
+    for (name, value) in processed.headers.iter() {
+        if name == "accept-encoding"
+            || name == "content-length"
+            || name == super::utils::loop_detector::HOP_HEADER
+        {
+            continue;
+        }
+        builder = builder.header(name, value);
+    }
+    if !processed.body.is_empty() {
+        builder = builder.body(processed.body);
+    }
+
+    let started = Instant::now();
+    let response = builder.send().await.context("重放请求失败")?;
+    let status = response.status().as_u16();
+    let headers = redact_headers(response.headers());
+    let body = response.bytes().await.context("读取重放响应失败")?;
+
+    tracing::info!(
+        tool_id = %tool_id,
+        capture_id = %capture_id,
+        status = status,
+        "已重放抓包请求"
+    );
+
+    Ok(ReplayResult {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        target_url: redact_url(&processed.target_url),
+        profile_name: upstream.profile_name,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+fn next_capture_id() -> String {
+    let seq = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    format!("{}-{}", chrono::Utc::now().timestamp_millis(), seq)
+}
+
+/// 截断并转换为字符串（非 UTF-8 内容按有损方式转换）
+fn truncate_body(body: &[u8], max: usize) -> (String, bool) {
+    if body.len() <= max {
+        (String::from_utf8_lossy(body).into_owned(), false)
+    } else {
+        (String::from_utf8_lossy(&body[..max]).into_owned(), true)
+    }
+}
+
+/// 脱敏 headers（多值 header 以 ", " 连接）
+pub fn redact_headers(headers: &reqwest::header::HeaderMap) -> BTreeMap<String, String> {
+    let mut result: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in headers.iter() {
+        let key = name.as_str().to_string();
+        let value = if SENSITIVE_HEADERS.contains(&name.as_str()) {
+            REDACTED.to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+        result
+            .entry(key)
+            .and_modify(|v| {
+                v.push_str(", ");
+                v.push_str(&value);
+            })
+            .or_insert(value);
+    }
+    result
+}
+
+/// 脱敏查询字符串中的敏感参数
+fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if SENSITIVE_QUERY_PARAMS.contains(&name) => {
+                format!("{name}={REDACTED}")
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// 脱敏 URL 中的敏感查询参数
+fn redact_url(url: &str) -> String {
+    match url.split_once('?') {
+        Some((base, query)) => format!("{base}?{}", redact_query(query)),
+        None => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    fn entry(id: &str, body_len: usize) -> CaptureEntry {
+        CaptureEntry {
+            id: id.to_string(),
+            tool_id: "claude-code".to_string(),
+            method: "POST".to_string(),
+            path: "/v1/messages".to_string(),
+            request_body: "x".repeat(body_len),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_redaction() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer sk-secret"),
+        );
+        headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+
+        let redacted = redact_headers(&headers);
+        assert_eq!(redacted["authorization"], REDACTED);
+        assert_eq!(redacted["anthropic-version"], "2023-06-01");
+
+        assert_eq!(
+            redact_url("https://example.com/v1beta/models/x:generateContent?alt=sse&key=abc"),
+            "https://example.com/v1beta/models/x:generateContent?alt=sse&key=[REDACTED]"
+        );
+    }
+
+    #[test]
+    fn test_rotation_and_lookup() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = CaptureConfig {
+            enabled: true,
+            max_body_bytes: 1024,
+            max_file_bytes: 300,
+            max_files: 2,
+        };
+        let store = CaptureStore::with_dir(temp.path().to_path_buf(), &config);
+
+        for i in 0..4 {
+            store.append(&entry(&format!("id-{i}"), 150)).unwrap();
+        }
+
+        // 每个文件只能容纳一条记录，最多保留 2 个文件
+        let list = store.list(10).unwrap();
+        let ids: Vec<&str> = list.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["id-3", "id-2"]);
+
+        assert!(store.get("id-2").unwrap().is_some());
+        assert!(store.get("id-0").unwrap().is_none());
+
+        store.clear().unwrap();
+        assert!(store.list(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_lookup() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = CaptureConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let store = CaptureStore::with_dir(temp.path().to_path_buf(), &config);
+
+        for i in 0..3 {
+            store.append(&entry(&format!("id-{i}"), 10 + i)).unwrap();
+        }
+        assert!(store.index_path(0).exists());
+        assert_eq!(store.get("id-1").unwrap().unwrap(), entry("id-1", 11));
+
+        // 没有索引的旧文件按行扫描
+        fs::remove_file(store.index_path(0)).unwrap();
+        let ids: Vec<String> = store.list(2).unwrap().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec!["id-2", "id-1"]);
+        assert_eq!(store.get("id-2").unwrap().unwrap(), entry("id-2", 12));
+    }
+
+    #[test]
+    fn test_recorder_truncates_sse() {
+        let temp = tempfile::tempdir().unwrap();
+        let config = CaptureConfig {
+            enabled: true,
+            max_body_bytes: 8,
+            ..Default::default()
+        };
+        let store = CaptureStore::with_dir(temp.path().to_path_buf(), &config);
+
+        let mut recorder = CaptureRecorder::new(
+            store.clone(),
+            &config,
+            "claude-code",
+            "POST",
+            "/v1/messages",
+            None,
+            "https://api.example.com/v1/messages",
+            None,
+            &HeaderMap::new(),
+            b"{}",
+        );
+        recorder.set_response(200, &HeaderMap::new());
+        recorder.push_sse_chunk(b"data: 1\n\n");
+        recorder.push_sse_chunk(b"data: 2\n\n");
+        recorder.finish();
+
+        let list = store.list(10).unwrap();
+        let saved = store.get(&list[0].id).unwrap().unwrap();
+        assert_eq!(saved.sse_chunks, vec!["data: 1\n".to_string()]);
+        assert!(saved.response_truncated);
+        assert!(!saved.request_body_truncated);
+    }
+}
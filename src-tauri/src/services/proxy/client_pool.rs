@@ -0,0 +1,131 @@
+//! 上游 HTTP 客户端池
+//!
+//! 按上游源站（scheme + host + port）复用 `reqwest::Client`：
+//! - 保持长连接，避免每次请求重新握手 TLS
+//! - 上游通过 ALPN 支持时自动使用 HTTP/2
+//! - 出站代理沿用 `http_client` 的设置，全局代理配置变化后重建
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use url::Url;
+
+use crate::http_client;
+use crate::ProxyService;
+
+/// 全局上游客户端池（所有代理实例共享）
+pub static UPSTREAM_CLIENTS: Lazy<UpstreamClients> = Lazy::new(UpstreamClients::new);
+
+/// 上游客户端池
+#[derive(Debug, Default)]
+pub struct UpstreamClients {
+    cache: Mutex<ClientCache>,
+}
+
+#[derive(Debug, Default)]
+struct ClientCache {
+    /// 构建客户端时的出站代理设置 (代理 URL, NO_PROXY)
+    proxy_settings: Option<(Option<String>, Option<String>)>,
+    clients: HashMap<String, Client>,
+}
+
+impl UpstreamClients {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取目标 URL 对应上游的共享客户端
+    pub fn get(&self, target_url: &str) -> Result<Client> {
+        let proxy_settings = Some(current_proxy_settings());
+        let key = origin_key(target_url);
+
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.proxy_settings != proxy_settings {
+            if !cache.clients.is_empty() {
+                tracing::info!("出站代理设置已变化，重建上游客户端");
+            }
+            cache.clients.clear();
+            cache.proxy_settings = proxy_settings;
+        }
+
+        if let Some(client) = cache.clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        let client = build_upstream_client()?;
+        cache.clients.insert(key, client.clone());
+        Ok(client)
+    }
+}
+
+fn current_proxy_settings() -> (Option<String>, Option<String>) {
+    let no_proxy = std::env::var("NO_PROXY")
+        .or_else(|_| std::env::var("no_proxy"))
+        .ok();
+    (ProxyService::get_current_proxy(), no_proxy)
+}
+
+/// 上游源站标识（解析失败时使用原始 URL）
+fn origin_key(target_url: &str) -> String {
+    match Url::parse(target_url) {
+        Ok(url) => format!(
+            "{}://{}:{}",
+            url.scheme(),
+            url.host_str().unwrap_or_default(),
+            url.port_or_known_default().unwrap_or_default()
+        ),
+        Err(_) => target_url.to_string(),
+    }
+}
+
+/// 构建上游客户端
+///
+/// 不设置总超时：SSE 流式响应可能持续数分钟
+fn build_upstream_client() -> Result<Client> {
+    let builder = Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .pool_max_idle_per_host(32)
+        .tcp_keepalive(Duration::from_secs(60))
+        .http2_adaptive_window(true)
+        .http2_keep_alive_interval(Duration::from_secs(30))
+        .http2_keep_alive_while_idle(true);
+
+    http_client::apply_proxy(builder)
+        .map_err(anyhow::Error::msg)?
+        .build()
+        .context("创建上游 HTTP 客户端失败")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_key() {
+        assert_eq!(
+            origin_key("https://api.example.com/v1/messages?beta=true"),
+            "https://api.example.com:443"
+        );
+        assert_eq!(
+            origin_key("http://127.0.0.1:3000/v1/chat/completions"),
+            "http://127.0.0.1:3000"
+        );
+        assert_eq!(origin_key("not a url"), "not a url");
+    }
+
+    #[test]
+    fn test_clients_reused_per_origin() {
+        let clients = UpstreamClients::new();
+        clients.get("https://a.example.com/v1/messages").unwrap();
+        clients.get("https://a.example.com/v1/models").unwrap();
+        clients.get("https://b.example.com/v1/messages").unwrap();
+
+        let cache = clients.cache.lock().unwrap();
+        assert_eq!(cache.clients.len(), 2);
+    }
+}
@@ -0,0 +1,447 @@
+//! 统一网关
+//!
+//! 在单一端口上接收所有工具的请求，分发到对应工具的代理实例：
+//! - 路径前缀优先：`/claude-code/v1/messages` → claude-code，转发时去掉前缀（自定义工具同样以 ID 为前缀）
+//! - 无前缀时按请求特征推断（Anthropic 头、`/responses`、Gemini 路径等）
+//! - 仍无法判断且仅有一个工具运行时，转发到该工具
+//!
+//! 鉴权、来源地址过滤、上游、限流与会话仍由各工具的代理实例按自身配置处理
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use hyper::body::Incoming;
+use hyper::{Request, Response, Uri};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use super::ip_filter::IpFilter;
+use super::listener::{self, ConnectionHandler, ListenSpec, ServerHandle};
+use super::proxy_instance::ToolRoute;
+use super::utils::body::BoxBody;
+use super::utils::error_responses;
+use crate::models::proxy_config::GatewayConfig;
+
+/// 网关内置支持的工具（同时作为路径前缀）
+pub const GATEWAY_TOOLS: [&str; 3] = ["claude-code", "codex", "gemini-cli"];
+
+/// 网关运行状态
+#[derive(Debug, Clone, Serialize)]
+pub struct GatewayStatus {
+    pub running: bool,
+    pub port: u16,
+    pub tls_port: Option<u16>,
+    /// 当前可转发的工具（对应工具代理已启动）
+    pub tools: Vec<String>,
+}
+
+/// 统一网关
+pub struct Gateway {
+    state: GatewayState,
+    server_handle: RwLock<Option<ServerHandle>>,
+}
+
+/// 网关连接共享的状态
+#[derive(Clone)]
+struct GatewayState {
+    config: Arc<RwLock<GatewayConfig>>,
+    routes: Arc<RwLock<BTreeMap<String, ToolRoute>>>,
+    ip_filter: Arc<RwLock<Arc<IpFilter>>>,
+}
+
+impl Gateway {
+    pub fn new(config: GatewayConfig) -> Self {
+        let ip_filter = IpFilter::new(&config.ip_filter).unwrap_or_else(|e| {
+            tracing::error!(error = ?e, "网关来源地址过滤配置无效，仅允许本机连接");
+            IpFilter::loopback_only()
+        });
+        Self {
+            state: GatewayState {
+                config: Arc::new(RwLock::new(config)),
+                routes: Arc::new(RwLock::new(BTreeMap::new())),
+                ip_filter: Arc::new(RwLock::new(Arc::new(ip_filter))),
+            },
+            server_handle: RwLock::new(None),
+        }
+    }
+
+    /// 注册工具的请求处理入口（工具代理启动时调用）
+    pub async fn register(&self, tool_id: &str, route: ToolRoute) {
+        self.state
+            .routes
+            .write()
+            .await
+            .insert(tool_id.to_string(), route);
+    }
+
+    /// 移除工具的请求处理入口（工具代理停止时调用）
+    pub async fn unregister(&self, tool_id: &str) {
+        self.state.routes.write().await.remove(tool_id);
+    }
+
+    /// 启动网关
+    pub async fn start(&self) -> Result<()> {
+        let mut server = self.server_handle.write().await;
+        if server.is_some() {
+            anyhow::bail!("统一网关已在运行");
+        }
+
+        let config = self.state.config.read().await.clone();
+        IpFilter::new(&config.ip_filter).context("来源地址过滤配置无效")?;
+
+        let spec = listen_spec(&config);
+        let listeners = listener::bind(&spec).await?;
+        tracing::info!(addr = %spec, "统一网关启动成功");
+
+        *server = Some(listener::serve(listeners, Arc::new(self.state.clone())));
+        Ok(())
+    }
+
+    /// 停止网关（等待进行中的请求完成，超时后强制关闭）
+    pub async fn stop(&self) -> Result<()> {
+        let Some(server) = self.server_handle.write().await.take() else {
+            return Ok(());
+        };
+
+        let drain_timeout = Duration::from_secs(self.state.config.read().await.drain_timeout_secs);
+        server.close_listener().await.drain(drain_timeout).await;
+        tracing::info!("统一网关已停止");
+        Ok(())
+    }
+
+    pub async fn is_running(&self) -> bool {
+        self.server_handle.read().await.is_some()
+    }
+
+    pub async fn status(&self) -> GatewayStatus {
+        let config = self.state.config.read().await;
+        GatewayStatus {
+            running: self.is_running().await,
+            port: config.port,
+            tls_port: config.tls.resolve_port(config.port),
+            tools: self.state.routes.read().await.keys().cloned().collect(),
+        }
+    }
+
+    /// 更新配置（无需重启；监听地址变化时切换监听，失败时保持原配置）
+    pub async fn update_config(&self, new_config: GatewayConfig) -> Result<()> {
+        let old_config = self.state.config.read().await.clone();
+        let ip_filter = IpFilter::new(&new_config.ip_filter).context("来源地址过滤配置无效")?;
+
+        let old_spec = listen_spec(&old_config);
+        let new_spec = listen_spec(&new_config);
+        if old_spec != new_spec {
+            listener::swap(
+                &mut *self.server_handle.write().await,
+                &old_spec,
+                &new_spec,
+                Arc::new(self.state.clone()),
+                Duration::from_secs(new_config.drain_timeout_secs),
+            )
+            .await
+            .context("切换网关监听地址失败")?;
+        }
+
+        *self.state.ip_filter.write().await = Arc::new(ip_filter);
+        *self.state.config.write().await = new_config;
+        tracing::info!("统一网关配置已更新");
+        Ok(())
+    }
+}
+
+fn listen_spec(config: &GatewayConfig) -> ListenSpec {
+    ListenSpec {
+        port: config.port,
+        allow_public: config.allow_public,
+        tls: config.tls.clone(),
+    }
+}
+
+#[async_trait]
+impl ConnectionHandler for GatewayState {
+    fn name(&self) -> &str {
+        "gateway"
+    }
+
+    async fn permits(&self, peer: SocketAddr) -> bool {
+        self.ip_filter.read().await.permits(peer.ip())
+    }
+
+    async fn handle(&self, mut req: Request<Incoming>, peer: SocketAddr) -> Response<BoxBody> {
+        let (tool_id, route) = {
+            let routes = self.routes.read().await;
+            let path = req.uri().path();
+            let resolved = routes
+                .keys()
+                .filter(|id| !GATEWAY_TOOLS.contains(&id.as_str()))
+                .find_map(|id| strip_tool_prefix(path, id).map(|rest| (id.clone(), Some(rest))))
+                .or_else(|| {
+                    resolve_tool(path, req.headers()).map(|(id, rest)| (id.to_string(), rest))
+                })
+                // 仅有一个工具运行时，无法判断的请求都交给它
+                .or_else(|| match routes.len() {
+                    1 => routes.keys().next().map(|id| (id.clone(), None)),
+                    _ => None,
+                });
+            let Some((tool_id, stripped)) = resolved else {
+                return error_responses::gateway_route_not_found(path);
+            };
+            let Some(route) = routes.get(&tool_id).cloned() else {
+                return error_responses::tool_not_running(&tool_id);
+            };
+            // 目标工具自身的来源地址限制同样适用（仅本机的工具不因网关公开而暴露）
+            if !route.permits_peer(peer).await {
+                tracing::warn!(tool_id = %tool_id, peer = %peer, "网关拒绝未授权地址访问工具");
+                return error_responses::peer_not_allowed(&tool_id);
+            }
+
+            if let Some(path) = stripped {
+                *req.uri_mut() = replace_path(req.uri(), &path);
+            }
+            (tool_id, route)
+        };
+
+        tracing::debug!(tool_id = %tool_id, path = %req.uri().path(), "网关转发请求");
+        route.dispatch(req).await
+    }
+}
+
+/// 判断请求所属的工具
+///
+/// 返回工具 ID，以及去掉工具前缀后的路径（按请求特征推断时为 None，路径保持不变）
+pub fn resolve_tool(
+    path: &str,
+    headers: &hyper::HeaderMap,
+) -> Option<(&'static str, Option<String>)> {
+    for tool_id in GATEWAY_TOOLS {
+        if let Some(rest) = strip_tool_prefix(path, tool_id) {
+            return Some((tool_id, Some(rest)));
+        }
+    }
+
+    infer_tool(path, headers).map(|tool_id| (tool_id, None))
+}
+
+/// 去掉 `/<tool_id>` 前缀（前缀须为完整路径段）
+fn strip_tool_prefix(path: &str, tool_id: &str) -> Option<String> {
+    let rest = path.strip_prefix('/')?.strip_prefix(tool_id)?;
+    if rest.is_empty() {
+        Some("/".to_string())
+    } else if rest.starts_with('/') {
+        Some(rest.to_string())
+    } else {
+        None
+    }
+}
+
+/// 按请求特征推断工具
+fn infer_tool(path: &str, headers: &hyper::HeaderMap) -> Option<&'static str> {
+    if headers.contains_key("anthropic-version") || path.starts_with("/v1/messages") {
+        return Some("claude-code");
+    }
+    if headers.contains_key("x-goog-api-key")
+        || path.starts_with("/v1beta/")
+        || path.contains(":generateContent")
+        || path.contains(":streamGenerateContent")
+    {
+        return Some("gemini-cli");
+    }
+    if path.ends_with("/responses") || path.contains("/responses/") {
+        return Some("codex");
+    }
+    None
+}
+
+/// 替换请求路径（保留查询参数）
+fn replace_path(uri: &Uri, path: &str) -> Uri {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path.to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    match path_and_query.parse() {
+        Ok(pq) => {
+            parts.path_and_query = Some(pq);
+            Uri::from_parts(parts).unwrap_or_else(|_| uri.clone())
+        }
+        Err(_) => uri.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::proxy_config::ToolProxyConfig;
+    use crate::services::proxy::headers::create_request_processor_for;
+    use crate::services::proxy::proxy_instance::ProxyInstance;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> hyper::HeaderMap {
+        let mut headers = hyper::HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_resolve_tool_by_prefix() {
+        let empty = hyper::HeaderMap::new();
+        assert_eq!(
+            resolve_tool("/claude-code/v1/messages", &empty),
+            Some(("claude-code", Some("/v1/messages".to_string())))
+        );
+        assert_eq!(
+            resolve_tool("/codex/v1/responses", &empty),
+            Some(("codex", Some("/v1/responses".to_string())))
+        );
+        assert_eq!(
+            resolve_tool("/gemini-cli", &empty),
+            Some(("gemini-cli", Some("/".to_string())))
+        );
+        // 前缀须为完整路径段
+        assert_eq!(resolve_tool("/codex-beta/v1/models", &empty), None);
+    }
+
+    #[test]
+    fn test_resolve_tool_by_request_shape() {
+        let empty = hyper::HeaderMap::new();
+        assert_eq!(
+            resolve_tool("/v1/messages", &empty),
+            Some(("claude-code", None))
+        );
+        assert_eq!(
+            resolve_tool(
+                "/v1/models",
+                &headers(&[("anthropic-version", "2023-06-01")])
+            ),
+            Some(("claude-code", None))
+        );
+        assert_eq!(resolve_tool("/v1/responses", &empty), Some(("codex", None)));
+        assert_eq!(
+            resolve_tool(
+                "/v1beta/models/gemini-2.5-pro:streamGenerateContent",
+                &empty
+            ),
+            Some(("gemini-cli", None))
+        );
+        assert_eq!(
+            resolve_tool("/v1/models", &headers(&[("x-goog-api-key", "key")])),
+            Some(("gemini-cli", None))
+        );
+        assert_eq!(resolve_tool("/v1/models", &empty), None);
+    }
+
+    #[test]
+    fn test_replace_path_keeps_query() {
+        let uri: Uri = "/gemini-cli/v1beta/models?alt=sse".parse().unwrap();
+        assert_eq!(
+            replace_path(&uri, "/v1beta/models").to_string(),
+            "/v1beta/models?alt=sse"
+        );
+    }
+
+    /// 启动一个返回请求路径的上游
+    async fn echo_path_upstream() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]);
+                    let path = request.split(' ').nth(1).unwrap_or("").to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{path}",
+                        path.len()
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        addr
+    }
+
+    async fn free_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[tokio::test]
+    async fn test_gateway_routes_to_running_tools() {
+        let upstream = echo_path_upstream().await;
+        let mut tool_config = ToolProxyConfig::new(free_port().await);
+        tool_config.real_api_key = Some("sk-test".to_string());
+        tool_config.real_base_url = Some(format!("http://{upstream}"));
+        let processor =
+            create_request_processor_for("claude-code", tool_config.upstream_protocol).unwrap();
+        let instance = ProxyInstance::new("claude-code".to_string(), tool_config, processor);
+
+        let port = free_port().await;
+        let gateway = Gateway::new(GatewayConfig {
+            port,
+            ..GatewayConfig::default()
+        });
+        gateway.register("claude-code", instance.route()).await;
+        gateway.start().await.unwrap();
+
+        let response = reqwest::get(format!("http://127.0.0.1:{port}/claude-code/v1/models?x=1"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "/v1/models?x=1");
+
+        // 仅一个工具运行时，无前缀请求交给该工具
+        let response = reqwest::get(format!("http://127.0.0.1:{port}/v1/models"))
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "/v1/models");
+
+        // 目标工具未运行
+        let response = reqwest::get(format!("http://127.0.0.1:{port}/codex/v1/responses"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 503);
+
+        gateway.unregister("claude-code").await;
+        let response = reqwest::get(format!("http://127.0.0.1:{port}/v1/models"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+
+        gateway.stop().await.unwrap();
+        assert!(!gateway.is_running().await);
+    }
+
+    #[tokio::test]
+    async fn test_tool_route_enforces_tool_peer_restrictions() {
+        let mut tool_config = ToolProxyConfig::new(free_port().await);
+        tool_config.real_api_key = Some("sk-test".to_string());
+        tool_config.real_base_url = Some("http://127.0.0.1:1".to_string());
+        let processor =
+            create_request_processor_for("claude-code", tool_config.upstream_protocol).unwrap();
+        let instance =
+            ProxyInstance::new("claude-code".to_string(), tool_config.clone(), processor);
+        let route = instance.route();
+        let lan: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+        let local: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+
+        // 仅本机的工具拒绝经网关转发的局域网请求
+        assert!(route.permits_peer(local).await);
+        assert!(!route.permits_peer(lan).await);
+
+        tool_config.allow_public = true;
+        instance.update_config(tool_config.clone()).await.unwrap();
+        assert!(route.permits_peer(lan).await);
+
+        tool_config.ip_filter.deny = vec!["192.168.1.0/24".to_string()];
+        instance.update_config(tool_config).await.unwrap();
+        assert!(!route.permits_peer(lan).await);
+    }
+}
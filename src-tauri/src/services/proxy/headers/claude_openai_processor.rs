@@ -0,0 +1,73 @@
+// Claude Code -> OpenAI Chat Completions 协议转换处理器
+
+use super::{ClaudeHeadersProcessor, ProcessedRequest, RequestProcessor};
+use crate::services::proxy::translate::anthropic_openai::{
+    messages_to_chat_request, ChatToMessagesTranslator,
+};
+use crate::services::proxy::translate::openai_endpoint;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::HeaderMap as HyperHeaderMap;
+
+/// Claude Code 连接 OpenAI 兼容上游的请求处理器
+///
+/// 在 `ClaudeHeadersProcessor` 的基础上（会话识别、认证替换）：
+/// - `/v1/messages` 请求转换为 `/v1/chat/completions` 请求
+/// - 移除 `anthropic-*` headers
+/// - 附带 `ChatToMessagesTranslator` 将响应转换回 Anthropic 格式
+///
+/// 其他路径（如 `/v1/messages/count_tokens`）原样透传
+#[derive(Debug)]
+pub struct ClaudeOpenAIProcessor;
+
+#[async_trait]
+impl RequestProcessor for ClaudeOpenAIProcessor {
+    fn tool_id(&self) -> &str {
+        "claude-code"
+    }
+
+    async fn process_outgoing_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        path: &str,
+        query: Option<&str>,
+        original_headers: &HyperHeaderMap,
+        body: &[u8],
+    ) -> Result<ProcessedRequest> {
+        let mut processed = ClaudeHeadersProcessor
+            .process_outgoing_request(base_url, api_key, path, query, original_headers, body)
+            .await?;
+
+        if path != "/v1/messages" {
+            return Ok(processed);
+        }
+
+        // 1. 构建目标 URL
+        processed.target_url = openai_endpoint(&processed.base_url, "/chat/completions");
+
+        // 2. 移除 Anthropic 专用 headers
+        let anthropic_headers: Vec<_> = processed
+            .headers
+            .keys()
+            .filter(|name| name.as_str().starts_with("anthropic-"))
+            .cloned()
+            .collect();
+        for name in anthropic_headers {
+            processed.headers.remove(name);
+        }
+
+        // 3. 转换请求体
+        let request: serde_json::Value =
+            serde_json::from_slice(body).context("解析 Messages 请求体失败")?;
+        let converted = messages_to_chat_request(&request)?;
+        processed.body = Bytes::from(serde_json::to_vec(&converted)?);
+
+        // 4. 附带响应转换器
+        let model = request["model"].as_str().unwrap_or_default();
+        processed.response_translator = Some(Box::new(ChatToMessagesTranslator::new(model)));
+
+        Ok(processed)
+    }
+}
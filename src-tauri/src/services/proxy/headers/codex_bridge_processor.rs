@@ -0,0 +1,101 @@
+// Codex wire_api 桥接处理器（Responses API <-> Chat Completions）
+
+use super::{CodexHeadersProcessor, ProcessedRequest, RequestProcessor};
+use crate::models::proxy_config::UpstreamProtocol;
+use crate::services::proxy::translate::responses_chat::{
+    chat_to_responses_request, responses_to_chat_request, ChatToResponsesTranslator,
+    ResponsesToChatTranslator,
+};
+use crate::services::proxy::translate::ResponseTranslator;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::HeaderMap as HyperHeaderMap;
+
+const RESPONSES_PATH: &str = "/responses";
+const CHAT_PATH: &str = "/chat/completions";
+
+/// Codex 连接不同 wire_api 上游的请求处理器
+///
+/// 在 `CodexHeadersProcessor` 的基础上，根据请求路径与上游协议决定是否转换：
+/// - `.../responses` 请求 + Chat 上游：转换为 `.../chat/completions`
+/// - `.../chat/completions` 请求 + Responses 上游：转换为 `.../responses`
+///
+/// 协议一致或其他路径（如 `/models`）原样透传
+#[derive(Debug)]
+pub struct CodexBridgeProcessor {
+    upstream: UpstreamProtocol,
+}
+
+impl CodexBridgeProcessor {
+    pub fn new(upstream: UpstreamProtocol) -> Self {
+        Self { upstream }
+    }
+}
+
+#[async_trait]
+impl RequestProcessor for CodexBridgeProcessor {
+    fn tool_id(&self) -> &str {
+        "codex"
+    }
+
+    async fn process_outgoing_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        path: &str,
+        query: Option<&str>,
+        original_headers: &HyperHeaderMap,
+        body: &[u8],
+    ) -> Result<ProcessedRequest> {
+        let route = match self.upstream {
+            UpstreamProtocol::OpenaiChat => path
+                .strip_suffix(RESPONSES_PATH)
+                .map(|prefix| (prefix, CHAT_PATH)),
+            UpstreamProtocol::OpenaiResponses => path
+                .strip_suffix(CHAT_PATH)
+                .map(|prefix| (prefix, RESPONSES_PATH)),
+            _ => None,
+        };
+        let Some((prefix, target_endpoint)) = route else {
+            return CodexHeadersProcessor
+                .process_outgoing_request(base_url, api_key, path, query, original_headers, body)
+                .await;
+        };
+
+        // 1. 转换请求体并创建响应转换器
+        let request: serde_json::Value =
+            serde_json::from_slice(body).context("解析 Codex 请求体失败")?;
+        let model = request["model"].as_str().unwrap_or_default();
+        let (converted, translator): (_, Box<dyn ResponseTranslator>) = match self.upstream {
+            UpstreamProtocol::OpenaiChat => {
+                let (converted, custom_tools) = responses_to_chat_request(&request)?;
+                (
+                    converted,
+                    Box::new(ChatToResponsesTranslator::new(model, custom_tools)),
+                )
+            }
+            _ => (
+                chat_to_responses_request(&request)?,
+                Box::new(ResponsesToChatTranslator::new(model)),
+            ),
+        };
+        let converted = Bytes::from(serde_json::to_vec(&converted)?);
+
+        // 2. 按转换后的路径构建 URL 与 headers（沿用 Codex 的 /v1 去重逻辑）
+        let target_path = format!("{prefix}{target_endpoint}");
+        let mut processed = CodexHeadersProcessor
+            .process_outgoing_request(
+                base_url,
+                api_key,
+                &target_path,
+                query,
+                original_headers,
+                &converted,
+            )
+            .await?;
+        processed.response_translator = Some(translator);
+
+        Ok(processed)
+    }
+}
@@ -83,8 +83,10 @@ impl RequestProcessor for CodexHeadersProcessor {
         // 4. 返回处理后的请求
         Ok(ProcessedRequest {
             target_url,
+            base_url: base.to_string(),
             headers,
             body: Bytes::copy_from_slice(body),
+            response_translator: None,
         })
     }
 
@@ -0,0 +1,135 @@
+// 自定义工具请求处理器（按工具描述文件处理）
+
+use super::{ProcessedRequest, RequestProcessor};
+use crate::models::tool_descriptor::ToolDescriptor;
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::HeaderMap as HyperHeaderMap;
+use reqwest::header::{HeaderMap as ReqwestHeaderMap, HeaderName};
+
+/// 常见的认证 headers（转发前统一移除客户端携带的本地密钥）
+const AUTH_HEADERS: [&str; 3] = ["authorization", "x-api-key", "x-goog-api-key"];
+
+/// 自定义工具请求处理器
+///
+/// 按描述文件中的规则转换请求：
+/// - URL 构建：`url.base_path` / `url.strip_prefix`
+/// - 认证方式：`auth` 声明的 header 格式
+#[derive(Debug)]
+pub struct DescriptorProcessor {
+    descriptor: ToolDescriptor,
+}
+
+impl DescriptorProcessor {
+    pub fn new(descriptor: ToolDescriptor) -> Self {
+        Self { descriptor }
+    }
+}
+
+#[async_trait]
+impl RequestProcessor for DescriptorProcessor {
+    fn tool_id(&self) -> &str {
+        &self.descriptor.id
+    }
+
+    async fn process_outgoing_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        path: &str,
+        query: Option<&str>,
+        original_headers: &HyperHeaderMap,
+        body: &[u8],
+    ) -> Result<ProcessedRequest> {
+        // 1. 按描述规则构建目标 URL
+        let target_url = self.descriptor.url.build(base_url, path, query);
+
+        // 2. 处理 headers（复制非认证 headers）
+        let auth_header = self.descriptor.auth.header_name();
+        let mut headers = ReqwestHeaderMap::new();
+        for (name, value) in original_headers.iter() {
+            let name_str = name.as_str();
+            if name_str.eq_ignore_ascii_case("host")
+                || name_str.eq_ignore_ascii_case(&auth_header)
+                || AUTH_HEADERS
+                    .iter()
+                    .any(|h| name_str.eq_ignore_ascii_case(h))
+            {
+                continue;
+            }
+            headers.insert(name.clone(), value.clone());
+        }
+
+        // 3. 添加真实的 API Key
+        let header_name = HeaderName::from_bytes(auth_header.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Invalid auth header name: {e}"))?;
+        headers.insert(
+            header_name,
+            self.descriptor
+                .auth
+                .header_value(api_key)
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid auth header value: {e}"))?,
+        );
+
+        // 4. 返回处理后的请求
+        Ok(ProcessedRequest {
+            target_url,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            headers,
+            body: Bytes::copy_from_slice(body),
+            response_translator: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tool_descriptor::AuthStyle;
+
+    #[tokio::test]
+    async fn test_descriptor_processor() {
+        let descriptor: ToolDescriptor = serde_json::from_value(serde_json::json!({
+            "id": "qwen-code",
+            "name": "Qwen Code",
+            "command": "qwen",
+            "config_dir": "~/.qwen",
+            "auth": { "style": "header", "name": "X-Token", "prefix": "Token " },
+            "url": { "base_path": "/v1", "strip_prefix": "/v1" },
+            "default_port": 8791
+        }))
+        .unwrap();
+        assert!(matches!(descriptor.auth, AuthStyle::Header { .. }));
+        let processor = DescriptorProcessor::new(descriptor);
+
+        let mut original = HyperHeaderMap::new();
+        original.insert("authorization", "Bearer local-key".parse().unwrap());
+        original.insert("x-token", "Token local-key".parse().unwrap());
+        original.insert("content-type", "application/json".parse().unwrap());
+
+        let processed = processor
+            .process_outgoing_request(
+                "https://relay.example.com",
+                "sk-real",
+                "/v1/chat/completions",
+                None,
+                &original,
+                b"{}",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            processed.target_url,
+            "https://relay.example.com/v1/chat/completions"
+        );
+        assert_eq!(processed.headers.get("x-token").unwrap(), "Token sk-real");
+        assert!(processed.headers.get("authorization").is_none());
+        assert_eq!(
+            processed.headers.get("content-type").unwrap(),
+            "application/json"
+        );
+    }
+}
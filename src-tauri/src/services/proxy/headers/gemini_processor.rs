@@ -27,6 +27,18 @@ impl RequestProcessor for GeminiHeadersProcessor {
         "gemini-cli"
     }
 
+    /// Gemini 的模型名称位于路径中（`/v1beta/models/<model>:generateContent`）
+    fn rewrite_model(&self, path: &str, body: &[u8], model: &str) -> (String, Bytes) {
+        let Some((prefix, rest)) = path.split_once("/models/") else {
+            return (path.to_string(), Bytes::copy_from_slice(body));
+        };
+        let suffix = rest.find([':', '/']).map(|i| &rest[i..]).unwrap_or("");
+        (
+            format!("{prefix}/models/{model}{suffix}"),
+            Bytes::copy_from_slice(body),
+        )
+    }
+
     async fn process_outgoing_request(
         &self,
         base_url: &str,
@@ -74,8 +86,10 @@ impl RequestProcessor for GeminiHeadersProcessor {
         // 4. 返回处理后的请求
         Ok(ProcessedRequest {
             target_url,
+            base_url: base.to_string(),
             headers,
             body: Bytes::copy_from_slice(body),
+            response_translator: None,
         })
     }
 
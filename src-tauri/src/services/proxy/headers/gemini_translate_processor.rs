@@ -0,0 +1,112 @@
+// Gemini CLI -> OpenAI Chat Completions / Anthropic Messages 协议转换处理器
+
+use super::{GeminiHeadersProcessor, ProcessedRequest, RequestProcessor};
+use crate::models::proxy_config::UpstreamProtocol;
+use crate::services::proxy::translate::gemini::{
+    gemini_to_chat_request, gemini_to_messages_request, parse_generate_path,
+    ChatToGeminiTranslator, MessagesToGeminiTranslator,
+};
+use crate::services::proxy::translate::{openai_endpoint, ResponseTranslator};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use hyper::HeaderMap as HyperHeaderMap;
+
+/// Anthropic Messages 请求使用的 API 版本
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Gemini CLI 连接 OpenAI / Anthropic 兼容上游的请求处理器
+///
+/// 在 `GeminiHeadersProcessor` 的基础上：
+/// - `models/<model>:generateContent` / `:streamGenerateContent` 请求转换为
+///   `/v1/chat/completions` 或 `/v1/messages` 请求（查询参数不再转发）
+/// - 移除 `x-goog-*` headers，按上游协议设置认证
+/// - 附带响应转换器将响应转换回 Gemini 格式（流式响应按 `alt=sse` 输出）
+///
+/// 其他路径（如 `:countTokens`）原样透传
+#[derive(Debug)]
+pub struct GeminiTranslateProcessor {
+    upstream: UpstreamProtocol,
+}
+
+impl GeminiTranslateProcessor {
+    pub fn new(upstream: UpstreamProtocol) -> Self {
+        Self { upstream }
+    }
+}
+
+#[async_trait]
+impl RequestProcessor for GeminiTranslateProcessor {
+    fn tool_id(&self) -> &str {
+        "gemini-cli"
+    }
+
+    fn rewrite_model(&self, path: &str, body: &[u8], model: &str) -> (String, Bytes) {
+        GeminiHeadersProcessor.rewrite_model(path, body, model)
+    }
+
+    async fn process_outgoing_request(
+        &self,
+        base_url: &str,
+        api_key: &str,
+        path: &str,
+        query: Option<&str>,
+        original_headers: &HyperHeaderMap,
+        body: &[u8],
+    ) -> Result<ProcessedRequest> {
+        let mut processed = GeminiHeadersProcessor
+            .process_outgoing_request(base_url, api_key, path, query, original_headers, body)
+            .await?;
+
+        let Some((model, stream)) = parse_generate_path(path) else {
+            return Ok(processed);
+        };
+
+        // 1. 转换请求体并创建响应转换器
+        let request: serde_json::Value =
+            serde_json::from_slice(body).context("解析 Gemini 请求体失败")?;
+        let (converted, endpoint, translator): (_, _, Box<dyn ResponseTranslator>) =
+            match self.upstream {
+                UpstreamProtocol::AnthropicMessages => (
+                    gemini_to_messages_request(&model, &request, stream)?,
+                    "/messages",
+                    Box::new(MessagesToGeminiTranslator::new(&model)),
+                ),
+                _ => (
+                    gemini_to_chat_request(&model, &request, stream)?,
+                    "/chat/completions",
+                    Box::new(ChatToGeminiTranslator::new(&model)),
+                ),
+            };
+        processed.target_url = openai_endpoint(&processed.base_url, endpoint);
+        processed.body = Bytes::from(serde_json::to_vec(&converted)?);
+        processed.response_translator = Some(translator);
+
+        // 2. 替换认证 headers
+        let google_headers: Vec<_> = processed
+            .headers
+            .keys()
+            .filter(|name| name.as_str().starts_with("x-goog-"))
+            .cloned()
+            .collect();
+        for name in google_headers {
+            processed.headers.remove(name);
+        }
+        let auth_error = |e| anyhow::anyhow!("Invalid authorization header: {e}");
+        if self.upstream == UpstreamProtocol::AnthropicMessages {
+            processed
+                .headers
+                .insert("x-api-key", api_key.parse().map_err(auth_error)?);
+            processed
+                .headers
+                .insert("anthropic-version", ANTHROPIC_VERSION.parse()?);
+        } else {
+            processed.headers.insert(
+                "authorization",
+                format!("Bearer {api_key}").parse().map_err(auth_error)?,
+            );
+        }
+
+        Ok(processed)
+    }
+}
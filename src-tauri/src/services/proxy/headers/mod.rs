@@ -6,23 +6,38 @@ use bytes::Bytes;
 use hyper::HeaderMap as HyperHeaderMap;
 use reqwest::header::HeaderMap as ReqwestHeaderMap;
 
+use super::translate::ResponseTranslator;
+use crate::models::proxy_config::UpstreamProtocol;
+
+mod claude_openai_processor;
 mod claude_processor;
+mod codex_bridge_processor;
 mod codex_processor;
+mod descriptor_processor;
 mod gemini_processor;
+mod gemini_translate_processor;
 
+pub use claude_openai_processor::ClaudeOpenAIProcessor;
 pub use claude_processor::ClaudeHeadersProcessor;
+pub use codex_bridge_processor::CodexBridgeProcessor;
 pub use codex_processor::CodexHeadersProcessor;
+pub use descriptor_processor::DescriptorProcessor;
 pub use gemini_processor::GeminiHeadersProcessor;
+pub use gemini_translate_processor::GeminiTranslateProcessor;
 
 /// 处理后的请求信息
 #[derive(Debug)]
 pub struct ProcessedRequest {
     /// 目标 URL（完整 URL，包含 base_url + path + query）
     pub target_url: String,
+    /// 实际使用的上游 Base URL（已去除末尾 `/`，协议转换处理器据此重建目标 URL）
+    pub base_url: String,
     /// 处理后的请求 headers
     pub headers: ReqwestHeaderMap,
     /// 处理后的请求体（大多数情况下与原始 body 相同）
     pub body: Bytes,
+    /// 响应转换器（请求经过协议转换时设置，用于将上游响应转换回客户端格式）
+    pub response_translator: Option<Box<dyn ResponseTranslator>>,
 }
 
 /// 请求处理器 trait
@@ -89,12 +104,39 @@ pub trait RequestProcessor: Send + Sync + std::fmt::Debug {
     fn should_process_response(&self) -> bool {
         false
     }
+
+    /// 改写请求中的模型名称（模型映射规则命中时调用）
+    ///
+    /// 默认改写 JSON 请求体中的 `model` 字段，返回改写后的 (path, body)。
+    /// 模型位于 URL 路径中的工具（如 Gemini）需要覆盖此方法。
+    fn rewrite_model(&self, path: &str, body: &[u8], model: &str) -> (String, Bytes) {
+        (path.to_string(), rewrite_body_model(body, model))
+    }
+}
+
+/// 改写 JSON 请求体中的 `model` 字段
+///
+/// 请求体不是 JSON 对象或不包含 `model` 字段时原样返回
+pub fn rewrite_body_model(body: &[u8], model: &str) -> Bytes {
+    let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return Bytes::copy_from_slice(body);
+    };
+
+    match value.get_mut("model") {
+        Some(field) if field.is_string() => {
+            *field = serde_json::Value::String(model.to_string());
+            serde_json::to_vec(&value)
+                .map(Bytes::from)
+                .unwrap_or_else(|_| Bytes::copy_from_slice(body))
+        }
+        _ => Bytes::copy_from_slice(body),
+    }
 }
 
 /// 创建请求处理器工厂函数
 ///
 /// # 参数
-/// - `tool_id`: 工具标识符 ("claude-code", "codex", "gemini-cli")
+/// - `tool_id`: 工具标识符 ("claude-code", "codex", "gemini-cli" 或自定义工具 ID)
 ///
 /// # 返回
 /// - `Ok(Box<dyn RequestProcessor>)`: 对应工具的 RequestProcessor 实例
@@ -104,7 +146,38 @@ pub fn create_request_processor(tool_id: &str) -> Result<Box<dyn RequestProcesso
         "claude-code" => Ok(Box::new(ClaudeHeadersProcessor)),
         "codex" => Ok(Box::new(CodexHeadersProcessor)),
         "gemini-cli" => Ok(Box::new(GeminiHeadersProcessor)),
-        _ => Err(anyhow::anyhow!("不支持的工具: {}", tool_id)),
+        _ => match crate::models::ToolDescriptor::find(tool_id) {
+            Some(descriptor) => Ok(Box::new(DescriptorProcessor::new(descriptor))),
+            None => Err(anyhow::anyhow!("不支持的工具: {}", tool_id)),
+        },
+    }
+}
+
+/// 按上游协议创建请求处理器
+///
+/// # 参数
+/// - `tool_id`: 工具标识符
+/// - `protocol`: 上游使用的 API 协议（`Native` 时与 `create_request_processor` 相同）
+///
+/// # 返回
+/// - `Err`: 当工具不支持该协议转换时返回错误
+pub fn create_request_processor_for(
+    tool_id: &str,
+    protocol: UpstreamProtocol,
+) -> Result<Box<dyn RequestProcessor>> {
+    match (tool_id, protocol) {
+        (_, UpstreamProtocol::Native) => create_request_processor(tool_id),
+        ("claude-code", UpstreamProtocol::OpenaiChat) => Ok(Box::new(ClaudeOpenAIProcessor)),
+        ("claude-code", UpstreamProtocol::AnthropicMessages) => {
+            Ok(Box::new(ClaudeHeadersProcessor))
+        }
+        ("codex", UpstreamProtocol::OpenaiChat | UpstreamProtocol::OpenaiResponses) => {
+            Ok(Box::new(CodexBridgeProcessor::new(protocol)))
+        }
+        ("gemini-cli", UpstreamProtocol::OpenaiChat | UpstreamProtocol::AnthropicMessages) => {
+            Ok(Box::new(GeminiTranslateProcessor::new(protocol)))
+        }
+        _ => Err(anyhow::anyhow!("{} 不支持上游协议 {:?}", tool_id, protocol)),
     }
 }
 
@@ -165,4 +238,150 @@ mod tests {
             .unwrap();
         assert_eq!(auth_header, "Bearer test-api-key");
     }
+
+    #[test]
+    fn test_rewrite_model() {
+        let (path, body) = ClaudeHeadersProcessor.rewrite_model(
+            "/v1/messages",
+            br#"{"model":"claude-haiku-4-5","max_tokens":10}"#,
+            "relay/claude-haiku",
+        );
+        assert_eq!(path, "/v1/messages");
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["model"], "relay/claude-haiku");
+        assert_eq!(value["max_tokens"], 10);
+
+        let (path, _) = GeminiHeadersProcessor.rewrite_model(
+            "/v1beta/models/gemini-2.5-pro:streamGenerateContent",
+            b"",
+            "gemini-2.5-flash",
+        );
+        assert_eq!(
+            path,
+            "/v1beta/models/gemini-2.5-flash:streamGenerateContent"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_claude_openai_processor() {
+        let processor = create_request_processor_for("claude-code", UpstreamProtocol::OpenaiChat)
+            .expect("should create translating processor");
+        let mut headers = HyperHeaderMap::new();
+        headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+
+        let result = processor
+            .process_outgoing_request(
+                "https://relay.example.com/v1",
+                "sk-relay",
+                "/v1/messages",
+                Some("beta=true"),
+                &headers,
+                br#"{"model":"gpt-4o","max_tokens":16,"messages":[{"role":"user","content":"hi"}]}"#,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.base_url, "https://relay.example.com/v1");
+        assert_eq!(
+            result.target_url,
+            "https://relay.example.com/v1/chat/completions"
+        );
+        assert!(result.headers.get("anthropic-version").is_none());
+        assert!(result.response_translator.is_some());
+        let body: serde_json::Value = serde_json::from_slice(&result.body).unwrap();
+        assert_eq!(body["messages"][0]["content"], "hi");
+
+        assert!(
+            create_request_processor_for("gemini-cli", UpstreamProtocol::OpenaiResponses).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_codex_bridge_processor() {
+        let headers = HyperHeaderMap::new();
+        let body = br#"{"model":"gpt-5-codex","stream":true,"input":"hi"}"#;
+
+        // Responses 请求 + Chat 上游：转换路径与请求体
+        let processor = create_request_processor_for("codex", UpstreamProtocol::OpenaiChat)
+            .expect("should create codex bridge processor");
+        let result = processor
+            .process_outgoing_request(
+                "https://relay.example.com/v1",
+                "sk-relay",
+                "/v1/responses",
+                None,
+                &headers,
+                body,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.target_url,
+            "https://relay.example.com/v1/chat/completions"
+        );
+        assert!(result.response_translator.is_some());
+        let converted: serde_json::Value = serde_json::from_slice(&result.body).unwrap();
+        assert_eq!(converted["messages"][0]["content"], "hi");
+
+        // 协议一致时透传
+        let processor = create_request_processor_for("codex", UpstreamProtocol::OpenaiResponses)
+            .expect("should create codex bridge processor");
+        let result = processor
+            .process_outgoing_request(
+                "https://api.openai.com/v1",
+                "sk-test",
+                "/responses",
+                None,
+                &headers,
+                body,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.target_url, "https://api.openai.com/v1/responses");
+        assert!(result.response_translator.is_none());
+        assert_eq!(&result.body[..], &body[..]);
+    }
+
+    #[tokio::test]
+    async fn test_gemini_translate_processor() {
+        let mut headers = HyperHeaderMap::new();
+        headers.insert("x-goog-api-client", "gl-node/22".parse().unwrap());
+        let body = br#"{"contents":[{"role":"user","parts":[{"text":"hi"}]}]}"#;
+
+        let processor =
+            create_request_processor_for("gemini-cli", UpstreamProtocol::AnthropicMessages)
+                .expect("should create gemini translate processor");
+        let result = processor
+            .process_outgoing_request(
+                "https://relay.example.com",
+                "sk-relay",
+                "/v1beta/models/claude-sonnet-4-5:streamGenerateContent",
+                Some("alt=sse"),
+                &headers,
+                body,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.target_url, "https://relay.example.com/v1/messages");
+        assert!(result.headers.get("x-goog-api-client").is_none());
+        assert!(result.headers.get("x-goog-api-key").is_none());
+        assert_eq!(result.headers.get("x-api-key").unwrap(), "sk-relay");
+        let converted: serde_json::Value = serde_json::from_slice(&result.body).unwrap();
+        assert_eq!(converted["model"], "claude-sonnet-4-5");
+        assert_eq!(converted["stream"], true);
+
+        // 非生成接口原样透传
+        let result = processor
+            .process_outgoing_request(
+                "https://relay.example.com",
+                "sk-relay",
+                "/v1beta/models/claude-sonnet-4-5:countTokens",
+                None,
+                &headers,
+                body,
+            )
+            .await
+            .unwrap();
+        assert!(result.response_translator.is_none());
+    }
 }
@@ -0,0 +1,182 @@
+//! 连接来源地址过滤
+//!
+//! 按 `IpFilterConfig` 的 CIDR 列表检查对端地址：
+//! - 命中拒绝列表的连接一律拒绝
+//! - 允许列表非空时，仅允许命中的地址（本机回环地址始终允许，避免本地 CLI 被拦截）
+
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::models::proxy_config::IpFilterConfig;
+
+/// 单个 CIDR 网段（IPv4 映射的 IPv6 地址按 IPv4 处理）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// 解析 `192.168.1.0/24`、`fd00::/8` 或单个地址（视为 /32、/128）
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .with_context(|| format!("无效的 IP 地址: {value}"))?;
+        let addr = addr.to_canonical();
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|&p| p <= max_prefix)
+                .ok_or_else(|| anyhow!("无效的前缀长度: {value}"))?,
+            None => max_prefix,
+        };
+
+        Ok(Self {
+            network: mask(addr, prefix),
+            prefix,
+        })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        ip.is_ipv4() == self.network.is_ipv4() && mask(ip, self.prefix) == self.network
+    }
+}
+
+/// 保留前 `prefix` 位
+fn mask(ip: IpAddr, prefix: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let bits = u32::from(v4);
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((bits & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let bits = u128::from(v6);
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((bits & mask).into())
+        }
+    }
+}
+
+/// 代理实例的来源地址过滤器
+#[derive(Debug, Default)]
+pub struct IpFilter {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+}
+
+impl IpFilter {
+    /// 解析配置（任一条目无效时返回错误）
+    pub fn new(config: &IpFilterConfig) -> Result<Self> {
+        let parse = |list: &[String]| -> Result<Vec<Cidr>> {
+            list.iter()
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| Cidr::parse(s))
+                .collect()
+        };
+        Ok(Self {
+            allow: parse(&config.allow)?,
+            deny: parse(&config.deny)?,
+        })
+    }
+
+    /// 仅允许本机回环地址（配置无效时使用，避免放行所有地址）
+    pub fn loopback_only() -> Self {
+        Self {
+            allow: vec![Cidr {
+                network: IpAddr::from([127, 0, 0, 0]),
+                prefix: 8,
+            }],
+            deny: Vec::new(),
+        }
+    }
+
+    /// 是否允许该地址连接
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        if self.deny.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty()
+            || ip.to_canonical().is_loopback()
+            || self.allow.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_parse_and_contains() {
+        let office = Cidr::parse("192.168.10.0/24").unwrap();
+        assert!(office.contains(ip("192.168.10.42")));
+        assert!(!office.contains(ip("192.168.11.1")));
+        // IPv4 映射的 IPv6 地址（双栈监听时的对端地址）
+        assert!(office.contains(ip("::ffff:192.168.10.42")));
+
+        // 主机位不为 0 时按网段处理
+        assert_eq!(
+            Cidr::parse("172.20.1.5/12").unwrap(),
+            Cidr::parse("172.16.0.0/12").unwrap()
+        );
+
+        let single = Cidr::parse("10.0.0.1").unwrap();
+        assert!(single.contains(ip("10.0.0.1")));
+        assert!(!single.contains(ip("10.0.0.2")));
+
+        let v6 = Cidr::parse("fd00::/8").unwrap();
+        assert!(v6.contains(ip("fd12::1")));
+        assert!(!v6.contains(ip("10.0.0.1")));
+
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(ip("8.8.8.8")));
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("office").is_err());
+    }
+
+    #[test]
+    fn test_filter_allow_and_deny() {
+        let filter = IpFilter::new(&IpFilterConfig {
+            allow: vec!["192.168.10.0/24".to_string(), "172.16.0.0/12".to_string()],
+            deny: vec!["192.168.10.13".to_string()],
+        })
+        .unwrap();
+
+        assert!(filter.permits(ip("192.168.10.42")));
+        assert!(filter.permits(ip("172.25.80.1")));
+        assert!(!filter.permits(ip("192.168.10.13")));
+        assert!(!filter.permits(ip("8.8.8.8")));
+        // 本机回环地址不受允许列表限制
+        assert!(filter.permits(ip("127.0.0.1")));
+        assert!(filter.permits(ip("::1")));
+    }
+
+    #[test]
+    fn test_filter_deny_only() {
+        let filter = IpFilter::new(&IpFilterConfig {
+            allow: Vec::new(),
+            deny: vec!["10.0.0.0/8".to_string()],
+        })
+        .unwrap();
+        assert!(!filter.permits(ip("10.1.2.3")));
+        assert!(filter.permits(ip("192.168.1.1")));
+
+        assert!(IpFilter::new(&IpFilterConfig {
+            allow: vec!["not-an-ip".to_string()],
+            deny: Vec::new(),
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,412 @@
+//! 代理监听与连接管理
+//!
+//! 工具代理实例与统一网关共用：
+//! - HTTP / HTTPS 监听绑定（开启 HTTPS 且允许公网访问时，仅 HTTPS 端口对外开放）
+//! - 来源地址过滤、TLS 握手
+//! - 停止时排空进行中的连接、切换监听地址
+//! - 登记监听地址，供回环检测比对
+
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+use super::tls::CertStore;
+use super::utils::body::BoxBody;
+use super::utils::loop_detector;
+use crate::models::proxy_config::{TlsListenerConfig, ToolProxyConfig};
+
+/// TLS 握手超时
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 监听参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenSpec {
+    pub port: u16,
+    pub allow_public: bool,
+    pub tls: TlsListenerConfig,
+}
+
+impl From<&ToolProxyConfig> for ListenSpec {
+    fn from(config: &ToolProxyConfig) -> Self {
+        Self {
+            port: config.port,
+            allow_public: config.allow_public,
+            tls: config.tls.clone(),
+        }
+    }
+}
+
+impl ListenSpec {
+    /// HTTP 监听地址（允许公网访问时绑定 0.0.0.0；开启 HTTPS 后仅 HTTPS 端口对外开放）
+    pub fn http_addr(&self) -> SocketAddr {
+        if self.allow_public && !self.tls.enabled {
+            SocketAddr::from(([0, 0, 0, 0], self.port))
+        } else {
+            SocketAddr::from(([127, 0, 0, 1], self.port))
+        }
+    }
+
+    /// HTTPS 监听地址（未开启 HTTPS 时为 None）
+    pub fn tls_addr(&self) -> Option<SocketAddr> {
+        let port = self.tls.resolve_port(self.port)?;
+        Some(if self.allow_public {
+            SocketAddr::from(([0, 0, 0, 0], port))
+        } else {
+            SocketAddr::from(([127, 0, 0, 1], port))
+        })
+    }
+
+    /// 占用的全部端口
+    pub fn ports(&self) -> Vec<u16> {
+        std::iter::once(self.port)
+            .chain(self.tls.resolve_port(self.port))
+            .collect()
+    }
+}
+
+impl fmt::Display for ListenSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tls_addr() {
+            Some(tls_addr) => write!(f, "http://{}, https://{}", self.http_addr(), tls_addr),
+            None => write!(f, "http://{}", self.http_addr()),
+        }
+    }
+}
+
+/// 连接上的请求处理（工具代理实例或统一网关）
+#[async_trait]
+pub trait ConnectionHandler: Send + Sync + 'static {
+    /// 日志中使用的名称
+    fn name(&self) -> &str;
+
+    /// 是否接受来自该地址的连接
+    async fn permits(&self, peer: SocketAddr) -> bool;
+
+    /// 处理单个请求（`peer` 为连接的对端地址）
+    async fn handle(&self, req: Request<Incoming>, peer: SocketAddr) -> Response<BoxBody>;
+}
+
+/// 已绑定的 HTTP 与 HTTPS 监听
+pub struct Listeners {
+    http: TcpListener,
+    https: Option<(TcpListener, TlsAcceptor)>,
+}
+
+/// 绑定监听（开启 HTTPS 时按需生成证书）
+pub async fn bind(spec: &ListenSpec) -> Result<Listeners> {
+    let http = TcpListener::bind(spec.http_addr())
+        .await
+        .context(format!("绑定端口 {} 失败", spec.port))?;
+
+    let https = match spec.tls_addr() {
+        Some(addr) => {
+            let acceptor = CertStore::open()
+                .and_then(|store| store.acceptor(&spec.tls.extra_hosts))
+                .context("加载 HTTPS 证书失败")?;
+            let listener = TcpListener::bind(addr)
+                .await
+                .context(format!("绑定 HTTPS 端口 {} 失败", addr.port()))?;
+            Some((listener, acceptor))
+        }
+        None => None,
+    };
+
+    Ok(Listeners { http, https })
+}
+
+/// 运行中的监听与连接
+pub struct ServerHandle {
+    name: String,
+    /// 实际绑定的地址（已登记到回环检测）
+    addrs: Vec<SocketAddr>,
+    /// HTTP 与（开启时）HTTPS 监听任务
+    accept_tasks: Vec<tokio::task::JoinHandle<()>>,
+    /// 停止接收新连接，并通知已有连接在当前响应结束后关闭
+    shutdown: CancellationToken,
+    /// 排空超时后强制关闭剩余连接
+    force_close: CancellationToken,
+    connections: TaskTracker,
+}
+
+/// 单个监听上的连接共享的上下文
+#[derive(Clone)]
+struct ConnectionContext {
+    handler: Arc<dyn ConnectionHandler>,
+    shutdown: CancellationToken,
+    force_close: CancellationToken,
+    connections: TaskTracker,
+}
+
+/// 在已绑定的监听上接收连接
+pub fn serve(listeners: Listeners, handler: Arc<dyn ConnectionHandler>) -> ServerHandle {
+    let ctx = ConnectionContext {
+        handler,
+        shutdown: CancellationToken::new(),
+        force_close: CancellationToken::new(),
+        connections: TaskTracker::new(),
+    };
+
+    let addrs: Vec<SocketAddr> = std::iter::once(&listeners.http)
+        .chain(listeners.https.as_ref().map(|(listener, _)| listener))
+        .filter_map(|listener| listener.local_addr().ok())
+        .collect();
+    loop_detector::register_listen_addrs(&addrs);
+
+    let mut accept_tasks = vec![tokio::spawn(accept_loop(listeners.http, None, ctx.clone()))];
+    if let Some((listener, acceptor)) = listeners.https {
+        accept_tasks.push(tokio::spawn(accept_loop(
+            listener,
+            Some(acceptor),
+            ctx.clone(),
+        )));
+    }
+
+    ServerHandle {
+        name: ctx.handler.name().to_string(),
+        addrs,
+        accept_tasks,
+        shutdown: ctx.shutdown,
+        force_close: ctx.force_close,
+        connections: ctx.connections,
+    }
+}
+
+impl ServerHandle {
+    /// 停止接收新连接，并通知已有连接在当前响应结束后关闭
+    pub async fn close_listener(mut self) -> Self {
+        self.shutdown.cancel();
+        for task in &mut self.accept_tasks {
+            if let Err(e) = task.await {
+                tracing::warn!(tool_id = %self.name, error = ?e, "监听任务异常退出");
+            }
+        }
+        loop_detector::unregister_listen_addrs(&std::mem::take(&mut self.addrs));
+        self
+    }
+
+    /// 在后台等待已有连接处理完成
+    pub fn drain_in_background(self, timeout: Duration) {
+        tokio::spawn(async move {
+            self.drain(timeout).await;
+        });
+    }
+
+    /// 等待已有连接处理完成（调用前需已关闭监听）
+    pub async fn drain(self, timeout: Duration) {
+        self.connections.close();
+        if self.connections.is_empty() {
+            return;
+        }
+
+        tracing::info!(
+            tool_id = %self.name,
+            connections = self.connections.len(),
+            timeout_secs = timeout.as_secs(),
+            "等待进行中的请求完成"
+        );
+        if tokio::time::timeout(timeout, self.connections.wait())
+            .await
+            .is_err()
+        {
+            tracing::warn!(
+                tool_id = %self.name,
+                connections = self.connections.len(),
+                "排空超时，强制关闭剩余连接"
+            );
+            self.force_close.cancel();
+            self.connections.wait().await;
+        }
+    }
+}
+
+/// 切换监听地址
+///
+/// 先绑定新地址再关闭旧监听，旧连接在后台排空；新地址无法绑定时保留（或恢复）旧监听并返回错误
+pub async fn swap(
+    server: &mut Option<ServerHandle>,
+    old_spec: &ListenSpec,
+    new_spec: &ListenSpec,
+    handler: Arc<dyn ConnectionHandler>,
+    drain_timeout: Duration,
+) -> Result<()> {
+    if server.is_none() {
+        return Ok(());
+    }
+    let name = handler.name().to_string();
+
+    let ports_overlap = new_spec
+        .ports()
+        .iter()
+        .any(|port| old_spec.ports().contains(port));
+    let listeners = match bind(new_spec).await {
+        Ok(listeners) => listeners,
+        // 新端口不可用，旧监听保持不变
+        Err(e) if !ports_overlap => return Err(e),
+        // 同一端口仅切换绑定地址时与旧监听冲突，需先释放旧监听
+        Err(_) => {
+            if let Some(old) = server.take() {
+                old.close_listener()
+                    .await
+                    .drain_in_background(drain_timeout);
+            }
+            match bind(new_spec).await {
+                Ok(listeners) => listeners,
+                Err(e) => {
+                    match bind(old_spec).await {
+                        Ok(listeners) => {
+                            *server = Some(serve(listeners, handler));
+                            tracing::warn!(
+                                tool_id = %name,
+                                addr = %old_spec,
+                                "新监听地址绑定失败，已恢复原监听"
+                            );
+                        }
+                        Err(rollback_err) => {
+                            tracing::error!(
+                                tool_id = %name,
+                                error = ?rollback_err,
+                                "恢复原监听失败，代理已停止"
+                            );
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    };
+
+    if let Some(old) = server.replace(serve(listeners, handler)) {
+        old.close_listener()
+            .await
+            .drain_in_background(drain_timeout);
+    }
+
+    tracing::info!(
+        tool_id = %name,
+        from = %old_spec,
+        to = %new_spec,
+        "透明代理监听已切换"
+    );
+    Ok(())
+}
+
+/// 接收连接直到停止（`acceptor` 不为空时先完成 TLS 握手）
+async fn accept_loop(listener: TcpListener, acceptor: Option<TlsAcceptor>, ctx: ConnectionContext) {
+    loop {
+        let accepted = tokio::select! {
+            _ = ctx.shutdown.cancelled() => break,
+            accepted = listener.accept() => accepted,
+        };
+        match accepted {
+            Ok((stream, addr)) => {
+                if !ctx.handler.permits(addr).await {
+                    tracing::warn!(
+                        tool_id = %ctx.handler.name(),
+                        peer = %addr,
+                        "拒绝来自未授权地址的连接"
+                    );
+                    continue;
+                }
+
+                let acceptor = acceptor.clone();
+                let conn_ctx = ctx.clone();
+                ctx.connections.spawn(async move {
+                    match acceptor {
+                        None => serve_connection(TokioIo::new(stream), addr, conn_ctx).await,
+                        Some(acceptor) => {
+                            if let Some(stream) =
+                                tls_handshake(&acceptor, stream, addr, &conn_ctx).await
+                            {
+                                serve_connection(TokioIo::new(stream), addr, conn_ctx).await;
+                            }
+                        }
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::error!(
+                    tool_id = %ctx.handler.name(),
+                    error = ?e,
+                    "接受连接失败"
+                );
+            }
+        }
+    }
+}
+
+async fn tls_handshake(
+    acceptor: &TlsAcceptor,
+    stream: TcpStream,
+    addr: SocketAddr,
+    ctx: &ConnectionContext,
+) -> Option<tokio_rustls::server::TlsStream<TcpStream>> {
+    let handshake = tokio::select! {
+        result = tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)) => result,
+        _ = ctx.shutdown.cancelled() => return None,
+    };
+    match handshake {
+        Ok(Ok(stream)) => Some(stream),
+        Ok(Err(e)) => {
+            tracing::debug!(tool_id = %ctx.handler.name(), peer = %addr, error = ?e, "TLS 握手失败");
+            None
+        }
+        Err(_) => {
+            tracing::debug!(tool_id = %ctx.handler.name(), peer = %addr, "TLS 握手超时");
+            None
+        }
+    }
+}
+
+async fn serve_connection<I>(io: I, peer: SocketAddr, ctx: ConnectionContext)
+where
+    I: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+{
+    let ConnectionContext {
+        handler,
+        shutdown,
+        force_close,
+        ..
+    } = ctx;
+    let service_handler = Arc::clone(&handler);
+    let service = service_fn(move |req| {
+        let handler = Arc::clone(&service_handler);
+        async move { Ok::<_, Infallible>(handler.handle(req, peer).await) }
+    });
+
+    let conn = http1::Builder::new().serve_connection(io, service);
+    tokio::pin!(conn);
+
+    // 停止时不再接收新请求，等待当前响应（包括 SSE 流）传输完成
+    let result = tokio::select! {
+        result = conn.as_mut() => result,
+        _ = shutdown.cancelled() => {
+            conn.as_mut().graceful_shutdown();
+            tokio::select! {
+                result = conn.as_mut() => result,
+                _ = force_close.cancelled() => Ok(()),
+            }
+        }
+    };
+
+    if let Err(err) = result {
+        tracing::error!(
+            tool_id = %handler.name(),
+            error = ?err,
+            "处理连接失败"
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! 代理实例运行指标
+//!
+//! 每个代理实例维护一组无锁计数器：
+//! - 请求总数、进行中请求数
+//! - 按类别统计的错误数（本地拒绝、上游连接失败、上游 4xx/5xx、流中断等）
+//! - 请求耗时与 SSE 首字节时间（TTFB）直方图
+//! - 客户端请求体字节数（入）与响应体字节数（出）
+//!
+//! 指标通过 Tauri 命令以快照形式提供给前端，也可渲染为 Prometheus 文本格式
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use bytes::Bytes;
+use hyper::body::{Body, Frame};
+use pin_project_lite::pin_project;
+use serde::Serialize;
+
+use super::utils::BoxBody;
+
+/// Prometheus 指标路由
+pub const METRICS_PATH: &str = "/__duckcoding/metrics";
+
+/// 直方图桶上界（毫秒），最后隐含 +Inf
+const BUCKETS_MS: [u64; 11] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000, 120_000, 300_000,
+];
+
+/// 错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// 本地 API Key 校验失败
+    Unauthorized,
+    /// 本地限流排队超时
+    RateLimited,
+    /// 代理配置不完整
+    Configuration,
+    /// 代理回环
+    ProxyLoop,
+    /// 上游连接失败
+    UpstreamConnect,
+    /// 上游请求超时
+    UpstreamTimeout,
+    /// 上游返回 4xx
+    Upstream4xx,
+    /// 上游返回 5xx
+    Upstream5xx,
+    /// 响应流传输中断
+    StreamInterrupted,
+    /// 其他内部错误
+    Internal,
+}
+
+impl ErrorClass {
+    const ALL: [ErrorClass; 10] = [
+        ErrorClass::Unauthorized,
+        ErrorClass::RateLimited,
+        ErrorClass::Configuration,
+        ErrorClass::ProxyLoop,
+        ErrorClass::UpstreamConnect,
+        ErrorClass::UpstreamTimeout,
+        ErrorClass::Upstream4xx,
+        ErrorClass::Upstream5xx,
+        ErrorClass::StreamInterrupted,
+        ErrorClass::Internal,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorClass::Unauthorized => "unauthorized",
+            ErrorClass::RateLimited => "rate_limited",
+            ErrorClass::Configuration => "configuration",
+            ErrorClass::ProxyLoop => "proxy_loop",
+            ErrorClass::UpstreamConnect => "upstream_connect",
+            ErrorClass::UpstreamTimeout => "upstream_timeout",
+            ErrorClass::Upstream4xx => "upstream_4xx",
+            ErrorClass::Upstream5xx => "upstream_5xx",
+            ErrorClass::StreamInterrupted => "stream_interrupted",
+            ErrorClass::Internal => "internal",
+        }
+    }
+
+    /// 按上游响应状态码分类（非错误状态返回 None）
+    pub fn from_status(status: u16) -> Option<Self> {
+        match status {
+            400..=499 => Some(ErrorClass::Upstream4xx),
+            500..=599 => Some(ErrorClass::Upstream5xx),
+            _ => None,
+        }
+    }
+
+    /// 按请求处理错误分类
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        match error.downcast_ref::<reqwest::Error>() {
+            Some(e) if e.is_timeout() => ErrorClass::UpstreamTimeout,
+            Some(e) if e.is_connect() || e.is_request() => ErrorClass::UpstreamConnect,
+            _ => ErrorClass::Internal,
+        }
+    }
+}
+
+/// 无锁直方图
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; BUCKETS_MS.len() + 1],
+    count: AtomicU64,
+    sum_ms: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, ms: u64) {
+        let index = BUCKETS_MS
+            .iter()
+            .position(|&le| ms <= le)
+            .unwrap_or(BUCKETS_MS.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_ms.fetch_add(ms, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets: Vec<HistogramBucket> = self
+            .buckets
+            .iter()
+            .enumerate()
+            .map(|(i, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                HistogramBucket {
+                    le_ms: BUCKETS_MS.get(i).copied(),
+                    count: cumulative,
+                }
+            })
+            .collect();
+        let count = self.count.load(Ordering::Relaxed);
+        let sum_ms = self.sum_ms.load(Ordering::Relaxed);
+
+        HistogramSnapshot {
+            avg_ms: (count > 0).then(|| sum_ms / count),
+            p50_ms: estimate_quantile(&buckets, 0.5),
+            p95_ms: estimate_quantile(&buckets, 0.95),
+            buckets,
+            count,
+            sum_ms,
+        }
+    }
+}
+
+/// 按桶上界估算分位数（落在 +Inf 桶时取最大有限上界）
+fn estimate_quantile(buckets: &[HistogramBucket], q: f64) -> Option<u64> {
+    let total = buckets.last()?.count;
+    if total == 0 {
+        return None;
+    }
+    let rank = ((total as f64) * q).ceil().max(1.0) as u64;
+    buckets
+        .iter()
+        .find(|b| b.count >= rank)
+        .map(|b| b.le_ms.unwrap_or(BUCKETS_MS[BUCKETS_MS.len() - 1]))
+}
+
+/// 直方图桶（累计计数）
+#[derive(Debug, Clone, Serialize)]
+pub struct HistogramBucket {
+    /// 桶上界（毫秒），None 表示 +Inf
+    pub le_ms: Option<u64>,
+    pub count: u64,
+}
+
+/// 直方图快照
+#[derive(Debug, Clone, Serialize)]
+pub struct HistogramSnapshot {
+    pub buckets: Vec<HistogramBucket>,
+    pub count: u64,
+    pub sum_ms: u64,
+    pub avg_ms: Option<u64>,
+    /// 按桶上界估算的 P50
+    pub p50_ms: Option<u64>,
+    /// 按桶上界估算的 P95
+    pub p95_ms: Option<u64>,
+}
+
+/// 代理实例指标
+#[derive(Debug)]
+pub struct ProxyMetrics {
+    started_at: i64,
+    requests: AtomicU64,
+    in_flight: AtomicU64,
+    errors: [AtomicU64; ErrorClass::ALL.len()],
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    latency: Histogram,
+    ttfb: Histogram,
+}
+
+impl Default for ProxyMetrics {
+    fn default() -> Self {
+        Self {
+            started_at: chrono::Utc::now().timestamp(),
+            requests: AtomicU64::default(),
+            in_flight: AtomicU64::default(),
+            errors: Default::default(),
+            bytes_in: AtomicU64::default(),
+            bytes_out: AtomicU64::default(),
+            latency: Histogram::default(),
+            ttfb: Histogram::default(),
+        }
+    }
+}
+
+impl ProxyMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始跟踪一个请求（守卫释放时记录耗时并减少进行中请求数）
+    pub fn track(self: &Arc<Self>) -> RequestGuard {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        RequestGuard {
+            metrics: Arc::clone(self),
+            started: Instant::now(),
+            first_byte_recorded: false,
+        }
+    }
+
+    pub fn record_error(&self, class: ErrorClass) {
+        let index = ErrorClass::ALL
+            .iter()
+            .position(|&c| c == class)
+            .unwrap_or(ErrorClass::ALL.len() - 1);
+        self.errors[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_bytes_in(&self, bytes: usize) {
+        self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ProxyMetricsSnapshot {
+        let errors_by_class: BTreeMap<String, u64> = ErrorClass::ALL
+            .iter()
+            .zip(self.errors.iter())
+            .map(|(class, count)| (class.as_str().to_string(), count.load(Ordering::Relaxed)))
+            .collect();
+
+        ProxyMetricsSnapshot {
+            started_at: self.started_at,
+            requests_total: self.requests.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            errors_total: errors_by_class.values().sum(),
+            errors_by_class,
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            latency: self.latency.snapshot(),
+            ttfb: self.ttfb.snapshot(),
+        }
+    }
+}
+
+/// 指标快照（供前端仪表盘使用）
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyMetricsSnapshot {
+    /// 指标开始统计的时间（实例创建时间，Unix 秒）
+    pub started_at: i64,
+    pub requests_total: u64,
+    pub in_flight: u64,
+    pub errors_total: u64,
+    pub errors_by_class: BTreeMap<String, u64>,
+    /// 客户端请求体字节数
+    pub bytes_in: u64,
+    /// 返回给客户端的响应体字节数
+    pub bytes_out: u64,
+    /// 请求总耗时（到响应体传输结束）
+    pub latency: HistogramSnapshot,
+    /// SSE 响应的首字节时间
+    pub ttfb: HistogramSnapshot,
+}
+
+/// 单个请求的跟踪守卫
+///
+/// 随响应体移动，响应传输结束（或被丢弃）时记录耗时
+#[derive(Debug)]
+pub struct RequestGuard {
+    metrics: Arc<ProxyMetrics>,
+    started: Instant,
+    first_byte_recorded: bool,
+}
+
+impl RequestGuard {
+    pub fn record_error(&self, class: ErrorClass) {
+        self.metrics.record_error(class);
+    }
+
+    fn on_data(&mut self, len: usize, is_sse: bool) {
+        self.metrics
+            .bytes_out
+            .fetch_add(len as u64, Ordering::Relaxed);
+        if is_sse && !self.first_byte_recorded && len > 0 {
+            self.first_byte_recorded = true;
+            self.metrics.ttfb.observe(elapsed_ms(self.started));
+        }
+    }
+
+    /// 包装响应体，统计出站字节数与 SSE 首字节时间
+    pub fn wrap_body(self, body: BoxBody, is_sse: bool) -> BoxBody {
+        super::utils::box_body(MeteredBody {
+            inner: body,
+            guard: self,
+            is_sse,
+        })
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.metrics.latency.observe(elapsed_ms(self.started));
+    }
+}
+
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+pin_project! {
+    struct MeteredBody {
+        #[pin]
+        inner: BoxBody,
+        guard: RequestGuard,
+        is_sse: bool,
+    }
+}
+
+impl Body for MeteredBody {
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let result = this.inner.poll_frame(cx);
+        match &result {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let Some(data) = frame.data_ref() {
+                    this.guard.on_data(data.len(), *this.is_sse);
+                }
+            }
+            Poll::Ready(Some(Err(_))) => this.guard.record_error(ErrorClass::StreamInterrupted),
+            _ => {}
+        }
+        result
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> hyper::body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// 渲染为 Prometheus 文本格式
+pub fn render_prometheus(metrics: &[(String, ProxyMetricsSnapshot)]) -> String {
+    let mut out = String::new();
+
+    let header = |out: &mut String, name: &str, help: &str, kind: &str| {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+    };
+
+    header(
+        &mut out,
+        "duckcoding_proxy_requests_total",
+        "Total requests handled by the proxy",
+        "counter",
+    );
+    for (tool, m) in metrics {
+        let _ = writeln!(
+            out,
+            "duckcoding_proxy_requests_total{{tool=\"{tool}\"}} {}",
+            m.requests_total
+        );
+    }
+
+    header(
+        &mut out,
+        "duckcoding_proxy_in_flight",
+        "Requests currently in flight",
+        "gauge",
+    );
+    for (tool, m) in metrics {
+        let _ = writeln!(
+            out,
+            "duckcoding_proxy_in_flight{{tool=\"{tool}\"}} {}",
+            m.in_flight
+        );
+    }
+
+    header(
+        &mut out,
+        "duckcoding_proxy_errors_total",
+        "Failed requests by error class",
+        "counter",
+    );
+    for (tool, m) in metrics {
+        for (class, count) in &m.errors_by_class {
+            let _ = writeln!(
+                out,
+                "duckcoding_proxy_errors_total{{tool=\"{tool}\",class=\"{class}\"}} {count}"
+            );
+        }
+    }
+
+    for (name, help, pick) in [
+        (
+            "duckcoding_proxy_received_bytes_total",
+            "Request body bytes received from clients",
+            (|m: &ProxyMetricsSnapshot| m.bytes_in) as fn(&ProxyMetricsSnapshot) -> u64,
+        ),
+        (
+            "duckcoding_proxy_sent_bytes_total",
+            "Response body bytes sent to clients",
+            |m: &ProxyMetricsSnapshot| m.bytes_out,
+        ),
+    ] {
+        header(&mut out, name, help, "counter");
+        for (tool, m) in metrics {
+            let _ = writeln!(out, "{name}{{tool=\"{tool}\"}} {}", pick(m));
+        }
+    }
+
+    for (name, help, pick) in [
+        (
+            "duckcoding_proxy_request_duration_seconds",
+            "Request duration until the response body completes",
+            (|m: &ProxyMetricsSnapshot| &m.latency)
+                as fn(&ProxyMetricsSnapshot) -> &HistogramSnapshot,
+        ),
+        (
+            "duckcoding_proxy_sse_ttfb_seconds",
+            "Time to first byte of streaming responses",
+            |m: &ProxyMetricsSnapshot| &m.ttfb,
+        ),
+    ] {
+        header(&mut out, name, help, "histogram");
+        for (tool, m) in metrics {
+            let h = pick(m);
+            for bucket in &h.buckets {
+                let le = bucket
+                    .le_ms
+                    .map(|ms| format!("{}", ms as f64 / 1000.0))
+                    .unwrap_or_else(|| "+Inf".to_string());
+                let _ = writeln!(
+                    out,
+                    "{name}_bucket{{tool=\"{tool}\",le=\"{le}\"}} {}",
+                    bucket.count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{name}_sum{{tool=\"{tool}\"}} {}",
+                h.sum_ms as f64 / 1000.0
+            );
+            let _ = writeln!(out, "{name}_count{{tool=\"{tool}\"}} {}", h.count);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    #[test]
+    fn test_histogram_snapshot() {
+        let histogram = Histogram::default();
+        for ms in [50, 80, 300, 700, 400_000] {
+            histogram.observe(ms);
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 5);
+        assert_eq!(snapshot.buckets[0].count, 2); // <= 100ms
+        assert_eq!(snapshot.buckets[2].count, 3); // <= 500ms
+        assert_eq!(snapshot.buckets.last().unwrap().le_ms, None);
+        assert_eq!(snapshot.buckets.last().unwrap().count, 5);
+        assert_eq!(snapshot.p50_ms, Some(500));
+        assert_eq!(snapshot.p95_ms, Some(300_000));
+    }
+
+    #[tokio::test]
+    async fn test_request_guard_tracks_body() {
+        let metrics = Arc::new(ProxyMetrics::new());
+        let guard = metrics.track();
+        assert_eq!(metrics.snapshot().in_flight, 1);
+
+        let body =
+            super::super::utils::box_body(http_body_util::Full::new(Bytes::from("data: {}\n\n")));
+        let body = guard.wrap_body(body, true);
+        let collected = body.collect().await.unwrap().to_bytes();
+        assert_eq!(collected.len(), 10);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.requests_total, 1);
+        assert_eq!(snapshot.in_flight, 0);
+        assert_eq!(snapshot.bytes_out, 10);
+        assert_eq!(snapshot.latency.count, 1);
+        assert_eq!(snapshot.ttfb.count, 1);
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let metrics = ProxyMetrics::new();
+        metrics.record_error(ErrorClass::Upstream5xx);
+        metrics.record_bytes_in(42);
+        metrics.latency.observe(1_200);
+
+        let text = render_prometheus(&[("codex".to_string(), metrics.snapshot())]);
+        assert!(text.contains("# TYPE duckcoding_proxy_requests_total counter"));
+        assert!(
+            text.contains("duckcoding_proxy_errors_total{tool=\"codex\",class=\"upstream_5xx\"} 1")
+        );
+        assert!(text.contains("duckcoding_proxy_received_bytes_total{tool=\"codex\"} 42"));
+        assert!(text.contains(
+            "duckcoding_proxy_request_duration_seconds_bucket{tool=\"codex\",le=\"2.5\"} 1"
+        ));
+        assert!(text.contains(
+            "duckcoding_proxy_request_duration_seconds_bucket{tool=\"codex\",le=\"1\"} 0"
+        ));
+        assert!(text.contains("duckcoding_proxy_request_duration_seconds_sum{tool=\"codex\"} 1.2"));
+    }
+}
@@ -2,11 +2,26 @@
 //
 // 包含代理配置、透明代理等功能
 
+pub mod api_keys;
+pub mod capture;
+pub mod client_pool;
 pub mod config; // 代理配置辅助模块
+pub mod gateway;
 pub mod headers;
+pub mod ip_filter;
+pub mod listener;
+pub mod metrics;
+pub mod model_rules;
 pub mod proxy_instance;
 pub mod proxy_manager;
 pub mod proxy_service;
+pub mod rate_limit;
+pub mod retry;
+pub mod tls;
+pub mod transcript;
+pub mod translate;
+pub mod upstream;
+pub mod usage;
 pub mod utils;
 
 pub use headers::{create_request_processor, ProcessedRequest, RequestProcessor};
@@ -0,0 +1,164 @@
+//! 模型映射与路由规则
+//!
+//! 根据请求中的模型名称匹配 `ToolProxyConfig::model_rules`：
+//! - 改写模型名称（由 `RequestProcessor::rewrite_model` 执行）
+//! - 将请求路由到指定 Profile 或 Base URL（如后台 haiku 请求走低价中转）
+
+use crate::models::proxy_config::ModelRule;
+use crate::services::profile_manager::ProfileManager;
+
+use super::upstream::ResolvedUpstream;
+
+/// 查找第一条匹配模型名称的启用规则
+pub fn match_rule<'a>(rules: &'a [ModelRule], model: &str) -> Option<&'a ModelRule> {
+    rules
+        .iter()
+        .find(|rule| rule.enabled && wildcard_match(&rule.pattern, model))
+}
+
+/// 通配符匹配（`*` 匹配任意长度字符，其余字符精确匹配）
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() {
+        return false;
+    }
+    if !text.ends_with(last) {
+        return false;
+    }
+
+    // 中间片段按顺序贪婪匹配
+    let mut remaining = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match remaining.find(part) {
+            Some(pos) => remaining = &remaining[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// 解析规则指定的路由上游
+///
+/// - 配置了 `profile_name` 时通过 ProfileManager 解析该 Profile 的 URL、Key 与协议
+/// - 否则使用 `base_url` 与规则中的 `api_key`（必须填写，不沿用其他上游的 Key）
+///
+/// 规则未配置路由或解析失败时返回 None（继续使用正常上游）
+pub fn resolve_route(
+    tool_id: &str,
+    rule: &ModelRule,
+    profile_manager: Option<&ProfileManager>,
+) -> Option<ResolvedUpstream> {
+    if let Some(profile_name) = rule.profile_name.as_deref().filter(|s| !s.is_empty()) {
+        let credentials = profile_manager
+            .ok_or_else(|| anyhow::anyhow!("ProfileManager 不可用"))
+            .and_then(|mgr| {
+                let (api_key, base_url) = mgr.get_profile_credentials(tool_id, profile_name)?;
+                let protocol = mgr.get_profile_protocol(tool_id, profile_name)?;
+                Ok((api_key, base_url, protocol))
+            });
+        return match credentials {
+            Ok((api_key, base_url, protocol)) => Some(ResolvedUpstream {
+                index: None,
+                profile_name: Some(profile_name.to_string()),
+                base_url,
+                api_key,
+                protocol,
+            }),
+            Err(e) => {
+                tracing::warn!(
+                    tool_id = %tool_id,
+                    pattern = %rule.pattern,
+                    profile = %profile_name,
+                    error = ?e,
+                    "模型路由 Profile 解析失败，使用默认上游"
+                );
+                None
+            }
+        };
+    }
+
+    let base_url = rule.base_url.as_deref().filter(|s| !s.is_empty())?;
+    let Some(api_key) = rule.api_key.as_deref().filter(|s| !s.is_empty()) else {
+        tracing::warn!(
+            tool_id = %tool_id,
+            pattern = %rule.pattern,
+            base_url = %base_url,
+            "模型路由未配置 API Key，已跳过"
+        );
+        return None;
+    };
+
+    Some(ResolvedUpstream {
+        index: None,
+        profile_name: None,
+        base_url: base_url.to_string(),
+        api_key: api_key.to_string(),
+        protocol: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str) -> ModelRule {
+        ModelRule {
+            pattern: pattern.to_string(),
+            rewrite_to: None,
+            profile_name: None,
+            base_url: None,
+            api_key: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("claude-sonnet-4-5", "claude-sonnet-4-5"));
+        assert!(!wildcard_match(
+            "claude-sonnet-4-5",
+            "claude-sonnet-4-5-20250929"
+        ));
+        assert!(wildcard_match(
+            "claude-haiku-*",
+            "claude-haiku-4-5-20251001"
+        ));
+        assert!(wildcard_match("*-mini", "gpt-5-mini"));
+        assert!(wildcard_match("claude-*-4-5*", "claude-opus-4-5-20251101"));
+        assert!(!wildcard_match("claude-*-4-5*", "claude-opus-4-1"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(!wildcard_match("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_match_rule_order_and_enabled() {
+        let mut disabled = rule("claude-haiku-*");
+        disabled.enabled = false;
+        let rules = vec![disabled, rule("claude-*"), rule("claude-haiku-*")];
+
+        let matched = match_rule(&rules, "claude-haiku-4-5").unwrap();
+        assert_eq!(matched.pattern, "claude-*");
+        assert!(match_rule(&rules, "gpt-5").is_none());
+    }
+
+    #[test]
+    fn test_resolve_route_base_url() {
+        let mut r = rule("claude-haiku-*");
+        assert!(resolve_route("claude-code", &r, None).is_none());
+
+        // 未配置 Key 的 Base URL 路由不沿用其他上游的 Key
+        r.base_url = Some("https://cheap.example.com".to_string());
+        assert!(resolve_route("claude-code", &r, None).is_none());
+
+        r.api_key = Some("sk-cheap".to_string());
+        let upstream = resolve_route("claude-code", &r, None).unwrap();
+        assert_eq!(upstream.base_url, "https://cheap.example.com");
+        assert_eq!(upstream.api_key, "sk-cheap");
+        assert_eq!(upstream.profile_name, None);
+    }
+}
@@ -6,30 +6,87 @@
 // - Headers 处理的协调
 
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use bytes::Bytes;
 use http_body_util::BodyExt;
 use hyper::body::{Frame, Incoming};
-use hyper::server::conn::http1;
-use hyper::service::service_fn;
 use hyper::{Method, Request, Response, StatusCode};
-use hyper_util::rt::TokioIo;
-use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::net::TcpListener;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
-use super::headers::RequestProcessor;
+use super::api_keys::{self, AuthError};
+use super::capture::{CaptureRecorder, CaptureStore};
+use super::client_pool::UPSTREAM_CLIENTS;
+use super::headers::{create_request_processor_for, RequestProcessor};
+use super::ip_filter::IpFilter;
+use super::listener::{self, ConnectionHandler, ListenSpec, ServerHandle};
+use super::metrics::{self, ErrorClass, ProxyMetrics, ProxyMetricsSnapshot, METRICS_PATH};
+use super::model_rules;
+use super::rate_limit::{RateLimitPermit, RateLimiter};
+use super::retry::RetryPolicy;
+use super::transcript::TranscriptTap;
+use super::translate::ResponseTranslator;
+use super::upstream::{should_failover_status, ResolvedUpstream, UpstreamPool};
+use super::usage::{self, UsageContext, UsageExtractor, UsageTap};
 use super::utils::body::{box_body, BoxBody};
-use super::utils::{error_responses, loop_detector};
-use crate::models::proxy_config::ToolProxyConfig;
+use super::utils::{error_responses, loop_detector, session};
+use crate::models::proxy_config::{LocalApiKey, ToolProxyConfig};
+use crate::services::session::{SessionEvent, SESSION_MANAGER};
 
 /// 单个代理实例
 pub struct ProxyInstance {
     tool_id: String,
+    state: InstanceState,
+    server_handle: Arc<RwLock<Option<ServerHandle>>>,
+}
+
+/// 请求处理共享的实例状态（更新配置时替换其中的组件，无需重启）
+#[derive(Clone)]
+struct InstanceState {
     config: Arc<RwLock<ToolProxyConfig>>,
-    processor: Arc<dyn RequestProcessor>,
-    server_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    upstream_pool: Arc<RwLock<Arc<UpstreamPool>>>,
+    processor: Arc<RwLock<Arc<dyn RequestProcessor>>>,
+    rate_limiter: Arc<RwLock<Arc<RateLimiter>>>,
+    ip_filter: Arc<RwLock<Arc<IpFilter>>>,
+    metrics: Arc<ProxyMetrics>,
+}
+
+/// 工具的请求处理入口（代理实例自身的监听与统一网关共用）
+#[derive(Clone)]
+pub struct ToolRoute {
+    tool_id: String,
+    state: InstanceState,
+}
+
+impl ToolRoute {
+    /// 处理单个请求
+    pub async fn dispatch(&self, req: Request<Incoming>) -> Response<BoxBody> {
+        handle_request(req, &self.state, &self.tool_id).await
+    }
+
+    /// 是否允许该地址访问（未开启 `allow_public` 时仅允许本机，经统一网关转发时同样生效）
+    pub async fn permits_peer(&self, peer: SocketAddr) -> bool {
+        let ip = peer.ip().to_canonical();
+        (ip.is_loopback() || self.state.config.read().await.allow_public)
+            && self.state.ip_filter.read().await.permits(ip)
+    }
+}
+
+#[async_trait]
+impl ConnectionHandler for ToolRoute {
+    fn name(&self) -> &str {
+        &self.tool_id
+    }
+
+    async fn permits(&self, peer: SocketAddr) -> bool {
+        self.state.ip_filter.read().await.permits(peer.ip())
+    }
+
+    async fn handle(&self, req: Request<Incoming>, _peer: SocketAddr) -> Response<BoxBody> {
+        self.dispatch(req).await
+    }
 }
 
 impl ProxyInstance {
@@ -39,124 +96,87 @@ impl ProxyInstance {
         config: ToolProxyConfig,
         processor: Box<dyn RequestProcessor>,
     ) -> Self {
+        let upstream_pool = UpstreamPool::from_config(&tool_id, &config);
+        let rate_limiter = RateLimiter::new(&config.rate_limit);
+        let ip_filter = IpFilter::new(&config.ip_filter).unwrap_or_else(|e| {
+            tracing::error!(tool_id = %tool_id, error = ?e, "来源地址过滤配置无效，仅允许本机连接");
+            IpFilter::loopback_only()
+        });
         Self {
             tool_id,
-            config: Arc::new(RwLock::new(config)),
-            processor: Arc::from(processor),
+            state: InstanceState {
+                config: Arc::new(RwLock::new(config)),
+                upstream_pool: Arc::new(RwLock::new(Arc::new(upstream_pool))),
+                processor: Arc::new(RwLock::new(Arc::from(processor))),
+                rate_limiter: Arc::new(RwLock::new(Arc::new(rate_limiter))),
+                ip_filter: Arc::new(RwLock::new(Arc::new(ip_filter))),
+                metrics: Arc::new(ProxyMetrics::new()),
+            },
             server_handle: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// 请求处理入口（注册到统一网关）
+    pub fn route(&self) -> ToolRoute {
+        ToolRoute {
+            tool_id: self.tool_id.clone(),
+            state: self.state.clone(),
+        }
+    }
+
     /// 启动代理服务
     pub async fn start(&self) -> Result<()> {
-        // 检查是否已经在运行
-        {
-            let handle = self.server_handle.read().await;
-            if handle.is_some() {
-                anyhow::bail!("代理实例已在运行");
-            }
+        // 持有写锁直到启动完成，避免并发启动
+        let mut server = self.server_handle.write().await;
+        if server.is_some() {
+            anyhow::bail!("代理实例已在运行");
         }
 
-        let config = self.config.read().await.clone();
+        let config = self.state.config.read().await.clone();
 
         // 验证配置
-        if config.real_api_key.is_none() || config.real_base_url.is_none() {
+        IpFilter::new(&config.ip_filter).context("来源地址过滤配置无效")?;
+        if !config.has_upstream() {
             tracing::warn!(
                 tool_id = %self.tool_id,
                 "代理启动时缺少配置，将在运行时拦截请求"
             );
         }
 
-        // 绑定地址
-        let addr = if config.allow_public {
-            SocketAddr::from(([0, 0, 0, 0], config.port))
-        } else {
-            SocketAddr::from(([127, 0, 0, 1], config.port))
-        };
-
-        let listener = TcpListener::bind(addr)
-            .await
-            .context(format!("绑定端口 {} 失败", config.port))?;
+        let spec = ListenSpec::from(&config);
+        let listeners = listener::bind(&spec).await?;
 
         tracing::info!(
             tool_id = %self.tool_id,
-            addr = %addr,
+            addr = %spec,
             bind_mode = if config.allow_public { "0.0.0.0" } else { "127.0.0.1" },
             "透明代理启动成功"
         );
 
-        let config_clone = Arc::clone(&self.config);
-        let processor_clone = Arc::clone(&self.processor);
-        let port = config.port;
-        let tool_id = self.tool_id.clone();
-
-        // 启动服务器
-        let handle = tokio::spawn(async move {
-            loop {
-                match listener.accept().await {
-                    Ok((stream, _addr)) => {
-                        let config = Arc::clone(&config_clone);
-                        let processor = Arc::clone(&processor_clone);
-                        let tool_id_inner = tool_id.clone();
-                        let tool_id_for_error = tool_id.clone();
-
-                        tokio::spawn(async move {
-                            let io = TokioIo::new(stream);
-                            let service = service_fn(move |req| {
-                                let config = Arc::clone(&config);
-                                let processor = Arc::clone(&processor);
-                                let tool_id = tool_id_inner.clone();
-                                async move {
-                                    handle_request(req, config, processor, port, &tool_id).await
-                                }
-                            });
-
-                            if let Err(err) =
-                                http1::Builder::new().serve_connection(io, service).await
-                            {
-                                tracing::error!(
-                                    tool_id = %tool_id_for_error,
-                                    error = ?err,
-                                    "处理连接失败"
-                                );
-                            }
-                        });
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            tool_id = %tool_id,
-                            error = ?e,
-                            "接受连接失败"
-                        );
-                    }
-                }
-            }
-        });
-
-        // 保存服务器句柄
-        {
-            let mut h = self.server_handle.write().await;
-            *h = Some(handle);
-        }
-
+        *server = Some(listener::serve(listeners, Arc::new(self.route())));
         Ok(())
     }
 
     /// 停止代理服务
+    ///
+    /// 立即停止接收新连接，等待进行中的请求完成（最长 `drain_timeout_secs`），超时后强制关闭
     pub async fn stop(&self) -> Result<()> {
-        let handle = {
-            let mut h = self.server_handle.write().await;
-            h.take()
+        let Some(server) = self.server_handle.write().await.take() else {
+            return Ok(());
         };
 
-        if let Some(handle) = handle {
-            handle.abort();
-            tracing::info!(tool_id = %self.tool_id, "透明代理已停止");
-        }
+        let server = server.close_listener().await;
+        let drain_timeout = self.drain_timeout().await;
+        server.drain(drain_timeout).await;
+        tracing::info!(tool_id = %self.tool_id, "透明代理已停止");
 
         Ok(())
     }
 
+    async fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.state.config.read().await.drain_timeout_secs)
+    }
+
     /// 检查服务是否在运行
     pub fn is_running(&self) -> bool {
         // 使用 blocking 方式读取，因为这是同步方法
@@ -170,10 +190,58 @@ impl ProxyInstance {
         handle.is_some()
     }
 
+    /// 获取运行指标快照
+    pub fn metrics_snapshot(&self) -> ProxyMetricsSnapshot {
+        self.state.metrics.snapshot()
+    }
+
     /// 更新配置（无需重启）
+    ///
+    /// 端口、绑定地址或 HTTPS 配置变化时切换监听；切换失败时保持原配置不变并返回错误
     pub async fn update_config(&self, new_config: ToolProxyConfig) -> Result<()> {
-        let mut config = self.config.write().await;
-        *config = new_config;
+        let old_config = self.state.config.read().await.clone();
+
+        let ip_filter = IpFilter::new(&new_config.ip_filter).context("来源地址过滤配置无效")?;
+
+        // 上游协议变化时切换请求处理器（先创建，确保切换监听前配置有效）
+        let processor = if old_config.upstream_protocol != new_config.upstream_protocol {
+            Some(
+                create_request_processor_for(&self.tool_id, new_config.upstream_protocol)
+                    .context("创建请求处理器失败")?,
+            )
+        } else {
+            None
+        };
+
+        let old_spec = ListenSpec::from(&old_config);
+        let new_spec = ListenSpec::from(&new_config);
+        if old_spec != new_spec {
+            listener::swap(
+                &mut *self.server_handle.write().await,
+                &old_spec,
+                &new_spec,
+                Arc::new(self.route()),
+                Duration::from_secs(new_config.drain_timeout_secs),
+            )
+            .await
+            .context("切换监听地址失败")?;
+        }
+
+        if let Some(processor) = processor {
+            *self.state.processor.write().await = Arc::from(processor);
+        }
+        *self.state.ip_filter.write().await = Arc::new(ip_filter);
+
+        // 限流配置未变化时保留计数（避免每次保存配置都重置窗口）
+        if old_config.rate_limit != new_config.rate_limit {
+            let limiter = RateLimiter::new(&new_config.rate_limit);
+            *self.state.rate_limiter.write().await = Arc::new(limiter);
+        }
+
+        let pool = UpstreamPool::from_config(&self.tool_id, &new_config);
+        *self.state.upstream_pool.write().await = Arc::new(pool);
+
+        *self.state.config.write().await = new_config;
         tracing::info!(tool_id = %self.tool_id, "透明代理配置已更新");
         Ok(())
     }
@@ -182,123 +250,504 @@ impl ProxyInstance {
 /// 处理单个请求
 async fn handle_request(
     req: Request<Incoming>,
-    config: Arc<RwLock<ToolProxyConfig>>,
-    processor: Arc<dyn RequestProcessor>,
-    own_port: u16,
+    state: &InstanceState,
     tool_id: &str,
-) -> Result<Response<BoxBody>, Infallible> {
-    match handle_request_inner(req, config, processor, own_port, tool_id).await {
-        Ok(res) => Ok(res),
+) -> Response<BoxBody> {
+    // Prometheus 指标路由（不计入请求指标）
+    if req.uri().path() == METRICS_PATH && req.method() == Method::GET {
+        if let Some(res) = serve_metrics(&req, state, tool_id).await {
+            return res;
+        }
+    }
+
+    let guard = state.metrics.track();
+    let response = match handle_request_inner(req, state, tool_id).await {
+        Ok(res) => {
+            let class = res
+                .extensions()
+                .get::<ErrorClass>()
+                .copied()
+                .or_else(|| ErrorClass::from_status(res.status().as_u16()));
+            if let Some(class) = class {
+                guard.record_error(class);
+            }
+            res
+        }
         Err(e) => {
             tracing::error!(
                 tool_id = %tool_id,
                 error = ?e,
                 "请求处理失败"
             );
-            Ok(error_responses::internal_error(&e.to_string()))
+            guard.record_error(ErrorClass::from_error(&e));
+            error_responses::internal_error(&e.to_string())
+        }
+    };
+
+    // 响应体传输结束时记录耗时（流式响应同时记录首字节时间）
+    let is_sse = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"));
+    response.map(|body| guard.wrap_body(body, is_sse))
+}
+
+/// 输出 Prometheus 格式指标（未开启指标路由时返回 None，按普通请求转发）
+async fn serve_metrics(
+    req: &Request<Incoming>,
+    state: &InstanceState,
+    tool_id: &str,
+) -> Option<Response<BoxBody>> {
+    {
+        let config = state.config.read().await;
+        if !config.metrics_endpoint {
+            return None;
+        }
+        if let Err(e) =
+            api_keys::authenticate(req.headers(), &config, chrono::Utc::now().timestamp())
+        {
+            return Some(auth_error_response(&e));
         }
     }
+
+    let text = metrics::render_prometheus(&[(tool_id.to_string(), state.metrics.snapshot())]);
+    Some(
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain; version=0.0.4")
+            .body(box_body(http_body_util::Full::new(Bytes::from(text))))
+            .unwrap(),
+    )
+}
+
+/// 本地 Key 校验失败的响应
+fn auth_error_response(error: &AuthError) -> Response<BoxBody> {
+    match error {
+        AuthError::Invalid => error_responses::unauthorized(),
+        AuthError::Revoked(label) => error_responses::api_key_revoked(label),
+        AuthError::Expired(label) => error_responses::api_key_expired(label),
+    }
+}
+
+/// 签发 Key 的 Token 配额是否已用尽
+async fn quota_exhausted(tool_id: &str, key: &LocalApiKey) -> bool {
+    match key.token_quota {
+        Some(quota) => api_keys::KEY_USAGE.used(tool_id, &key.label).await >= quota,
+        None => false,
+    }
 }
 
 async fn handle_request_inner(
     req: Request<Incoming>,
-    config: Arc<RwLock<ToolProxyConfig>>,
-    processor: Arc<dyn RequestProcessor>,
-    own_port: u16,
+    state: &InstanceState,
     tool_id: &str,
 ) -> Result<Response<BoxBody>> {
+    // 跳数超限：请求在多个代理之间形成环路
+    let hops = loop_detector::hop_count(req.headers());
+    if hops >= loop_detector::MAX_HOPS {
+        tracing::warn!(tool_id = %tool_id, hops = hops, "代理跳数超限，疑似多代理回环");
+        return Ok(error_responses::proxy_hop_limit_exceeded(tool_id, hops));
+    }
+
     // 获取配置
     let proxy_config = {
-        let cfg = config.read().await;
-        if cfg.real_api_key.is_none() || cfg.real_base_url.is_none() {
+        let cfg = state.config.read().await;
+        if !cfg.has_upstream() {
             return Ok(error_responses::configuration_missing(tool_id));
         }
         cfg.clone()
     };
 
-    // 验证本地 API Key
-    let auth_header = req
-        .headers()
-        .get("authorization")
-        .or_else(|| req.headers().get("x-api-key"))
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
-
-    let provided_key = if let Some(stripped) = auth_header.strip_prefix("Bearer ") {
-        stripped
-    } else if let Some(stripped) = auth_header.strip_prefix("x-api-key ") {
-        stripped
-    } else {
-        auth_header
+    // 验证本地 API Key（签发的 Key 同时校验配额）
+    let api_key = match api_keys::authenticate(
+        req.headers(),
+        &proxy_config,
+        chrono::Utc::now().timestamp(),
+    ) {
+        Ok(key) => key,
+        Err(e) => return Ok(auth_error_response(&e)),
     };
-
-    if let Some(local_key) = &proxy_config.local_api_key {
-        if provided_key != local_key {
-            return Ok(error_responses::unauthorized());
+    if let Some(key) = api_key {
+        if quota_exhausted(tool_id, key).await {
+            return Ok(error_responses::quota_exceeded(&key.label));
         }
     }
+    let key_label = api_key.map(|key| key.label.clone());
+
+    // 解析本次请求的候选上游
+    let pool = Arc::clone(&*state.upstream_pool.read().await);
+    let processor = Arc::clone(&*state.processor.read().await);
+    let rate_limiter = Arc::clone(&*state.rate_limiter.read().await);
+    let mut candidates = pool.candidates();
+    if candidates.is_empty() {
+        return Ok(error_responses::configuration_missing(tool_id));
+    }
 
     // 提取请求信息（先借用，避免与后续的 collect 冲突）
-    let path = req.uri().path().to_string();
+    let mut path = req.uri().path().to_string();
     let query = req.uri().query().map(|s| s.to_string());
     let method = req.method().clone();
     let headers = req.headers().clone();
 
-    let base = proxy_config
-        .real_base_url
-        .as_ref()
-        .unwrap()
-        .trim_end_matches('/');
-
     // 读取请求体（消费 req）
-    let body_bytes = if method != Method::GET && method != Method::HEAD {
+    let mut body_bytes = if method != Method::GET && method != Method::HEAD {
         req.collect().await?.to_bytes()
     } else {
         Bytes::new()
     };
+    state.metrics.record_bytes_in(body_bytes.len());
 
-    // 使用 RequestProcessor 统一处理请求（URL + headers + body）
-    let processed = processor
-        .process_outgoing_request(
-            base,
-            proxy_config.real_api_key.as_ref().unwrap(),
-            &path,
-            query.as_deref(),
-            &headers,
-            &body_bytes,
-        )
-        .await
-        .context("处理出站请求失败")?;
+    let mut request_model = usage::extract_request_model(&path, &body_bytes);
 
-    // 回环检测
-    if loop_detector::is_proxy_loop(&processed.target_url, own_port) {
-        return Ok(error_responses::proxy_loop_detected(tool_id));
-    }
+    // 识别会话：记录会话请求，会话配置了自定义端点时所有候选上游都改用该端点
+    // （模型路由规则优先，命中路由时不使用会话端点）
+    let session_id = session::identify(tool_id, &headers, &body_bytes);
+    let mut session_endpoint = session_id
+        .as_deref()
+        .and_then(|id| session::resolve_endpoint(tool_id, id));
 
-    tracing::debug!(
-        tool_id = %tool_id,
-        method = %method,
-        path = %path,
-        target_url = %processed.target_url,
-        "代理请求"
-    );
+    // 模型映射与路由规则
+    if let Some(rule) = request_model
+        .as_deref()
+        .and_then(|model| model_rules::match_rule(&proxy_config.model_rules, model))
+    {
+        if let Some(target) = rule.rewrite_to.as_deref().filter(|s| !s.is_empty()) {
+            tracing::debug!(
+                tool_id = %tool_id,
+                from = ?request_model,
+                to = %target,
+                "改写模型名称"
+            );
+            (path, body_bytes) = processor.rewrite_model(&path, &body_bytes, target);
+            request_model = Some(target.to_string());
+        }
 
-    // 构建上游请求（使用处理后的信息）
-    let mut reqwest_builder = reqwest::Client::new().request(method.clone(), &processed.target_url);
+        // 路由上游优先尝试，失败时回退到正常上游
+        if let Some(route) = pool.route(rule) {
+            tracing::debug!(
+                tool_id = %tool_id,
+                pattern = %rule.pattern,
+                profile = ?route.profile_name,
+                "模型路由命中"
+            );
+            candidates.insert(0, route);
+            if session_endpoint.take().is_some() {
+                tracing::debug!(tool_id = %tool_id, session_id = ?session_id, "模型路由命中，忽略会话端点");
+            }
+        }
+    }
 
-    // 应用处理后的 headers
-    for (name, value) in processed.headers.iter() {
-        reqwest_builder = reqwest_builder.header(name, value);
+    // 签发的 Key 仅能使用允许的 Profile（会话端点替换所有候选上游，按会话绑定的 Profile 检查）
+    if let Some(key) = api_key.filter(|key| !key.allowed_profiles.is_empty()) {
+        match &session_endpoint {
+            Some(endpoint) => {
+                if !key.allows_profile(endpoint.profile_name.as_deref()) {
+                    return Ok(error_responses::profile_not_allowed(&key.label));
+                }
+            }
+            None => {
+                // 未引用 Profile 的路由上游（Base URL 路由）不在允许范围内
+                candidates.retain(|c| key.allows_profile(c.profile_name.as_deref()));
+                if candidates.is_empty() {
+                    return Ok(error_responses::profile_not_allowed(&key.label));
+                }
+            }
+        }
     }
 
-    // 添加请求体
-    if !processed.body.is_empty() {
-        reqwest_builder = reqwest_builder.body(processed.body.to_vec());
+    // 会话端点替换全部候选上游：只发送到该端点，不参与上游池的健康统计（index 为 None）
+    if let Some(endpoint) = session_endpoint {
+        candidates = vec![ResolvedUpstream {
+            index: None,
+            profile_name: endpoint.profile_name,
+            base_url: endpoint.base_url,
+            api_key: endpoint.api_key,
+            protocol: endpoint.protocol,
+        }];
     }
 
-    // 发送请求
-    let upstream_res = reqwest_builder.send().await.context("上游请求失败")?;
+    // 抓包存储（仅在开启抓包时创建）
+    let capture_store = if proxy_config.capture.enabled {
+        match CaptureStore::for_tool(tool_id, &proxy_config.capture) {
+            Ok(store) => Some(store),
+            Err(e) => {
+                tracing::warn!(tool_id = %tool_id, error = ?e, "打开抓包存储失败");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // 本地限流：超出阈值时排队等待，截止时间对工具级与 Profile 级共用
+    let deadline = rate_limiter.deadline();
+    let Some(mut permit) = rate_limiter.acquire_tool(deadline).await else {
+        tracing::warn!(tool_id = %tool_id, "本地限流排队超时");
+        return Ok(error_responses::rate_limited(tool_id));
+    };
+
+    let retry_policy = RetryPolicy::new(&proxy_config.retry);
+    let last_index = candidates.len() - 1;
+    // 发送到上游的总次数（含重试与故障转移）
+    let mut attempts: u32 = 0;
+
+    // 按顺序尝试候选上游：瞬时错误先在同一上游退避重试，仍失败时（或 5xx、429）切换到下一个
+    'upstreams: for (index, upstream) in candidates.iter().enumerate() {
+        let is_last = index == last_index;
 
+        // Profile 声明的协议与代理配置不同时（如 Codex Profile 的 wire_api），使用对应的处理器
+        let upstream_processor: Arc<dyn RequestProcessor> = match upstream.protocol {
+            Some(protocol) if protocol != proxy_config.upstream_protocol => {
+                create_request_processor_for(tool_id, protocol)
+                    .map(Arc::from)
+                    .unwrap_or_else(|_| Arc::clone(&processor))
+            }
+            _ => Arc::clone(&processor),
+        };
+
+        // 使用 RequestProcessor 统一处理请求（URL + headers + body）
+        let processed = upstream_processor
+            .process_outgoing_request(
+                upstream.base_url.trim_end_matches('/'),
+                &upstream.api_key,
+                &path,
+                query.as_deref(),
+                &headers,
+                &body_bytes,
+            )
+            .await
+            .context("处理出站请求失败")?;
+
+        // 回环检测（目标解析后是否落在本机任一代理监听地址上）
+        let own_ports: Vec<u16> = std::iter::once(proxy_config.port)
+            .chain(proxy_config.tls_port())
+            .collect();
+        if loop_detector::is_proxy_loop(&processed.target_url, &own_ports).await {
+            return Ok(error_responses::proxy_loop_detected(tool_id));
+        }
+
+        let profile_name = upstream.profile_name.clone();
+        let Some(profile_permit) = rate_limiter
+            .acquire_profile(profile_name.as_deref(), deadline)
+            .await
+        else {
+            tracing::warn!(
+                tool_id = %tool_id,
+                profile = ?profile_name,
+                "Profile 限流排队超时"
+            );
+            if is_last {
+                return Ok(error_responses::rate_limited(tool_id));
+            }
+            continue 'upstreams;
+        };
+
+        let usage_context_for = |status_code: u16, attempts: u32| UsageContext {
+            tool_id: tool_id.to_string(),
+            session_id: session_id.clone(),
+            profile_name: profile_name.clone(),
+            request_model: request_model.clone(),
+            status_code,
+            attempts,
+            key_label: key_label.clone(),
+        };
+
+        let mut retries: u32 = 0;
+        loop {
+            attempts += 1;
+
+            // 每次发送都计入请求数窗口（首次发送已在获取许可时计入）
+            if attempts > 1 && !permit.readmit(deadline).await {
+                tracing::warn!(tool_id = %tool_id, "本地限流排队超时");
+                return Ok(error_responses::rate_limited(tool_id));
+            }
+            if retries > 0 && !profile_permit.readmit(deadline).await {
+                tracing::warn!(
+                    tool_id = %tool_id,
+                    profile = ?profile_name,
+                    "Profile 限流排队超时"
+                );
+                if is_last {
+                    return Ok(error_responses::rate_limited(tool_id));
+                }
+                continue 'upstreams;
+            }
+
+            tracing::debug!(
+                tool_id = %tool_id,
+                method = %method,
+                path = %path,
+                target_url = %processed.target_url,
+                profile = ?upstream.profile_name,
+                attempt = attempts,
+                "代理请求"
+            );
+
+            let mut capture = capture_store.as_ref().map(|store| {
+                CaptureRecorder::new(
+                    store.clone(),
+                    &proxy_config.capture,
+                    tool_id,
+                    method.as_str(),
+                    &path,
+                    query.as_deref(),
+                    &processed.target_url,
+                    upstream.profile_name.as_deref(),
+                    &processed.headers,
+                    &body_bytes,
+                )
+            });
+
+            // 构建上游请求（使用处理后的信息，按上游复用连接）
+            let client = UPSTREAM_CLIENTS.get(&processed.target_url)?;
+            let mut reqwest_builder = client.request(method.clone(), &processed.target_url);
+
+            // 应用处理后的 headers
+            // - 去掉 accept-encoding，确保响应体可解析用量
+            // - 去掉 content-length，请求体可能已被改写，由 reqwest 重新计算
+            // - 跳数头由代理重新设置（仅发往可能是 DuckCoding 实例的本机 / 内网地址）
+            for (name, value) in processed.headers.iter() {
+                if name == "accept-encoding"
+                    || name == "content-length"
+                    || name == loop_detector::HOP_HEADER
+                {
+                    continue;
+                }
+                reqwest_builder = reqwest_builder.header(name, value);
+            }
+            if loop_detector::forwards_hops(&processed.target_url) {
+                reqwest_builder = reqwest_builder.header(loop_detector::HOP_HEADER, hops + 1);
+            }
+
+            // 添加请求体
+            if !processed.body.is_empty() {
+                reqwest_builder = reqwest_builder.body(processed.body.clone());
+            }
+
+            // 发送请求
+            let upstream_res = match reqwest_builder.send().await {
+                Ok(res) => res,
+                Err(e) => {
+                    if let Some(capture) = capture.as_mut() {
+                        capture.set_error(&e.to_string());
+                    }
+                    if let Some(delay) = retry_policy.delay_for_error(&e, retries) {
+                        tracing::warn!(
+                            tool_id = %tool_id,
+                            profile = ?upstream.profile_name,
+                            error = ?e,
+                            delay_ms = delay.as_millis() as u64,
+                            "上游连接失败，退避后重试"
+                        );
+                        retries += 1;
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                    pool.report_failure(upstream);
+                    if is_last {
+                        // 连接失败同样记录尝试次数（客户端收到内部错误响应）
+                        usage::record_usage(
+                            &usage_context_for(
+                                StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                                attempts,
+                            ),
+                            None,
+                        );
+                        return Err(e).context("上游请求失败");
+                    }
+                    tracing::warn!(
+                        tool_id = %tool_id,
+                        profile = ?upstream.profile_name,
+                        error = ?e,
+                        "上游连接失败，切换到下一个上游"
+                    );
+                    continue 'upstreams;
+                }
+            };
+
+            let status = upstream_res.status().as_u16();
+            if let Some(capture) = capture.as_mut() {
+                capture.set_response(status, upstream_res.headers());
+            }
+
+            // 响应尚未发送给客户端，可以丢弃后重试
+            if let Some(delay) =
+                retry_policy.delay_for_status(status, upstream_res.headers(), retries)
+            {
+                tracing::warn!(
+                    tool_id = %tool_id,
+                    profile = ?upstream.profile_name,
+                    status = status,
+                    delay_ms = delay.as_millis() as u64,
+                    "上游返回瞬时错误，退避后重试"
+                );
+                drop(upstream_res);
+                retries += 1;
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            if should_failover_status(status) {
+                pool.report_failure(upstream);
+                if !is_last {
+                    tracing::warn!(
+                        tool_id = %tool_id,
+                        profile = ?upstream.profile_name,
+                        status = status,
+                        "上游返回错误状态，切换到下一个上游"
+                    );
+                    continue 'upstreams;
+                }
+            } else {
+                pool.report_success(upstream);
+            }
+
+            // 记录会话使用的本地 Key
+            if let (Some(session_id), Some(label)) = (&session_id, &key_label) {
+                if let Err(e) = SESSION_MANAGER.send_event(SessionEvent::KeyLabel {
+                    session_id: session_id.clone(),
+                    key_label: label.clone(),
+                }) {
+                    tracing::warn!("Session 事件发送失败: {}", e);
+                }
+            }
+
+            let usage_context = usage_context_for(status, attempts);
+
+            // 对话记录（仅在开启且识别到会话时记录成功的响应）
+            let transcript = session_id
+                .as_deref()
+                .filter(|_| proxy_config.transcript.enabled && status < 400)
+                .map(|id| TranscriptTap::new(tool_id, id, &body_bytes, &proxy_config.transcript));
+
+            permit.merge(profile_permit);
+            return build_response(
+                upstream_res,
+                tool_id,
+                usage_context,
+                capture,
+                transcript,
+                processed.response_translator,
+                permit,
+            )
+            .await;
+        }
+    }
+
+    anyhow::bail!("没有可用的上游")
+}
+
+/// 将上游响应转换为返回给客户端的响应
+async fn build_response(
+    upstream_res: reqwest::Response,
+    tool_id: &str,
+    usage_context: UsageContext,
+    mut capture: Option<CaptureRecorder>,
+    mut transcript: Option<TranscriptTap>,
+    translator: Option<Box<dyn ResponseTranslator>>,
+    permit: RateLimitPermit,
+) -> Result<Response<BoxBody>> {
     // 构建响应
     let status = StatusCode::from_u16(upstream_res.status().as_u16())
         .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
@@ -313,8 +762,11 @@ async fn handle_request_inner(
 
     let mut response = Response::builder().status(status);
 
-    // 复制响应 headers
+    // 复制响应 headers（响应体需要转换时去掉 content-length）
     for (name, value) in upstream_res.headers().iter() {
+        if translator.is_some() && name == "content-length" {
+            continue;
+        }
         response = response.header(name.as_str(), value.as_bytes());
     }
 
@@ -322,20 +774,283 @@ async fn handle_request_inner(
         tracing::debug!(tool_id = %tool_id, "SSE 流式响应");
         use futures_util::StreamExt;
 
+        // 用量探针随流移动，流结束或被丢弃时写入用量记录（基于上游原始数据）
+        // 限流许可同样随流移动，传输结束后才释放并发数
+        let mut tap = UsageTap::new(usage_context).with_rate_limit_permit(permit);
+        let translator = translator.map(|t| Arc::new(std::sync::Mutex::new(t)));
+        let chunk_translator = translator.clone();
         let stream = upstream_res.bytes_stream();
-        let mapped_stream = stream.map(|result| {
+        let mapped_stream = stream.map(move |result| {
+            if let Ok(chunk) = &result {
+                tap.observe(chunk);
+                if let Some(capture) = capture.as_mut() {
+                    capture.push_sse_chunk(chunk);
+                }
+                if let Some(transcript) = transcript.as_mut() {
+                    transcript.observe(chunk);
+                }
+            }
+            let result = match (&chunk_translator, result) {
+                (Some(translator), Ok(chunk)) => Ok(Bytes::from(
+                    translator
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .translate_chunk(&chunk),
+                )),
+                (_, result) => result,
+            };
             result
                 .map(Frame::data)
                 .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)
         });
 
-        let body = http_body_util::StreamBody::new(mapped_stream);
+        // 上游流结束后由转换器补发剩余事件
+        let tail = futures_util::stream::once(async move {
+            translator.map(|translator| {
+                let bytes = translator
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .finish();
+                Ok(Frame::data(Bytes::from(bytes)))
+            })
+        })
+        .filter_map(|frame| async move { frame });
+
+        let body = http_body_util::StreamBody::new(mapped_stream.chain(tail));
         Ok(response.body(box_body(body)).unwrap())
     } else {
         // 普通响应
         let body_bytes = upstream_res.bytes().await.context("读取响应体失败")?;
+
+        let mut extractor = UsageExtractor::new();
+        extractor.feed_json_body(&body_bytes);
+        let extracted = extractor.finish();
+        if let Some(extracted) = &extracted {
+            permit.record_tokens(extracted.total_tokens());
+        }
+        usage::record_usage(&usage_context, extracted);
+
+        if let Some(mut capture) = capture {
+            capture.set_response_body(&body_bytes);
+            capture.finish();
+        }
+        if let Some(mut transcript) = transcript {
+            transcript.observe_json(&body_bytes);
+        }
+
+        let body_bytes = match translator {
+            Some(mut translator) => match translator.translate_body(status.as_u16(), &body_bytes) {
+                Ok(translated) => Bytes::from(translated),
+                Err(e) => {
+                    tracing::warn!(tool_id = %tool_id, error = ?e, "响应转换失败，返回原始响应");
+                    body_bytes
+                }
+            },
+            None => body_bytes,
+        };
+
         Ok(response
             .body(box_body(http_body_util::Full::new(body_bytes)))
             .unwrap())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 启动一个延迟响应的上游，返回其地址
+    async fn slow_upstream(delay: Duration) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let _ = stream.read(&mut buf).await;
+                    tokio::time::sleep(delay).await;
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                        .await;
+                });
+            }
+        });
+        addr
+    }
+
+    async fn free_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    async fn start_instance(upstream: SocketAddr, drain_timeout_secs: u64) -> (ProxyInstance, u16) {
+        let port = free_port().await;
+        let mut config = ToolProxyConfig::new(port);
+        config.enabled = true;
+        config.real_api_key = Some("sk-test".to_string());
+        config.real_base_url = Some(format!("http://{upstream}"));
+        config.drain_timeout_secs = drain_timeout_secs;
+
+        let processor =
+            create_request_processor_for("claude-code", config.upstream_protocol).unwrap();
+        let instance = ProxyInstance::new("claude-code".to_string(), config, processor);
+        instance.start().await.unwrap();
+        (instance, port)
+    }
+
+    /// 等待请求到达代理
+    async fn wait_in_flight(instance: &ProxyInstance) {
+        while instance.metrics_snapshot().in_flight == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_drains_in_flight_requests() {
+        let upstream = slow_upstream(Duration::from_millis(300)).await;
+        let (instance, port) = start_instance(upstream, 5).await;
+
+        let request = tokio::spawn(async move {
+            reqwest::get(format!("http://127.0.0.1:{port}/v1/models")).await
+        });
+        wait_in_flight(&instance).await;
+
+        instance.stop().await.unwrap();
+        assert!(!instance.is_running_async().await);
+
+        // 停止前已发出的请求正常完成
+        let response = request.await.unwrap().unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "ok");
+
+        // 停止后不再接收新连接
+        assert!(reqwest::get(format!("http://127.0.0.1:{port}/v1/models"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stop_force_closes_after_drain_timeout() {
+        let upstream = slow_upstream(Duration::from_secs(30)).await;
+        let (instance, port) = start_instance(upstream, 0).await;
+
+        let request = tokio::spawn(async move {
+            reqwest::get(format!("http://127.0.0.1:{port}/v1/models")).await
+        });
+        wait_in_flight(&instance).await;
+
+        tokio::time::timeout(Duration::from_secs(5), instance.stop())
+            .await
+            .expect("排空超时后应强制关闭")
+            .unwrap();
+        assert!(request.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_config_swaps_listener() {
+        let upstream = slow_upstream(Duration::from_millis(300)).await;
+        let (instance, old_port) = start_instance(upstream, 5).await;
+
+        // 切换前发出的请求在旧连接上完成
+        let request = tokio::spawn(async move {
+            reqwest::get(format!("http://127.0.0.1:{old_port}/v1/models")).await
+        });
+        wait_in_flight(&instance).await;
+
+        let new_port = free_port().await;
+        let mut config = instance.state.config.read().await.clone();
+        config.port = new_port;
+        instance.update_config(config).await.unwrap();
+
+        assert_eq!(request.await.unwrap().unwrap().status(), 200);
+        let response = reqwest::get(format!("http://127.0.0.1:{new_port}/v1/models"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(
+            reqwest::get(format!("http://127.0.0.1:{old_port}/v1/models"))
+                .await
+                .is_err()
+        );
+
+        instance.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_config_keeps_listener_on_port_conflict() {
+        let upstream = slow_upstream(Duration::ZERO).await;
+        let (instance, old_port) = start_instance(upstream, 5).await;
+
+        let occupied = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config = instance.state.config.read().await.clone();
+        config.port = occupied.local_addr().unwrap().port();
+        assert!(instance.update_config(config).await.is_err());
+
+        // 原监听与配置保持不变
+        assert_eq!(instance.state.config.read().await.port, old_port);
+        let response = reqwest::get(format!("http://127.0.0.1:{old_port}/v1/models"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        instance.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ip_filter_rejects_denied_peers() {
+        let upstream = slow_upstream(Duration::ZERO).await;
+        let (instance, port) = start_instance(upstream, 5).await;
+        let url = format!("http://127.0.0.1:{port}/v1/models");
+
+        let mut config = instance.state.config.read().await.clone();
+        config.ip_filter.deny = vec!["127.0.0.0/8".to_string()];
+        instance.update_config(config.clone()).await.unwrap();
+        assert!(reqwest::get(&url).await.is_err());
+
+        // 无效条目不生效，保持原过滤规则
+        config.ip_filter.deny.push("office".to_string());
+        assert!(instance.update_config(config.clone()).await.is_err());
+        assert!(reqwest::get(&url).await.is_err());
+
+        config.ip_filter.deny.clear();
+        instance.update_config(config).await.unwrap();
+        assert_eq!(reqwest::get(&url).await.unwrap().status(), 200);
+
+        instance.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_loop_detection_covers_other_proxies_and_hops() {
+        let upstream = slow_upstream(Duration::ZERO).await;
+        let (target, target_port) = start_instance(upstream, 0).await;
+
+        // 上游指向本机另一个代理（经 localhost 访问）
+        let (chained, chained_port) = start_instance(upstream, 0).await;
+        let mut config = chained.state.config.read().await.clone();
+        config.real_base_url = Some(format!("http://localhost:{target_port}"));
+        chained.update_config(config).await.unwrap();
+
+        let url = format!("http://127.0.0.1:{chained_port}/v1/models");
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), 502);
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("PROXY_LOOP_DETECTED"));
+
+        // 跳数达到上限的请求直接拒绝
+        let response = reqwest::Client::new()
+            .get(format!("http://127.0.0.1:{target_port}/v1/models"))
+            .header(loop_detector::HOP_HEADER, loop_detector::MAX_HOPS)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 508);
+
+        chained.stop().await.unwrap();
+        target.stop().await.unwrap();
+    }
+}
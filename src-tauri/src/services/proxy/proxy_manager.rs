@@ -4,26 +4,36 @@
 // - 启动和停止指定工具的代理
 // - 管理所有代理实例的状态
 // - 确保端口不冲突
+// - 维护统一网关的工具路由
 
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use super::headers::create_request_processor;
+use super::gateway::{Gateway, GatewayStatus};
+use super::headers::create_request_processor_for;
+use super::metrics::ProxyMetricsSnapshot;
 use super::proxy_instance::ProxyInstance;
-use crate::models::proxy_config::ToolProxyConfig;
+use crate::models::proxy_config::{GatewayConfig, ToolProxyConfig};
 
 /// 代理管理器
 pub struct ProxyManager {
     instances: Arc<RwLock<HashMap<String, ProxyInstance>>>,
+    gateway: Gateway,
 }
 
 impl ProxyManager {
     /// 创建新的代理管理器
     pub fn new() -> Self {
+        Self::with_gateway_config(GatewayConfig::default())
+    }
+
+    /// 使用已保存的网关配置创建代理管理器（网关不会自动启动）
+    pub fn with_gateway_config(gateway_config: GatewayConfig) -> Self {
         Self {
             instances: Arc::new(RwLock::new(HashMap::new())),
+            gateway: Gateway::new(gateway_config),
         }
     }
 
@@ -59,7 +69,8 @@ impl ProxyManager {
         }
 
         // 创建 RequestProcessor
-        let processor = create_request_processor(tool_id).context("创建请求处理器失败")?;
+        let processor = create_request_processor_for(tool_id, config.upstream_protocol)
+            .context("创建请求处理器失败")?;
 
         // 创建并启动代理实例
         let instance = ProxyInstance::new(tool_id.to_string(), config, processor);
@@ -68,7 +79,8 @@ impl ProxyManager {
             .await
             .context(format!("启动 {tool_id} 代理失败"))?;
 
-        // 存入 HashMap
+        // 存入 HashMap，并注册到统一网关
+        self.gateway.register(tool_id, instance.route()).await;
         {
             let mut instances = self.instances.write().await;
             instances.insert(tool_id.to_string(), instance);
@@ -86,9 +98,11 @@ impl ProxyManager {
     /// - `Ok(())`: 停止成功（或代理未运行）
     /// - `Err`: 停止失败
     pub async fn stop_proxy(&self, tool_id: &str) -> Result<()> {
-        let mut instances = self.instances.write().await;
+        // 先移出实例再停止，排空期间不阻塞其他状态查询
+        let instance = self.instances.write().await.remove(tool_id);
+        self.gateway.unregister(tool_id).await;
 
-        if let Some(instance) = instances.remove(tool_id) {
+        if let Some(instance) = instance {
             instance
                 .stop()
                 .await
@@ -100,26 +114,55 @@ impl ProxyManager {
         Ok(())
     }
 
-    /// 停止所有运行中的代理
+    /// 停止所有运行中的代理及统一网关（并行排空，总耗时不超过最长的排空超时）
     pub async fn stop_all(&self) -> Result<()> {
-        let mut instances = self.instances.write().await;
-        let tool_ids: Vec<String> = instances.keys().cloned().collect();
-
-        for tool_id in tool_ids {
-            if let Some(instance) = instances.remove(&tool_id) {
-                if let Err(e) = instance.stop().await {
-                    tracing::error!(
-                        tool_id = %tool_id,
-                        error = ?e,
-                        "停止代理失败"
-                    );
-                }
-            }
+        let instances: Vec<_> = self.instances.write().await.drain().collect();
+        for tool_id in instances.iter().map(|(tool_id, _)| tool_id) {
+            self.gateway.unregister(tool_id).await;
         }
 
+        let stops = instances.into_iter().map(|(tool_id, instance)| async move {
+            if let Err(e) = instance.stop().await {
+                tracing::error!(
+                    tool_id = %tool_id,
+                    error = ?e,
+                    "停止代理失败"
+                );
+            }
+        });
+        let stop_gateway = async {
+            if let Err(e) = self.gateway.stop().await {
+                tracing::error!(error = ?e, "停止统一网关失败");
+            }
+        };
+        futures_util::future::join(futures_util::future::join_all(stops), stop_gateway).await;
+
         Ok(())
     }
 
+    /// 启动统一网关（仅转发到已启动的工具代理）
+    pub async fn start_gateway(&self) -> Result<()> {
+        self.gateway.start().await.context("启动统一网关失败")
+    }
+
+    /// 停止统一网关（不影响各工具代理）
+    pub async fn stop_gateway(&self) -> Result<()> {
+        self.gateway.stop().await.context("停止统一网关失败")
+    }
+
+    /// 获取统一网关状态
+    pub async fn gateway_status(&self) -> GatewayStatus {
+        self.gateway.status().await
+    }
+
+    /// 更新统一网关配置（网关运行中时立即生效）
+    pub async fn update_gateway_config(&self, config: GatewayConfig) -> Result<()> {
+        self.gateway
+            .update_config(config)
+            .await
+            .context("更新统一网关配置失败")
+    }
+
     /// 检查指定工具的代理是否在运行
     pub async fn is_running(&self, tool_id: &str) -> bool {
         let instances = self.instances.read().await;
@@ -151,6 +194,23 @@ impl ProxyManager {
         status_map
     }
 
+    /// 获取所有运行中代理的指标快照
+    ///
+    /// # 返回
+    /// - HashMap<tool_id, 指标快照>
+    pub async fn get_all_metrics(&self) -> HashMap<String, ProxyMetricsSnapshot> {
+        let instances = self.instances.read().await;
+        let mut metrics_map = HashMap::new();
+
+        for (tool_id, instance) in instances.iter() {
+            if instance.is_running_async().await {
+                metrics_map.insert(tool_id.clone(), instance.metrics_snapshot());
+            }
+        }
+
+        metrics_map
+    }
+
     /// 更新指定工具的代理配置（无需重启）
     pub async fn update_config(&self, tool_id: &str, config: ToolProxyConfig) -> Result<()> {
         let instances = self.instances.read().await;
@@ -193,5 +253,20 @@ mod tests {
         assert!(status.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_get_all_metrics_empty() {
+        let manager = ProxyManager::new();
+        assert!(manager.get_all_metrics().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_gateway_status_default() {
+        let manager = ProxyManager::new();
+        let status = manager.gateway_status().await;
+        assert!(!status.running);
+        assert_eq!(status.port, 8790);
+        assert!(status.tools.is_empty());
+    }
+
     // 更多测试需要 mock 或集成测试环境
 }
@@ -225,6 +225,7 @@ mod tests {
             hide_transparent_proxy_tip: false,
             hide_session_config_hint: false,
             log_config: crate::models::config::LogConfig::default(),
+            session_retention: crate::models::config::SessionRetentionConfig::default(),
             onboarding_status: None,
             external_watch_enabled: true,
             external_poll_interval_ms: 5000,
@@ -253,6 +254,7 @@ mod tests {
             hide_transparent_proxy_tip: false,
             hide_session_config_hint: false,
             log_config: crate::models::config::LogConfig::default(),
+            session_retention: crate::models::config::SessionRetentionConfig::default(),
             onboarding_status: None,
             external_watch_enabled: true,
             external_poll_interval_ms: 5000,
@@ -284,6 +286,7 @@ mod tests {
             hide_transparent_proxy_tip: false,
             hide_session_config_hint: false,
             log_config: crate::models::config::LogConfig::default(),
+            session_retention: crate::models::config::SessionRetentionConfig::default(),
             onboarding_status: None,
             external_watch_enabled: true,
             external_poll_interval_ms: 5000,
@@ -0,0 +1,346 @@
+//! 本地限流
+//!
+//! 按 `RateLimitConfig` 对工具级与 Profile 级分别限制：
+//! - 每分钟请求数（滑动窗口，按发往上游的次数计，含重试与故障转移）
+//! - 每分钟 Token 数（滑动窗口，响应结束后按用量记入）
+//! - 最大并发请求数（信号量，许可随响应流一起释放）
+//!
+//! 超出阈值的请求排队等待，直到截止时间仍无法发送时放弃
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::models::proxy_config::{RateLimitConfig, RateLimits};
+
+/// 滑动窗口长度
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// 单个限流桶（工具级或某个 Profile）
+#[derive(Debug)]
+struct Bucket {
+    limits: RateLimits,
+    semaphore: Option<Arc<Semaphore>>,
+    window: Mutex<Window>,
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    /// 窗口内的请求发送时间
+    requests: VecDeque<Instant>,
+    /// 窗口内的 Token 消耗 (记录时间, Token 数)
+    tokens: VecDeque<(Instant, u64)>,
+}
+
+impl Window {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .requests
+            .front()
+            .is_some_and(|&t| now.duration_since(t) >= WINDOW)
+        {
+            self.requests.pop_front();
+        }
+        while self
+            .tokens
+            .front()
+            .is_some_and(|&(t, _)| now.duration_since(t) >= WINDOW)
+        {
+            self.tokens.pop_front();
+        }
+    }
+}
+
+impl Bucket {
+    fn new(limits: RateLimits) -> Self {
+        Self {
+            semaphore: limits
+                .max_concurrent
+                .map(|n| Arc::new(Semaphore::new(n.max(1) as usize))),
+            limits,
+            window: Mutex::new(Window::default()),
+        }
+    }
+
+    /// 窗口阈值允许发送时记录本次请求并返回 None，否则返回需要等待的时间
+    fn try_admit(&self, now: Instant) -> Option<Duration> {
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.prune(now);
+
+        let mut wait = Duration::ZERO;
+        if let Some(rpm) = self.limits.requests_per_minute {
+            if window.requests.len() >= rpm.max(1) as usize {
+                let oldest = window.requests[window.requests.len() - rpm.max(1) as usize];
+                wait = wait.max(WINDOW.saturating_sub(now.duration_since(oldest)));
+            }
+        }
+        if let Some(tpm) = self.limits.tokens_per_minute {
+            let used: u64 = window.tokens.iter().map(|&(_, n)| n).sum();
+            if used >= tpm {
+                // 等待最早的消耗移出窗口
+                if let Some(&(oldest, _)) = window.tokens.front() {
+                    wait = wait.max(WINDOW.saturating_sub(now.duration_since(oldest)));
+                }
+            }
+        }
+
+        if wait.is_zero() {
+            window.requests.push_back(now);
+            None
+        } else {
+            Some(wait)
+        }
+    }
+
+    fn record_tokens(&self, tokens: u64) {
+        if self.limits.tokens_per_minute.is_none() || tokens == 0 {
+            return;
+        }
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.tokens.push_back((Instant::now(), tokens));
+    }
+
+    /// 排队直到请求数 / Token 窗口允许发送；超过截止时间返回 false
+    async fn admit(&self, deadline: Instant) -> bool {
+        loop {
+            let now = Instant::now();
+            match self.try_admit(now) {
+                None => return true,
+                Some(wait) if now + wait > deadline => return false,
+                Some(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+
+    /// 排队直到允许发送；超过截止时间返回 None
+    async fn acquire(self: &Arc<Self>, deadline: Instant) -> Option<PermitPart> {
+        let permit = match &self.semaphore {
+            Some(semaphore) => Some(
+                tokio::time::timeout_at(deadline, Arc::clone(semaphore).acquire_owned())
+                    .await
+                    .ok()?
+                    .ok()?,
+            ),
+            None => None,
+        };
+
+        if !self.admit(deadline).await {
+            return None;
+        }
+
+        Some(PermitPart {
+            bucket: Arc::clone(self),
+            _permit: permit,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct PermitPart {
+    bucket: Arc<Bucket>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// 限流许可
+///
+/// 持有期间计入并发数（流式响应应随响应流一起移动），释放前通过 `record_tokens` 记入 Token 消耗
+#[derive(Debug, Default)]
+pub struct RateLimitPermit {
+    parts: Vec<PermitPart>,
+}
+
+impl RateLimitPermit {
+    /// 合并另一个许可（如工具级许可 + Profile 级许可）
+    pub fn merge(&mut self, other: RateLimitPermit) {
+        self.parts.extend(other.parts);
+    }
+
+    /// 再次发送（重试、故障转移）前重新计入请求数窗口，超过截止时间返回 false
+    ///
+    /// 并发许可已持有，不再重复获取
+    pub async fn readmit(&self, deadline: Instant) -> bool {
+        for part in &self.parts {
+            if !part.bucket.admit(deadline).await {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// 记录本次请求消耗的 Token
+    pub fn record_tokens(&self, tokens: u64) {
+        for part in &self.parts {
+            part.bucket.record_tokens(tokens);
+        }
+    }
+}
+
+/// 代理实例的限流器
+#[derive(Debug)]
+pub struct RateLimiter {
+    tool: Option<Arc<Bucket>>,
+    profiles: HashMap<String, Arc<Bucket>>,
+    queue_timeout: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            tool: config
+                .limits
+                .is_limited()
+                .then(|| Arc::new(Bucket::new(config.limits.clone()))),
+            profiles: config
+                .profiles
+                .iter()
+                .filter(|(_, limits)| limits.is_limited())
+                .map(|(name, limits)| (name.clone(), Arc::new(Bucket::new(limits.clone()))))
+                .collect(),
+            queue_timeout: Duration::from_secs(config.queue_timeout_secs),
+        }
+    }
+
+    /// 本次请求的排队截止时间
+    pub fn deadline(&self) -> Instant {
+        Instant::now() + self.queue_timeout
+    }
+
+    /// 获取工具级许可（未配置工具级阈值时立即返回空许可）
+    pub async fn acquire_tool(&self, deadline: Instant) -> Option<RateLimitPermit> {
+        Self::acquire_bucket(self.tool.as_ref(), deadline).await
+    }
+
+    /// 获取 Profile 级许可（该 Profile 未配置阈值时立即返回空许可）
+    pub async fn acquire_profile(
+        &self,
+        profile_name: Option<&str>,
+        deadline: Instant,
+    ) -> Option<RateLimitPermit> {
+        let bucket = profile_name.and_then(|name| self.profiles.get(name));
+        Self::acquire_bucket(bucket, deadline).await
+    }
+
+    async fn acquire_bucket(
+        bucket: Option<&Arc<Bucket>>,
+        deadline: Instant,
+    ) -> Option<RateLimitPermit> {
+        let Some(bucket) = bucket else {
+            return Some(RateLimitPermit::default());
+        };
+        let part = bucket.acquire(deadline).await?;
+        Some(RateLimitPermit { parts: vec![part] })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn config(limits: RateLimits, timeout_secs: u64) -> RateLimitConfig {
+        RateLimitConfig {
+            limits,
+            profiles: BTreeMap::new(),
+            queue_timeout_secs: timeout_secs,
+        }
+    }
+
+    #[test]
+    fn test_requests_per_minute_window() {
+        let bucket = Bucket::new(RateLimits {
+            requests_per_minute: Some(2),
+            ..Default::default()
+        });
+
+        let t0 = Instant::now();
+        assert!(bucket.try_admit(t0).is_none());
+        assert!(bucket.try_admit(t0 + Duration::from_secs(10)).is_none());
+        // 第三个请求需要等待第一个请求滑出窗口
+        assert_eq!(
+            bucket.try_admit(t0 + Duration::from_secs(20)),
+            Some(Duration::from_secs(40))
+        );
+        assert!(bucket.try_admit(t0 + WINDOW).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_queue_timeout() {
+        let limiter = RateLimiter::new(&config(
+            RateLimits {
+                requests_per_minute: Some(1),
+                ..Default::default()
+            },
+            10,
+        ));
+        assert!(limiter.acquire_tool(limiter.deadline()).await.is_some());
+        // 需要等待的时间超过截止时间，立即放弃
+        assert!(limiter.acquire_tool(limiter.deadline()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_readmit_counts_each_send() {
+        let limiter = RateLimiter::new(&config(
+            RateLimits {
+                requests_per_minute: Some(2),
+                max_concurrent: Some(1),
+                ..Default::default()
+            },
+            10,
+        ));
+        let permit = limiter.acquire_tool(limiter.deadline()).await.unwrap();
+        // 重试不重复占用并发许可，但计入请求数
+        assert!(permit.readmit(limiter.deadline()).await);
+        assert!(!permit.readmit(limiter.deadline()).await);
+        assert!(RateLimitPermit::default().readmit(limiter.deadline()).await);
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_released_on_drop() {
+        let limiter = RateLimiter::new(&config(
+            RateLimits {
+                max_concurrent: Some(1),
+                ..Default::default()
+            },
+            60,
+        ));
+        let deadline = || Instant::now() + Duration::from_millis(50);
+
+        let first = limiter.acquire_tool(deadline()).await.unwrap();
+        assert!(limiter.acquire_tool(deadline()).await.is_none());
+        drop(first);
+        assert!(limiter.acquire_tool(deadline()).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_per_minute_and_profiles() {
+        let mut cfg = config(RateLimits::default(), 5);
+        cfg.profiles.insert(
+            "relay-a".to_string(),
+            RateLimits {
+                tokens_per_minute: Some(1000),
+                ..Default::default()
+            },
+        );
+        let limiter = RateLimiter::new(&cfg);
+
+        // 未配置阈值的 Profile 不受限制
+        assert!(limiter
+            .acquire_profile(Some("relay-b"), limiter.deadline())
+            .await
+            .is_some());
+
+        let permit = limiter
+            .acquire_profile(Some("relay-a"), limiter.deadline())
+            .await
+            .unwrap();
+        permit.record_tokens(1500);
+        drop(permit);
+        assert!(limiter
+            .acquire_profile(Some("relay-a"), limiter.deadline())
+            .await
+            .is_none());
+    }
+}
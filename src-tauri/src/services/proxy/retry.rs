@@ -0,0 +1,188 @@
+//! 瞬时错误重试策略
+//!
+//! 仅在上游响应尚未发送给客户端之前重试，覆盖以下情况：
+//! - 连接建立失败、连接被重置/中断（请求未被上游处理）
+//! - 502 / 503 / 504
+//! - 429（优先使用 Retry-After，超过等待上限时不重试）
+//!
+//! 等待时间为指数退避加随机抖动：`min(max_delay, base * 2^n)` 的 50%~100%
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+use crate::models::proxy_config::RetryConfig;
+
+/// 重试策略
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &RetryConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms.max(config.base_delay_ms)),
+        }
+    }
+
+    /// 请求发送失败后的重试等待时间（None 表示不重试）
+    ///
+    /// `retries` 为已重试次数
+    pub fn delay_for_error(&self, error: &reqwest::Error, retries: u32) -> Option<Duration> {
+        if retries >= self.max_retries || !is_transient_error(error) {
+            return None;
+        }
+        Some(self.backoff(retries))
+    }
+
+    /// 上游返回错误状态后的重试等待时间（None 表示不重试）
+    pub fn delay_for_status(
+        &self,
+        status: u16,
+        headers: &HeaderMap,
+        retries: u32,
+    ) -> Option<Duration> {
+        if retries >= self.max_retries {
+            return None;
+        }
+        match status {
+            502..=504 => Some(self.backoff(retries)),
+            429 => match headers
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after)
+            {
+                Some(delay) if delay > self.max_delay => None,
+                Some(delay) => Some(delay),
+                None => Some(self.backoff(retries)),
+            },
+            _ => None,
+        }
+    }
+
+    /// 指数退避 + 随机抖动
+    fn backoff(&self, retries: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(retries).unwrap_or(u32::MAX));
+        let capped = exp.min(self.max_delay);
+        capped.mul_f64(0.5 + 0.5 * jitter())
+    }
+}
+
+/// 0~1 之间的随机数（仅用于抖动，不要求密码学强度）
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    (hasher.finish() % 10_000) as f64 / 10_000.0
+}
+
+/// 解析 Retry-After（秒数或 HTTP 日期）
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(secs as u64))
+}
+
+/// 是否为可安全重试的连接错误（请求未被上游完整处理）
+fn is_transient_error(error: &reqwest::Error) -> bool {
+    if error.is_connect() {
+        return true;
+    }
+
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if matches!(
+                io.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+        }
+        if let Some(hyper_err) = err.downcast_ref::<hyper::Error>() {
+            // 复用的连接在发送前已被对端关闭
+            if hyper_err.is_incomplete_message() || hyper_err.is_canceled() {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy::new(&RetryConfig {
+            max_retries,
+            base_delay_ms: 100,
+            max_delay_ms: 1_000,
+        })
+    }
+
+    #[test]
+    fn test_backoff_bounds() {
+        let policy = policy(10);
+        for retries in 0..10 {
+            let delay = policy.backoff(retries);
+            let expected = Duration::from_millis((100u64 << retries).min(1_000));
+            assert!(delay >= expected / 2 && delay <= expected, "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_delay_for_status() {
+        let policy = policy(2);
+        let headers = HeaderMap::new();
+        assert!(policy.delay_for_status(503, &headers, 0).is_some());
+        assert!(policy.delay_for_status(503, &headers, 2).is_none());
+        assert!(policy.delay_for_status(500, &headers, 0).is_none());
+        assert!(policy.delay_for_status(400, &headers, 0).is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "1".parse().unwrap());
+        assert_eq!(
+            policy.delay_for_status(429, &headers, 0),
+            Some(Duration::from_secs(1))
+        );
+        // Retry-After 超过等待上限时不重试
+        headers.insert(RETRY_AFTER, "60".parse().unwrap());
+        assert!(policy.delay_for_status(429, &headers, 0).is_none());
+
+        assert!(self::policy(0)
+            .delay_for_status(502, &HeaderMap::new(), 0)
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert!(parse_retry_after("soon").is_none());
+    }
+}
@@ -0,0 +1,427 @@
+//! 透明代理 HTTPS 监听证书
+//!
+//! 首次开启 HTTPS 监听时在 `config_dir/tls/` 下生成本地 CA（ECDSA P-256，有效期 10 年），
+//! 并用其签发服务端证书（覆盖 localhost、127.0.0.1、::1、本机主机名与配置的附加主机）。
+//! 导出 CA 证书后，可通过 `NODE_EXTRA_CA_CERTS` 等方式让 CLI 信任该证书。
+//!
+//! CA 带有 nameConstraints 扩展，只能为上述主机签发证书，私钥泄露也无法伪造其他域名。
+//! 服务端证书在主机列表变化或临近过期时自动重新签发；新增主机超出 CA 约束范围时
+//! 重新生成 CA，需要重新导出并信任。
+
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use rcgen::{
+    BasicConstraints, CertificateParams, CidrSubnet, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, GeneralSubtree, IsCa, KeyPair, KeyUsagePurpose, NameConstraints,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio_rustls::rustls;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::TlsAcceptor;
+
+use crate::utils::config::config_dir;
+
+const CA_CERT_FILE: &str = "ca.pem";
+const CA_KEY_FILE: &str = "ca.key.pem";
+const CA_META_FILE: &str = "ca.json";
+const SERVER_CERT_FILE: &str = "server.pem";
+const SERVER_KEY_FILE: &str = "server.key.pem";
+const SERVER_META_FILE: &str = "server.json";
+
+const CA_COMMON_NAME: &str = "DuckCoding Local CA";
+/// CA 有效期
+const CA_VALIDITY_DAYS: i64 = 365 * 10;
+/// 服务端证书有效期
+const SERVER_VALIDITY_DAYS: i64 = 365;
+/// 剩余有效期不足时重新签发
+const SERVER_RENEW_BEFORE_DAYS: i64 = 30;
+
+/// 证书存储目录（`config_dir/tls`）
+#[derive(Debug, Clone)]
+pub struct CertStore {
+    dir: PathBuf,
+}
+
+/// CA 元数据（nameConstraints 允许的主机）
+#[derive(Debug, Serialize, Deserialize)]
+struct CaMeta {
+    permitted_hosts: Vec<String>,
+}
+
+/// 服务端证书元数据（用于判断是否需要重新签发）
+#[derive(Debug, Serialize, Deserialize)]
+struct ServerCertMeta {
+    hosts: Vec<String>,
+    not_after: i64,
+    /// 签发时 CA 证书的摘要（CA 重新生成后失效）
+    ca_fingerprint: String,
+}
+
+struct LocalCa {
+    cert_der: Vec<u8>,
+    /// 按 CA 参数重建的签发者（仅用于签名，与磁盘上的证书主体、密钥一致）
+    issuer: rcgen::Certificate,
+    key: KeyPair,
+}
+
+impl CertStore {
+    /// 打开默认证书目录
+    pub fn open() -> Result<Self> {
+        let dir = config_dir().map_err(|e| anyhow!(e))?.join("tls");
+        Ok(Self::at(dir))
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// CA 证书路径（PEM）
+    pub fn ca_cert_path(&self) -> PathBuf {
+        self.dir.join(CA_CERT_FILE)
+    }
+
+    /// 将 CA 证书导出到指定路径（CA 不存在时先生成）
+    pub fn export_ca(&self, target: &Path, extra_hosts: &[String]) -> Result<()> {
+        self.load_or_create_ca(&server_hosts(extra_hosts))?;
+        if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).context("创建导出目录失败")?;
+        }
+        fs::copy(self.ca_cert_path(), target).context("导出 CA 证书失败")?;
+        Ok(())
+    }
+
+    /// 构建 TLS 接收器（按需生成 CA 与服务端证书）
+    pub fn acceptor(&self, extra_hosts: &[String]) -> Result<TlsAcceptor> {
+        let hosts = server_hosts(extra_hosts);
+        let ca = self.load_or_create_ca(&hosts)?;
+        let (cert_der, key_der) = self.load_or_issue_server_cert(&ca, &hosts)?;
+        let config = server_config(vec![cert_der, ca.cert_der.into()], key_der)?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    /// 读取 CA，不存在或约束范围未覆盖 `hosts` 时重新生成
+    fn load_or_create_ca(&self, hosts: &[String]) -> Result<LocalCa> {
+        let cert_path = self.ca_cert_path();
+        let key_path = self.dir.join(CA_KEY_FILE);
+        let meta_path = self.dir.join(CA_META_FILE);
+
+        let meta = fs::read_to_string(&meta_path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<CaMeta>(&s).ok());
+        if let Some(meta) = &meta {
+            if hosts.iter().all(|h| meta.permitted_hosts.contains(h)) {
+                let loaded = (|| -> Result<_> {
+                    let cert_der = CertificateDer::from_pem_file(&cert_path)?.to_vec();
+                    let key = KeyPair::from_pem(&fs::read_to_string(&key_path)?)?;
+                    let issuer = ca_params(&meta.permitted_hosts)?.self_signed(&key)?;
+                    Ok(LocalCa {
+                        cert_der,
+                        issuer,
+                        key,
+                    })
+                })();
+                match loaded {
+                    Ok(ca) => return Ok(ca),
+                    Err(e) => tracing::warn!(error = ?e, "读取本地 CA 失败，重新生成"),
+                }
+            } else {
+                tracing::warn!(
+                    permitted = ?meta.permitted_hosts,
+                    hosts = ?hosts,
+                    "主机超出本地 CA 约束范围，重新生成 CA，需要重新导出并信任"
+                );
+            }
+        } else if cert_path.exists() {
+            tracing::warn!("本地 CA 缺少名称约束，重新生成 CA，需要重新导出并信任");
+        }
+
+        // 保留原有约束主机，避免不同监听的附加主机交替触发重新生成
+        let mut permitted = meta.map(|m| m.permitted_hosts).unwrap_or_default();
+        for host in hosts {
+            if !permitted.contains(host) {
+                permitted.push(host.clone());
+            }
+        }
+
+        fs::create_dir_all(&self.dir).context("创建证书目录失败")?;
+        let key = KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+        let mut params = ca_params(&permitted)?;
+        set_validity(&mut params, Utc::now() + Duration::days(CA_VALIDITY_DAYS));
+        let cert = params.self_signed(&key)?;
+
+        write_private(&key_path, &key.serialize_pem())?;
+        fs::write(&cert_path, cert.pem()).context("保存 CA 证书失败")?;
+        tracing::info!(path = %cert_path.display(), hosts = ?permitted, "已生成透明代理本地 CA");
+        let meta = CaMeta {
+            permitted_hosts: permitted,
+        };
+        fs::write(&meta_path, serde_json::to_string_pretty(&meta)?)?;
+
+        Ok(LocalCa {
+            cert_der: cert.der().to_vec(),
+            issuer: cert,
+            key,
+        })
+    }
+
+    /// 读取服务端证书，主机列表变化、临近过期或 CA 变化时重新签发
+    fn load_or_issue_server_cert(
+        &self,
+        ca: &LocalCa,
+        hosts: &[String],
+    ) -> Result<(CertificateDer<'static>, PrivateKeyDer<'static>)> {
+        let cert_path = self.dir.join(SERVER_CERT_FILE);
+        let key_path = self.dir.join(SERVER_KEY_FILE);
+        let meta_path = self.dir.join(SERVER_META_FILE);
+        let ca_fingerprint = fingerprint(&ca.cert_der);
+
+        let meta = fs::read_to_string(&meta_path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<ServerCertMeta>(&s).ok());
+        let renew_at = (Utc::now() + Duration::days(SERVER_RENEW_BEFORE_DAYS)).timestamp();
+        if let Some(meta) = meta.filter(|m| {
+            m.hosts == hosts && m.not_after > renew_at && m.ca_fingerprint == ca_fingerprint
+        }) {
+            let loaded = (|| -> Result<_> {
+                let cert = CertificateDer::from_pem_file(&cert_path)?;
+                let key = PrivateKeyDer::from_pem_file(&key_path)?;
+                Ok((cert, key))
+            })();
+            match loaded {
+                Ok(pair) => return Ok(pair),
+                Err(e) => {
+                    tracing::warn!(error = ?e, not_after = meta.not_after, "读取服务端证书失败，重新签发")
+                }
+            }
+        }
+
+        let key = KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)?;
+        let mut params = CertificateParams::new(hosts.to_vec())?;
+        params.distinguished_name = distinguished_name(&hosts[0]);
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+        let not_after = set_validity(
+            &mut params,
+            Utc::now() + Duration::days(SERVER_VALIDITY_DAYS),
+        );
+        let cert = params.signed_by(&key, &ca.issuer, &ca.key)?;
+
+        write_private(&key_path, &key.serialize_pem())?;
+        fs::write(&cert_path, cert.pem()).context("保存服务端证书失败")?;
+        let meta = ServerCertMeta {
+            hosts: hosts.to_vec(),
+            not_after,
+            ca_fingerprint,
+        };
+        fs::write(&meta_path, serde_json::to_string_pretty(&meta)?)?;
+        tracing::info!(hosts = ?hosts, "已签发透明代理服务端证书");
+
+        Ok((
+            cert.der().clone(),
+            PrivateKeyDer::try_from(key.serialize_der()).map_err(|e| anyhow!(e))?,
+        ))
+    }
+}
+
+/// 服务端证书覆盖的主机（去重，保持顺序）
+fn server_hosts(extra_hosts: &[String]) -> Vec<String> {
+    let hostname = hostname::get().ok().and_then(|h| h.into_string().ok());
+
+    let mut hosts: Vec<String> = Vec::new();
+    let candidates = ["localhost", "127.0.0.1", "::1"]
+        .into_iter()
+        .map(str::to_string)
+        .chain(hostname)
+        .chain(extra_hosts.iter().cloned());
+    for host in candidates {
+        let host = host.trim().to_lowercase();
+        if !host.is_empty() && !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    hosts
+}
+
+fn server_config(
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> Result<rustls::ServerConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .context("初始化 TLS 配置失败")?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .context("加载服务端证书失败")?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// CA 证书参数（有效期之外的部分固定，便于用已保存的密钥重建签发者）
+fn ca_params(permitted_hosts: &[String]) -> Result<CertificateParams> {
+    let mut params = CertificateParams::default();
+    params.distinguished_name = distinguished_name(CA_COMMON_NAME);
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        KeyUsagePurpose::DigitalSignature,
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+    ];
+    params.name_constraints = Some(NameConstraints {
+        permitted_subtrees: permitted_hosts
+            .iter()
+            .map(|host| match host.parse::<IpAddr>() {
+                Ok(ip) => {
+                    let prefix = if ip.is_ipv4() { 32 } else { 128 };
+                    GeneralSubtree::IpAddress(CidrSubnet::from_addr_prefix(ip, prefix))
+                }
+                Err(_) => GeneralSubtree::DnsName(host.clone()),
+            })
+            .collect(),
+        excluded_subtrees: Vec::new(),
+    });
+    Ok(params)
+}
+
+fn distinguished_name(common_name: &str) -> DistinguishedName {
+    let mut name = DistinguishedName::new();
+    name.push(DnType::OrganizationName, "DuckCoding");
+    name.push(DnType::CommonName, common_name);
+    name
+}
+
+/// 设置有效期（提前一天生效，按日对齐），返回到期时间戳
+fn set_validity(params: &mut CertificateParams, not_after: DateTime<Utc>) -> i64 {
+    let ymd = |at: DateTime<Utc>| rcgen::date_time_ymd(at.year(), at.month() as u8, at.day() as u8);
+    params.not_before = ymd(Utc::now() - Duration::days(1));
+    params.not_after = ymd(not_after);
+    params.not_after.unix_timestamp()
+}
+
+fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 写入私钥文件（Unix 下仅当前用户可读）
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content).with_context(|| format!("保存私钥失败: {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::pki_types::ServerName;
+
+    /// 以导出的 CA 为信任根连接 `acceptor`，返回服务端发送的内容
+    async fn handshake(acceptor: TlsAcceptor, ca_pem: &Path, server_name: &str) -> Result<String> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            if let Ok(mut tls) = acceptor.accept(stream).await {
+                let _ = tls.write_all(b"hello").await;
+                let _ = tls.shutdown().await;
+            }
+        });
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(CertificateDer::from_pem_file(ca_pem)?)?;
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+
+        let stream = tokio::net::TcpStream::connect(addr).await?;
+        let mut tls = connector
+            .connect(ServerName::try_from(server_name.to_string())?, stream)
+            .await?;
+        let mut buf = String::new();
+        tls.read_to_string(&mut buf).await?;
+        Ok(buf)
+    }
+
+    #[tokio::test]
+    async fn test_tls_handshake_with_exported_ca() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CertStore::at(dir.path().join("tls"));
+        let hosts = ["relay.lan".to_string()];
+        let acceptor = store.acceptor(&hosts).unwrap();
+
+        // 再次加载复用同一张服务端证书
+        let cert_before = fs::read(dir.path().join("tls").join(SERVER_CERT_FILE)).unwrap();
+        store.acceptor(&hosts).unwrap();
+        let cert_after = fs::read(dir.path().join("tls").join(SERVER_CERT_FILE)).unwrap();
+        assert_eq!(cert_before, cert_after);
+
+        let exported = dir.path().join("export").join("duckcoding-ca.pem");
+        store.export_ca(&exported, &hosts).unwrap();
+        assert_eq!(
+            handshake(acceptor, &exported, "relay.lan").await.unwrap(),
+            "hello"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ca_name_constraints() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CertStore::at(dir.path().join("tls"));
+        let hosts = server_hosts(&[]);
+        let ca = store.load_or_create_ca(&hosts).unwrap();
+        let exported = dir.path().join("ca.pem");
+        store.export_ca(&exported, &[]).unwrap();
+
+        // 用 CA 私钥签发约束范围之外的证书，客户端应拒绝
+        let key = KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256).unwrap();
+        let mut params = CertificateParams::new(vec!["example.com".to_string()]).unwrap();
+        params.distinguished_name = distinguished_name("example.com");
+        let cert = params.signed_by(&key, &ca.issuer, &ca.key).unwrap();
+        let config = server_config(
+            vec![cert.der().clone(), ca.cert_der.clone().into()],
+            PrivateKeyDer::try_from(key.serialize_der()).unwrap(),
+        )
+        .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        assert!(handshake(acceptor, &exported, "example.com").await.is_err());
+
+        // 新增主机超出约束时重新生成 CA，并保留原有主机
+        let fingerprint_before = fingerprint(&ca.cert_der);
+        let ca = store
+            .load_or_create_ca(&server_hosts(&["relay.lan".to_string()]))
+            .unwrap();
+        assert_ne!(fingerprint(&ca.cert_der), fingerprint_before);
+        let meta: CaMeta = serde_json::from_str(
+            &fs::read_to_string(dir.path().join("tls").join(CA_META_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert!(hosts.iter().all(|h| meta.permitted_hosts.contains(h)));
+        assert!(meta.permitted_hosts.contains(&"relay.lan".to_string()));
+    }
+
+    #[test]
+    fn test_server_hosts_dedup() {
+        let hosts = server_hosts(&["LOCALHOST".to_string(), "192.168.1.5".to_string()]);
+        assert_eq!(&hosts[..3], &["localhost", "127.0.0.1", "::1"]);
+        assert_eq!(hosts.last().unwrap(), "192.168.1.5");
+        assert_eq!(hosts.iter().filter(|h| *h == "localhost").count(), 1);
+    }
+}
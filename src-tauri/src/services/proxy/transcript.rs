@@ -0,0 +1,557 @@
+//! 会话对话记录提取
+//!
+//! 开启对话记录后，从请求体中提取本轮用户输入，从上游响应（SSE 流或 JSON）中提取助手回复与工具调用：
+//! - Anthropic Messages：`content_block_start` / `content_block_delta` 事件及非流式 `message`
+//! - OpenAI Responses：`response.completed` 事件及非流式 `response` 的 `output`
+//! - OpenAI Chat Completions：`choices[].delta` 及非流式 `choices[].message`
+//! - Gemini：`candidates[].content.parts`（忽略思考内容）
+//!
+//! 只有请求的最后一条消息是用户文本时才记录用户输入，工具结果回传不视为新的输入。
+
+use serde_json::Value;
+
+use crate::models::proxy_config::TranscriptConfig;
+use crate::services::session::{SessionEvent, TranscriptRole, TranscriptTurn, SESSION_MANAGER};
+
+/// 增量对话内容提取器
+///
+/// SSE 响应按块调用 `feed_sse`，JSON 响应调用 `feed_json_body`，最后调用 `finish` 获取结果
+#[derive(Debug, Default)]
+pub struct TranscriptExtractor {
+    /// 未完成的 SSE 行缓冲
+    line_buffer: Vec<u8>,
+    text: String,
+    tool_calls: Vec<ToolCall>,
+}
+
+/// 累积中的工具调用（流式响应中参数分多次到达）
+#[derive(Debug, Default)]
+struct ToolCall {
+    /// 流式事件中的序号（用于关联后续参数分块）
+    index: Option<i64>,
+    name: String,
+    arguments: String,
+}
+
+impl TranscriptExtractor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输入一段 SSE 数据（可以是任意切分的字节块）
+    pub fn feed_sse(&mut self, chunk: &[u8]) {
+        self.line_buffer.extend_from_slice(chunk);
+
+        while let Some(pos) = self.line_buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.line_buffer.drain(..=pos).collect();
+            self.process_sse_line(&line);
+        }
+    }
+
+    /// 输入完整的 JSON 响应体
+    pub fn feed_json_body(&mut self, body: &[u8]) {
+        if let Ok(value) = serde_json::from_slice::<Value>(body) {
+            self.process_value(&value);
+        }
+    }
+
+    fn process_sse_line(&mut self, line: &[u8]) {
+        let Ok(line) = std::str::from_utf8(line) else {
+            return;
+        };
+        let Some(data) = line.trim_end().strip_prefix("data:") else {
+            return;
+        };
+        let data = data.trim();
+        if data.is_empty() || data == "[DONE]" {
+            return;
+        }
+        if let Ok(value) = serde_json::from_str::<Value>(data) {
+            self.process_value(&value);
+        }
+    }
+
+    /// 处理单个 JSON 对象（SSE 事件或完整响应体）
+    fn process_value(&mut self, value: &Value) {
+        match value["type"].as_str() {
+            // Anthropic 流式：内容块开始与增量
+            Some("content_block_start") => {
+                let block = &value["content_block"];
+                match block["type"].as_str() {
+                    Some("text") => self.push_block(block["text"].as_str().unwrap_or("")),
+                    Some("tool_use") => self.push_tool_call(
+                        value["index"].as_i64(),
+                        block["name"].as_str(),
+                        input_arguments(&block["input"]),
+                    ),
+                    _ => {}
+                }
+                return;
+            }
+            Some("content_block_delta") => {
+                let delta = &value["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => self.text.push_str(delta["text"].as_str().unwrap_or("")),
+                    Some("input_json_delta") => self.append_arguments(
+                        value["index"].as_i64(),
+                        delta["partial_json"].as_str().unwrap_or(""),
+                    ),
+                    _ => {}
+                }
+                return;
+            }
+            // Anthropic 非流式
+            Some("message") => {
+                for block in value["content"].as_array().into_iter().flatten() {
+                    match block["type"].as_str() {
+                        Some("text") => self.push_block(block["text"].as_str().unwrap_or("")),
+                        Some("tool_use") => self.push_tool_call(
+                            None,
+                            block["name"].as_str(),
+                            input_arguments(&block["input"]),
+                        ),
+                        _ => {}
+                    }
+                }
+                return;
+            }
+            // OpenAI Responses 流式（完成事件携带完整输出）
+            Some("response.completed") => {
+                self.apply_responses_output(&value["response"]["output"]);
+                return;
+            }
+            _ => {}
+        }
+
+        // OpenAI Responses 非流式
+        if value["object"].as_str() == Some("response") {
+            self.apply_responses_output(&value["output"]);
+            return;
+        }
+
+        // OpenAI Chat Completions
+        if let Some(choice) = value["choices"].get(0) {
+            self.apply_chat_choice(choice);
+            return;
+        }
+
+        // Gemini（Code Assist 接口的响应包在 response 字段中）
+        let candidates = if value["candidates"].is_array() {
+            &value["candidates"]
+        } else {
+            &value["response"]["candidates"]
+        };
+        for part in candidates[0]["content"]["parts"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            if part["thought"].as_bool() == Some(true) {
+                continue;
+            }
+            if let Some(text) = part["text"].as_str() {
+                self.text.push_str(text);
+            } else if part["functionCall"].is_object() {
+                let call = &part["functionCall"];
+                self.push_tool_call(None, call["name"].as_str(), input_arguments(&call["args"]));
+            }
+        }
+    }
+
+    fn apply_responses_output(&mut self, output: &Value) {
+        for item in output.as_array().into_iter().flatten() {
+            match item["type"].as_str() {
+                Some("message") => {
+                    for content in item["content"].as_array().into_iter().flatten() {
+                        if content["type"].as_str() == Some("output_text") {
+                            self.push_block(content["text"].as_str().unwrap_or(""));
+                        }
+                    }
+                }
+                Some("function_call") => self.push_tool_call(
+                    None,
+                    item["name"].as_str(),
+                    item["arguments"].as_str().unwrap_or("").to_string(),
+                ),
+                Some("custom_tool_call") => self.push_tool_call(
+                    None,
+                    item["name"].as_str(),
+                    item["input"].as_str().unwrap_or("").to_string(),
+                ),
+                Some("local_shell_call") => {
+                    self.push_tool_call(None, Some("local_shell"), input_arguments(&item["action"]))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn apply_chat_choice(&mut self, choice: &Value) {
+        // 流式增量
+        let delta = &choice["delta"];
+        if delta.is_object() {
+            self.text.push_str(delta["content"].as_str().unwrap_or(""));
+            for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                let index = call["index"].as_i64();
+                let function = &call["function"];
+                if !self
+                    .tool_calls
+                    .iter()
+                    .any(|c| c.index.is_some() && c.index == index)
+                {
+                    self.push_tool_call(index, function["name"].as_str(), String::new());
+                }
+                self.append_arguments(index, function["arguments"].as_str().unwrap_or(""));
+            }
+            return;
+        }
+
+        // 非流式
+        let message = &choice["message"];
+        self.push_block(message["content"].as_str().unwrap_or(""));
+        for call in message["tool_calls"].as_array().into_iter().flatten() {
+            let function = &call["function"];
+            self.push_tool_call(
+                None,
+                function["name"].as_str(),
+                function["arguments"].as_str().unwrap_or("").to_string(),
+            );
+        }
+    }
+
+    /// 追加一个独立的文本块（与已有文本之间空一行）
+    fn push_block(&mut self, text: &str) {
+        if !self.text.is_empty() {
+            self.text.push_str("\n\n");
+        }
+        self.text.push_str(text);
+    }
+
+    fn push_tool_call(&mut self, index: Option<i64>, name: Option<&str>, arguments: String) {
+        self.tool_calls.push(ToolCall {
+            index,
+            name: name.unwrap_or("").to_string(),
+            arguments,
+        });
+    }
+
+    fn append_arguments(&mut self, index: Option<i64>, arguments: &str) {
+        if let Some(call) = self
+            .tool_calls
+            .iter_mut()
+            .rev()
+            .find(|c| c.index.is_some() && c.index == index)
+        {
+            call.arguments.push_str(arguments);
+        }
+    }
+
+    /// 结束提取，返回助手回复与工具调用（按出现顺序，回复在前）
+    pub fn finish(mut self) -> Vec<(TranscriptRole, String)> {
+        // 处理没有换行结尾的最后一行
+        if !self.line_buffer.is_empty() {
+            let line = std::mem::take(&mut self.line_buffer);
+            self.process_sse_line(&line);
+        }
+
+        let mut turns = Vec::new();
+        let text = self.text.trim();
+        if !text.is_empty() {
+            turns.push((TranscriptRole::Assistant, text.to_string()));
+        }
+        for call in self.tool_calls {
+            if call.name.is_empty() {
+                continue;
+            }
+            let content = format!("{} {}", call.name, call.arguments.trim());
+            turns.push((TranscriptRole::ToolCall, content.trim_end().to_string()));
+        }
+        turns
+    }
+}
+
+/// 工具调用参数转为紧凑 JSON（空对象视为无参数）
+fn input_arguments(input: &Value) -> String {
+    match input {
+        Value::Null => String::new(),
+        Value::Object(map) if map.is_empty() => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// 从请求体中提取本轮用户输入
+///
+/// 支持 Anthropic / Chat Completions 的 `messages`、Responses 的 `input`、Gemini 的 `contents`，
+/// 最后一条消息不是用户文本（如工具结果回传）时返回 None
+pub fn extract_prompt(body: &[u8]) -> Option<String> {
+    let value: Value = serde_json::from_slice(body).ok()?;
+    // Gemini Code Assist 接口的请求包在 request 字段中
+    let request = if value["contents"].is_null() && value["request"].is_object() {
+        &value["request"]
+    } else {
+        &value
+    };
+
+    let text = if let Some(messages) = request["messages"].as_array() {
+        let last = messages.last()?;
+        if last["role"].as_str() != Some("user") {
+            return None;
+        }
+        content_text(&last["content"], &["text", "input_text"])
+    } else if let Some(input) = request["input"].as_str() {
+        input.to_string()
+    } else if let Some(items) = request["input"].as_array() {
+        let last = items.last()?;
+        let is_message = last["type"].as_str().is_none_or(|t| t == "message");
+        if !is_message || last["role"].as_str() != Some("user") {
+            return None;
+        }
+        content_text(&last["content"], &["input_text", "text"])
+    } else if let Some(contents) = request["contents"].as_array() {
+        let last = contents.last()?;
+        if last["role"].as_str().is_some_and(|role| role != "user") {
+            return None;
+        }
+        last["parts"]
+            .as_array()?
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        return None;
+    };
+
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// 读取消息内容中的文本（字符串或内容块数组）
+fn content_text(content: &Value, text_types: &[&str]) -> String {
+    if let Some(text) = content.as_str() {
+        return text.to_string();
+    }
+    content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|block| {
+            block["type"]
+                .as_str()
+                .is_some_and(|t| text_types.contains(&t))
+        })
+        .filter_map(|block| block["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 按字节上限截断内容（保证 UTF-8 字符完整），返回是否截断
+fn truncate(mut content: String, max_bytes: usize) -> (String, bool) {
+    if content.len() <= max_bytes {
+        return (content, false);
+    }
+    let mut end = max_bytes;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    content.truncate(end);
+    (content, true)
+}
+
+/// 响应的对话记录探针
+///
+/// 随响应一起移动，在响应结束（或客户端断开导致流被丢弃）时写入本轮对话记录
+pub struct TranscriptTap {
+    tool_id: String,
+    session_id: String,
+    prompt: Option<String>,
+    config: TranscriptConfig,
+    extractor: Option<TranscriptExtractor>,
+}
+
+impl TranscriptTap {
+    pub fn new(
+        tool_id: &str,
+        session_id: &str,
+        request_body: &[u8],
+        config: &TranscriptConfig,
+    ) -> Self {
+        Self {
+            tool_id: tool_id.to_string(),
+            session_id: session_id.to_string(),
+            prompt: extract_prompt(request_body),
+            config: config.clone(),
+            extractor: Some(TranscriptExtractor::new()),
+        }
+    }
+
+    /// 观察一个 SSE 数据块
+    pub fn observe(&mut self, chunk: &[u8]) {
+        if let Some(extractor) = self.extractor.as_mut() {
+            extractor.feed_sse(chunk);
+        }
+    }
+
+    /// 观察完整的 JSON 响应体
+    pub fn observe_json(&mut self, body: &[u8]) {
+        if let Some(extractor) = self.extractor.as_mut() {
+            extractor.feed_json_body(body);
+        }
+    }
+}
+
+impl Drop for TranscriptTap {
+    fn drop(&mut self) {
+        let Some(extractor) = self.extractor.take() else {
+            return;
+        };
+
+        let created_at = chrono::Utc::now().timestamp();
+        let turns: Vec<TranscriptTurn> = self
+            .prompt
+            .take()
+            .map(|prompt| (TranscriptRole::User, prompt))
+            .into_iter()
+            .chain(extractor.finish())
+            .map(|(role, content)| {
+                let (content, truncated) = truncate(content, self.config.max_turn_bytes);
+                TranscriptTurn {
+                    id: 0,
+                    session_id: self.session_id.clone(),
+                    tool_id: self.tool_id.clone(),
+                    role,
+                    content,
+                    truncated,
+                    created_at,
+                }
+            })
+            .collect();
+        if turns.is_empty() {
+            return;
+        }
+
+        if let Err(e) = SESSION_MANAGER.send_event(SessionEvent::Transcript {
+            turns,
+            max_total_bytes: self.config.max_total_bytes,
+        }) {
+            tracing::warn!("对话记录事件发送失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anthropic_sse_transcript() {
+        let mut extractor = TranscriptExtractor::new();
+        let stream = concat!(
+            "event: content_block_start\n",
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"thinking\",\"thinking\":\"\"}}\n\n",
+            "data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"Running the \"}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"tests.\"}}\n\n",
+            "data: {\"type\":\"content_block_start\",\"index\":2,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"Bash\",\"input\":{}}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":2,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"command\\\":\"}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":2,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"\\\"cargo test\\\"}\"}}\n\n",
+        );
+
+        // 按任意位置切分，验证跨块拼接
+        let (a, b) = stream.as_bytes().split_at(101);
+        extractor.feed_sse(a);
+        extractor.feed_sse(b);
+
+        assert_eq!(
+            extractor.finish(),
+            vec![
+                (TranscriptRole::Assistant, "Running the tests.".to_string()),
+                (
+                    TranscriptRole::ToolCall,
+                    r#"Bash {"command":"cargo test"}"#.to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_responses_and_chat_transcript() {
+        let mut extractor = TranscriptExtractor::new();
+        extractor.feed_sse(
+            br#"data: {"type":"response.completed","response":{"output":[{"type":"reasoning","summary":[]},{"type":"message","content":[{"type":"output_text","text":"Done."}]},{"type":"function_call","name":"shell","arguments":"{\"command\":[\"ls\"]}"}]}}"#,
+        );
+        assert_eq!(
+            extractor.finish(),
+            vec![
+                (TranscriptRole::Assistant, "Done.".to_string()),
+                (
+                    TranscriptRole::ToolCall,
+                    r#"shell {"command":["ls"]}"#.to_string()
+                ),
+            ]
+        );
+
+        let mut extractor = TranscriptExtractor::new();
+        extractor
+            .feed_sse(b"data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hel\"}}]}\n\n");
+        extractor.feed_sse(b"data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\",\"tool_calls\":[{\"index\":0,\"function\":{\"name\":\"read\",\"arguments\":\"{\\\"p\"}}]}}]}\n\n");
+        extractor.feed_sse(b"data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\":1}\"}}]}}]}\n\ndata: [DONE]\n\n");
+        assert_eq!(
+            extractor.finish(),
+            vec![
+                (TranscriptRole::Assistant, "Hello".to_string()),
+                (TranscriptRole::ToolCall, r#"read {"p":1}"#.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gemini_transcript() {
+        let mut extractor = TranscriptExtractor::new();
+        extractor.feed_sse(b"data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"thinking\",\"thought\":true},{\"text\":\"Hi \"}]}}]}\r\n\r\n");
+        extractor.feed_sse(b"data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"there\"},{\"functionCall\":{\"name\":\"read_file\",\"args\":{\"path\":\"a.rs\"}}}]}}]}");
+        assert_eq!(
+            extractor.finish(),
+            vec![
+                (TranscriptRole::Assistant, "Hi there".to_string()),
+                (
+                    TranscriptRole::ToolCall,
+                    r#"read_file {"path":"a.rs"}"#.to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_prompt() {
+        // Anthropic：最后一条为用户文本
+        let body = br#"{"messages":[{"role":"user","content":"hi"},{"role":"assistant","content":"yo"},{"role":"user","content":[{"type":"text","text":"fix the migration"}]}]}"#;
+        assert_eq!(extract_prompt(body).as_deref(), Some("fix the migration"));
+
+        // 工具结果回传不视为用户输入
+        let body = br#"{"messages":[{"role":"user","content":[{"type":"tool_result","tool_use_id":"t","content":"ok"}]}]}"#;
+        assert_eq!(extract_prompt(body), None);
+
+        // Responses
+        let body = br#"{"input":[{"type":"message","role":"user","content":[{"type":"input_text","text":"list files"}]}]}"#;
+        assert_eq!(extract_prompt(body).as_deref(), Some("list files"));
+        let body = br#"{"input":[{"type":"function_call_output","call_id":"c","output":"ok"}]}"#;
+        assert_eq!(extract_prompt(body), None);
+
+        // Gemini
+        let body = r#"{"contents":[{"role":"user","parts":[{"text":"解释这段代码"}]}]}"#;
+        assert_eq!(
+            extract_prompt(body.as_bytes()).as_deref(),
+            Some("解释这段代码")
+        );
+        let body = br#"{"contents":[{"role":"user","parts":[{"functionResponse":{"name":"f"}}]}]}"#;
+        assert_eq!(extract_prompt(body), None);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("abc".to_string(), 10), ("abc".to_string(), false));
+        // 不截断在多字节字符中间
+        assert_eq!(truncate("迁移".to_string(), 4), ("迁".to_string(), true));
+    }
+}
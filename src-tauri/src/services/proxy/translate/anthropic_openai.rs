@@ -0,0 +1,655 @@
+//! Anthropic Messages <-> OpenAI Chat Completions 转换
+//!
+//! 请求：Messages 请求体 -> Chat Completions 请求体（system、tools、tool_use/tool_result、图片、流式）
+//! 响应：Chat Completions（JSON 或 SSE）-> Messages（JSON 或 message_start/content_block_*/message_stop 事件）
+//!
+//! 不支持的字段（thinking、cache_control、top_k 等）会被丢弃
+
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+use super::{sse_event, ResponseTranslator, SseLineBuffer};
+
+/// 将 Anthropic Messages 请求体转换为 Chat Completions 请求体
+pub fn messages_to_chat_request(body: &Value) -> Result<Value> {
+    let source = body.as_object().context("请求体不是 JSON 对象")?;
+    let mut request = Map::new();
+
+    if let Some(model) = source.get("model") {
+        request.insert("model".into(), model.clone());
+    }
+
+    let mut messages = Vec::new();
+    if let Some(system) = source.get("system") {
+        let text = join_text(system);
+        if !text.is_empty() {
+            messages.push(json!({ "role": "system", "content": text }));
+        }
+    }
+    for message in source
+        .get("messages")
+        .and_then(Value::as_array)
+        .context("缺少 messages 字段")?
+    {
+        convert_message(message, &mut messages);
+    }
+    request.insert("messages".into(), Value::Array(messages));
+
+    if let Some(max_tokens) = source.get("max_tokens") {
+        request.insert("max_tokens".into(), max_tokens.clone());
+    }
+    for key in ["temperature", "top_p"] {
+        if let Some(value) = source.get(key) {
+            request.insert(key.into(), value.clone());
+        }
+    }
+    if let Some(stop) = source.get("stop_sequences") {
+        request.insert("stop".into(), stop.clone());
+    }
+
+    if let Some(tools) = source.get("tools").and_then(Value::as_array) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter(|tool| tool.get("input_schema").is_some())
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool["name"],
+                        "description": tool.get("description").cloned().unwrap_or(Value::Null),
+                        "parameters": tool["input_schema"],
+                    }
+                })
+            })
+            .collect();
+        if !tools.is_empty() {
+            request.insert("tools".into(), Value::Array(tools));
+        }
+    }
+    if let Some(choice) = source.get("tool_choice") {
+        let converted = match choice["type"].as_str() {
+            Some("auto") => Some(json!("auto")),
+            Some("any") => Some(json!("required")),
+            Some("none") => Some(json!("none")),
+            Some("tool") => Some(json!({
+                "type": "function",
+                "function": { "name": choice["name"] }
+            })),
+            _ => None,
+        };
+        if let Some(converted) = converted {
+            request.insert("tool_choice".into(), converted);
+        }
+    }
+
+    if source.get("stream").and_then(Value::as_bool) == Some(true) {
+        request.insert("stream".into(), json!(true));
+        // 要求上游在流末尾返回用量
+        request.insert("stream_options".into(), json!({ "include_usage": true }));
+    }
+
+    Ok(Value::Object(request))
+}
+
+/// 转换单条消息（tool_result 会拆分为独立的 tool 消息）
+fn convert_message(message: &Value, output: &mut Vec<Value>) {
+    let role = message["role"].as_str().unwrap_or("user");
+    let content = &message["content"];
+
+    let Some(blocks) = content.as_array() else {
+        output.push(json!({ "role": role, "content": content.as_str().unwrap_or_default() }));
+        return;
+    };
+
+    if role == "assistant" {
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block["type"].as_str() {
+                Some("text") => text.push_str(block["text"].as_str().unwrap_or_default()),
+                Some("tool_use") => tool_calls.push(json!({
+                    "id": block["id"],
+                    "type": "function",
+                    "function": {
+                        "name": block["name"],
+                        "arguments": block["input"].to_string(),
+                    }
+                })),
+                _ => {}
+            }
+        }
+
+        let mut converted = json!({ "role": "assistant" });
+        converted["content"] = if text.is_empty() && !tool_calls.is_empty() {
+            Value::Null
+        } else {
+            Value::String(text)
+        };
+        if !tool_calls.is_empty() {
+            converted["tool_calls"] = Value::Array(tool_calls);
+        }
+        output.push(converted);
+        return;
+    }
+
+    // 用户消息：tool_result 在前（必须紧跟 assistant 的 tool_calls），其余内容合并为一条 user 消息
+    let mut parts = Vec::new();
+    let mut has_image = false;
+    for block in blocks {
+        match block["type"].as_str() {
+            Some("tool_result") => output.push(json!({
+                "role": "tool",
+                "tool_call_id": block["tool_use_id"],
+                "content": join_text(&block["content"]),
+            })),
+            Some("text") => parts.push(json!({ "type": "text", "text": block["text"] })),
+            Some("image") => {
+                let source = &block["source"];
+                let url = match source["type"].as_str() {
+                    Some("base64") => format!(
+                        "data:{};base64,{}",
+                        source["media_type"].as_str().unwrap_or("image/png"),
+                        source["data"].as_str().unwrap_or_default()
+                    ),
+                    _ => source["url"].as_str().unwrap_or_default().to_string(),
+                };
+                has_image = true;
+                parts.push(json!({ "type": "image_url", "image_url": { "url": url } }));
+            }
+            _ => {}
+        }
+    }
+
+    if parts.is_empty() {
+        return;
+    }
+    let content = if has_image {
+        Value::Array(parts)
+    } else {
+        let text: Vec<&str> = parts.iter().filter_map(|p| p["text"].as_str()).collect();
+        Value::String(text.join("\n"))
+    };
+    output.push(json!({ "role": role, "content": content }));
+}
+
+/// 提取文本（字符串或 text 块数组）
+fn join_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|b| b["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// 将 OpenAI finish_reason 映射为 Anthropic stop_reason
+fn map_finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "length" => "max_tokens",
+        "tool_calls" | "function_call" => "tool_use",
+        _ => "end_turn",
+    }
+}
+
+/// 将 OpenAI 用量转换为 Anthropic 用量（input_tokens 不含缓存命中部分）
+fn convert_usage(usage: &Value) -> Value {
+    let prompt = usage["prompt_tokens"].as_i64().unwrap_or(0);
+    let cached = usage["prompt_tokens_details"]["cached_tokens"]
+        .as_i64()
+        .unwrap_or(0);
+    json!({
+        "input_tokens": (prompt - cached).max(0),
+        "output_tokens": usage["completion_tokens"].as_i64().unwrap_or(0),
+        "cache_read_input_tokens": cached,
+    })
+}
+
+/// 将 HTTP 状态码映射为 Anthropic 错误类型
+fn error_type(status: u16) -> &'static str {
+    match status {
+        400 => "invalid_request_error",
+        401 => "authentication_error",
+        403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        529 => "overloaded_error",
+        _ => "api_error",
+    }
+}
+
+/// 将 OpenAI 错误转换为 Anthropic 错误格式
+fn convert_error(status: u16, error: &Value) -> Value {
+    let message = error["message"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| error.to_string());
+    json!({
+        "type": "error",
+        "error": { "type": error_type(status), "message": message }
+    })
+}
+
+/// 将 Chat Completions 非流式响应转换为 Anthropic Messages 响应
+pub fn chat_to_messages_response(response: &Value, fallback_model: &str) -> Value {
+    let choice = &response["choices"][0];
+    let message = &choice["message"];
+
+    let mut content = Vec::new();
+    if let Some(text) = message["content"].as_str().filter(|s| !s.is_empty()) {
+        content.push(json!({ "type": "text", "text": text }));
+    }
+    if let Some(tool_calls) = message["tool_calls"].as_array() {
+        for call in tool_calls {
+            content.push(json!({
+                "type": "tool_use",
+                "id": call["id"],
+                "name": call["function"]["name"],
+                "input": parse_arguments(call["function"]["arguments"].as_str().unwrap_or("")),
+            }));
+        }
+    }
+
+    json!({
+        "id": response["id"].as_str().unwrap_or("msg_proxy"),
+        "type": "message",
+        "role": "assistant",
+        "model": response["model"].as_str().unwrap_or(fallback_model),
+        "content": content,
+        "stop_reason": map_finish_reason(choice["finish_reason"].as_str().unwrap_or("stop")),
+        "stop_sequence": null,
+        "usage": convert_usage(&response["usage"]),
+    })
+}
+
+/// 解析工具调用参数（非法 JSON 时保留原始字符串）
+pub(super) fn parse_arguments(arguments: &str) -> Value {
+    if arguments.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(arguments).unwrap_or_else(|_| json!({ "arguments": arguments }))
+}
+
+/// Chat Completions -> Anthropic Messages 响应转换器
+#[derive(Debug)]
+pub struct ChatToMessagesTranslator {
+    lines: SseLineBuffer,
+    /// 请求中的模型名称（上游未返回模型时使用）
+    model: String,
+    started: bool,
+    finished: bool,
+    next_index: usize,
+    /// 当前打开的内容块索引
+    open_block: Option<usize>,
+    /// 当前打开的块是否为文本块
+    open_is_text: bool,
+    /// OpenAI tool_calls 索引 -> Anthropic 内容块索引
+    tool_blocks: HashMap<u64, usize>,
+    stop_reason: Option<&'static str>,
+    usage: Option<Value>,
+}
+
+impl ChatToMessagesTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            lines: SseLineBuffer::default(),
+            model: model.to_string(),
+            started: false,
+            finished: false,
+            next_index: 0,
+            open_block: None,
+            open_is_text: false,
+            tool_blocks: HashMap::new(),
+            stop_reason: None,
+            usage: None,
+        }
+    }
+
+    fn handle_payload(&mut self, data: &str, out: &mut Vec<u8>) {
+        if data == "[DONE]" {
+            self.finish_message(out);
+            return;
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            return;
+        };
+
+        if chunk["error"].is_object() {
+            let error = convert_error(500, &chunk["error"]);
+            out.extend(sse_event("error", &error));
+            return;
+        }
+
+        self.ensure_started(&chunk, out);
+
+        if chunk["usage"].is_object() {
+            self.usage = Some(convert_usage(&chunk["usage"]));
+        }
+
+        let Some(choice) = chunk["choices"].get(0) else {
+            return;
+        };
+        let delta = &choice["delta"];
+
+        if let Some(text) = delta["content"].as_str().filter(|s| !s.is_empty()) {
+            if !(self.open_block.is_some() && self.open_is_text) {
+                self.close_block(out);
+                let index = self.open_new_block(true);
+                out.extend(sse_event(
+                    "content_block_start",
+                    &json!({
+                        "type": "content_block_start",
+                        "index": index,
+                        "content_block": { "type": "text", "text": "" }
+                    }),
+                ));
+            }
+            out.extend(sse_event(
+                "content_block_delta",
+                &json!({
+                    "type": "content_block_delta",
+                    "index": self.open_block,
+                    "delta": { "type": "text_delta", "text": text }
+                }),
+            ));
+        }
+
+        if let Some(tool_calls) = delta["tool_calls"].as_array() {
+            for call in tool_calls {
+                let call_index = call["index"].as_u64().unwrap_or(0);
+                let block_index = match self.tool_blocks.get(&call_index) {
+                    Some(&index) => index,
+                    None => {
+                        self.close_block(out);
+                        let index = self.open_new_block(false);
+                        self.tool_blocks.insert(call_index, index);
+                        let id = call["id"]
+                            .as_str()
+                            .map(str::to_string)
+                            .unwrap_or_else(|| format!("toolu_proxy_{index}"));
+                        out.extend(sse_event(
+                            "content_block_start",
+                            &json!({
+                                "type": "content_block_start",
+                                "index": index,
+                                "content_block": {
+                                    "type": "tool_use",
+                                    "id": id,
+                                    "name": call["function"]["name"],
+                                    "input": {}
+                                }
+                            }),
+                        ));
+                        index
+                    }
+                };
+
+                if let Some(arguments) = call["function"]["arguments"]
+                    .as_str()
+                    .filter(|s| !s.is_empty())
+                {
+                    out.extend(sse_event(
+                        "content_block_delta",
+                        &json!({
+                            "type": "content_block_delta",
+                            "index": block_index,
+                            "delta": { "type": "input_json_delta", "partial_json": arguments }
+                        }),
+                    ));
+                }
+            }
+        }
+
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.stop_reason = Some(map_finish_reason(reason));
+        }
+    }
+
+    fn ensure_started(&mut self, chunk: &Value, out: &mut Vec<u8>) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        if let Some(model) = chunk["model"].as_str().filter(|s| !s.is_empty()) {
+            self.model = model.to_string();
+        }
+        out.extend(sse_event(
+            "message_start",
+            &json!({
+                "type": "message_start",
+                "message": {
+                    "id": chunk["id"].as_str().unwrap_or("msg_proxy"),
+                    "type": "message",
+                    "role": "assistant",
+                    "model": self.model,
+                    "content": [],
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": { "input_tokens": 0, "output_tokens": 0 }
+                }
+            }),
+        ));
+    }
+
+    fn open_new_block(&mut self, is_text: bool) -> usize {
+        let index = self.next_index;
+        self.next_index += 1;
+        self.open_block = Some(index);
+        self.open_is_text = is_text;
+        index
+    }
+
+    fn close_block(&mut self, out: &mut Vec<u8>) {
+        if let Some(index) = self.open_block.take() {
+            out.extend(sse_event(
+                "content_block_stop",
+                &json!({ "type": "content_block_stop", "index": index }),
+            ));
+        }
+    }
+
+    fn finish_message(&mut self, out: &mut Vec<u8>) {
+        if self.finished || !self.started {
+            return;
+        }
+        self.finished = true;
+        self.close_block(out);
+
+        let usage = self
+            .usage
+            .take()
+            .unwrap_or_else(|| json!({ "output_tokens": 0 }));
+        out.extend(sse_event(
+            "message_delta",
+            &json!({
+                "type": "message_delta",
+                "delta": {
+                    "stop_reason": self.stop_reason.unwrap_or("end_turn"),
+                    "stop_sequence": null
+                },
+                "usage": usage
+            }),
+        ));
+        out.extend(sse_event(
+            "message_stop",
+            &json!({ "type": "message_stop" }),
+        ));
+    }
+}
+
+impl ResponseTranslator for ChatToMessagesTranslator {
+    fn translate_chunk(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for payload in self.lines.push(chunk) {
+            self.handle_payload(&payload, &mut out);
+        }
+        out
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if let Some(payload) = self.lines.flush() {
+            self.handle_payload(&payload, &mut out);
+        }
+        self.finish_message(&mut out);
+        out
+    }
+
+    fn translate_body(&mut self, status: u16, body: &[u8]) -> Result<Vec<u8>> {
+        let Ok(response) = serde_json::from_slice::<Value>(body) else {
+            return Ok(body.to_vec());
+        };
+
+        let converted = if status >= 400 || response["error"].is_object() {
+            let error = if response["error"].is_null() {
+                &response
+            } else {
+                &response["error"]
+            };
+            convert_error(status, error)
+        } else {
+            chat_to_messages_response(&response, &self.model)
+        };
+        Ok(serde_json::to_vec(&converted)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 解析转换器输出的 SSE 事件（事件名, 数据）
+    fn parse_events(output: &[u8]) -> Vec<(String, Value)> {
+        let text = String::from_utf8(output.to_vec()).unwrap();
+        text.split("\n\n")
+            .filter(|e| !e.trim().is_empty())
+            .map(|event| {
+                let mut lines = event.lines();
+                let name = lines.next().unwrap().trim_start_matches("event: ");
+                let data = lines.next().unwrap().trim_start_matches("data: ");
+                (name.to_string(), serde_json::from_str(data).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_request_conversion() {
+        let body = json!({
+            "model": "claude-sonnet-4-5",
+            "max_tokens": 1024,
+            "system": [{ "type": "text", "text": "You are helpful.", "cache_control": {"type": "ephemeral"} }],
+            "stream": true,
+            "tools": [{ "name": "Read", "description": "read a file", "input_schema": { "type": "object" } }],
+            "tool_choice": { "type": "any" },
+            "messages": [
+                { "role": "user", "content": "hi" },
+                { "role": "assistant", "content": [
+                    { "type": "thinking", "thinking": "..." },
+                    { "type": "text", "text": "Reading." },
+                    { "type": "tool_use", "id": "toolu_1", "name": "Read", "input": { "path": "a.rs" } }
+                ]},
+                { "role": "user", "content": [
+                    { "type": "tool_result", "tool_use_id": "toolu_1", "content": [{ "type": "text", "text": "fn main() {}" }] },
+                    { "type": "text", "text": "explain" }
+                ]}
+            ]
+        });
+
+        let request = messages_to_chat_request(&body).unwrap();
+        assert_eq!(request["model"], "claude-sonnet-4-5");
+        assert_eq!(request["stream_options"]["include_usage"], true);
+        assert_eq!(request["tool_choice"], "required");
+        assert_eq!(request["tools"][0]["function"]["name"], "Read");
+
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "You are helpful.");
+        assert_eq!(messages[2]["content"], "Reading.");
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"],
+            r#"{"path":"a.rs"}"#
+        );
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_call_id"], "toolu_1");
+        assert_eq!(messages[3]["content"], "fn main() {}");
+        assert_eq!(messages[4]["content"], "explain");
+    }
+
+    #[test]
+    fn test_stream_translation() {
+        let mut translator = ChatToMessagesTranslator::new("claude-sonnet-4-5");
+        let upstream = concat!(
+            "data: {\"id\":\"c1\",\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hel\"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"Read\",\"arguments\":\"\"}}]}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"path\\\":1}\"}}]}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[],\"usage\":{\"prompt_tokens\":100,\"completion_tokens\":20,\"prompt_tokens_details\":{\"cached_tokens\":60}}}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let mut output = Vec::new();
+        for chunk in upstream.as_bytes().chunks(50) {
+            output.extend(translator.translate_chunk(chunk));
+        }
+        output.extend(translator.finish());
+
+        let events = parse_events(&output);
+        let names: Vec<&str> = events.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+        assert_eq!(events[0].1["message"]["model"], "gpt-4o");
+        assert_eq!(events[5].1["content_block"]["type"], "tool_use");
+        assert_eq!(events[5].1["content_block"]["id"], "call_1");
+        assert_eq!(events[6].1["delta"]["partial_json"], "{\"path\":1}");
+        assert_eq!(events[8].1["delta"]["stop_reason"], "tool_use");
+        assert_eq!(events[8].1["usage"]["input_tokens"], 40);
+        assert_eq!(events[8].1["usage"]["cache_read_input_tokens"], 60);
+    }
+
+    #[test]
+    fn test_body_translation() {
+        let mut translator = ChatToMessagesTranslator::new("claude-sonnet-4-5");
+        let body = json!({
+            "id": "c2",
+            "choices": [{ "message": { "role": "assistant", "content": "done" }, "finish_reason": "length" }],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 3 }
+        });
+        let converted: Value = serde_json::from_slice(
+            &translator
+                .translate_body(200, body.to_string().as_bytes())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(converted["type"], "message");
+        assert_eq!(converted["model"], "claude-sonnet-4-5");
+        assert_eq!(converted["content"][0]["text"], "done");
+        assert_eq!(converted["stop_reason"], "max_tokens");
+
+        let error = br#"{"error":{"message":"invalid key","type":"invalid_request_error"}}"#;
+        let converted: Value =
+            serde_json::from_slice(&translator.translate_body(401, error).unwrap()).unwrap();
+        assert_eq!(converted["type"], "error");
+        assert_eq!(converted["error"]["type"], "authentication_error");
+        assert_eq!(converted["error"]["message"], "invalid key");
+    }
+}
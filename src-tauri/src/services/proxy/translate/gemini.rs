@@ -0,0 +1,983 @@
+//! Gemini generateContent <-> OpenAI Chat Completions / Anthropic Messages 转换
+//!
+//! 请求：`models/<model>:generateContent` / `:streamGenerateContent` 请求体转换为 Chat 或 Messages 请求体
+//! （systemInstruction、functionDeclarations、functionCall/functionResponse、inlineData、generationConfig）
+//! 响应：Chat 或 Messages（JSON 或 SSE）转换为 GenerateContentResponse（JSON 或 `alt=sse` 流）
+//!
+//! 流式响应中的函数调用在参数完整后一次性输出（与 Gemini 原生行为一致）
+
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use super::anthropic_openai::parse_arguments;
+use super::{sse_data, ResponseTranslator, SseLineBuffer};
+
+/// Messages 请求必须提供 max_tokens，Gemini 请求未指定时使用该默认值
+const DEFAULT_MAX_TOKENS: u64 = 8192;
+
+/// 从请求路径解析模型名称与是否流式
+///
+/// 仅识别 `.../models/<model>:generateContent` 与 `.../models/<model>:streamGenerateContent`
+pub fn parse_generate_path(path: &str) -> Option<(String, bool)> {
+    let (_, rest) = path.split_once("/models/")?;
+    let (model, method) = rest.split_once(':')?;
+    match method {
+        "generateContent" => Some((model.to_string(), false)),
+        "streamGenerateContent" => Some((model.to_string(), true)),
+        _ => None,
+    }
+}
+
+// ==================== 请求转换 ====================
+
+/// 函数调用 ID 分配
+///
+/// Gemini 的 functionCall/functionResponse 可能不带 id，按名称顺序配对生成
+#[derive(Default)]
+struct CallIds {
+    pending: HashMap<String, VecDeque<String>>,
+    counter: usize,
+}
+
+impl CallIds {
+    fn for_call(&mut self, call: &Value) -> String {
+        let name = call["name"].as_str().unwrap_or_default().to_string();
+        let id = call["id"].as_str().map(str::to_string).unwrap_or_else(|| {
+            self.counter += 1;
+            format!("call_{}_{}", name, self.counter)
+        });
+        self.pending.entry(name).or_default().push_back(id.clone());
+        id
+    }
+
+    fn for_response(&mut self, response: &Value) -> String {
+        let name = response["name"].as_str().unwrap_or_default();
+        let queued = self
+            .pending
+            .get_mut(name)
+            .and_then(|queue| queue.pop_front());
+        match response["id"].as_str() {
+            Some(id) => id.to_string(),
+            None => queued.unwrap_or_else(|| format!("call_{name}")),
+        }
+    }
+}
+
+/// 将 Gemini Schema（类型名可能为大写，如 `OBJECT`）规范化为 JSON Schema
+fn normalize_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = match (key.as_str(), value) {
+                        ("type", Value::String(t)) => Value::String(t.to_lowercase()),
+                        _ => normalize_schema(value),
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(normalize_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 提取函数声明 (name, description, JSON Schema)
+fn function_declarations(body: &Value) -> Vec<(Value, Value, Value)> {
+    body["tools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|tool| {
+            tool["functionDeclarations"]
+                .as_array()
+                .into_iter()
+                .flatten()
+        })
+        .map(|decl| {
+            let schema = decl
+                .get("parametersJsonSchema")
+                .or_else(|| decl.get("parameters"))
+                .map(normalize_schema)
+                .unwrap_or_else(|| json!({ "type": "object", "properties": {} }));
+            (
+                decl["name"].clone(),
+                decl.get("description").cloned().unwrap_or(Value::Null),
+                schema,
+            )
+        })
+        .collect()
+}
+
+fn system_text(body: &Value) -> String {
+    body["systemInstruction"]["parts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 函数调用响应内容（序列化为字符串）
+fn function_response_text(response: &Value) -> String {
+    match &response["response"] {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// 将 Gemini 请求体转换为 Chat Completions 请求体
+pub fn gemini_to_chat_request(model: &str, body: &Value, stream: bool) -> Result<Value> {
+    let contents = body["contents"].as_array().context("缺少 contents 字段")?;
+    let mut request = Map::new();
+    request.insert("model".into(), json!(model));
+
+    let mut messages = Vec::new();
+    let system = system_text(body);
+    if !system.is_empty() {
+        messages.push(json!({ "role": "system", "content": system }));
+    }
+
+    let mut ids = CallIds::default();
+    for content in contents {
+        let parts = content["parts"].as_array().cloned().unwrap_or_default();
+        if content["role"] == "model" {
+            let text: String = parts
+                .iter()
+                .filter(|p| p["thought"] != true)
+                .filter_map(|p| p["text"].as_str())
+                .collect();
+            let tool_calls: Vec<Value> = parts
+                .iter()
+                .filter(|p| p["functionCall"].is_object())
+                .map(|p| {
+                    let call = &p["functionCall"];
+                    json!({
+                        "id": ids.for_call(call),
+                        "type": "function",
+                        "function": { "name": call["name"], "arguments": call["args"].to_string() }
+                    })
+                })
+                .collect();
+            let mut message = json!({
+                "role": "assistant",
+                "content": if text.is_empty() { Value::Null } else { json!(text) },
+            });
+            if !tool_calls.is_empty() {
+                message["tool_calls"] = Value::Array(tool_calls);
+            }
+            messages.push(message);
+            continue;
+        }
+
+        // 用户消息：函数调用结果转换为 tool 消息，其余内容组成 user 消息
+        let mut user_parts = Vec::new();
+        for part in &parts {
+            if let Some(response) = part.get("functionResponse") {
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": ids.for_response(response),
+                    "content": function_response_text(response),
+                }));
+            } else if let Some(text) = part["text"].as_str() {
+                user_parts.push(json!({ "type": "text", "text": text }));
+            } else if let Some(data) = part.get("inlineData") {
+                user_parts.push(json!({
+                    "type": "image_url",
+                    "image_url": {
+                        "url": format!(
+                            "data:{};base64,{}",
+                            data["mimeType"].as_str().unwrap_or("image/png"),
+                            data["data"].as_str().unwrap_or_default()
+                        )
+                    }
+                }));
+            }
+        }
+        if user_parts.is_empty() {
+            continue;
+        }
+        let content = if user_parts.iter().all(|p| p["type"] == "text") {
+            json!(user_parts
+                .iter()
+                .filter_map(|p| p["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n"))
+        } else {
+            Value::Array(user_parts)
+        };
+        messages.push(json!({ "role": "user", "content": content }));
+    }
+    request.insert("messages".into(), Value::Array(messages));
+
+    let tools: Vec<Value> = function_declarations(body)
+        .into_iter()
+        .map(|(name, description, parameters)| {
+            json!({
+                "type": "function",
+                "function": { "name": name, "description": description, "parameters": parameters }
+            })
+        })
+        .collect();
+    if !tools.is_empty() {
+        request.insert("tools".into(), Value::Array(tools));
+        let config = &body["toolConfig"]["functionCallingConfig"];
+        let choice = match config["mode"].as_str() {
+            Some("ANY") => match config["allowedFunctionNames"].as_array() {
+                Some(names) if names.len() == 1 => {
+                    json!({ "type": "function", "function": { "name": names[0] } })
+                }
+                _ => json!("required"),
+            },
+            Some("NONE") => json!("none"),
+            Some(_) => json!("auto"),
+            None => Value::Null,
+        };
+        if !choice.is_null() {
+            request.insert("tool_choice".into(), choice);
+        }
+    }
+
+    let config = &body["generationConfig"];
+    if let Some(max_tokens) = config.get("maxOutputTokens") {
+        request.insert("max_tokens".into(), max_tokens.clone());
+    }
+    for (from, to) in [
+        ("temperature", "temperature"),
+        ("topP", "top_p"),
+        ("stopSequences", "stop"),
+    ] {
+        if let Some(value) = config.get(from) {
+            request.insert(to.into(), value.clone());
+        }
+    }
+    if stream {
+        request.insert("stream".into(), json!(true));
+        request.insert("stream_options".into(), json!({ "include_usage": true }));
+    }
+
+    Ok(Value::Object(request))
+}
+
+/// 将 Gemini 请求体转换为 Anthropic Messages 请求体
+pub fn gemini_to_messages_request(model: &str, body: &Value, stream: bool) -> Result<Value> {
+    let contents = body["contents"].as_array().context("缺少 contents 字段")?;
+    let mut request = Map::new();
+    request.insert("model".into(), json!(model));
+
+    let system = system_text(body);
+    if !system.is_empty() {
+        request.insert("system".into(), json!(system));
+    }
+
+    let mut ids = CallIds::default();
+    let mut messages = Vec::new();
+    for content in contents {
+        let role = if content["role"] == "model" {
+            "assistant"
+        } else {
+            "user"
+        };
+        let blocks: Vec<Value> = content["parts"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|part| part["thought"] != true)
+            .filter_map(|part| {
+                if let Some(call) = part.get("functionCall") {
+                    Some(json!({
+                        "type": "tool_use",
+                        "id": ids.for_call(call),
+                        "name": call["name"],
+                        "input": if call["args"].is_object() { call["args"].clone() } else { json!({}) },
+                    }))
+                } else if let Some(response) = part.get("functionResponse") {
+                    Some(json!({
+                        "type": "tool_result",
+                        "tool_use_id": ids.for_response(response),
+                        "content": function_response_text(response),
+                    }))
+                } else if let Some(data) = part.get("inlineData") {
+                    Some(json!({
+                        "type": "image",
+                        "source": {
+                            "type": "base64",
+                            "media_type": data["mimeType"],
+                            "data": data["data"],
+                        }
+                    }))
+                } else {
+                    part["text"]
+                        .as_str()
+                        .filter(|t| !t.is_empty())
+                        .map(|text| json!({ "type": "text", "text": text }))
+                }
+            })
+            .collect();
+        if !blocks.is_empty() {
+            messages.push(json!({ "role": role, "content": blocks }));
+        }
+    }
+    request.insert("messages".into(), Value::Array(messages));
+
+    let config = &body["generationConfig"];
+    request.insert(
+        "max_tokens".into(),
+        config
+            .get("maxOutputTokens")
+            .cloned()
+            .unwrap_or_else(|| json!(DEFAULT_MAX_TOKENS)),
+    );
+    for (from, to) in [
+        ("temperature", "temperature"),
+        ("topP", "top_p"),
+        ("topK", "top_k"),
+        ("stopSequences", "stop_sequences"),
+    ] {
+        if let Some(value) = config.get(from) {
+            request.insert(to.into(), value.clone());
+        }
+    }
+
+    let tools: Vec<Value> = function_declarations(body)
+        .into_iter()
+        .map(|(name, description, schema)| {
+            json!({ "name": name, "description": description, "input_schema": schema })
+        })
+        .collect();
+    if !tools.is_empty() {
+        request.insert("tools".into(), Value::Array(tools));
+        let config = &body["toolConfig"]["functionCallingConfig"];
+        let choice = match config["mode"].as_str() {
+            Some("ANY") => match config["allowedFunctionNames"].as_array() {
+                Some(names) if names.len() == 1 => json!({ "type": "tool", "name": names[0] }),
+                _ => json!({ "type": "any" }),
+            },
+            Some("NONE") => json!({ "type": "none" }),
+            Some(_) => json!({ "type": "auto" }),
+            None => Value::Null,
+        };
+        if !choice.is_null() {
+            request.insert("tool_choice".into(), choice);
+        }
+    }
+    if stream {
+        request.insert("stream".into(), json!(true));
+    }
+
+    Ok(Value::Object(request))
+}
+
+// ==================== 响应转换 ====================
+
+/// 将 HTTP 状态码映射为 Google API 错误状态
+fn error_status(status: u16) -> &'static str {
+    match status {
+        400 => "INVALID_ARGUMENT",
+        401 => "UNAUTHENTICATED",
+        403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        429 => "RESOURCE_EXHAUSTED",
+        503 | 529 => "UNAVAILABLE",
+        _ => "INTERNAL",
+    }
+}
+
+/// 将上游错误（OpenAI 或 Anthropic 格式）转换为 Gemini 错误格式
+fn convert_error(status: u16, body: &Value) -> Value {
+    let message = body["error"]["message"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| body.to_string());
+    json!({
+        "error": { "code": status, "message": message, "status": error_status(status) }
+    })
+}
+
+/// 构建 GenerateContentResponse
+fn gemini_response(
+    model: &str,
+    parts: Vec<Value>,
+    finish_reason: Option<&str>,
+    usage: Option<&Value>,
+) -> Value {
+    let mut candidate = json!({
+        "content": { "role": "model", "parts": parts },
+        "index": 0,
+    });
+    if let Some(reason) = finish_reason {
+        candidate["finishReason"] = json!(reason);
+    }
+    let mut response = json!({ "candidates": [candidate], "modelVersion": model });
+    if let Some(usage) = usage {
+        response["usageMetadata"] = usage.clone();
+    }
+    response
+}
+
+fn usage_metadata(prompt: i64, cached: i64, output: i64, thoughts: i64) -> Value {
+    json!({
+        "promptTokenCount": prompt,
+        "candidatesTokenCount": output,
+        "totalTokenCount": prompt + output,
+        "cachedContentTokenCount": cached,
+        "thoughtsTokenCount": thoughts,
+    })
+}
+
+/// Chat 用量 -> usageMetadata
+fn chat_usage_metadata(usage: &Value) -> Value {
+    usage_metadata(
+        usage["prompt_tokens"].as_i64().unwrap_or(0),
+        usage["prompt_tokens_details"]["cached_tokens"]
+            .as_i64()
+            .unwrap_or(0),
+        usage["completion_tokens"].as_i64().unwrap_or(0),
+        usage["completion_tokens_details"]["reasoning_tokens"]
+            .as_i64()
+            .unwrap_or(0),
+    )
+}
+
+/// Messages 用量 -> usageMetadata（Gemini 的 promptTokenCount 包含缓存部分）
+fn messages_usage_metadata(usage: &Value) -> Value {
+    let cached = usage["cache_read_input_tokens"].as_i64().unwrap_or(0);
+    let prompt = usage["input_tokens"].as_i64().unwrap_or(0)
+        + cached
+        + usage["cache_creation_input_tokens"].as_i64().unwrap_or(0);
+    usage_metadata(
+        prompt,
+        cached,
+        usage["output_tokens"].as_i64().unwrap_or(0),
+        0,
+    )
+}
+
+fn chat_finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "length" => "MAX_TOKENS",
+        "content_filter" => "SAFETY",
+        _ => "STOP",
+    }
+}
+
+fn messages_finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "max_tokens" => "MAX_TOKENS",
+        "refusal" => "SAFETY",
+        _ => "STOP",
+    }
+}
+
+fn function_call_part(id: &Value, name: &Value, args: Value) -> Value {
+    json!({ "functionCall": { "id": id, "name": name, "args": args } })
+}
+
+/// 将 Chat Completions 非流式响应转换为 Gemini 响应
+pub fn chat_to_gemini_response(response: &Value, fallback_model: &str) -> Value {
+    let choice = &response["choices"][0];
+    let message = &choice["message"];
+
+    let mut parts = Vec::new();
+    if let Some(text) = message["content"].as_str().filter(|s| !s.is_empty()) {
+        parts.push(json!({ "text": text }));
+    }
+    for call in message["tool_calls"].as_array().into_iter().flatten() {
+        parts.push(function_call_part(
+            &call["id"],
+            &call["function"]["name"],
+            parse_arguments(call["function"]["arguments"].as_str().unwrap_or_default()),
+        ));
+    }
+
+    gemini_response(
+        response["model"].as_str().unwrap_or(fallback_model),
+        parts,
+        Some(chat_finish_reason(
+            choice["finish_reason"].as_str().unwrap_or("stop"),
+        )),
+        Some(&chat_usage_metadata(&response["usage"])),
+    )
+}
+
+/// 将 Anthropic Messages 非流式响应转换为 Gemini 响应
+pub fn messages_to_gemini_response(response: &Value, fallback_model: &str) -> Value {
+    let parts: Vec<Value> = response["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|block| match block["type"].as_str() {
+            Some("text") => Some(json!({ "text": block["text"] })),
+            Some("thinking") => Some(json!({ "text": block["thinking"], "thought": true })),
+            Some("tool_use") => Some(function_call_part(
+                &block["id"],
+                &block["name"],
+                block["input"].clone(),
+            )),
+            _ => None,
+        })
+        .collect();
+
+    gemini_response(
+        response["model"].as_str().unwrap_or(fallback_model),
+        parts,
+        Some(messages_finish_reason(
+            response["stop_reason"].as_str().unwrap_or("end_turn"),
+        )),
+        Some(&messages_usage_metadata(&response["usage"])),
+    )
+}
+
+/// 流式工具调用缓冲 (id, name, 参数 JSON 片段)
+type PendingCall = (Value, Value, String);
+
+/// Chat Completions -> Gemini 响应转换器
+#[derive(Debug)]
+pub struct ChatToGeminiTranslator {
+    lines: SseLineBuffer,
+    model: String,
+    finished: bool,
+    /// Chat tool_calls 索引 -> 缓冲中的调用
+    tool_calls: BTreeMap<u64, PendingCall>,
+    finish_reason: Option<&'static str>,
+    usage: Option<Value>,
+}
+
+impl ChatToGeminiTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            lines: SseLineBuffer::default(),
+            model: model.to_string(),
+            finished: false,
+            tool_calls: BTreeMap::new(),
+            finish_reason: None,
+            usage: None,
+        }
+    }
+
+    fn handle_payload(&mut self, data: &str, out: &mut Vec<u8>) {
+        if data == "[DONE]" {
+            self.finish_stream(out);
+            return;
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            return;
+        };
+        if chunk["error"].is_object() {
+            out.extend(sse_data(&convert_error(500, &chunk)));
+            self.finished = true;
+            return;
+        }
+        if let Some(model) = chunk["model"].as_str().filter(|s| !s.is_empty()) {
+            self.model = model.to_string();
+        }
+        if chunk["usage"].is_object() {
+            self.usage = Some(chat_usage_metadata(&chunk["usage"]));
+        }
+
+        let Some(choice) = chunk["choices"].get(0) else {
+            return;
+        };
+        let delta = &choice["delta"];
+
+        let mut parts = Vec::new();
+        if let Some(thought) = delta["reasoning_content"]
+            .as_str()
+            .filter(|s| !s.is_empty())
+        {
+            parts.push(json!({ "text": thought, "thought": true }));
+        }
+        if let Some(text) = delta["content"].as_str().filter(|s| !s.is_empty()) {
+            parts.push(json!({ "text": text }));
+        }
+        if !parts.is_empty() {
+            out.extend(sse_data(&gemini_response(&self.model, parts, None, None)));
+        }
+
+        for call in delta["tool_calls"].as_array().into_iter().flatten() {
+            let entry = self
+                .tool_calls
+                .entry(call["index"].as_u64().unwrap_or(0))
+                .or_insert_with(|| (Value::Null, Value::Null, String::new()));
+            if call["id"].is_string() {
+                entry.0 = call["id"].clone();
+            }
+            if call["function"]["name"].is_string() {
+                entry.1 = call["function"]["name"].clone();
+            }
+            if let Some(arguments) = call["function"]["arguments"].as_str() {
+                entry.2.push_str(arguments);
+            }
+        }
+
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(chat_finish_reason(reason));
+        }
+    }
+
+    /// 输出缓冲的函数调用与结束原因、用量
+    fn finish_stream(&mut self, out: &mut Vec<u8>) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        let parts: Vec<Value> = std::mem::take(&mut self.tool_calls)
+            .into_values()
+            .map(|(id, name, arguments)| {
+                function_call_part(&id, &name, parse_arguments(&arguments))
+            })
+            .collect();
+        out.extend(sse_data(&gemini_response(
+            &self.model,
+            parts,
+            Some(self.finish_reason.unwrap_or("STOP")),
+            self.usage.as_ref(),
+        )));
+    }
+}
+
+impl ResponseTranslator for ChatToGeminiTranslator {
+    fn translate_chunk(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for payload in self.lines.push(chunk) {
+            self.handle_payload(&payload, &mut out);
+        }
+        out
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if let Some(payload) = self.lines.flush() {
+            self.handle_payload(&payload, &mut out);
+        }
+        self.finish_stream(&mut out);
+        out
+    }
+
+    fn translate_body(&mut self, status: u16, body: &[u8]) -> Result<Vec<u8>> {
+        let Ok(response) = serde_json::from_slice::<Value>(body) else {
+            return Ok(body.to_vec());
+        };
+        let converted = if status >= 400 || response["error"].is_object() {
+            convert_error(status, &response)
+        } else {
+            chat_to_gemini_response(&response, &self.model)
+        };
+        Ok(serde_json::to_vec(&converted)?)
+    }
+}
+
+/// Anthropic Messages -> Gemini 响应转换器
+#[derive(Debug)]
+pub struct MessagesToGeminiTranslator {
+    lines: SseLineBuffer,
+    model: String,
+    finished: bool,
+    /// 内容块索引 -> 缓冲中的工具调用
+    tool_blocks: HashMap<u64, PendingCall>,
+    finish_reason: Option<&'static str>,
+    /// message_start 中的用量（输入部分），message_delta 中更新输出部分
+    usage: Value,
+}
+
+impl MessagesToGeminiTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            lines: SseLineBuffer::default(),
+            model: model.to_string(),
+            finished: false,
+            tool_blocks: HashMap::new(),
+            finish_reason: None,
+            usage: json!({}),
+        }
+    }
+
+    fn handle_payload(&mut self, data: &str, out: &mut Vec<u8>) {
+        if self.finished {
+            return;
+        }
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            return;
+        };
+
+        match event["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                let message = &event["message"];
+                if let Some(model) = message["model"].as_str().filter(|s| !s.is_empty()) {
+                    self.model = model.to_string();
+                }
+                if message["usage"].is_object() {
+                    self.usage = message["usage"].clone();
+                }
+            }
+            "content_block_start" if event["content_block"]["type"] == "tool_use" => {
+                let block = &event["content_block"];
+                self.tool_blocks.insert(
+                    event["index"].as_u64().unwrap_or(0),
+                    (block["id"].clone(), block["name"].clone(), String::new()),
+                );
+            }
+            "content_block_delta" => {
+                let delta = &event["delta"];
+                let part = match delta["type"].as_str() {
+                    Some("text_delta") => Some(json!({ "text": delta["text"] })),
+                    Some("thinking_delta") => {
+                        Some(json!({ "text": delta["thinking"], "thought": true }))
+                    }
+                    Some("input_json_delta") => {
+                        let index = event["index"].as_u64().unwrap_or(0);
+                        if let Some(block) = self.tool_blocks.get_mut(&index) {
+                            block
+                                .2
+                                .push_str(delta["partial_json"].as_str().unwrap_or_default());
+                        }
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(part) = part {
+                    out.extend(sse_data(&gemini_response(
+                        &self.model,
+                        vec![part],
+                        None,
+                        None,
+                    )));
+                }
+            }
+            "content_block_stop" => {
+                let index = event["index"].as_u64().unwrap_or(0);
+                if let Some((id, name, arguments)) = self.tool_blocks.remove(&index) {
+                    let part = function_call_part(&id, &name, parse_arguments(&arguments));
+                    out.extend(sse_data(&gemini_response(
+                        &self.model,
+                        vec![part],
+                        None,
+                        None,
+                    )));
+                }
+            }
+            "message_delta" => {
+                if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                    self.finish_reason = Some(messages_finish_reason(reason));
+                }
+                if let Some(usage) = event["usage"].as_object() {
+                    for (key, value) in usage {
+                        self.usage[key] = value.clone();
+                    }
+                }
+            }
+            "message_stop" => self.finish_stream(out),
+            "error" => {
+                let status = match event["error"]["type"].as_str() {
+                    Some("overloaded_error") => 529,
+                    Some("rate_limit_error") => 429,
+                    _ => 500,
+                };
+                out.extend(sse_data(&convert_error(status, &event)));
+                self.finished = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn finish_stream(&mut self, out: &mut Vec<u8>) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        out.extend(sse_data(&gemini_response(
+            &self.model,
+            Vec::new(),
+            Some(self.finish_reason.unwrap_or("STOP")),
+            Some(&messages_usage_metadata(&self.usage)),
+        )));
+    }
+}
+
+impl ResponseTranslator for MessagesToGeminiTranslator {
+    fn translate_chunk(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for payload in self.lines.push(chunk) {
+            self.handle_payload(&payload, &mut out);
+        }
+        out
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if let Some(payload) = self.lines.flush() {
+            self.handle_payload(&payload, &mut out);
+        }
+        self.finish_stream(&mut out);
+        out
+    }
+
+    fn translate_body(&mut self, status: u16, body: &[u8]) -> Result<Vec<u8>> {
+        let Ok(response) = serde_json::from_slice::<Value>(body) else {
+            return Ok(body.to_vec());
+        };
+        let converted = if status >= 400 || response["type"] == "error" {
+            convert_error(status, &response)
+        } else {
+            messages_to_gemini_response(&response, &self.model)
+        };
+        Ok(serde_json::to_vec(&converted)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_data(output: &[u8]) -> Vec<Value> {
+        String::from_utf8(output.to_vec())
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect()
+    }
+
+    fn sample_request() -> Value {
+        json!({
+            "systemInstruction": { "parts": [{ "text": "be brief" }] },
+            "contents": [
+                { "role": "user", "parts": [{ "text": "read a.rs" }] },
+                { "role": "model", "parts": [
+                    { "text": "thinking", "thought": true },
+                    { "functionCall": { "name": "read_file", "args": { "path": "a.rs" } } }
+                ]},
+                { "role": "user", "parts": [
+                    { "functionResponse": { "name": "read_file", "response": { "output": "fn main() {}" } } }
+                ]}
+            ],
+            "tools": [{ "functionDeclarations": [{
+                "name": "read_file",
+                "parameters": { "type": "OBJECT", "properties": { "path": { "type": "STRING" } } }
+            }]}],
+            "toolConfig": { "functionCallingConfig": { "mode": "AUTO" } },
+            "generationConfig": { "temperature": 0.2, "maxOutputTokens": 1024 }
+        })
+    }
+
+    #[test]
+    fn test_parse_generate_path() {
+        assert_eq!(
+            parse_generate_path("/v1beta/models/gemini-2.5-pro:streamGenerateContent"),
+            Some(("gemini-2.5-pro".to_string(), true))
+        );
+        assert_eq!(
+            parse_generate_path("/v1beta/models/gemini-2.5-pro:generateContent"),
+            Some(("gemini-2.5-pro".to_string(), false))
+        );
+        assert!(parse_generate_path("/v1beta/models/gemini-2.5-pro:countTokens").is_none());
+        assert!(parse_generate_path("/v1beta/models").is_none());
+    }
+
+    #[test]
+    fn test_gemini_to_chat_request() {
+        let request = gemini_to_chat_request("gpt-4o", &sample_request(), true).unwrap();
+        assert_eq!(request["model"], "gpt-4o");
+        assert_eq!(request["max_tokens"], 1024);
+        assert_eq!(request["tool_choice"], "auto");
+        assert_eq!(
+            request["tools"][0]["function"]["parameters"]["properties"]["path"]["type"],
+            "string"
+        );
+
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["content"], "be brief");
+        // 思考内容不回传
+        assert!(messages[2]["content"].is_null());
+        let call_id = &messages[2]["tool_calls"][0]["id"];
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(&messages[3]["tool_call_id"], call_id);
+    }
+
+    #[test]
+    fn test_gemini_to_messages_request() {
+        let request =
+            gemini_to_messages_request("claude-sonnet-4-5", &sample_request(), false).unwrap();
+        assert_eq!(request["system"], "be brief");
+        assert_eq!(request["tools"][0]["input_schema"]["type"], "object");
+
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"]["path"], "a.rs");
+        assert_eq!(
+            messages[2]["content"][0]["tool_use_id"],
+            messages[1]["content"][0]["id"]
+        );
+    }
+
+    #[test]
+    fn test_chat_stream_to_gemini() {
+        let mut translator = ChatToGeminiTranslator::new("gpt-4o");
+        let upstream = concat!(
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ok\"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"c1\",\"function\":{\"name\":\"read_file\",\"arguments\":\"{\\\"path\\\"\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\":\\\"a.rs\\\"}\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":5}}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let mut output = translator.translate_chunk(upstream.as_bytes());
+        output.extend(translator.finish());
+        let chunks = parse_data(&output);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[0]["candidates"][0]["content"]["parts"][0]["text"],
+            "ok"
+        );
+
+        let last = &chunks[1];
+        let call = &last["candidates"][0]["content"]["parts"][0]["functionCall"];
+        assert_eq!(call["id"], "c1");
+        assert_eq!(call["args"]["path"], "a.rs");
+        assert_eq!(last["candidates"][0]["finishReason"], "STOP");
+        assert_eq!(last["usageMetadata"]["totalTokenCount"], 17);
+    }
+
+    #[test]
+    fn test_messages_stream_to_gemini() {
+        let mut translator = MessagesToGeminiTranslator::new("claude-sonnet-4-5");
+        let upstream = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-sonnet-4-5\",\"usage\":{\"input_tokens\":8,\"cache_read_input_tokens\":2}}}\n\n",
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"hi\"}}\n\n",
+            "data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"t1\",\"name\":\"ls\"}}\n\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{}\"}}\n\n",
+            "data: {\"type\":\"content_block_stop\",\"index\":1}\n\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"max_tokens\"},\"usage\":{\"output_tokens\":3}}\n\n",
+            "data: {\"type\":\"message_stop\"}\n\n",
+        );
+
+        let mut output = translator.translate_chunk(upstream.as_bytes());
+        output.extend(translator.finish());
+        let chunks = parse_data(&output);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            chunks[1]["candidates"][0]["content"]["parts"][0]["functionCall"]["name"],
+            "ls"
+        );
+        assert_eq!(chunks[2]["candidates"][0]["finishReason"], "MAX_TOKENS");
+        assert_eq!(chunks[2]["usageMetadata"]["promptTokenCount"], 10);
+        assert_eq!(chunks[2]["usageMetadata"]["cachedContentTokenCount"], 2);
+    }
+
+    #[test]
+    fn test_error_body_converted() {
+        let mut translator = MessagesToGeminiTranslator::new("m");
+        let body = br#"{"type":"error","error":{"type":"rate_limit_error","message":"slow down"}}"#;
+        let converted: Value =
+            serde_json::from_slice(&translator.translate_body(429, body).unwrap()).unwrap();
+        assert_eq!(converted["error"]["status"], "RESOURCE_EXHAUSTED");
+        assert_eq!(converted["error"]["message"], "slow down");
+    }
+}
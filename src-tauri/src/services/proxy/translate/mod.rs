@@ -0,0 +1,108 @@
+//! 协议转换
+//!
+//! 当上游协议与客户端协议不一致时（如 Claude Code 连接仅支持 OpenAI 格式的中转），
+//! 请求由对应的 RequestProcessor 转换，响应由 `ResponseTranslator` 转换回客户端格式：
+//! - anthropic_openai: Anthropic Messages <-> OpenAI Chat Completions
+//! - responses_chat: OpenAI Responses API <-> Chat Completions（Codex wire_api 桥接）
+//! - gemini: Gemini generateContent <-> OpenAI Chat Completions / Anthropic Messages
+
+pub mod anthropic_openai;
+pub mod gemini;
+pub mod responses_chat;
+
+use anyhow::Result;
+
+/// 响应转换器
+///
+/// 每个请求创建一个实例（流式转换需要保存状态）
+pub trait ResponseTranslator: Send + std::fmt::Debug {
+    /// 转换上游 SSE 数据块（可以是任意切分的字节块），返回发送给客户端的 SSE 数据
+    fn translate_chunk(&mut self, chunk: &[u8]) -> Vec<u8>;
+
+    /// 上游流结束，返回需要补发的事件
+    fn finish(&mut self) -> Vec<u8>;
+
+    /// 转换非流式响应体（包括错误响应）
+    fn translate_body(&mut self, status: u16, body: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// SSE 行缓冲：将任意切分的字节块拆分为完整的 `data:` 负载
+#[derive(Debug, Default)]
+pub struct SseLineBuffer {
+    buffer: Vec<u8>,
+}
+
+impl SseLineBuffer {
+    /// 输入数据块，返回本次解析出的所有 `data:` 负载（已去除前缀与首尾空白）
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut payloads = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            if let Some(data) = parse_data_line(&line) {
+                payloads.push(data);
+            }
+        }
+        payloads
+    }
+
+    /// 取出缓冲中剩余的最后一行（流结束时调用）
+    pub fn flush(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.buffer);
+        parse_data_line(&line)
+    }
+}
+
+fn parse_data_line(line: &[u8]) -> Option<String> {
+    let line = std::str::from_utf8(line).ok()?;
+    let data = line.trim_end().strip_prefix("data:")?.trim();
+    (!data.is_empty()).then(|| data.to_string())
+}
+
+/// 格式化一个 SSE 事件
+pub fn sse_event(event: &str, data: &serde_json::Value) -> Vec<u8> {
+    format!("event: {event}\ndata: {data}\n\n").into_bytes()
+}
+
+/// 格式化一个不带事件名的 SSE 数据行
+pub fn sse_data(data: &serde_json::Value) -> Vec<u8> {
+    format!("data: {data}\n\n").into_bytes()
+}
+
+/// 拼接 OpenAI 风格的接口地址
+///
+/// 兼容 `https://host`、`https://host/v1` 两种 Base URL 写法
+pub fn openai_endpoint(base_url: &str, endpoint: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    if base.ends_with("/v1") {
+        format!("{base}{endpoint}")
+    } else {
+        format!("{base}/v1{endpoint}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_line_buffer() {
+        let mut buffer = SseLineBuffer::default();
+        assert!(buffer.push(b"event: x\ndata: {\"a\"").is_empty());
+        assert_eq!(buffer.push(b":1}\n\ndata: [DONE]"), vec!["{\"a\":1}"]);
+        assert_eq!(buffer.flush().as_deref(), Some("[DONE]"));
+    }
+
+    #[test]
+    fn test_openai_endpoint() {
+        assert_eq!(
+            openai_endpoint("https://relay.example.com/", "/chat/completions"),
+            "https://relay.example.com/v1/chat/completions"
+        );
+        assert_eq!(
+            openai_endpoint("https://relay.example.com/v1", "/chat/completions"),
+            "https://relay.example.com/v1/chat/completions"
+        );
+    }
+}
@@ -0,0 +1,1220 @@
+//! OpenAI Responses API <-> Chat Completions 转换（Codex wire_api 桥接）
+//!
+//! - Responses 请求 -> Chat 请求，Chat 响应 -> Responses 响应（Codex 使用 responses，上游仅支持 chat）
+//! - Chat 请求 -> Responses 请求，Responses 响应 -> Chat 响应（Codex 使用 chat，上游仅支持 responses）
+//!
+//! 自定义工具（`type: custom`，如 apply_patch）在 Chat 侧表示为仅有一个 `input` 字符串参数的函数。
+//! 推理内容、内置工具（web_search 等）不做转换。
+
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+use super::{sse_data, sse_event, ResponseTranslator, SseLineBuffer};
+
+// ==================== Responses -> Chat（请求） ====================
+
+/// 将 Responses 请求体转换为 Chat Completions 请求体
+///
+/// 返回转换后的请求体和自定义工具名称集合（响应转换时需要还原为 custom_tool_call）
+pub fn responses_to_chat_request(body: &Value) -> Result<(Value, HashSet<String>)> {
+    let source = body.as_object().context("请求体不是 JSON 对象")?;
+    let mut request = Map::new();
+    let mut custom_tools = HashSet::new();
+
+    if let Some(model) = source.get("model") {
+        request.insert("model".into(), model.clone());
+    }
+
+    let mut messages: Vec<Value> = Vec::new();
+    if let Some(instructions) = body["instructions"].as_str().filter(|s| !s.is_empty()) {
+        messages.push(json!({ "role": "system", "content": instructions }));
+    }
+    match source.get("input") {
+        Some(Value::String(text)) => messages.push(json!({ "role": "user", "content": text })),
+        Some(Value::Array(items)) => {
+            for item in items {
+                convert_input_item(item, &mut messages);
+            }
+        }
+        _ => {}
+    }
+    request.insert("messages".into(), Value::Array(messages));
+
+    if let Some(tools) = source.get("tools").and_then(Value::as_array) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter_map(|tool| match tool["type"].as_str() {
+                Some("function") => Some(json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool["name"],
+                        "description": tool.get("description").cloned().unwrap_or(Value::Null),
+                        "parameters": tool.get("parameters").cloned().unwrap_or_else(|| json!({ "type": "object" })),
+                    }
+                })),
+                Some("custom") => {
+                    custom_tools.insert(tool["name"].as_str().unwrap_or_default().to_string());
+                    Some(json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool["name"],
+                            "description": tool.get("description").cloned().unwrap_or(Value::Null),
+                            "parameters": {
+                                "type": "object",
+                                "properties": { "input": { "type": "string" } },
+                                "required": ["input"]
+                            },
+                        }
+                    }))
+                }
+                _ => None,
+            })
+            .collect();
+        if !tools.is_empty() {
+            request.insert("tools".into(), Value::Array(tools));
+        }
+    }
+    if let Some(choice) = source.get("tool_choice") {
+        let converted = match choice {
+            Value::String(_) => Some(choice.clone()),
+            Value::Object(_) if choice["name"].is_string() => Some(json!({
+                "type": "function",
+                "function": { "name": choice["name"] }
+            })),
+            _ => None,
+        };
+        if let Some(converted) = converted {
+            request.insert("tool_choice".into(), converted);
+        }
+    }
+    if let Some(parallel) = source.get("parallel_tool_calls") {
+        request.insert("parallel_tool_calls".into(), parallel.clone());
+    }
+    if let Some(max_tokens) = source.get("max_output_tokens") {
+        request.insert("max_tokens".into(), max_tokens.clone());
+    }
+    for key in ["temperature", "top_p"] {
+        if let Some(value) = source.get(key) {
+            request.insert(key.into(), value.clone());
+        }
+    }
+    if let Some(effort) = body["reasoning"]["effort"].as_str() {
+        request.insert("reasoning_effort".into(), json!(effort));
+    }
+    if source.get("stream").and_then(Value::as_bool) == Some(true) {
+        request.insert("stream".into(), json!(true));
+        request.insert("stream_options".into(), json!({ "include_usage": true }));
+    }
+
+    Ok((Value::Object(request), custom_tools))
+}
+
+/// 转换单个 Responses 输入项
+fn convert_input_item(item: &Value, messages: &mut Vec<Value>) {
+    match item["type"].as_str().unwrap_or("message") {
+        "message" => {
+            let role = match item["role"].as_str().unwrap_or("user") {
+                "developer" => "system",
+                role => role,
+            };
+            let content = match &item["content"] {
+                Value::String(text) => Value::String(text.clone()),
+                Value::Array(parts) => {
+                    let has_image = parts.iter().any(|p| p["type"] == "input_image");
+                    if has_image {
+                        Value::Array(
+                            parts
+                                .iter()
+                                .filter_map(|part| match part["type"].as_str() {
+                                    Some("input_text") | Some("output_text") => {
+                                        Some(json!({ "type": "text", "text": part["text"] }))
+                                    }
+                                    Some("input_image") => Some(json!({
+                                        "type": "image_url",
+                                        "image_url": { "url": part["image_url"] }
+                                    })),
+                                    _ => None,
+                                })
+                                .collect(),
+                        )
+                    } else {
+                        Value::String(
+                            parts
+                                .iter()
+                                .filter_map(|p| p["text"].as_str())
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        )
+                    }
+                }
+                _ => Value::String(String::new()),
+            };
+            messages.push(json!({ "role": role, "content": content }));
+        }
+        "function_call" => push_tool_call(
+            messages,
+            json!({
+                "id": item["call_id"],
+                "type": "function",
+                "function": { "name": item["name"], "arguments": item["arguments"] }
+            }),
+        ),
+        "custom_tool_call" => push_tool_call(
+            messages,
+            json!({
+                "id": item["call_id"],
+                "type": "function",
+                "function": {
+                    "name": item["name"],
+                    "arguments": json!({ "input": item["input"] }).to_string()
+                }
+            }),
+        ),
+        "function_call_output" | "custom_tool_call_output" => messages.push(json!({
+            "role": "tool",
+            "tool_call_id": item["call_id"],
+            "content": output_text(&item["output"]),
+        })),
+        // reasoning、内置工具调用等不转换
+        _ => {}
+    }
+}
+
+/// 追加工具调用：连续的调用合并到同一条 assistant 消息
+fn push_tool_call(messages: &mut Vec<Value>, call: Value) {
+    if let Some(last) = messages.last_mut() {
+        if last["role"] == "assistant" {
+            match last["tool_calls"].as_array_mut() {
+                Some(calls) => calls.push(call),
+                None => last["tool_calls"] = json!([call]),
+            }
+            return;
+        }
+    }
+    messages.push(json!({ "role": "assistant", "content": null, "tool_calls": [call] }));
+}
+
+/// 提取工具输出文本（字符串、`{content}` 对象或内容数组）
+fn output_text(output: &Value) -> String {
+    match output {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Object(_) => output["content"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| output.to_string()),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+// ==================== Chat -> Responses（响应） ====================
+
+/// Chat 用量 -> Responses 用量
+fn chat_usage_to_responses(usage: &Value) -> Value {
+    let input = usage["prompt_tokens"].as_i64().unwrap_or(0);
+    let output = usage["completion_tokens"].as_i64().unwrap_or(0);
+    json!({
+        "input_tokens": input,
+        "input_tokens_details": {
+            "cached_tokens": usage["prompt_tokens_details"]["cached_tokens"].as_i64().unwrap_or(0)
+        },
+        "output_tokens": output,
+        "output_tokens_details": {
+            "reasoning_tokens": usage["completion_tokens_details"]["reasoning_tokens"].as_i64().unwrap_or(0)
+        },
+        "total_tokens": usage["total_tokens"].as_i64().unwrap_or(input + output),
+    })
+}
+
+/// 构建 Responses 工具调用输出项
+fn tool_call_item(
+    id: &str,
+    call_id: &str,
+    name: &str,
+    arguments: &str,
+    custom: bool,
+    status: &str,
+) -> Value {
+    if custom {
+        let input = serde_json::from_str::<Value>(arguments)
+            .ok()
+            .and_then(|v| v["input"].as_str().map(str::to_string))
+            .unwrap_or_else(|| arguments.to_string());
+        json!({
+            "type": "custom_tool_call",
+            "id": id,
+            "call_id": call_id,
+            "name": name,
+            "input": input,
+            "status": status,
+        })
+    } else {
+        json!({
+            "type": "function_call",
+            "id": id,
+            "call_id": call_id,
+            "name": name,
+            "arguments": arguments,
+            "status": status,
+        })
+    }
+}
+
+fn message_item(id: &str, text: &str, status: &str) -> Value {
+    json!({
+        "type": "message",
+        "id": id,
+        "status": status,
+        "role": "assistant",
+        "content": [{ "type": "output_text", "text": text, "annotations": [] }],
+    })
+}
+
+/// 将 Chat 非流式响应转换为 Responses 响应
+pub fn chat_to_responses_response(
+    response: &Value,
+    fallback_model: &str,
+    custom_tools: &HashSet<String>,
+) -> Value {
+    let id = response["id"].as_str().unwrap_or("resp_proxy");
+    let message = &response["choices"][0]["message"];
+
+    let mut output = Vec::new();
+    if let Some(text) = message["content"].as_str().filter(|s| !s.is_empty()) {
+        output.push(message_item(&format!("msg_{id}"), text, "completed"));
+    }
+    if let Some(calls) = message["tool_calls"].as_array() {
+        for (i, call) in calls.iter().enumerate() {
+            let name = call["function"]["name"].as_str().unwrap_or_default();
+            output.push(tool_call_item(
+                &format!("fc_{id}_{i}"),
+                call["id"].as_str().unwrap_or_default(),
+                name,
+                call["function"]["arguments"].as_str().unwrap_or_default(),
+                custom_tools.contains(name),
+                "completed",
+            ));
+        }
+    }
+
+    json!({
+        "id": id,
+        "object": "response",
+        "created_at": response["created"].as_i64().unwrap_or_else(|| chrono::Utc::now().timestamp()),
+        "status": "completed",
+        "model": response["model"].as_str().unwrap_or(fallback_model),
+        "output": output,
+        "usage": chat_usage_to_responses(&response["usage"]),
+    })
+}
+
+#[derive(Debug)]
+struct StreamingToolCall {
+    output_index: usize,
+    item_id: String,
+    call_id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Chat Completions 流 -> Responses 事件流
+#[derive(Debug)]
+pub struct ChatToResponsesTranslator {
+    lines: SseLineBuffer,
+    model: String,
+    custom_tools: HashSet<String>,
+    response_id: String,
+    created_at: i64,
+    started: bool,
+    finished: bool,
+    next_output_index: usize,
+    /// 当前文本输出项 (output_index, item_id, 已累计文本)
+    text_item: Option<(usize, String, String)>,
+    /// Chat tool_calls 索引 -> 工具调用
+    tool_calls: HashMap<u64, StreamingToolCall>,
+    /// 已完成的输出项 (output_index, item)
+    done_items: Vec<(usize, Value)>,
+    finish_reason: Option<String>,
+    usage: Option<Value>,
+}
+
+impl ChatToResponsesTranslator {
+    pub fn new(model: &str, custom_tools: HashSet<String>) -> Self {
+        Self {
+            lines: SseLineBuffer::default(),
+            model: model.to_string(),
+            custom_tools,
+            response_id: format!("resp_proxy_{}", chrono::Utc::now().timestamp_millis()),
+            created_at: chrono::Utc::now().timestamp(),
+            started: false,
+            finished: false,
+            next_output_index: 0,
+            text_item: None,
+            tool_calls: HashMap::new(),
+            done_items: Vec::new(),
+            finish_reason: None,
+            usage: None,
+        }
+    }
+
+    fn response_object(&self, status: &str, output: Vec<Value>) -> Value {
+        let mut response = json!({
+            "id": self.response_id,
+            "object": "response",
+            "created_at": self.created_at,
+            "status": status,
+            "model": self.model,
+            "output": output,
+        });
+        if let Some(usage) = &self.usage {
+            response["usage"] = usage.clone();
+        }
+        response
+    }
+
+    fn handle_payload(&mut self, data: &str, out: &mut Vec<u8>) {
+        if data == "[DONE]" {
+            self.finish_response(out);
+            return;
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            return;
+        };
+
+        if chunk["error"].is_object() {
+            out.extend(sse_event(
+                "response.failed",
+                &json!({
+                    "type": "response.failed",
+                    "response": {
+                        "id": self.response_id,
+                        "object": "response",
+                        "status": "failed",
+                        "error": chunk["error"],
+                    }
+                }),
+            ));
+            self.finished = true;
+            return;
+        }
+
+        if !self.started {
+            self.started = true;
+            if let Some(model) = chunk["model"].as_str().filter(|s| !s.is_empty()) {
+                self.model = model.to_string();
+            }
+            if let Some(id) = chunk["id"].as_str().filter(|s| !s.is_empty()) {
+                self.response_id = format!("resp_{id}");
+            }
+            let response = self.response_object("in_progress", Vec::new());
+            out.extend(sse_event(
+                "response.created",
+                &json!({ "type": "response.created", "response": response }),
+            ));
+        }
+
+        if chunk["usage"].is_object() {
+            self.usage = Some(chat_usage_to_responses(&chunk["usage"]));
+        }
+
+        let Some(choice) = chunk["choices"].get(0) else {
+            return;
+        };
+        let delta = &choice["delta"];
+
+        if let Some(text) = delta["content"].as_str().filter(|s| !s.is_empty()) {
+            self.push_text(text, out);
+        }
+
+        if let Some(calls) = delta["tool_calls"].as_array() {
+            for call in calls {
+                self.push_tool_call(call, out);
+            }
+        }
+
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(reason.to_string());
+        }
+    }
+
+    fn push_text(&mut self, text: &str, out: &mut Vec<u8>) {
+        if self.text_item.is_none() {
+            let output_index = self.next_output_index;
+            self.next_output_index += 1;
+            let item_id = format!("msg_{}_{}", self.response_id, output_index);
+            out.extend(sse_event(
+                "response.output_item.added",
+                &json!({
+                    "type": "response.output_item.added",
+                    "output_index": output_index,
+                    "item": {
+                        "type": "message",
+                        "id": item_id,
+                        "status": "in_progress",
+                        "role": "assistant",
+                        "content": []
+                    }
+                }),
+            ));
+            out.extend(sse_event(
+                "response.content_part.added",
+                &json!({
+                    "type": "response.content_part.added",
+                    "item_id": item_id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "part": { "type": "output_text", "text": "", "annotations": [] }
+                }),
+            ));
+            self.text_item = Some((output_index, item_id, String::new()));
+        }
+
+        if let Some((output_index, item_id, buffer)) = self.text_item.as_mut() {
+            buffer.push_str(text);
+            out.extend(sse_event(
+                "response.output_text.delta",
+                &json!({
+                    "type": "response.output_text.delta",
+                    "item_id": item_id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "delta": text
+                }),
+            ));
+        }
+    }
+
+    fn close_text(&mut self, out: &mut Vec<u8>) {
+        let Some((output_index, item_id, text)) = self.text_item.take() else {
+            return;
+        };
+        out.extend(sse_event(
+            "response.output_text.done",
+            &json!({
+                "type": "response.output_text.done",
+                "item_id": item_id,
+                "output_index": output_index,
+                "content_index": 0,
+                "text": text
+            }),
+        ));
+        out.extend(sse_event(
+            "response.content_part.done",
+            &json!({
+                "type": "response.content_part.done",
+                "item_id": item_id,
+                "output_index": output_index,
+                "content_index": 0,
+                "part": { "type": "output_text", "text": text, "annotations": [] }
+            }),
+        ));
+        let item = message_item(&item_id, &text, "completed");
+        out.extend(sse_event(
+            "response.output_item.done",
+            &json!({ "type": "response.output_item.done", "output_index": output_index, "item": item }),
+        ));
+        self.done_items.push((output_index, item));
+    }
+
+    fn push_tool_call(&mut self, call: &Value, out: &mut Vec<u8>) {
+        let index = call["index"].as_u64().unwrap_or(0);
+
+        if !self.tool_calls.contains_key(&index) {
+            self.close_text(out);
+            let output_index = self.next_output_index;
+            self.next_output_index += 1;
+            let tool_call = StreamingToolCall {
+                output_index,
+                item_id: format!("fc_{}_{}", self.response_id, output_index),
+                call_id: call["id"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("call_proxy_{output_index}")),
+                name: call["function"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                arguments: String::new(),
+            };
+            let custom = self.custom_tools.contains(&tool_call.name);
+            out.extend(sse_event(
+                "response.output_item.added",
+                &json!({
+                    "type": "response.output_item.added",
+                    "output_index": output_index,
+                    "item": tool_call_item(
+                        &tool_call.item_id,
+                        &tool_call.call_id,
+                        &tool_call.name,
+                        "",
+                        custom,
+                        "in_progress",
+                    )
+                }),
+            ));
+            self.tool_calls.insert(index, tool_call);
+        }
+
+        let Some(tool_call) = self.tool_calls.get_mut(&index) else {
+            return;
+        };
+        if let Some(arguments) = call["function"]["arguments"]
+            .as_str()
+            .filter(|s| !s.is_empty())
+        {
+            tool_call.arguments.push_str(arguments);
+            // 自定义工具的输入在完成时一次性给出
+            if !self.custom_tools.contains(&tool_call.name) {
+                out.extend(sse_event(
+                    "response.function_call_arguments.delta",
+                    &json!({
+                        "type": "response.function_call_arguments.delta",
+                        "item_id": tool_call.item_id,
+                        "output_index": tool_call.output_index,
+                        "delta": arguments
+                    }),
+                ));
+            }
+        }
+    }
+
+    fn finish_response(&mut self, out: &mut Vec<u8>) {
+        if self.finished || !self.started {
+            return;
+        }
+        self.finished = true;
+        self.close_text(out);
+
+        let mut tool_calls: Vec<StreamingToolCall> =
+            self.tool_calls.drain().map(|(_, call)| call).collect();
+        tool_calls.sort_by_key(|call| call.output_index);
+        for call in tool_calls {
+            let custom = self.custom_tools.contains(&call.name);
+            if !custom {
+                out.extend(sse_event(
+                    "response.function_call_arguments.done",
+                    &json!({
+                        "type": "response.function_call_arguments.done",
+                        "item_id": call.item_id,
+                        "output_index": call.output_index,
+                        "arguments": call.arguments
+                    }),
+                ));
+            }
+            let item = tool_call_item(
+                &call.item_id,
+                &call.call_id,
+                &call.name,
+                &call.arguments,
+                custom,
+                "completed",
+            );
+            out.extend(sse_event(
+                "response.output_item.done",
+                &json!({ "type": "response.output_item.done", "output_index": call.output_index, "item": item }),
+            ));
+            self.done_items.push((call.output_index, item));
+        }
+
+        self.done_items.sort_by_key(|(index, _)| *index);
+        let output: Vec<Value> = self
+            .done_items
+            .iter()
+            .map(|(_, item)| item.clone())
+            .collect();
+        let mut response = self.response_object("completed", output);
+        if self.finish_reason.as_deref() == Some("length") {
+            response["status"] = json!("incomplete");
+            response["incomplete_details"] = json!({ "reason": "max_output_tokens" });
+        }
+        out.extend(sse_event(
+            "response.completed",
+            &json!({ "type": "response.completed", "response": response }),
+        ));
+    }
+}
+
+impl ResponseTranslator for ChatToResponsesTranslator {
+    fn translate_chunk(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for payload in self.lines.push(chunk) {
+            self.handle_payload(&payload, &mut out);
+        }
+        out
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if let Some(payload) = self.lines.flush() {
+            self.handle_payload(&payload, &mut out);
+        }
+        self.finish_response(&mut out);
+        out
+    }
+
+    fn translate_body(&mut self, status: u16, body: &[u8]) -> Result<Vec<u8>> {
+        let Ok(response) = serde_json::from_slice::<Value>(body) else {
+            return Ok(body.to_vec());
+        };
+        // 两种 API 的错误格式相同，直接透传
+        if status >= 400 || response["error"].is_object() {
+            return Ok(body.to_vec());
+        }
+        let converted = chat_to_responses_response(&response, &self.model, &self.custom_tools);
+        Ok(serde_json::to_vec(&converted)?)
+    }
+}
+
+// ==================== Chat -> Responses（请求） ====================
+
+/// 将 Chat Completions 请求体转换为 Responses 请求体
+pub fn chat_to_responses_request(body: &Value) -> Result<Value> {
+    let source = body.as_object().context("请求体不是 JSON 对象")?;
+    let mut request = Map::new();
+
+    if let Some(model) = source.get("model") {
+        request.insert("model".into(), model.clone());
+    }
+
+    let mut instructions = Vec::new();
+    let mut input = Vec::new();
+    for message in source
+        .get("messages")
+        .and_then(Value::as_array)
+        .context("缺少 messages 字段")?
+    {
+        let role = message["role"].as_str().unwrap_or("user");
+        match role {
+            "system" | "developer" => instructions.push(chat_content_text(&message["content"])),
+            "tool" => input.push(json!({
+                "type": "function_call_output",
+                "call_id": message["tool_call_id"],
+                "output": chat_content_text(&message["content"]),
+            })),
+            "assistant" => {
+                let text = chat_content_text(&message["content"]);
+                if !text.is_empty() {
+                    input.push(json!({
+                        "type": "message",
+                        "role": "assistant",
+                        "content": [{ "type": "output_text", "text": text }]
+                    }));
+                }
+                for call in message["tool_calls"].as_array().into_iter().flatten() {
+                    input.push(json!({
+                        "type": "function_call",
+                        "call_id": call["id"],
+                        "name": call["function"]["name"],
+                        "arguments": call["function"]["arguments"],
+                    }));
+                }
+            }
+            _ => {
+                let content = match &message["content"] {
+                    Value::Array(parts) => parts
+                        .iter()
+                        .filter_map(|part| match part["type"].as_str() {
+                            Some("text") => {
+                                Some(json!({ "type": "input_text", "text": part["text"] }))
+                            }
+                            Some("image_url") => Some(json!({
+                                "type": "input_image",
+                                "image_url": part["image_url"]["url"]
+                            })),
+                            _ => None,
+                        })
+                        .collect(),
+                    other => vec![json!({
+                        "type": "input_text",
+                        "text": other.as_str().unwrap_or_default()
+                    })],
+                };
+                input.push(json!({ "type": "message", "role": role, "content": content }));
+            }
+        }
+    }
+    if !instructions.is_empty() {
+        request.insert("instructions".into(), json!(instructions.join("\n\n")));
+    }
+    request.insert("input".into(), Value::Array(input));
+
+    if let Some(tools) = source.get("tools").and_then(Value::as_array) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .filter(|tool| tool["type"] == "function")
+            .map(|tool| {
+                let function = &tool["function"];
+                json!({
+                    "type": "function",
+                    "name": function["name"],
+                    "description": function.get("description").cloned().unwrap_or(Value::Null),
+                    "parameters": function.get("parameters").cloned().unwrap_or_else(|| json!({ "type": "object" })),
+                })
+            })
+            .collect();
+        if !tools.is_empty() {
+            request.insert("tools".into(), Value::Array(tools));
+        }
+    }
+    if let Some(choice) = source.get("tool_choice") {
+        let converted = match choice {
+            Value::String(_) => choice.clone(),
+            _ => json!({ "type": "function", "name": choice["function"]["name"] }),
+        };
+        request.insert("tool_choice".into(), converted);
+    }
+    if let Some(parallel) = source.get("parallel_tool_calls") {
+        request.insert("parallel_tool_calls".into(), parallel.clone());
+    }
+    if let Some(max_tokens) = source
+        .get("max_completion_tokens")
+        .or_else(|| source.get("max_tokens"))
+    {
+        request.insert("max_output_tokens".into(), max_tokens.clone());
+    }
+    for key in ["temperature", "top_p"] {
+        if let Some(value) = source.get(key) {
+            request.insert(key.into(), value.clone());
+        }
+    }
+    if let Some(effort) = body["reasoning_effort"].as_str() {
+        request.insert("reasoning".into(), json!({ "effort": effort }));
+    }
+    if source.get("stream").and_then(Value::as_bool) == Some(true) {
+        request.insert("stream".into(), json!(true));
+    }
+    // 代理不维护服务端状态
+    request.insert("store".into(), json!(false));
+
+    Ok(Value::Object(request))
+}
+
+fn chat_content_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+// ==================== Responses -> Chat（响应） ====================
+
+/// Responses 用量 -> Chat 用量
+fn responses_usage_to_chat(usage: &Value) -> Value {
+    let input = usage["input_tokens"].as_i64().unwrap_or(0);
+    let output = usage["output_tokens"].as_i64().unwrap_or(0);
+    json!({
+        "prompt_tokens": input,
+        "completion_tokens": output,
+        "total_tokens": usage["total_tokens"].as_i64().unwrap_or(input + output),
+        "prompt_tokens_details": {
+            "cached_tokens": usage["input_tokens_details"]["cached_tokens"].as_i64().unwrap_or(0)
+        },
+        "completion_tokens_details": {
+            "reasoning_tokens": usage["output_tokens_details"]["reasoning_tokens"].as_i64().unwrap_or(0)
+        },
+    })
+}
+
+/// 将 Responses 状态映射为 Chat finish_reason
+fn responses_finish_reason(response: &Value, has_tool_calls: bool) -> &'static str {
+    if response["status"] == "incomplete" {
+        return "length";
+    }
+    if has_tool_calls {
+        "tool_calls"
+    } else {
+        "stop"
+    }
+}
+
+/// 将 Responses 非流式响应转换为 Chat 响应
+pub fn responses_to_chat_response(response: &Value, fallback_model: &str) -> Value {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for item in response["output"].as_array().into_iter().flatten() {
+        match item["type"].as_str() {
+            Some("message") => {
+                for part in item["content"].as_array().into_iter().flatten() {
+                    if let Some(t) = part["text"].as_str() {
+                        text.push_str(t);
+                    }
+                }
+            }
+            Some("function_call") => tool_calls.push(json!({
+                "id": item["call_id"],
+                "type": "function",
+                "function": { "name": item["name"], "arguments": item["arguments"] }
+            })),
+            _ => {}
+        }
+    }
+
+    let finish_reason = responses_finish_reason(response, !tool_calls.is_empty());
+    let mut message = json!({ "role": "assistant", "content": text });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    json!({
+        "id": response["id"].as_str().unwrap_or("chatcmpl-proxy"),
+        "object": "chat.completion",
+        "created": response["created_at"].as_i64().unwrap_or_else(|| chrono::Utc::now().timestamp()),
+        "model": response["model"].as_str().unwrap_or(fallback_model),
+        "choices": [{ "index": 0, "message": message, "finish_reason": finish_reason }],
+        "usage": responses_usage_to_chat(&response["usage"]),
+    })
+}
+
+/// Responses 事件流 -> Chat Completions 流
+#[derive(Debug)]
+pub struct ResponsesToChatTranslator {
+    lines: SseLineBuffer,
+    model: String,
+    id: String,
+    created: i64,
+    started: bool,
+    finished: bool,
+    /// 输出项 ID -> Chat tool_calls 索引
+    tool_indices: HashMap<String, usize>,
+}
+
+impl ResponsesToChatTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            lines: SseLineBuffer::default(),
+            model: model.to_string(),
+            id: format!("chatcmpl-proxy-{}", chrono::Utc::now().timestamp_millis()),
+            created: chrono::Utc::now().timestamp(),
+            started: false,
+            finished: false,
+            tool_indices: HashMap::new(),
+        }
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> Vec<u8> {
+        sse_data(&json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }]
+        }))
+    }
+
+    fn ensure_started(&mut self, out: &mut Vec<u8>) {
+        if !self.started {
+            self.started = true;
+            out.extend(self.chunk(json!({ "role": "assistant", "content": "" }), None));
+        }
+    }
+
+    fn tool_index(&mut self, item_id: &str) -> usize {
+        let next = self.tool_indices.len();
+        *self.tool_indices.entry(item_id.to_string()).or_insert(next)
+    }
+
+    fn handle_payload(&mut self, data: &str, out: &mut Vec<u8>) {
+        if self.finished {
+            return;
+        }
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            return;
+        };
+
+        match event["type"].as_str().unwrap_or_default() {
+            "response.created" | "response.in_progress" => {
+                let response = &event["response"];
+                if let Some(model) = response["model"].as_str().filter(|s| !s.is_empty()) {
+                    self.model = model.to_string();
+                }
+                if let Some(id) = response["id"].as_str() {
+                    self.id = id.to_string();
+                }
+                self.ensure_started(out);
+            }
+            "response.output_text.delta" => {
+                self.ensure_started(out);
+                let delta = event["delta"].as_str().unwrap_or_default();
+                out.extend(self.chunk(json!({ "content": delta }), None));
+            }
+            "response.output_item.added" if event["item"]["type"] == "function_call" => {
+                self.ensure_started(out);
+                let item = &event["item"];
+                let index = self.tool_index(item["id"].as_str().unwrap_or_default());
+                out.extend(self.chunk(
+                    json!({ "tool_calls": [{
+                        "index": index,
+                        "id": item["call_id"],
+                        "type": "function",
+                        "function": { "name": item["name"], "arguments": "" }
+                    }]}),
+                    None,
+                ));
+            }
+            "response.function_call_arguments.delta" => {
+                let index = self.tool_index(event["item_id"].as_str().unwrap_or_default());
+                out.extend(self.chunk(
+                    json!({ "tool_calls": [{
+                        "index": index,
+                        "function": { "arguments": event["delta"] }
+                    }]}),
+                    None,
+                ));
+            }
+            "response.completed" | "response.incomplete" => {
+                self.ensure_started(out);
+                let response = &event["response"];
+                let finish_reason =
+                    responses_finish_reason(response, !self.tool_indices.is_empty());
+                out.extend(self.chunk(json!({}), Some(finish_reason)));
+                if response["usage"].is_object() {
+                    out.extend(sse_data(&json!({
+                        "id": self.id,
+                        "object": "chat.completion.chunk",
+                        "created": self.created,
+                        "model": self.model,
+                        "choices": [],
+                        "usage": responses_usage_to_chat(&response["usage"]),
+                    })));
+                }
+                out.extend(b"data: [DONE]\n\n");
+                self.finished = true;
+            }
+            "response.failed" | "error" => {
+                let error = if event["response"]["error"].is_object() {
+                    event["response"]["error"].clone()
+                } else {
+                    json!({ "message": event["message"], "code": event["code"] })
+                };
+                out.extend(sse_data(&json!({ "error": error })));
+                out.extend(b"data: [DONE]\n\n");
+                self.finished = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ResponseTranslator for ResponsesToChatTranslator {
+    fn translate_chunk(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for payload in self.lines.push(chunk) {
+            self.handle_payload(&payload, &mut out);
+        }
+        out
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if let Some(payload) = self.lines.flush() {
+            self.handle_payload(&payload, &mut out);
+        }
+        // 上游未发送完成事件时补发结束标记
+        if self.started && !self.finished {
+            self.finished = true;
+            out.extend(self.chunk(json!({}), Some("stop")));
+            out.extend(b"data: [DONE]\n\n");
+        }
+        out
+    }
+
+    fn translate_body(&mut self, status: u16, body: &[u8]) -> Result<Vec<u8>> {
+        let Ok(response) = serde_json::from_slice::<Value>(body) else {
+            return Ok(body.to_vec());
+        };
+        if status >= 400 || response["error"].is_object() {
+            return Ok(body.to_vec());
+        }
+        let converted = responses_to_chat_response(&response, &self.model);
+        Ok(serde_json::to_vec(&converted)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 解析 SSE 输出中的 JSON 数据（忽略 [DONE]）
+    fn parse_data(output: &[u8]) -> Vec<Value> {
+        String::from_utf8(output.to_vec())
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_responses_request_to_chat() {
+        let body = json!({
+            "model": "gpt-5-codex",
+            "instructions": "You are Codex.",
+            "stream": true,
+            "reasoning": { "effort": "high" },
+            "tools": [
+                { "type": "function", "name": "shell", "parameters": { "type": "object" } },
+                { "type": "custom", "name": "apply_patch", "format": { "type": "grammar" } },
+                { "type": "web_search" }
+            ],
+            "input": [
+                { "type": "message", "role": "user", "content": [{ "type": "input_text", "text": "list files" }] },
+                { "type": "reasoning", "summary": [] },
+                { "type": "function_call", "call_id": "c1", "name": "shell", "arguments": "{\"cmd\":\"ls\"}" },
+                { "type": "custom_tool_call", "call_id": "c2", "name": "apply_patch", "input": "*** Begin Patch" },
+                { "type": "function_call_output", "call_id": "c1", "output": "a.rs" },
+                { "type": "custom_tool_call_output", "call_id": "c2", "output": "ok" }
+            ]
+        });
+
+        let (request, custom_tools) = responses_to_chat_request(&body).unwrap();
+        assert!(custom_tools.contains("apply_patch"));
+        assert_eq!(request["reasoning_effort"], "high");
+        assert_eq!(request["tools"].as_array().unwrap().len(), 2);
+
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[1]["content"], "list files");
+        // 连续的工具调用合并到同一条 assistant 消息
+        let calls = messages[2]["tool_calls"].as_array().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            calls[1]["function"]["arguments"],
+            r#"{"input":"*** Begin Patch"}"#
+        );
+        assert_eq!(messages[3]["tool_call_id"], "c1");
+        assert_eq!(messages[4]["content"], "ok");
+    }
+
+    #[test]
+    fn test_chat_stream_to_responses_events() {
+        let mut translator =
+            ChatToResponsesTranslator::new("gpt-5-codex", HashSet::from(["apply_patch".into()]));
+        let upstream = concat!(
+            "data: {\"id\":\"x\",\"model\":\"deepseek-chat\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+            "data: {\"id\":\"x\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"function\":{\"name\":\"shell\",\"arguments\":\"{\\\"cmd\\\"\"}}]}}]}\n\n",
+            "data: {\"id\":\"x\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\":1}\"}}]}}]}\n\n",
+            "data: {\"id\":\"x\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":1,\"id\":\"call_2\",\"function\":{\"name\":\"apply_patch\",\"arguments\":\"{\\\"input\\\":\\\"P\\\"}\"}}]}}]}\n\n",
+            "data: {\"id\":\"x\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: {\"id\":\"x\",\"choices\":[],\"usage\":{\"prompt_tokens\":10,\"completion_tokens\":4}}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let mut output = translator.translate_chunk(upstream.as_bytes());
+        output.extend(translator.finish());
+        let events = parse_data(&output);
+        let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+
+        assert_eq!(types.first(), Some(&"response.created"));
+        assert_eq!(types.last(), Some(&"response.completed"));
+        assert_eq!(
+            types
+                .iter()
+                .filter(|t| **t == "response.output_item.done")
+                .count(),
+            3
+        );
+
+        let completed = &events.last().unwrap()["response"];
+        let output_items = completed["output"].as_array().unwrap();
+        assert_eq!(output_items[0]["type"], "message");
+        assert_eq!(output_items[0]["content"][0]["text"], "Hi");
+        assert_eq!(output_items[1]["type"], "function_call");
+        assert_eq!(output_items[1]["arguments"], "{\"cmd\":1}");
+        assert_eq!(output_items[2]["type"], "custom_tool_call");
+        assert_eq!(output_items[2]["input"], "P");
+        assert_eq!(completed["usage"]["input_tokens"], 10);
+        assert_eq!(completed["model"], "deepseek-chat");
+    }
+
+    #[test]
+    fn test_chat_request_to_responses() {
+        let body = json!({
+            "model": "gpt-5",
+            "stream": true,
+            "max_tokens": 100,
+            "messages": [
+                { "role": "system", "content": "sys" },
+                { "role": "user", "content": "hi" },
+                { "role": "assistant", "content": null, "tool_calls": [
+                    { "id": "c1", "type": "function", "function": { "name": "shell", "arguments": "{}" } }
+                ]},
+                { "role": "tool", "tool_call_id": "c1", "content": "done" }
+            ],
+            "tools": [{ "type": "function", "function": { "name": "shell", "parameters": { "type": "object" } } }]
+        });
+
+        let request = chat_to_responses_request(&body).unwrap();
+        assert_eq!(request["instructions"], "sys");
+        assert_eq!(request["max_output_tokens"], 100);
+        assert_eq!(request["store"], false);
+        assert_eq!(request["tools"][0]["name"], "shell");
+
+        let input = request["input"].as_array().unwrap();
+        assert_eq!(input.len(), 3);
+        assert_eq!(input[0]["content"][0]["type"], "input_text");
+        assert_eq!(input[1]["type"], "function_call");
+        assert_eq!(input[2]["type"], "function_call_output");
+    }
+
+    #[test]
+    fn test_responses_stream_to_chat_chunks() {
+        let mut translator = ResponsesToChatTranslator::new("gpt-5");
+        let upstream = concat!(
+            "event: response.created\n",
+            "data: {\"type\":\"response.created\",\"response\":{\"id\":\"resp_1\",\"model\":\"gpt-5\"}}\n\n",
+            "event: response.output_text.delta\n",
+            "data: {\"type\":\"response.output_text.delta\",\"item_id\":\"m1\",\"delta\":\"ok\"}\n\n",
+            "data: {\"type\":\"response.output_item.added\",\"item\":{\"type\":\"function_call\",\"id\":\"fc1\",\"call_id\":\"c1\",\"name\":\"shell\"}}\n\n",
+            "data: {\"type\":\"response.function_call_arguments.delta\",\"item_id\":\"fc1\",\"delta\":\"{}\"}\n\n",
+            "data: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp_1\",\"status\":\"completed\",\"usage\":{\"input_tokens\":7,\"output_tokens\":2}}}\n\n",
+        );
+
+        let output = translator.translate_chunk(upstream.as_bytes());
+        assert!(String::from_utf8_lossy(&output).ends_with("data: [DONE]\n\n"));
+        assert!(translator.finish().is_empty());
+
+        let chunks = parse_data(&output);
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "ok");
+        assert_eq!(
+            chunks[2]["choices"][0]["delta"]["tool_calls"][0]["id"],
+            "c1"
+        );
+        assert_eq!(
+            chunks[3]["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"],
+            "{}"
+        );
+        assert_eq!(chunks[4]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(chunks[5]["usage"]["prompt_tokens"], 7);
+    }
+
+    #[test]
+    fn test_non_stream_round_trip() {
+        let chat = json!({
+            "id": "x",
+            "model": "m",
+            "choices": [{ "message": { "role": "assistant", "content": "hello" }, "finish_reason": "stop" }],
+            "usage": { "prompt_tokens": 3, "completion_tokens": 1 }
+        });
+        let responses = chat_to_responses_response(&chat, "m", &HashSet::new());
+        assert_eq!(responses["output"][0]["content"][0]["text"], "hello");
+
+        let back = responses_to_chat_response(&responses, "m");
+        assert_eq!(back["choices"][0]["message"]["content"], "hello");
+        assert_eq!(back["choices"][0]["finish_reason"], "stop");
+        assert_eq!(back["usage"]["prompt_tokens"], 3);
+    }
+}
@@ -0,0 +1,387 @@
+//! 上游池
+//!
+//! 为单个工具代理维护一组有序上游（每个上游引用一个 Profile），负责：
+//! - 按策略（故障转移 / 加权轮询）给出本次请求的候选上游顺序
+//! - 记录上游失败并在冷却期内降低其优先级
+//! - 请求时通过 ProfileManager 解析上游的 URL 和 API Key（Profile 轮换 Key 后立即生效），
+//!   模型路由规则指定的 Profile 也复用同一个 ProfileManager 解析
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::proxy_config::{
+    LoadBalanceStrategy, ModelRule, ToolProxyConfig, UpstreamConfig, UpstreamProtocol,
+};
+use crate::services::profile_manager::ProfileManager;
+
+use super::model_rules;
+
+/// 上游失败后的冷却时间
+const FAILURE_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// 解析后的上游（单次请求使用）
+#[derive(Debug, Clone)]
+pub struct ResolvedUpstream {
+    /// 上游在池中的索引（None 表示使用 real_* 单一配置）
+    pub index: Option<usize>,
+    /// 引用的 Profile 名称
+    pub profile_name: Option<String>,
+    pub base_url: String,
+    pub api_key: String,
+    /// Profile 声明的上游协议（None 表示沿用工具代理配置）
+    pub protocol: Option<UpstreamProtocol>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct UpstreamState {
+    /// 平滑加权轮询的当前权重
+    current_weight: i64,
+    /// 冷却截止时间（失败后设置）
+    cooldown_until: Option<Instant>,
+    /// 连续失败次数
+    consecutive_failures: u32,
+}
+
+/// 上游池
+pub struct UpstreamPool {
+    tool_id: String,
+    strategy: LoadBalanceStrategy,
+    upstreams: Vec<UpstreamConfig>,
+    states: Mutex<Vec<UpstreamState>>,
+    /// 未配置上游池时使用的单一上游 (base_url, api_key)
+    fallback: Option<(String, String)>,
+    /// 单一上游来源的 Profile（用于解析其声明的上游协议）
+    fallback_profile: Option<String>,
+    profile_manager: Option<ProfileManager>,
+}
+
+impl UpstreamPool {
+    /// 根据代理配置创建上游池
+    pub fn from_config(tool_id: &str, config: &ToolProxyConfig) -> Self {
+        let needs_profiles = config.upstreams.iter().any(|u| u.enabled)
+            || config.real_profile_name.is_some()
+            || config
+                .model_rules
+                .iter()
+                .any(|r| r.enabled && r.profile_name.as_deref().is_some_and(|p| !p.is_empty()));
+        let profile_manager = if needs_profiles {
+            match ProfileManager::new() {
+                Ok(mgr) => Some(mgr),
+                Err(e) => {
+                    tracing::error!(
+                        tool_id = %tool_id,
+                        error = ?e,
+                        "创建 ProfileManager 失败，上游池不可用"
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Self::with_profile_manager(tool_id, config, profile_manager)
+    }
+
+    /// 使用指定的 ProfileManager 创建上游池
+    pub(crate) fn with_profile_manager(
+        tool_id: &str,
+        config: &ToolProxyConfig,
+        profile_manager: Option<ProfileManager>,
+    ) -> Self {
+        let upstreams: Vec<UpstreamConfig> = config
+            .upstreams
+            .iter()
+            .filter(|u| u.enabled)
+            .cloned()
+            .collect();
+
+        let fallback = match (&config.real_base_url, &config.real_api_key) {
+            (Some(url), Some(key)) => Some((url.clone(), key.clone())),
+            _ => None,
+        };
+
+        Self {
+            tool_id: tool_id.to_string(),
+            strategy: config.load_balance,
+            states: Mutex::new(vec![UpstreamState::default(); upstreams.len()]),
+            upstreams,
+            fallback,
+            fallback_profile: config.real_profile_name.clone(),
+            profile_manager,
+        }
+    }
+
+    /// 获取本次请求的候选上游（按尝试顺序排列）
+    ///
+    /// 无法解析的 Profile（已删除等）会被跳过；上游池为空时回退到 real_* 配置
+    pub fn candidates(&self) -> Vec<ResolvedUpstream> {
+        if self.upstreams.is_empty() {
+            return self
+                .fallback
+                .iter()
+                .map(|(base_url, api_key)| ResolvedUpstream {
+                    index: None,
+                    profile_name: self.fallback_profile.clone(),
+                    base_url: base_url.clone(),
+                    api_key: api_key.clone(),
+                    protocol: self.fallback_protocol(),
+                })
+                .collect();
+        }
+
+        let Some(profile_manager) = &self.profile_manager else {
+            return Vec::new();
+        };
+
+        self.ordered_indices(Instant::now())
+            .into_iter()
+            .filter_map(|idx| {
+                let profile_name = &self.upstreams[idx].profile_name;
+                match profile_manager.get_profile_credentials(&self.tool_id, profile_name) {
+                    Ok((api_key, base_url)) => Some(ResolvedUpstream {
+                        index: Some(idx),
+                        profile_name: Some(profile_name.clone()),
+                        base_url,
+                        api_key,
+                        protocol: profile_manager
+                            .get_profile_protocol(&self.tool_id, profile_name)
+                            .unwrap_or(None),
+                    }),
+                    Err(e) => {
+                        tracing::warn!(
+                            tool_id = %self.tool_id,
+                            profile = %profile_name,
+                            error = ?e,
+                            "上游 Profile 解析失败，已跳过"
+                        );
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// 解析模型路由规则指定的上游（复用上游池的 ProfileManager）
+    pub fn route(&self, rule: &ModelRule) -> Option<ResolvedUpstream> {
+        model_rules::resolve_route(&self.tool_id, rule, self.profile_manager.as_ref())
+    }
+
+    /// 单一上游来源 Profile 声明的协议（如 Codex Profile 的 wire_api）
+    fn fallback_protocol(&self) -> Option<UpstreamProtocol> {
+        let profile_name = self.fallback_profile.as_deref()?;
+        let profile_manager = self.profile_manager.as_ref()?;
+        match profile_manager.get_profile_protocol(&self.tool_id, profile_name) {
+            Ok(protocol) => protocol,
+            Err(e) => {
+                tracing::debug!(
+                    tool_id = %self.tool_id,
+                    profile = %profile_name,
+                    error = ?e,
+                    "读取 Profile 协议失败，沿用代理配置"
+                );
+                None
+            }
+        }
+    }
+
+    /// 计算候选上游的索引顺序
+    ///
+    /// 健康上游在前，冷却中的上游排在最后（所有上游都失败时仍可尝试）
+    fn ordered_indices(&self, now: Instant) -> Vec<usize> {
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+
+        let is_healthy = |state: &UpstreamState| {
+            state
+                .cooldown_until
+                .map(|until| until <= now)
+                .unwrap_or(true)
+        };
+
+        let mut healthy: Vec<usize> = (0..self.upstreams.len())
+            .filter(|&i| is_healthy(&states[i]))
+            .collect();
+        let cooling: Vec<usize> = (0..self.upstreams.len())
+            .filter(|&i| !is_healthy(&states[i]))
+            .collect();
+
+        if self.strategy == LoadBalanceStrategy::WeightedRoundRobin {
+            // 平滑加权轮询（nginx 算法），仅在健康且权重大于 0 的上游间选择
+            let weighted: Vec<usize> = healthy
+                .iter()
+                .copied()
+                .filter(|&i| self.upstreams[i].weight > 0)
+                .collect();
+
+            if !weighted.is_empty() {
+                let total: i64 = weighted
+                    .iter()
+                    .map(|&i| self.upstreams[i].weight as i64)
+                    .sum();
+                for &i in &weighted {
+                    states[i].current_weight += self.upstreams[i].weight as i64;
+                }
+                let selected = weighted
+                    .iter()
+                    .copied()
+                    .max_by_key(|&i| (states[i].current_weight, std::cmp::Reverse(i)))
+                    .unwrap_or(weighted[0]);
+                states[selected].current_weight -= total;
+
+                healthy.retain(|&i| i != selected);
+                healthy.insert(0, selected);
+            }
+        }
+
+        healthy.extend(cooling);
+        healthy
+    }
+
+    /// 记录上游请求成功
+    pub fn report_success(&self, upstream: &ResolvedUpstream) {
+        let Some(idx) = upstream.index else {
+            return;
+        };
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = states.get_mut(idx) {
+            state.cooldown_until = None;
+            state.consecutive_failures = 0;
+        }
+    }
+
+    /// 记录上游请求失败（进入冷却期）
+    pub fn report_failure(&self, upstream: &ResolvedUpstream) {
+        let Some(idx) = upstream.index else {
+            return;
+        };
+        let mut states = self.states.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = states.get_mut(idx) {
+            state.consecutive_failures += 1;
+            state.cooldown_until = Some(Instant::now() + FAILURE_COOLDOWN);
+            tracing::warn!(
+                tool_id = %self.tool_id,
+                profile = ?upstream.profile_name,
+                consecutive_failures = state.consecutive_failures,
+                "上游失败，进入冷却期"
+            );
+        }
+    }
+}
+
+/// 判断上游响应状态码是否应触发故障转移（5xx 与 429）
+pub fn should_failover_status(status: u16) -> bool {
+    status == 429 || (500..=599).contains(&status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn pool_with(strategy: LoadBalanceStrategy, weights: &[u32]) -> (UpstreamPool, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = ToolProxyConfig::new(8787);
+        config.load_balance = strategy;
+        config.upstreams = weights
+            .iter()
+            .enumerate()
+            .map(|(i, &weight)| UpstreamConfig {
+                profile_name: format!("profile-{i}"),
+                weight,
+                enabled: true,
+            })
+            .collect();
+        let profile_manager = ProfileManager::in_dir(temp_dir.path());
+        let pool =
+            UpstreamPool::with_profile_manager("claude-code", &config, Some(profile_manager));
+        (pool, temp_dir)
+    }
+
+    fn resolved(idx: usize) -> ResolvedUpstream {
+        ResolvedUpstream {
+            index: Some(idx),
+            profile_name: Some(format!("profile-{idx}")),
+            base_url: String::new(),
+            api_key: String::new(),
+            protocol: None,
+        }
+    }
+
+    #[test]
+    fn test_failover_keeps_list_order() {
+        let (pool, _temp) = pool_with(LoadBalanceStrategy::Failover, &[1, 1, 1]);
+        assert_eq!(pool.ordered_indices(Instant::now()), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_failed_upstream_moves_to_end() {
+        let (pool, _temp) = pool_with(LoadBalanceStrategy::Failover, &[1, 1, 1]);
+        pool.report_failure(&resolved(0));
+        assert_eq!(pool.ordered_indices(Instant::now()), vec![1, 2, 0]);
+
+        // 冷却期结束后恢复原顺序
+        let later = Instant::now() + FAILURE_COOLDOWN + Duration::from_secs(1);
+        assert_eq!(pool.ordered_indices(later), vec![0, 1, 2]);
+
+        pool.report_success(&resolved(0));
+        assert_eq!(pool.ordered_indices(Instant::now()), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_weighted_round_robin_distribution() {
+        let (pool, _temp) = pool_with(LoadBalanceStrategy::WeightedRoundRobin, &[3, 1, 0]);
+        let mut counts = [0usize; 3];
+        for _ in 0..40 {
+            let order = pool.ordered_indices(Instant::now());
+            assert_eq!(order.len(), 3);
+            counts[order[0]] += 1;
+        }
+        assert_eq!(counts, [30, 10, 0]);
+    }
+
+    #[test]
+    fn test_fallback_to_real_config() {
+        let mut config = ToolProxyConfig::new(8787);
+        config.real_base_url = Some("https://api.example.com".to_string());
+        config.real_api_key = Some("sk-test".to_string());
+        let pool = UpstreamPool::from_config("claude-code", &config);
+
+        let candidates = pool.candidates();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].index, None);
+        assert_eq!(candidates[0].profile_name, None);
+        assert_eq!(candidates[0].base_url, "https://api.example.com");
+    }
+
+    #[test]
+    fn test_fallback_uses_real_profile_protocol() {
+        let temp = TempDir::new().unwrap();
+        let profile_manager = ProfileManager::in_dir(temp.path());
+        profile_manager
+            .save_codex_profile_internal(
+                "chat-relay",
+                "sk-test".to_string(),
+                "https://relay.example.com/v1".to_string(),
+                Some("chat".to_string()),
+            )
+            .unwrap();
+
+        let mut config = ToolProxyConfig::new(8788);
+        config.real_base_url = Some("https://relay.example.com/v1".to_string());
+        config.real_api_key = Some("sk-test".to_string());
+        config.real_profile_name = Some("chat-relay".to_string());
+        let pool = UpstreamPool::with_profile_manager("codex", &config, Some(profile_manager));
+
+        let candidates = pool.candidates();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].protocol, Some(UpstreamProtocol::OpenaiChat));
+        assert_eq!(candidates[0].profile_name.as_deref(), Some("chat-relay"));
+    }
+
+    #[test]
+    fn test_should_failover_status() {
+        assert!(should_failover_status(429));
+        assert!(should_failover_status(502));
+        assert!(!should_failover_status(200));
+        assert!(!should_failover_status(400));
+    }
+}
@@ -0,0 +1,410 @@
+//! 请求用量提取
+//!
+//! 从上游响应（SSE 流或 JSON）中解析 Token 用量，支持：
+//! - Anthropic Messages：`message_start` / `message_delta` 事件及非流式 `message`
+//! - OpenAI Responses：`response.completed` 事件及非流式 `response`
+//! - OpenAI Chat Completions：`usage` 字段（流式需开启 include_usage）
+//! - Gemini：`usageMetadata` 字段
+//!
+//! 统一口径：`input_tokens` 不包含缓存命中部分，缓存读取单独记录在 `cache_read_tokens`。
+
+use serde_json::Value;
+
+use super::api_keys;
+use super::rate_limit::RateLimitPermit;
+use crate::services::session::{SessionEvent, UsageRecord, SESSION_MANAGER};
+
+/// 增量用量提取器
+///
+/// SSE 响应按块调用 `feed_sse`，JSON 响应调用 `feed_json_body`，最后调用 `finish` 获取结果
+#[derive(Debug, Default)]
+pub struct UsageExtractor {
+    /// 未完成的 SSE 行缓冲
+    line_buffer: Vec<u8>,
+    model: Option<String>,
+    input_tokens: i64,
+    output_tokens: i64,
+    cache_read_tokens: i64,
+    cache_creation_tokens: i64,
+    /// 是否解析到任何用量信息
+    found: bool,
+}
+
+impl UsageExtractor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 输入一段 SSE 数据（可以是任意切分的字节块）
+    pub fn feed_sse(&mut self, chunk: &[u8]) {
+        self.line_buffer.extend_from_slice(chunk);
+
+        while let Some(pos) = self.line_buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.line_buffer.drain(..=pos).collect();
+            self.process_sse_line(&line);
+        }
+    }
+
+    /// 输入完整的 JSON 响应体
+    pub fn feed_json_body(&mut self, body: &[u8]) {
+        if let Ok(value) = serde_json::from_slice::<Value>(body) {
+            self.process_value(&value);
+        }
+    }
+
+    fn process_sse_line(&mut self, line: &[u8]) {
+        let Ok(line) = std::str::from_utf8(line) else {
+            return;
+        };
+        let Some(data) = line.trim_end().strip_prefix("data:") else {
+            return;
+        };
+        let data = data.trim();
+        if data.is_empty() || data == "[DONE]" {
+            return;
+        }
+        if let Ok(value) = serde_json::from_str::<Value>(data) {
+            self.process_value(&value);
+        }
+    }
+
+    /// 处理单个 JSON 对象（SSE 事件或完整响应体）
+    fn process_value(&mut self, value: &Value) {
+        match value["type"].as_str() {
+            // Anthropic 流式：message_start 携带输入用量，message_delta 携带累计输出用量
+            Some("message_start") => {
+                let message = &value["message"];
+                self.set_model(message["model"].as_str());
+                self.apply_anthropic_usage(&message["usage"]);
+                return;
+            }
+            Some("message_delta") => {
+                self.apply_anthropic_usage(&value["usage"]);
+                return;
+            }
+            // Anthropic 非流式
+            Some("message") => {
+                self.set_model(value["model"].as_str());
+                self.apply_anthropic_usage(&value["usage"]);
+                return;
+            }
+            // OpenAI Responses 流式
+            Some("response.completed") => {
+                let response = &value["response"];
+                self.set_model(response["model"].as_str());
+                self.apply_openai_usage(&response["usage"]);
+                return;
+            }
+            _ => {}
+        }
+
+        // OpenAI Responses 非流式 / Chat Completions
+        if value["object"].is_string() && value["usage"].is_object() {
+            self.set_model(value["model"].as_str());
+            self.apply_openai_usage(&value["usage"]);
+            return;
+        }
+
+        // Gemini（流式每个分块的 usageMetadata 为累计值，取最后一次）
+        if value["usageMetadata"].is_object() {
+            self.set_model(value["modelVersion"].as_str());
+            self.apply_gemini_usage(&value["usageMetadata"]);
+        }
+    }
+
+    fn set_model(&mut self, model: Option<&str>) {
+        if let Some(model) = model.filter(|m| !m.is_empty()) {
+            self.model = Some(model.to_string());
+        }
+    }
+
+    fn apply_anthropic_usage(&mut self, usage: &Value) {
+        if !usage.is_object() {
+            return;
+        }
+        self.found = true;
+        // message_delta 中的字段为累计值，出现即覆盖
+        if let Some(v) = usage["input_tokens"].as_i64() {
+            self.input_tokens = v;
+        }
+        if let Some(v) = usage["output_tokens"].as_i64() {
+            self.output_tokens = v;
+        }
+        if let Some(v) = usage["cache_read_input_tokens"].as_i64() {
+            self.cache_read_tokens = v;
+        }
+        if let Some(v) = usage["cache_creation_input_tokens"].as_i64() {
+            self.cache_creation_tokens = v;
+        }
+    }
+
+    fn apply_openai_usage(&mut self, usage: &Value) {
+        if !usage.is_object() {
+            return;
+        }
+        self.found = true;
+
+        // Responses API 使用 input/output_tokens，Chat Completions 使用 prompt/completion_tokens
+        let input = usage["input_tokens"]
+            .as_i64()
+            .or_else(|| usage["prompt_tokens"].as_i64())
+            .unwrap_or(0);
+        let output = usage["output_tokens"]
+            .as_i64()
+            .or_else(|| usage["completion_tokens"].as_i64())
+            .unwrap_or(0);
+        let cached = usage["input_tokens_details"]["cached_tokens"]
+            .as_i64()
+            .or_else(|| usage["prompt_tokens_details"]["cached_tokens"].as_i64())
+            .unwrap_or(0);
+
+        self.input_tokens = (input - cached).max(0);
+        self.output_tokens = output;
+        self.cache_read_tokens = cached;
+    }
+
+    fn apply_gemini_usage(&mut self, usage: &Value) {
+        self.found = true;
+
+        let prompt = usage["promptTokenCount"].as_i64().unwrap_or(0);
+        let cached = usage["cachedContentTokenCount"].as_i64().unwrap_or(0);
+        let candidates = usage["candidatesTokenCount"].as_i64().unwrap_or(0);
+        let thoughts = usage["thoughtsTokenCount"].as_i64().unwrap_or(0);
+
+        self.input_tokens = (prompt - cached).max(0);
+        self.output_tokens = candidates + thoughts;
+        self.cache_read_tokens = cached;
+    }
+
+    /// 结束提取，未解析到用量时返回 None
+    pub fn finish(mut self) -> Option<ExtractedUsage> {
+        // 处理没有换行结尾的最后一行
+        if !self.line_buffer.is_empty() {
+            let line = std::mem::take(&mut self.line_buffer);
+            self.process_sse_line(&line);
+        }
+
+        self.found.then_some(ExtractedUsage {
+            model: self.model,
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            cache_read_tokens: self.cache_read_tokens,
+            cache_creation_tokens: self.cache_creation_tokens,
+        })
+    }
+}
+
+/// 提取结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractedUsage {
+    pub model: Option<String>,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+}
+
+impl ExtractedUsage {
+    /// 本次请求消耗的 Token 总数（含缓存读写，用于 TPM 限流）
+    pub fn total_tokens(&self) -> u64 {
+        (self.input_tokens
+            + self.output_tokens
+            + self.cache_read_tokens
+            + self.cache_creation_tokens)
+            .max(0) as u64
+    }
+}
+
+/// 从请求中提取模型名称（响应中缺失模型时使用）
+///
+/// 优先读取 JSON 请求体的 `model` 字段，其次解析 Gemini 风格路径
+/// （如 `/v1beta/models/gemini-2.5-pro:streamGenerateContent`）
+pub fn extract_request_model(path: &str, body: &[u8]) -> Option<String> {
+    if let Ok(value) = serde_json::from_slice::<Value>(body) {
+        if let Some(model) = value["model"].as_str() {
+            return Some(model.to_string());
+        }
+    }
+
+    path.split("/models/")
+        .nth(1)
+        .and_then(|rest| rest.split([':', '/']).next())
+        .filter(|m| !m.is_empty())
+        .map(|m| m.to_string())
+}
+
+/// 用量记录上下文（请求侧信息）
+#[derive(Debug, Clone)]
+pub struct UsageContext {
+    pub tool_id: String,
+    pub session_id: Option<String>,
+    pub profile_name: Option<String>,
+    pub request_model: Option<String>,
+    pub status_code: u16,
+    /// 发送到上游的次数（含重试与故障转移）
+    pub attempts: u32,
+    /// 请求使用的本地 Key 标签
+    pub key_label: Option<String>,
+}
+
+/// 流式响应的用量探针
+///
+/// 随响应流一起移动，在响应结束（或客户端断开导致流被丢弃）时写入用量记录
+pub struct UsageTap {
+    context: UsageContext,
+    extractor: Option<UsageExtractor>,
+    permit: Option<RateLimitPermit>,
+}
+
+impl UsageTap {
+    pub fn new(context: UsageContext) -> Self {
+        Self {
+            context,
+            extractor: Some(UsageExtractor::new()),
+            permit: None,
+        }
+    }
+
+    /// 持有限流许可，流结束时按用量记入 Token 并释放
+    pub fn with_rate_limit_permit(mut self, permit: RateLimitPermit) -> Self {
+        self.permit = Some(permit);
+        self
+    }
+
+    /// 观察一个 SSE 数据块
+    pub fn observe(&mut self, chunk: &[u8]) {
+        if let Some(extractor) = self.extractor.as_mut() {
+            extractor.feed_sse(chunk);
+        }
+    }
+}
+
+impl Drop for UsageTap {
+    fn drop(&mut self) {
+        if let Some(extractor) = self.extractor.take() {
+            let usage = extractor.finish();
+            if let (Some(permit), Some(usage)) = (&self.permit, &usage) {
+                permit.record_tokens(usage.total_tokens());
+            }
+            record_usage(&self.context, usage);
+        }
+    }
+}
+
+/// 将提取结果写入用量记录（通过 SessionManager 异步队列）
+///
+/// 未解析到用量时（错误响应、上游连接失败）同样写入记录，Token 计为 0，保留状态码与尝试次数
+pub fn record_usage(context: &UsageContext, usage: Option<ExtractedUsage>) {
+    let usage = usage.unwrap_or_default();
+    if let Some(label) = &context.key_label {
+        api_keys::KEY_USAGE.add(&context.tool_id, label, usage.total_tokens());
+    }
+
+    let record = UsageRecord {
+        id: 0,
+        session_id: context.session_id.clone(),
+        tool_id: context.tool_id.clone(),
+        profile_name: context.profile_name.clone(),
+        model: usage.model.or_else(|| context.request_model.clone()),
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cache_read_tokens: usage.cache_read_tokens,
+        cache_creation_tokens: usage.cache_creation_tokens,
+        status_code: context.status_code,
+        attempts: context.attempts as i64,
+        key_label: context.key_label.clone(),
+        origin: None,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+
+    if let Err(e) = SESSION_MANAGER.send_event(SessionEvent::RequestUsage(record)) {
+        tracing::warn!("用量事件发送失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anthropic_sse_usage() {
+        let mut extractor = UsageExtractor::new();
+        let stream = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"model\":\"claude-sonnet-4-5\",",
+            "\"usage\":{\"input_tokens\":12,\"cache_read_input_tokens\":3000,",
+            "\"cache_creation_input_tokens\":40,\"output_tokens\":1}}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"hi\"}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":87}}\n\n",
+        );
+
+        // 按任意位置切分，验证跨块拼接
+        let (a, b) = stream.as_bytes().split_at(37);
+        extractor.feed_sse(a);
+        extractor.feed_sse(b);
+
+        let usage = extractor.finish().unwrap();
+        assert_eq!(usage.model.as_deref(), Some("claude-sonnet-4-5"));
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.output_tokens, 87);
+        assert_eq!(usage.cache_read_tokens, 3000);
+        assert_eq!(usage.cache_creation_tokens, 40);
+    }
+
+    #[test]
+    fn test_openai_responses_completed() {
+        let mut extractor = UsageExtractor::new();
+        extractor.feed_sse(
+            b"event: response.completed\ndata: {\"type\":\"response.completed\",\"response\":{\"model\":\"gpt-5-codex\",\"usage\":{\"input_tokens\":1200,\"input_tokens_details\":{\"cached_tokens\":1000},\"output_tokens\":300}}}\n\n",
+        );
+
+        let usage = extractor.finish().unwrap();
+        assert_eq!(usage.model.as_deref(), Some("gpt-5-codex"));
+        assert_eq!(usage.input_tokens, 200);
+        assert_eq!(usage.cache_read_tokens, 1000);
+        assert_eq!(usage.output_tokens, 300);
+    }
+
+    #[test]
+    fn test_gemini_usage_metadata() {
+        let mut extractor = UsageExtractor::new();
+        extractor.feed_sse(b"data: {\"candidates\":[],\"usageMetadata\":{\"promptTokenCount\":10,\"candidatesTokenCount\":2}}\r\n\r\n");
+        extractor.feed_sse(b"data: {\"candidates\":[],\"usageMetadata\":{\"promptTokenCount\":10,\"candidatesTokenCount\":25,\"thoughtsTokenCount\":5},\"modelVersion\":\"gemini-2.5-pro\"}");
+
+        let usage = extractor.finish().unwrap();
+        assert_eq!(usage.model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!(usage.input_tokens, 10);
+        assert_eq!(usage.output_tokens, 30);
+    }
+
+    #[test]
+    fn test_json_body_and_missing_usage() {
+        let mut extractor = UsageExtractor::new();
+        extractor.feed_json_body(
+            br#"{"id":"chatcmpl-1","object":"chat.completion","model":"gpt-4o","usage":{"prompt_tokens":50,"completion_tokens":7}}"#,
+        );
+        let usage = extractor.finish().unwrap();
+        assert_eq!(usage.input_tokens, 50);
+        assert_eq!(usage.output_tokens, 7);
+
+        let mut empty = UsageExtractor::new();
+        empty.feed_json_body(br#"{"error":{"message":"bad request"}}"#);
+        assert!(empty.finish().is_none());
+    }
+
+    #[test]
+    fn test_extract_request_model() {
+        assert_eq!(
+            extract_request_model("/v1/messages", br#"{"model":"claude-haiku-4-5"}"#).as_deref(),
+            Some("claude-haiku-4-5")
+        );
+        assert_eq!(
+            extract_request_model("/v1beta/models/gemini-2.5-pro:streamGenerateContent", b"")
+                .as_deref(),
+            Some("gemini-2.5-pro")
+        );
+        assert_eq!(extract_request_model("/v1/responses", b""), None);
+    }
+}
@@ -4,13 +4,23 @@
 
 use bytes::Bytes;
 use hyper::{Response, StatusCode};
+use serde_json::{json, Value};
 
 use super::body::{box_body, BoxBody};
+use crate::services::proxy::metrics::ErrorClass;
+
+/// 序列化 JSON 错误体（含用户输入的字段须经此转义）
+fn json_body(value: &Value) -> BoxBody {
+    box_body(http_body_util::Full::new(Bytes::from(
+        serde_json::to_string_pretty(value).unwrap_or_default(),
+    )))
+}
 
 /// 配置缺失错误
 pub fn configuration_missing(tool_id: &str) -> Response<BoxBody> {
     Response::builder()
         .status(StatusCode::BAD_GATEWAY)
+        .extension(ErrorClass::Configuration)
         .header("content-type", "application/json")
         .body(box_body(http_body_util::Full::new(Bytes::from(format!(
             r#"{{
@@ -26,6 +36,7 @@ pub fn configuration_missing(tool_id: &str) -> Response<BoxBody> {
 pub fn proxy_loop_detected(tool_id: &str) -> Response<BoxBody> {
     Response::builder()
         .status(StatusCode::BAD_GATEWAY)
+        .extension(ErrorClass::ProxyLoop)
         .header("content-type", "application/json")
         .body(box_body(http_body_util::Full::new(Bytes::from(format!(
             r#"{{
@@ -37,10 +48,148 @@ pub fn proxy_loop_detected(tool_id: &str) -> Response<BoxBody> {
         .unwrap()
 }
 
+/// 代理跳数超限错误（请求经过多个代理形成环路）
+pub fn proxy_hop_limit_exceeded(tool_id: &str, hops: u32) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::LOOP_DETECTED)
+        .extension(ErrorClass::ProxyLoop)
+        .header("content-type", "application/json")
+        .body(box_body(http_body_util::Full::new(Bytes::from(format!(
+            r#"{{
+  "error": "PROXY_HOP_LIMIT_EXCEEDED",
+  "message": "{tool_id} 透明代理请求已经过 {hops} 个代理，疑似多个代理之间形成回环",
+  "details": "请检查各代理的 Base URL，避免互相指向"
+}}"#
+        )))))
+        .unwrap()
+}
+
+/// 本地限流排队超时
+pub fn rate_limited(tool_id: &str) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .extension(ErrorClass::RateLimited)
+        .header("content-type", "application/json")
+        .header("retry-after", "1")
+        .body(box_body(http_body_util::Full::new(Bytes::from(format!(
+            r#"{{
+  "error": "RATE_LIMITED",
+  "message": "{tool_id} 透明代理请求排队超时",
+  "details": "请求数、Token 数或并发数超出本地限流配置，请稍后重试或调整限流设置"
+}}"#
+        )))))
+        .unwrap()
+}
+
+/// 本地 Key 已吊销
+pub fn api_key_revoked(label: &str) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .extension(ErrorClass::Unauthorized)
+        .header("content-type", "application/json")
+        .body(json_body(&json!({
+            "error": "API_KEY_REVOKED",
+            "message": format!("本地 Key（{label}）已被吊销"),
+            "details": "请联系代理管理员重新签发"
+        })))
+        .unwrap()
+}
+
+/// 本地 Key 已过期
+pub fn api_key_expired(label: &str) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .extension(ErrorClass::Unauthorized)
+        .header("content-type", "application/json")
+        .body(json_body(&json!({
+            "error": "API_KEY_EXPIRED",
+            "message": format!("本地 Key（{label}）已过期"),
+            "details": "请联系代理管理员延长有效期或重新签发"
+        })))
+        .unwrap()
+}
+
+/// 本地 Key 的 Token 配额已用尽
+pub fn quota_exceeded(label: &str) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .extension(ErrorClass::RateLimited)
+        .header("content-type", "application/json")
+        .body(json_body(&json!({
+            "error": "QUOTA_EXCEEDED",
+            "message": format!("本地 Key（{label}）的 Token 配额已用尽"),
+            "details": "请联系代理管理员调整配额"
+        })))
+        .unwrap()
+}
+
+/// 本地 Key 无权使用任何候选上游
+pub fn profile_not_allowed(label: &str) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .extension(ErrorClass::Unauthorized)
+        .header("content-type", "application/json")
+        .body(json_body(&json!({
+            "error": "PROFILE_NOT_ALLOWED",
+            "message": format!("本地 Key（{label}）无权使用当前配置的上游"),
+            "details": "请联系代理管理员调整该 Key 允许使用的 Profile"
+        })))
+        .unwrap()
+}
+
+/// 统一网关无法判断请求所属的工具
+pub fn gateway_route_not_found(path: &str) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .extension(ErrorClass::Configuration)
+        .header("content-type", "application/json")
+        .body(box_body(http_body_util::Full::new(Bytes::from(format!(
+            r#"{{
+  "error": "GATEWAY_ROUTE_NOT_FOUND",
+  "message": "无法确定请求 {path} 所属的工具",
+  "details": "请在 Base URL 中添加工具前缀，如 /claude-code、/codex 或 /gemini-cli"
+}}"#
+        )))))
+        .unwrap()
+}
+
+/// 来源地址无权访问目标工具
+pub fn peer_not_allowed(tool_id: &str) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .extension(ErrorClass::Unauthorized)
+        .header("content-type", "application/json")
+        .body(box_body(http_body_util::Full::new(Bytes::from(format!(
+            r#"{{
+  "error": "PEER_NOT_ALLOWED",
+  "message": "当前来源地址无权访问 {tool_id} 透明代理",
+  "details": "请在该工具的代理设置中开启局域网访问或调整来源地址过滤"
+}}"#
+        )))))
+        .unwrap()
+}
+
+/// 统一网关目标工具的代理未运行
+pub fn tool_not_running(tool_id: &str) -> Response<BoxBody> {
+    Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .extension(ErrorClass::Configuration)
+        .header("content-type", "application/json")
+        .body(box_body(http_body_util::Full::new(Bytes::from(format!(
+            r#"{{
+  "error": "TOOL_PROXY_NOT_RUNNING",
+  "message": "{tool_id} 透明代理未启动",
+  "details": "统一网关仅转发到已启动的工具代理，请先启动 {tool_id} 透明代理"
+}}"#
+        )))))
+        .unwrap()
+}
+
 /// 未授权错误
 pub fn unauthorized() -> Response<BoxBody> {
     Response::builder()
         .status(StatusCode::UNAUTHORIZED)
+        .extension(ErrorClass::Unauthorized)
         .body(box_body(http_body_util::Full::new(Bytes::from(
             "Unauthorized: Invalid API Key",
         ))))
@@ -57,3 +206,21 @@ pub fn internal_error(message: &str) -> Response<BoxBody> {
         )))))
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    #[tokio::test]
+    async fn test_key_label_is_escaped() {
+        let response = quota_exceeded(r#"team "a" \ b"#);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let value: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value["error"], "QUOTA_EXCEEDED");
+        assert!(value["message"]
+            .as_str()
+            .unwrap()
+            .contains(r#"team "a" \ b"#));
+    }
+}
@@ -1,45 +1,236 @@
 //! 代理回环检测工具
 //!
-//! 防止代理配置指向自身导致无限循环
+//! 防止代理配置指向自身导致无限循环：
+//! - 转发前解析目标地址，与本机所有代理监听地址（各工具代理与统一网关）比对
+//! - 转发到本机或内网地址时携带跳数头，经过多个代理形成的环路在运行时由跳数上限拦截；
+//!   公网上游（厂商 API、中转站）不可能是级联的 DuckCoding，不发送跳数头
 
-/// 检查目标 URL 是否指向自身代理端口
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+use url::{Host, Url};
+
+/// 代理跳数请求头（每经过一个 DuckCoding 代理加一）
+pub const HOP_HEADER: &str = "x-duckcoding-hops";
+
+/// 允许的最大跳数（允许有限的代理级联，如转发到另一台机器上的 DuckCoding）
+pub const MAX_HOPS: u32 = 5;
+
+/// 当前进程中所有代理的监听地址
+static LISTEN_ADDRS: Lazy<RwLock<Vec<SocketAddr>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// 登记监听地址（监听启动时调用）
+pub fn register_listen_addrs(addrs: &[SocketAddr]) {
+    if let Ok(mut list) = LISTEN_ADDRS.write() {
+        list.extend_from_slice(addrs);
+    }
+}
+
+/// 注销监听地址（监听关闭时调用）
+pub fn unregister_listen_addrs(addrs: &[SocketAddr]) {
+    if let Ok(mut list) = LISTEN_ADDRS.write() {
+        for addr in addrs {
+            if let Some(index) = list.iter().position(|a| a == addr) {
+                list.swap_remove(index);
+            }
+        }
+    }
+}
+
+/// 当前登记的全部监听地址
+pub fn listen_addrs() -> Vec<SocketAddr> {
+    LISTEN_ADDRS
+        .read()
+        .map(|list| list.clone())
+        .unwrap_or_default()
+}
+
+/// 检查目标 URL 是否指向本机的代理监听地址
 ///
 /// # 参数
 /// - `target_url`: 目标 URL
-/// - `own_port`: 当前代理监听的端口
+/// - `own_ports`: 当前代理的端口（未登记监听时也参与比对，按监听全部地址处理）
 ///
 /// # 返回
 /// - `true`: 检测到回环
 /// - `false`: 未检测到回环
-pub fn is_proxy_loop(target_url: &str, own_port: u16) -> bool {
-    let loop_urls = vec![
-        format!("http://127.0.0.1:{}", own_port),
-        format!("https://127.0.0.1:{}", own_port),
-        format!("http://localhost:{}", own_port),
-        format!("https://localhost:{}", own_port),
-    ];
-
-    for loop_url in &loop_urls {
-        if target_url.starts_with(loop_url) {
-            return true;
+pub async fn is_proxy_loop(target_url: &str, own_ports: &[u16]) -> bool {
+    let mut listening = listen_addrs();
+    listening.extend(
+        own_ports
+            .iter()
+            .map(|port| SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), *port)),
+    );
+    is_loop_to(target_url, &listening).await
+}
+
+/// 检查目标 URL 解析后的地址是否落在给定的监听地址上
+pub async fn is_loop_to(target_url: &str, listening: &[SocketAddr]) -> bool {
+    let Ok(url) = Url::parse(target_url) else {
+        return false;
+    };
+    let Some(port) = url.port_or_known_default() else {
+        return false;
+    };
+    // 端口不匹配时无需解析域名
+    if !listening.iter().any(|addr| addr.port() == port) {
+        return false;
+    }
+
+    let targets: Vec<SocketAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(IpAddr::V4(ip), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(IpAddr::V6(ip), port)],
+        Some(Host::Domain(domain)) => match tokio::net::lookup_host((domain, port)).await {
+            Ok(addrs) => addrs.collect(),
+            Err(e) => {
+                tracing::debug!(host = %domain, error = ?e, "回环检测解析域名失败");
+                return false;
+            }
+        },
+        None => return false,
+    };
+
+    targets
+        .iter()
+        .any(|target| listening.iter().any(|listen| reaches(*target, *listen)))
+}
+
+/// 连接 `target` 是否会到达绑定在 `listen` 上的监听
+fn reaches(target: SocketAddr, listen: SocketAddr) -> bool {
+    if target.port() != listen.port() {
+        return false;
+    }
+    let target_ip = target.ip();
+    let listen_ip = listen.ip();
+
+    if listen_ip.is_unspecified() {
+        return is_local_ip(target_ip);
+    }
+    // 连接 0.0.0.0 / [::] 等同于连接本机回环地址
+    if target_ip.is_unspecified() {
+        return listen_ip.is_loopback();
+    }
+    target_ip == listen_ip
+}
+
+/// 是否为本机地址（回环、未指定或本机网卡地址）
+fn is_local_ip(ip: IpAddr) -> bool {
+    // 仅本机网卡上的地址可以绑定
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || std::net::UdpSocket::bind(SocketAddr::new(ip, 0)).is_ok()
+}
+
+/// 跳数头发往本机 / 内网时视为可能级联的 DuckCoding 实例的主机名后缀
+const LOCAL_DOMAIN_SUFFIXES: &[&str] = &[".local", ".lan", ".internal", ".home.arpa"];
+
+/// 是否向目标转发跳数头
+///
+/// 只有本机或内网地址（回环、私有网段、链路本地、`localhost`、单标签主机名与内网域名后缀）
+/// 可能是另一个 DuckCoding 实例，不做 DNS 解析
+pub fn forwards_hops(target_url: &str) -> bool {
+    let Ok(url) = Url::parse(target_url) else {
+        return false;
+    };
+    match url.host() {
+        Some(Host::Ipv4(ip)) => {
+            ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+        }
+        Some(Host::Ipv6(ip)) => {
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // fc00::/7 唯一本地地址、fe80::/10 链路本地地址
+                || (ip.segments()[0] & 0xfe00) == 0xfc00
+                || (ip.segments()[0] & 0xffc0) == 0xfe80
         }
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain == "localhost"
+                || domain.ends_with(".localhost")
+                || !domain.contains('.')
+                || LOCAL_DOMAIN_SUFFIXES
+                    .iter()
+                    .any(|suffix| domain.ends_with(suffix))
+        }
+        None => false,
     }
+}
 
-    false
+/// 读取请求已经过的代理跳数
+pub fn hop_count(headers: &hyper::HeaderMap) -> u32 {
+    headers
+        .get(HOP_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_loop_detection() {
+        assert!(is_proxy_loop("http://127.0.0.1:8787/v1/messages", &[8787]).await);
+        assert!(is_proxy_loop("https://localhost:8787/api", &[8787]).await);
+        assert!(is_proxy_loop("http://0.0.0.0:8787/api", &[8787]).await);
+        assert!(is_proxy_loop("http://[::1]:8787/api", &[8787]).await);
+        assert!(!is_proxy_loop("https://api.anthropic.com/v1/messages", &[8787]).await);
+        assert!(!is_proxy_loop("http://127.0.0.1:8788/v1/messages", &[8787]).await);
+    }
+
+    #[tokio::test]
+    async fn test_loop_detection_respects_bind_address() {
+        let loopback = [SocketAddr::from(([127, 0, 0, 1], 9100))];
+        assert!(is_loop_to("http://127.0.0.1:9100/", &loopback).await);
+        assert!(is_loop_to("http://0.0.0.0:9100/", &loopback).await);
+        // 仅监听回环地址时，其他地址不会到达
+        assert!(!is_loop_to("http://10.255.255.1:9100/", &loopback).await);
+
+        let public = [SocketAddr::from(([0, 0, 0, 0], 9100))];
+        assert!(is_loop_to("http://127.0.0.2:9100/", &public).await);
+        // 非本机地址
+        assert!(!is_loop_to("http://192.0.2.1:9100/", &public).await);
+        // 默认端口
+        assert!(!is_loop_to("https://localhost/", &public).await);
+    }
+
+    #[tokio::test]
+    async fn test_registered_listeners() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 9101));
+        assert!(!is_proxy_loop("http://localhost:9101/", &[]).await);
+        register_listen_addrs(&[addr]);
+        assert!(is_proxy_loop("http://localhost:9101/", &[]).await);
+        unregister_listen_addrs(&[addr]);
+        assert!(!is_proxy_loop("http://localhost:9101/", &[]).await);
+    }
+
     #[test]
-    fn test_loop_detection() {
-        assert!(is_proxy_loop("http://127.0.0.1:8787/v1/messages", 8787));
-        assert!(is_proxy_loop("https://localhost:8787/api", 8787));
-        assert!(!is_proxy_loop(
-            "https://api.anthropic.com/v1/messages",
-            8787
+    fn test_forwards_hops() {
+        assert!(forwards_hops("http://127.0.0.1:8787/v1/messages"));
+        assert!(forwards_hops("http://192.168.1.10:8787/v1"));
+        assert!(forwards_hops("http://[::1]:8787/"));
+        assert!(forwards_hops("http://[fd00::1]:8787/"));
+        assert!(forwards_hops("http://localhost:8787/"));
+        assert!(forwards_hops("http://nas:8787/"));
+        assert!(forwards_hops("http://relay.lan:8787/"));
+
+        assert!(!forwards_hops("https://api.anthropic.com/v1/messages"));
+        assert!(!forwards_hops(
+            "https://generativelanguage.googleapis.com/v1beta"
         ));
-        assert!(!is_proxy_loop("http://127.0.0.1:8788/v1/messages", 8787));
+        assert!(!forwards_hops("http://8.8.8.8/"));
+        assert!(!forwards_hops("not a url"));
+    }
+
+    #[test]
+    fn test_hop_count() {
+        let mut headers = hyper::HeaderMap::new();
+        assert_eq!(hop_count(&headers), 0);
+        headers.insert(HOP_HEADER, "3".parse().unwrap());
+        assert_eq!(hop_count(&headers), 3);
+        headers.insert(HOP_HEADER, "abc".parse().unwrap());
+        assert_eq!(hop_count(&headers), 0);
     }
 }
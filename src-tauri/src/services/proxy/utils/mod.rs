@@ -5,6 +5,7 @@
 pub mod body;
 pub mod error_responses;
 pub mod loop_detector;
+pub mod session;
 
 // 重新导出常用类型
 pub use body::{box_body, BoxBody};
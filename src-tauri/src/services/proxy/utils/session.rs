@@ -0,0 +1,210 @@
+//! 会话识别与会话级端点
+//!
+//! 按工具从请求中识别会话，记录会话并应用会话级自定义端点：
+//! - Claude Code：请求体 `metadata.user_id`（自带 `_session_<uuid>` 后缀）
+//! - Codex：`session_id` / `conversation_id` 请求头，缺失时取请求体 `prompt_cache_key`
+//! - Gemini CLI：请求体 `session_id` / `request.session_id` / `metadata.session_id`，缺失时取会话请求头
+//! - 其他工具：`session_id` / `conversation_id` / `x-session-id` 请求头
+//!
+//! 非 Claude Code 的会话 ID 统一记录为 `<tool_id>_session_<id>`，与 Claude Code 共用展示 ID 的提取规则，
+//! 也避免不同工具的会话 ID 冲突
+//!
+//! 会话绑定的 Profile（凭证与上游协议）在每次请求时通过 ProfileManager 解析，轮换 Key 后立即生效
+
+use hyper::HeaderMap;
+use serde_json::Value;
+
+use crate::models::proxy_config::UpstreamProtocol;
+use crate::services::profile_manager::ProfileManager;
+use crate::services::session::{SessionEndpoint, SessionEvent, SESSION_MANAGER};
+
+/// 会话 ID 请求头（按优先级）
+const SESSION_HEADERS: [&str; 3] = ["session_id", "conversation_id", "x-session-id"];
+
+/// 从请求中识别会话 ID
+pub fn identify(tool_id: &str, headers: &HeaderMap, body: &[u8]) -> Option<String> {
+    let json = || serde_json::from_slice::<Value>(body).ok();
+
+    if tool_id == "claude-code" {
+        return json()?["metadata"]["user_id"]
+            .as_str()
+            .filter(|id| !id.is_empty())
+            .map(str::to_string);
+    }
+
+    let raw = match tool_id {
+        "codex" => header_session(headers)
+            .or_else(|| json().and_then(|body| non_empty(&body["prompt_cache_key"]))),
+        "gemini-cli" => json()
+            .and_then(|body| {
+                non_empty(&body["session_id"])
+                    .or_else(|| non_empty(&body["request"]["session_id"]))
+                    .or_else(|| non_empty(&body["metadata"]["session_id"]))
+            })
+            .or_else(|| header_session(headers)),
+        _ => header_session(headers),
+    }?;
+
+    Some(format!("{tool_id}_session_{raw}"))
+}
+
+/// 会话级自定义端点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionUpstream {
+    pub base_url: String,
+    pub api_key: String,
+    /// 绑定的 Profile 名称（手动填写的 URL 和 Key 为 None）
+    pub profile_name: Option<String>,
+    /// 绑定的 Profile 声明的上游协议（None 表示沿用工具代理配置）
+    pub protocol: Option<UpstreamProtocol>,
+}
+
+/// 记录会话请求，并返回会话级自定义端点
+///
+/// 会话未配置自定义端点、或绑定的 Profile 已被删除时返回 None，使用代理的全局上游
+pub fn resolve_endpoint(tool_id: &str, session_id: &str) -> Option<SessionUpstream> {
+    if let Err(e) = SESSION_MANAGER.send_event(SessionEvent::NewRequest {
+        session_id: session_id.to_string(),
+        tool_id: tool_id.to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+    }) {
+        tracing::warn!("Session 事件发送失败: {}", e);
+    }
+
+    match SESSION_MANAGER.get_session_config(session_id) {
+        Ok(Some(SessionEndpoint::Profile(name))) => profile_endpoint(tool_id, session_id, &name),
+        Ok(Some(SessionEndpoint::Custom { url, api_key })) => Some(SessionUpstream {
+            base_url: url,
+            api_key,
+            profile_name: None,
+            protocol: None,
+        }),
+        _ => None,
+    }
+}
+
+/// 解析会话绑定的 Profile 凭证与协议
+fn profile_endpoint(tool_id: &str, session_id: &str, name: &str) -> Option<SessionUpstream> {
+    let credentials = ProfileManager::new().and_then(|manager| {
+        let (api_key, base_url) = manager.get_profile_credentials(tool_id, name)?;
+        let protocol = manager.get_profile_protocol(tool_id, name)?;
+        Ok((api_key, base_url, protocol))
+    });
+    match credentials {
+        Ok((api_key, base_url, protocol)) if !api_key.is_empty() && !base_url.is_empty() => {
+            Some(SessionUpstream {
+                base_url,
+                api_key,
+                profile_name: Some(name.to_string()),
+                protocol,
+            })
+        }
+        Ok(_) => {
+            tracing::warn!(
+                session_id = %session_id,
+                profile = %name,
+                "会话绑定的 Profile 凭证不完整，回退到全局配置"
+            );
+            None
+        }
+        Err(e) => {
+            tracing::warn!(
+                session_id = %session_id,
+                profile = %name,
+                error = %e,
+                "会话绑定的 Profile 不存在，回退到全局配置"
+            );
+            None
+        }
+    }
+}
+
+fn header_session(headers: &HeaderMap) -> Option<String> {
+    SESSION_HEADERS.iter().find_map(|name| {
+        headers
+            .get(*name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    })
+}
+
+fn non_empty(value: &Value) -> Option<String> {
+    value.as_str().filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::session::ProxySession;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_identify_claude_session() {
+        let body = br#"{"metadata":{"user_id":"user_abc_account__session_f7aa73fc"}}"#;
+        assert_eq!(
+            identify("claude-code", &HeaderMap::new(), body).as_deref(),
+            Some("user_abc_account__session_f7aa73fc")
+        );
+        assert_eq!(identify("claude-code", &HeaderMap::new(), b"{}"), None);
+    }
+
+    #[test]
+    fn test_identify_codex_session() {
+        let id = identify(
+            "codex",
+            &headers(&[("conversation_id", "0199-abcd")]),
+            b"{}",
+        )
+        .unwrap();
+        assert_eq!(id, "codex_session_0199-abcd");
+        assert_eq!(
+            ProxySession::extract_display_id(&id).as_deref(),
+            Some("0199-abcd")
+        );
+
+        // session_id 优先于 conversation_id
+        assert_eq!(
+            identify(
+                "codex",
+                &headers(&[("conversation_id", "a"), ("session_id", "b")]),
+                b"{}"
+            )
+            .as_deref(),
+            Some("codex_session_b")
+        );
+
+        // 请求头缺失时取 prompt_cache_key
+        assert_eq!(
+            identify("codex", &HeaderMap::new(), br#"{"prompt_cache_key":"c"}"#).as_deref(),
+            Some("codex_session_c")
+        );
+        assert_eq!(identify("codex", &HeaderMap::new(), b"{}"), None);
+    }
+
+    #[test]
+    fn test_identify_gemini_session() {
+        assert_eq!(
+            identify(
+                "gemini-cli",
+                &HeaderMap::new(),
+                br#"{"request":{"session_id":"g-1"}}"#
+            )
+            .as_deref(),
+            Some("gemini-cli_session_g-1")
+        );
+        assert_eq!(
+            identify("gemini-cli", &headers(&[("x-session-id", "g-2")]), b"{}").as_deref(),
+            Some("gemini-cli_session_g-2")
+        );
+        assert_eq!(identify("gemini-cli", &HeaderMap::new(), b"{}"), None);
+    }
+}
@@ -1,8 +1,9 @@
 //! 透明代理配置管理器
 
 use crate::data::DataManager;
-use crate::models::proxy_config::ProxyStore;
 use crate::models::proxy_config::ToolProxyConfig;
+use crate::models::proxy_config::{GatewayConfig, ProxyStore};
+use crate::models::ToolDescriptor;
 use anyhow::{Context, Result};
 use std::path::PathBuf;
 
@@ -47,10 +48,12 @@ impl ProxyConfigManager {
             .map_err(Into::into)
     }
 
-    /// 获取指定工具的代理配置
+    /// 获取指定工具的代理配置（自定义工具尚未配置时返回默认配置）
     pub fn get_config(&self, tool_id: &str) -> Result<Option<ToolProxyConfig>> {
         let store = self.load_proxy_store()?;
-        Ok(store.get_config(tool_id).cloned())
+        Ok(store.get_config(tool_id).cloned().or_else(|| {
+            ToolDescriptor::find(tool_id).map(|d| ToolProxyConfig::new(d.default_port))
+        }))
     }
 
     /// 更新指定工具的代理配置
@@ -68,6 +71,19 @@ impl ProxyConfigManager {
         self.save_proxy_store(&store)
     }
 
+    /// 获取统一网关配置
+    pub fn get_gateway_config(&self) -> Result<GatewayConfig> {
+        Ok(self.load_proxy_store()?.gateway)
+    }
+
+    /// 更新统一网关配置
+    pub fn update_gateway_config(&self, config: GatewayConfig) -> Result<()> {
+        let mut store = self.load_proxy_store()?;
+        store.gateway = config;
+        store.metadata.last_updated = chrono::Utc::now();
+        self.save_proxy_store(&store)
+    }
+
     /// 获取所有工具的配置
     pub fn get_all_configs(&self) -> Result<ProxyStore> {
         self.load_proxy_store()
@@ -0,0 +1,606 @@
+//! 会话导出与导入
+//!
+//! 会话包（JSON Lines）第一行为文件头，其余每行是一条带 `type` 标记的记录：
+//! 会话（`session`）、用量明细（`usage`）与对话记录（`transcript`）。
+//! 导出时清空会话中保存的 API Key；Markdown 格式仅用于阅读（如附在 PR 中），不支持导入。
+//!
+//! 导入时合并到本机 `sessions.db`：已存在的会话合并时间范围与请求次数，保留本机的端点配置；
+//! 用量明细按导出时写入的来源标识（导出实例 ID + 原记录 ID）去重，对话记录按内容去重，
+//! 重复导入同一个会话包不会产生重复记录。
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use chrono::TimeZone;
+use ring::rand::{SecureRandom, SystemRandom};
+use rusqlite::{OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::data::managers::SqliteManager;
+use crate::services::session::models::{ProxySession, TranscriptRole, TranscriptTurn, UsageRecord};
+use crate::services::session::transcript;
+
+/// 会话包文件头中的格式标识
+const BUNDLE_FORMAT: &str = "duckcoding-sessions";
+
+/// 当前会话包版本
+const BUNDLE_VERSION: u32 = 1;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// JSON Lines（可导入）
+    Jsonl,
+    /// Markdown（仅用于阅读）
+    Markdown,
+}
+
+/// 会话包中的一行
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BundleLine {
+    Header {
+        format: String,
+        version: u32,
+        exported_at: i64,
+    },
+    Session(ProxySession),
+    Usage(UsageRecord),
+    Transcript(TranscriptTurn),
+}
+
+/// 会话包（导出的会话及其用量明细与对话记录）
+#[derive(Debug, Clone, Default)]
+pub struct SessionBundle {
+    /// 导出时间（Unix 时间戳，秒）
+    pub exported_at: i64,
+    pub sessions: Vec<ProxySession>,
+    pub usage: Vec<UsageRecord>,
+    pub transcripts: Vec<TranscriptTurn>,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// 新增的会话数
+    pub sessions_added: usize,
+    /// 与本机已有会话合并的数量
+    pub sessions_merged: usize,
+    /// 新增的用量明细数（已存在的记录跳过）
+    pub usage_records: usize,
+    /// 新增的对话记录数（已存在的记录跳过）
+    pub transcript_turns: usize,
+}
+
+impl SessionBundle {
+    /// 按格式写入文件
+    pub fn save(&self, path: &Path, format: ExportFormat) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).context("创建导出目录失败")?;
+        }
+        let mut writer = BufWriter::new(File::create(path).context("创建导出文件失败")?);
+        match format {
+            ExportFormat::Jsonl => self.write_jsonl(&mut writer)?,
+            ExportFormat::Markdown => self.write_markdown(&mut writer)?,
+        }
+        writer.flush().context("写入导出文件失败")
+    }
+
+    /// 从 JSON Lines 文件读取
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).context("打开会话包失败")?;
+        Self::read_jsonl(BufReader::new(file))
+    }
+
+    /// 写入 JSON Lines
+    pub fn write_jsonl(&self, writer: &mut impl Write) -> Result<()> {
+        let mut write_line = |line: &BundleLine| -> Result<()> {
+            serde_json::to_writer(&mut *writer, line)?;
+            writer.write_all(b"\n")?;
+            Ok(())
+        };
+
+        write_line(&BundleLine::Header {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            exported_at: self.exported_at,
+        })?;
+        for session in &self.sessions {
+            write_line(&BundleLine::Session(ProxySession {
+                api_key: String::new(),
+                ..session.clone()
+            }))?;
+        }
+        for record in &self.usage {
+            write_line(&BundleLine::Usage(record.clone()))?;
+        }
+        for turn in &self.transcripts {
+            write_line(&BundleLine::Transcript(turn.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// 读取 JSON Lines（第一行必须是会话包文件头）
+    pub fn read_jsonl(reader: impl BufRead) -> Result<Self> {
+        let mut bundle = Self::default();
+        let mut has_header = false;
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.context("读取会话包失败")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parsed: BundleLine = serde_json::from_str(&line)
+                .with_context(|| format!("会话包第 {} 行格式无效", index + 1))?;
+
+            match parsed {
+                BundleLine::Header {
+                    format,
+                    version,
+                    exported_at,
+                } => {
+                    if format != BUNDLE_FORMAT {
+                        bail!("不是 DuckCoding 会话包: {}", format);
+                    }
+                    if version > BUNDLE_VERSION {
+                        bail!(
+                            "会话包版本 {} 高于当前支持的版本 {}",
+                            version,
+                            BUNDLE_VERSION
+                        );
+                    }
+                    bundle.exported_at = exported_at;
+                    has_header = true;
+                }
+                _ if !has_header => bail!("会话包缺少文件头"),
+                BundleLine::Session(session) => bundle.sessions.push(session),
+                BundleLine::Usage(record) => bundle.usage.push(record),
+                BundleLine::Transcript(turn) => bundle.transcripts.push(turn),
+            }
+        }
+
+        if !has_header {
+            bail!("会话包缺少文件头");
+        }
+        Ok(bundle)
+    }
+
+    /// 写入 Markdown（每个会话一节：基本信息、用量明细与对话记录）
+    pub fn write_markdown(&self, writer: &mut impl Write) -> Result<()> {
+        writeln!(writer, "# DuckCoding 会话导出")?;
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "导出时间：{} · 共 {} 个会话",
+            format_time(self.exported_at),
+            self.sessions.len()
+        )?;
+
+        for session in &self.sessions {
+            writeln!(writer)?;
+            writeln!(writer, "## {}（{}）", session.display_id, session.tool_id)?;
+            writeln!(writer)?;
+            writeln!(writer, "| 项目 | 值 |")?;
+            writeln!(writer, "| --- | --- |")?;
+            writeln!(writer, "| 会话 ID | `{}` |", session.session_id)?;
+            if let Some(note) = session.note.as_deref().filter(|n| !n.is_empty()) {
+                writeln!(writer, "| 备注 | {} |", escape_cell(note))?;
+            }
+            let config = match session.custom_profile_name.as_deref() {
+                Some(name) if session.config_name == "custom" && !name.is_empty() => name,
+                _ => session.config_name.as_str(),
+            };
+            writeln!(writer, "| 配置 | {} |", escape_cell(config))?;
+            if let Some(label) = session.key_label.as_deref() {
+                writeln!(writer, "| 本地 Key | {} |", escape_cell(label))?;
+            }
+            writeln!(
+                writer,
+                "| 首次活跃 | {} |",
+                format_time(session.first_seen_at)
+            )?;
+            writeln!(
+                writer,
+                "| 最后活跃 | {} |",
+                format_time(session.last_seen_at)
+            )?;
+            writeln!(writer, "| 请求次数 | {} |", session.request_count)?;
+
+            let usage: Vec<_> = self
+                .usage
+                .iter()
+                .filter(|r| r.session_id.as_deref() == Some(session.session_id.as_str()))
+                .collect();
+            if !usage.is_empty() {
+                writeln!(writer)?;
+                writeln!(writer, "### 用量")?;
+                writeln!(writer)?;
+                writeln!(
+                    writer,
+                    "| 时间 | 模型 | 输入 | 输出 | 缓存读取 | 缓存写入 | 状态 |"
+                )?;
+                writeln!(writer, "| --- | --- | ---: | ---: | ---: | ---: | ---: |")?;
+                for record in &usage {
+                    writeln!(
+                        writer,
+                        "| {} | {} | {} | {} | {} | {} | {} |",
+                        format_time(record.created_at),
+                        escape_cell(record.model.as_deref().unwrap_or("-")),
+                        record.input_tokens,
+                        record.output_tokens,
+                        record.cache_read_tokens,
+                        record.cache_creation_tokens,
+                        record.status_code
+                    )?;
+                }
+                writeln!(writer)?;
+                writeln!(
+                    writer,
+                    "合计：输入 {}，输出 {}，缓存读取 {}，缓存写入 {}",
+                    usage.iter().map(|r| r.input_tokens).sum::<i64>(),
+                    usage.iter().map(|r| r.output_tokens).sum::<i64>(),
+                    usage.iter().map(|r| r.cache_read_tokens).sum::<i64>(),
+                    usage.iter().map(|r| r.cache_creation_tokens).sum::<i64>()
+                )?;
+            }
+
+            let turns: Vec<_> = self
+                .transcripts
+                .iter()
+                .filter(|t| t.session_id == session.session_id)
+                .collect();
+            if !turns.is_empty() {
+                writeln!(writer)?;
+                writeln!(writer, "### 对话记录")?;
+                for turn in turns {
+                    writeln!(writer)?;
+                    let title = match turn.role {
+                        TranscriptRole::User => "用户",
+                        TranscriptRole::Assistant => "助手",
+                        TranscriptRole::ToolCall => "工具调用",
+                    };
+                    let truncated = if turn.truncated {
+                        "（已截断）"
+                    } else {
+                        ""
+                    };
+                    writeln!(
+                        writer,
+                        "**{}** · {}{}",
+                        title,
+                        format_time(turn.created_at),
+                        truncated
+                    )?;
+                    writeln!(writer)?;
+                    match turn.role {
+                        // 工具调用放在代码块中，围栏长度超过内容中最长的反引号串
+                        TranscriptRole::ToolCall => {
+                            let fence = "`".repeat(longest_backtick_run(&turn.content).max(2) + 1);
+                            writeln!(writer, "{fence}")?;
+                            writeln!(writer, "{}", turn.content)?;
+                            writeln!(writer, "{fence}")?;
+                        }
+                        // 对话内容放在引用块中，避免其中的标题等语法影响文档结构
+                        _ => {
+                            for line in turn.content.lines() {
+                                if line.is_empty() {
+                                    writeln!(writer, ">")?;
+                                } else {
+                                    writeln!(writer, "> {line}")?;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 本机数据库的实例 ID（首次调用时生成并保存，用于标识导出记录的来源）
+pub fn instance_id(tx: &Transaction) -> rusqlite::Result<String> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);",
+    )?;
+    let existing: Option<String> = tx
+        .query_row(
+            "SELECT value FROM session_meta WHERE key = 'instance_id'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let mut bytes = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| rusqlite::Error::InvalidQuery)?;
+    let id: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    tx.execute(
+        "INSERT INTO session_meta (key, value) VALUES ('instance_id', ?1)",
+        [&id],
+    )?;
+    Ok(id)
+}
+
+/// 用量记录的来源标识
+///
+/// 导出时写入的标识原样保留；旧版会话包中没有该字段，按完整记录（含原记录 ID）的哈希生成
+fn usage_origin(record: &UsageRecord) -> Result<String> {
+    if let Some(origin) = record.origin.as_deref().filter(|o| !o.is_empty()) {
+        return Ok(origin.to_string());
+    }
+    let digest = Sha256::digest(serde_json::to_vec(record)?);
+    Ok(format!("legacy:{digest:x}"))
+}
+
+/// 将会话包合并到数据库（单个事务内完成）
+pub fn import(db: &SqliteManager, bundle: &SessionBundle) -> Result<ImportSummary> {
+    let transcripts = bundle
+        .transcripts
+        .iter()
+        .map(|turn| Ok((turn, transcript::compress(&turn.content)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let usage = bundle
+        .usage
+        .iter()
+        .map(|record| Ok((record, usage_origin(record)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let now = chrono::Utc::now().timestamp();
+
+    let summary = db.transaction(|tx| {
+        let mut summary = ImportSummary::default();
+        let local_prefix = format!("{}:", instance_id(tx)?);
+
+        for session in &bundle.sessions {
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM claude_proxy_sessions WHERE session_id = ?1)",
+                [&session.session_id],
+                |row| row.get(0),
+            )?;
+            if exists {
+                tx.execute(
+                    "UPDATE claude_proxy_sessions SET
+                        first_seen_at = MIN(first_seen_at, ?2),
+                        last_seen_at = MAX(last_seen_at, ?3),
+                        request_count = MAX(request_count, ?4),
+                        note = COALESCE(NULLIF(note, ''), ?5),
+                        key_label = COALESCE(key_label, ?6),
+                        updated_at = ?7
+                     WHERE session_id = ?1",
+                    rusqlite::params![
+                        session.session_id,
+                        session.first_seen_at,
+                        session.last_seen_at,
+                        session.request_count,
+                        session.note,
+                        session.key_label,
+                        now,
+                    ],
+                )?;
+                summary.sessions_merged += 1;
+            } else {
+                tx.execute(
+                    "INSERT INTO claude_proxy_sessions (
+                        session_id, display_id, tool_id, config_name, custom_profile_name,
+                        url, api_key, note, first_seen_at, last_seen_at, request_count,
+                        created_at, updated_at, key_label
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, '', ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                    rusqlite::params![
+                        session.session_id,
+                        session.display_id,
+                        session.tool_id,
+                        session.config_name,
+                        session.custom_profile_name,
+                        session.url,
+                        session.note,
+                        session.first_seen_at,
+                        session.last_seen_at,
+                        session.request_count,
+                        session.created_at,
+                        now,
+                        session.key_label,
+                    ],
+                )?;
+                summary.sessions_added += 1;
+            }
+        }
+
+        for (record, origin) in &usage {
+            // 本机导出的记录仍在时跳过（本机记录没有来源标识）
+            if let Some(local_id) = origin
+                .strip_prefix(&local_prefix)
+                .and_then(|id| id.parse::<i64>().ok())
+            {
+                let exists: bool = tx.query_row(
+                    "SELECT EXISTS(SELECT 1 FROM proxy_usage_records WHERE id = ?1)",
+                    [local_id],
+                    |row| row.get(0),
+                )?;
+                if exists {
+                    continue;
+                }
+            }
+
+            // 来源标识有唯一索引，已导入过的记录被忽略
+            summary.usage_records += tx.execute(
+                "INSERT OR IGNORE INTO proxy_usage_records (
+                    session_id, tool_id, profile_name, model,
+                    input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
+                    status_code, created_at, attempts, key_label, origin
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                rusqlite::params![
+                    record.session_id,
+                    record.tool_id,
+                    record.profile_name,
+                    record.model,
+                    record.input_tokens,
+                    record.output_tokens,
+                    record.cache_read_tokens,
+                    record.cache_creation_tokens,
+                    record.status_code,
+                    record.created_at,
+                    record.attempts.max(1),
+                    record.key_label,
+                    origin,
+                ],
+            )?;
+        }
+
+        for (turn, compressed) in &transcripts {
+            if !transcript::contains_turn(tx, turn)? {
+                transcript::insert_turn(tx, turn, compressed)?;
+                summary.transcript_turns += 1;
+            }
+        }
+
+        Ok(summary)
+    })?;
+
+    Ok(summary)
+}
+
+/// 格式化为本地时间
+fn format_time(timestamp: i64) -> String {
+    chrono::Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// 转义表格单元格中的竖线与换行
+fn escape_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn longest_backtick_run(content: &str) -> usize {
+    content.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_bundle() -> SessionBundle {
+        let session_id = "codex_session_abc".to_string();
+        SessionBundle {
+            exported_at: 1_700_000_100,
+            sessions: vec![ProxySession {
+                session_id: session_id.clone(),
+                display_id: "abc".to_string(),
+                tool_id: "codex".to_string(),
+                config_name: "custom".to_string(),
+                custom_profile_name: Some("work".to_string()),
+                url: "https://api.example.com".to_string(),
+                api_key: "sk-secret".to_string(),
+                note: Some("迁移 | 修复".to_string()),
+                key_label: None,
+                first_seen_at: 1_700_000_000,
+                last_seen_at: 1_700_000_050,
+                request_count: 3,
+                created_at: 1_700_000_000,
+                updated_at: 1_700_000_050,
+            }],
+            usage: vec![UsageRecord {
+                session_id: Some(session_id.clone()),
+                tool_id: "codex".to_string(),
+                model: Some("gpt-5-codex".to_string()),
+                input_tokens: 100,
+                output_tokens: 20,
+                status_code: 200,
+                attempts: 1,
+                created_at: 1_700_000_010,
+                ..Default::default()
+            }],
+            transcripts: vec![
+                TranscriptTurn {
+                    id: 1,
+                    session_id: session_id.clone(),
+                    tool_id: "codex".to_string(),
+                    role: TranscriptRole::User,
+                    content: "fix the migration\n\n# not a heading".to_string(),
+                    truncated: false,
+                    created_at: 1_700_000_010,
+                },
+                TranscriptTurn {
+                    id: 2,
+                    session_id,
+                    tool_id: "codex".to_string(),
+                    role: TranscriptRole::ToolCall,
+                    content: "shell {\"command\":\"echo ```\"}".to_string(),
+                    truncated: true,
+                    created_at: 1_700_000_011,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_jsonl_roundtrip_redacts_api_key() {
+        let bundle = sample_bundle();
+        let mut buffer = Vec::new();
+        bundle.write_jsonl(&mut buffer).unwrap();
+
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(!text.contains("sk-secret"));
+        assert!(text.starts_with(r#"{"type":"header","format":"duckcoding-sessions""#));
+        assert_eq!(text.lines().count(), 5);
+
+        let loaded = SessionBundle::read_jsonl(buffer.as_slice()).unwrap();
+        assert_eq!(loaded.exported_at, bundle.exported_at);
+        assert_eq!(loaded.sessions.len(), 1);
+        assert_eq!(loaded.sessions[0].api_key, "");
+        assert_eq!(
+            loaded.sessions[0].custom_profile_name.as_deref(),
+            Some("work")
+        );
+        assert_eq!(loaded.usage, bundle.usage);
+        assert_eq!(loaded.transcripts, bundle.transcripts);
+    }
+
+    #[test]
+    fn test_read_jsonl_rejects_invalid_bundles() {
+        assert!(SessionBundle::read_jsonl("".as_bytes()).is_err());
+        assert!(SessionBundle::read_jsonl(
+            r#"{"type":"header","format":"other","version":1,"exported_at":0}"#.as_bytes()
+        )
+        .is_err());
+        assert!(SessionBundle::read_jsonl(
+            r#"{"type":"header","format":"duckcoding-sessions","version":99,"exported_at":0}"#
+                .as_bytes()
+        )
+        .is_err());
+
+        let err = SessionBundle::read_jsonl(
+            "{\"type\":\"header\",\"format\":\"duckcoding-sessions\",\"version\":1,\"exported_at\":0}\n{oops}"
+                .as_bytes(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("第 2 行"));
+    }
+
+    #[test]
+    fn test_markdown_export() {
+        let mut buffer = Vec::new();
+        sample_bundle().write_markdown(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+
+        assert!(text.contains("## abc（codex）"));
+        assert!(text.contains("| 备注 | 迁移 \\| 修复 |"));
+        assert!(text.contains("| 配置 | work |"));
+        assert!(text.contains("合计：输入 100，输出 20"));
+        // 对话内容在引用块中，工具调用的围栏长于内容中的反引号
+        assert!(text.contains("> fix the migration\n>\n> # not a heading"));
+        assert!(text.contains("````\nshell {\"command\":\"echo ```\"}\n````"));
+        assert!(text.contains("（已截断）"));
+        assert!(!text.contains("sk-secret"));
+    }
+}
@@ -3,12 +3,12 @@
 //! 提供 QueryRow ↔ ProxySession 转换逻辑，用于 SessionManager 与 DataManager 的适配层。
 
 use crate::data::managers::sqlite::QueryRow;
-use crate::services::session::models::ProxySession;
+use crate::services::session::models::{ProxySession, SessionEndpoint, UsageRecord, UsageSummary};
 use anyhow::{anyhow, Context, Result};
 
 /// 标准会话查询的 SQL 语句
 ///
-/// **字段顺序（共 13 个）：**
+/// **字段顺序（共 14 个）：**
 /// 1. session_id
 /// 2. display_id
 /// 3. tool_id
@@ -22,10 +22,18 @@ use anyhow::{anyhow, Context, Result};
 /// 11. request_count
 /// 12. created_at
 /// 13. updated_at
+/// 14. key_label
 pub const SELECT_SESSION_FIELDS: &str = "session_id, display_id, tool_id, config_name, \
                                           custom_profile_name, url, api_key, note, \
                                           first_seen_at, last_seen_at, request_count, \
-                                          created_at, updated_at";
+                                          created_at, updated_at, key_label";
+
+/// 清除已绑定 Profile 的会话中复制保存的 URL 与 Key（改为请求时按名称解析）
+pub const CLEAR_BOUND_CREDENTIALS_SQL: &str = "
+UPDATE claude_proxy_sessions SET url = '', api_key = ''
+WHERE config_name = 'custom' AND custom_profile_name IS NOT NULL AND custom_profile_name != ''
+  AND (url != '' OR api_key != '');
+";
 
 /// 创建表的 SQL 语句
 pub const CREATE_TABLE_SQL: &str = "
@@ -42,7 +50,8 @@ CREATE TABLE IF NOT EXISTS claude_proxy_sessions (
     last_seen_at INTEGER NOT NULL,
     request_count INTEGER NOT NULL DEFAULT 0,
     created_at INTEGER NOT NULL,
-    updated_at INTEGER NOT NULL
+    updated_at INTEGER NOT NULL,
+    key_label TEXT
 );
 
 CREATE INDEX IF NOT EXISTS idx_tool_id ON claude_proxy_sessions(tool_id);
@@ -56,6 +65,62 @@ ALTER TABLE claude_proxy_sessions ADD COLUMN custom_profile_name TEXT;
 ALTER TABLE claude_proxy_sessions ADD COLUMN note TEXT;
 ";
 
+/// 用量记录表（与会话表同库，按 session_id 关联）
+pub const CREATE_USAGE_TABLE_SQL: &str = "
+CREATE TABLE IF NOT EXISTS proxy_usage_records (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT,
+    tool_id TEXT NOT NULL,
+    profile_name TEXT,
+    model TEXT,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+    cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+    status_code INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    key_label TEXT,
+    origin TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_usage_session_id ON proxy_usage_records(session_id);
+CREATE INDEX IF NOT EXISTS idx_usage_tool_id ON proxy_usage_records(tool_id);
+CREATE INDEX IF NOT EXISTS idx_usage_created_at ON proxy_usage_records(created_at);
+";
+
+/// 兼容旧用量记录表的字段添加语句（单独执行，字段已存在时忽略错误）
+pub const ALTER_USAGE_TABLE_SQL: &str =
+    "ALTER TABLE proxy_usage_records ADD COLUMN attempts INTEGER NOT NULL DEFAULT 1;";
+
+/// 兼容旧数据库的本地 Key 标签字段及索引（逐条执行，字段已存在时忽略错误）
+pub const ALTER_KEY_LABEL_SQL: [&str; 3] = [
+    "ALTER TABLE claude_proxy_sessions ADD COLUMN key_label TEXT;",
+    "ALTER TABLE proxy_usage_records ADD COLUMN key_label TEXT;",
+    "CREATE INDEX IF NOT EXISTS idx_usage_key_label ON proxy_usage_records(key_label);",
+];
+
+/// 兼容旧数据库的用量记录来源字段及唯一索引（逐条执行，字段已存在时忽略错误）
+///
+/// 导入的记录保存导出时的来源标识，本机记录为 NULL
+pub const ALTER_USAGE_ORIGIN_SQL: [&str; 2] = [
+    "ALTER TABLE proxy_usage_records ADD COLUMN origin TEXT;",
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_usage_origin ON proxy_usage_records(origin);",
+];
+
+/// 用量记录查询的 SQL 字段（共 14 个，顺序与 `parse_usage_record` 对应）
+pub const SELECT_USAGE_FIELDS: &str = "id, session_id, tool_id, profile_name, model, \
+                                        input_tokens, output_tokens, cache_read_tokens, \
+                                        cache_creation_tokens, status_code, created_at, \
+                                        attempts, key_label, origin";
+
+/// 用量汇总查询的 SQL 字段（共 6 个，顺序与 `parse_usage_summary` 对应）
+pub const SELECT_USAGE_SUMMARY_FIELDS: &str = "COUNT(*), COALESCE(SUM(input_tokens), 0), \
+                                                COALESCE(SUM(output_tokens), 0), \
+                                                COALESCE(SUM(cache_read_tokens), 0), \
+                                                COALESCE(SUM(cache_creation_tokens), 0), \
+                                                COALESCE(SUM(attempts - 1), 0)";
+
 /// 从 QueryRow 解析为 ProxySession
 ///
 /// # 参数
@@ -72,10 +137,11 @@ ALTER TABLE claude_proxy_sessions ADD COLUMN note TEXT;
 /// - values[0..7]: 字符串字段
 /// - values[7]: note (可为 NULL)
 /// - values[8..12]: 整数字段
+/// - values[13]: key_label (可为 NULL)
 pub fn parse_proxy_session(row: &QueryRow) -> Result<ProxySession> {
-    if row.values.len() != 13 {
+    if row.values.len() != 14 {
         return Err(anyhow!(
-            "Invalid row: expected 13 columns, got {}",
+            "Invalid row: expected 14 columns, got {}",
             row.values.len()
         ));
     }
@@ -118,6 +184,7 @@ pub fn parse_proxy_session(row: &QueryRow) -> Result<ProxySession> {
         request_count: get_i32(10).context("request_count")?,
         created_at: get_i64(11).context("created_at")?,
         updated_at: get_i64(12).context("updated_at")?,
+        key_label: get_optional_string(13),
     })
 }
 
@@ -144,13 +211,16 @@ pub fn parse_count(row: &QueryRow) -> Result<usize> {
         .map(|v| v as usize)
 }
 
-/// 从 QueryRow 提取三元组配置 (config_name, url, api_key)
+/// 从 QueryRow 解析会话端点 (config_name, custom_profile_name, url, api_key)
 ///
-/// 用于 `get_session_config()` 方法的结果解析
-pub fn parse_session_config(row: &QueryRow) -> Result<(String, String, String)> {
-    if row.values.len() != 3 {
+/// 用于 `get_session_config()` 方法的结果解析：
+/// - custom 且绑定了 Profile：按名称引用 Profile
+/// - custom 且未绑定 Profile、URL 与 Key 均非空：旧版直接保存的端点
+/// - 其余情况：全局配置
+pub fn parse_session_config(row: &QueryRow) -> Result<SessionEndpoint> {
+    if row.values.len() != 4 {
         return Err(anyhow!(
-            "Invalid config row: expected 3 columns, got {}",
+            "Invalid config row: expected 4 columns, got {}",
             row.values.len()
         ));
     }
@@ -160,23 +230,104 @@ pub fn parse_session_config(row: &QueryRow) -> Result<(String, String, String)>
         .ok_or_else(|| anyhow!("config_name is not a string"))?
         .to_string();
 
-    let url = row.values[1]
+    let profile_name = row.values[1]
+        .as_str()
+        .filter(|name| !name.is_empty())
+        .map(str::to_string);
+
+    let url = row.values[2]
         .as_str()
         .ok_or_else(|| anyhow!("url is not a string"))?
         .to_string();
 
-    let api_key = row.values[2]
+    let api_key = row.values[3]
         .as_str()
         .ok_or_else(|| anyhow!("api_key is not a string"))?
         .to_string();
 
-    Ok((config_name, url, api_key))
+    if config_name != "custom" {
+        return Ok(SessionEndpoint::Global);
+    }
+
+    Ok(match profile_name {
+        Some(name) => SessionEndpoint::Profile(name),
+        None if !url.is_empty() && !api_key.is_empty() => SessionEndpoint::Custom { url, api_key },
+        None => SessionEndpoint::Global,
+    })
+}
+
+/// 从 QueryRow 解析为 UsageRecord
+///
+/// 依赖 `SELECT_USAGE_FIELDS` 定义的顺序
+pub fn parse_usage_record(row: &QueryRow) -> Result<UsageRecord> {
+    if row.values.len() != 14 {
+        return Err(anyhow!(
+            "Invalid usage row: expected 14 columns, got {}",
+            row.values.len()
+        ));
+    }
+
+    let get_optional_string =
+        |idx: usize| -> Option<String> { row.values[idx].as_str().map(|s| s.to_string()) };
+
+    let get_i64 = |idx: usize| -> Result<i64> {
+        row.values[idx]
+            .as_i64()
+            .ok_or_else(|| anyhow!("Column {} is not an integer", idx))
+    };
+
+    Ok(UsageRecord {
+        id: get_i64(0).context("id")?,
+        session_id: get_optional_string(1),
+        tool_id: row.values[2]
+            .as_str()
+            .ok_or_else(|| anyhow!("tool_id is not a string"))?
+            .to_string(),
+        profile_name: get_optional_string(3),
+        model: get_optional_string(4),
+        input_tokens: get_i64(5).context("input_tokens")?,
+        output_tokens: get_i64(6).context("output_tokens")?,
+        cache_read_tokens: get_i64(7).context("cache_read_tokens")?,
+        cache_creation_tokens: get_i64(8).context("cache_creation_tokens")?,
+        status_code: get_i64(9).context("status_code")? as u16,
+        created_at: get_i64(10).context("created_at")?,
+        attempts: get_i64(11).context("attempts")?,
+        key_label: get_optional_string(12),
+        origin: get_optional_string(13),
+    })
+}
+
+/// 从 QueryRow 解析为 UsageSummary
+///
+/// 依赖 `SELECT_USAGE_SUMMARY_FIELDS` 定义的顺序
+pub fn parse_usage_summary(row: &QueryRow) -> Result<UsageSummary> {
+    if row.values.len() != 6 {
+        return Err(anyhow!(
+            "Invalid usage summary row: expected 6 columns, got {}",
+            row.values.len()
+        ));
+    }
+
+    let get_i64 = |idx: usize| -> Result<i64> {
+        row.values[idx]
+            .as_i64()
+            .ok_or_else(|| anyhow!("Column {} is not an integer", idx))
+    };
+
+    Ok(UsageSummary {
+        request_count: get_i64(0).context("request_count")?,
+        input_tokens: get_i64(1).context("input_tokens")?,
+        output_tokens: get_i64(2).context("output_tokens")?,
+        cache_read_tokens: get_i64(3).context("cache_read_tokens")?,
+        cache_creation_tokens: get_i64(4).context("cache_creation_tokens")?,
+        retry_count: get_i64(5).context("retry_count")?,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json::json;
+    use serde_json::{json, Value};
 
     #[test]
     fn test_parse_proxy_session_full() {
@@ -195,6 +346,7 @@ mod tests {
                 "request_count".to_string(),
                 "created_at".to_string(),
                 "updated_at".to_string(),
+                "key_label".to_string(),
             ],
             values: vec![
                 json!("test_session_1"),
@@ -210,6 +362,7 @@ mod tests {
                 json!(5),
                 json!(1000),
                 json!(2000),
+                json!("alice"),
             ],
         };
 
@@ -228,6 +381,7 @@ mod tests {
         assert_eq!(session.request_count, 5);
         assert_eq!(session.created_at, 1000);
         assert_eq!(session.updated_at, 2000);
+        assert_eq!(session.key_label, Some("alice".to_string()));
     }
 
     #[test]
@@ -247,6 +401,7 @@ mod tests {
                 "request_count".to_string(),
                 "created_at".to_string(),
                 "updated_at".to_string(),
+                "key_label".to_string(),
             ],
             values: vec![
                 json!("test_session_2"),
@@ -262,6 +417,7 @@ mod tests {
                 json!(10),
                 json!(3000),
                 json!(4000),
+                json!(null), // key_label
             ],
         };
 
@@ -271,6 +427,7 @@ mod tests {
         assert_eq!(session.config_name, "global");
         assert_eq!(session.custom_profile_name, None);
         assert_eq!(session.note, None);
+        assert_eq!(session.key_label, None);
         assert_eq!(session.request_count, 10);
     }
 
@@ -285,26 +442,41 @@ mod tests {
         assert_eq!(count, 42);
     }
 
-    #[test]
-    fn test_parse_session_config() {
-        let row = QueryRow {
+    fn config_row(config_name: &str, profile: Value, url: &str, api_key: &str) -> QueryRow {
+        QueryRow {
             columns: vec![
                 "config_name".to_string(),
+                "custom_profile_name".to_string(),
                 "url".to_string(),
                 "api_key".to_string(),
             ],
-            values: vec![
-                json!("custom"),
-                json!("https://api.test.com"),
-                json!("sk-xxx"),
-            ],
-        };
-
-        let (config_name, url, api_key) = parse_session_config(&row).unwrap();
+            values: vec![json!(config_name), profile, json!(url), json!(api_key)],
+        }
+    }
 
-        assert_eq!(config_name, "custom");
-        assert_eq!(url, "https://api.test.com");
-        assert_eq!(api_key, "sk-xxx");
+    #[test]
+    fn test_parse_session_config() {
+        // 旧版自定义会话：直接保存的端点
+        let row = config_row("custom", Value::Null, "https://api.test.com", "sk-xxx");
+        assert_eq!(
+            parse_session_config(&row).unwrap(),
+            SessionEndpoint::Custom {
+                url: "https://api.test.com".to_string(),
+                api_key: "sk-xxx".to_string(),
+            }
+        );
+
+        // 绑定 Profile 时忽略残留的 URL 与 Key
+        let row = config_row("custom", json!("work"), "https://stale.com", "sk-old");
+        assert_eq!(
+            parse_session_config(&row).unwrap(),
+            SessionEndpoint::Profile("work".to_string())
+        );
+
+        let row = config_row("custom", json!(""), "", "");
+        assert_eq!(parse_session_config(&row).unwrap(), SessionEndpoint::Global);
+        let row = config_row("global", json!("work"), "", "");
+        assert_eq!(parse_session_config(&row).unwrap(), SessionEndpoint::Global);
     }
 
     #[test]
@@ -319,6 +491,45 @@ mod tests {
         assert!(result
             .unwrap_err()
             .to_string()
-            .contains("expected 13 columns"));
+            .contains("expected 14 columns"));
+    }
+
+    #[test]
+    fn test_parse_usage_record() {
+        let row = QueryRow {
+            columns: SELECT_USAGE_FIELDS
+                .split(',')
+                .map(|c| c.trim().to_string())
+                .collect(),
+            values: vec![
+                json!(7),
+                json!("test_session_1"),
+                json!("claude-code"),
+                json!(null),
+                json!("claude-sonnet-4-5"),
+                json!(100),
+                json!(50),
+                json!(1000),
+                json!(0),
+                json!(200),
+                json!(1700000000),
+                json!(3),
+                json!("alice"),
+                json!(null),
+            ],
+        };
+
+        let record = parse_usage_record(&row).unwrap();
+
+        assert_eq!(record.id, 7);
+        assert_eq!(record.session_id, Some("test_session_1".to_string()));
+        assert_eq!(record.profile_name, None);
+        assert_eq!(record.model, Some("claude-sonnet-4-5".to_string()));
+        assert_eq!(record.input_tokens, 100);
+        assert_eq!(record.cache_read_tokens, 1000);
+        assert_eq!(record.status_code, 200);
+        assert_eq!(record.attempts, 3);
+        assert_eq!(record.key_label, Some("alice".to_string()));
+        assert_eq!(record.origin, None);
     }
 }
@@ -1,14 +1,27 @@
 // SessionManager 单例 - 会话管理核心模块
 
+use crate::data::managers::sqlite::QueryRow;
 use crate::data::DataManager;
+use crate::models::config::SessionRetentionConfig;
+use crate::services::session::bundle::{self, ImportSummary, SessionBundle};
 use crate::services::session::db_utils::{
-    parse_count, parse_proxy_session, parse_session_config, ALTER_TABLE_SQL, CREATE_TABLE_SQL,
-    SELECT_SESSION_FIELDS,
+    parse_count, parse_proxy_session, parse_session_config, parse_usage_record,
+    parse_usage_summary, ALTER_KEY_LABEL_SQL, ALTER_TABLE_SQL, ALTER_USAGE_ORIGIN_SQL,
+    ALTER_USAGE_TABLE_SQL, CLEAR_BOUND_CREDENTIALS_SQL, CREATE_TABLE_SQL, CREATE_USAGE_TABLE_SQL,
+    SELECT_SESSION_FIELDS, SELECT_USAGE_FIELDS, SELECT_USAGE_SUMMARY_FIELDS,
 };
-use crate::services::session::models::{ProxySession, SessionEvent, SessionListResponse};
-use anyhow::Result;
+use crate::services::session::models::{
+    ProxySession, SessionEndpoint, SessionEvent, SessionListResponse, TranscriptSearchHit,
+    TranscriptTurn, UsageRecord, UsageSummary,
+};
+use crate::services::session::retention::{self, RetentionReport};
+use crate::services::session::transcript::{
+    self, ALTER_TRANSCRIPT_HASH_SQL, CREATE_TRANSCRIPT_TABLE_SQL,
+};
+use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
 use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -42,9 +55,17 @@ impl SessionManager {
         // 初始化数据库表结构
         let db = manager_instance.sqlite(&db_path)?;
         db.execute_raw(CREATE_TABLE_SQL)?;
+        db.execute_raw(CREATE_USAGE_TABLE_SQL)?;
+        db.execute_raw(CREATE_TRANSCRIPT_TABLE_SQL)?;
 
         // 兼容旧数据库（忽略错误）
         let _ = db.execute_raw(ALTER_TABLE_SQL);
+        let _ = db.execute_raw(ALTER_USAGE_TABLE_SQL);
+        for sql in ALTER_KEY_LABEL_SQL.iter().chain(&ALTER_USAGE_ORIGIN_SQL) {
+            let _ = db.execute_raw(sql);
+        }
+        let _ = db.execute_raw(ALTER_TRANSCRIPT_HASH_SQL);
+        let _ = db.execute_raw(CLEAR_BOUND_CREDENTIALS_SQL);
 
         // 创建事件队列
         let (event_sender, event_receiver) = mpsc::unbounded_channel();
@@ -76,6 +97,7 @@ impl SessionManager {
         // 批量写入任务
         tokio::spawn(async move {
             let mut buffer: Vec<SessionEvent> = Vec::new();
+            let mut transcript_totals = transcript::TranscriptTotals::default();
             let mut tick_interval = interval(Duration::from_millis(100));
 
             loop {
@@ -83,7 +105,7 @@ impl SessionManager {
                     _ = CANCELLATION_TOKEN.cancelled() => {
                         // 应用关闭，刷盘缓冲区
                         if !buffer.is_empty() {
-                            Self::flush_events(&manager, &db_path, &mut buffer);
+                            Self::flush_events(&manager, &db_path, &mut buffer, &mut transcript_totals);
                             tracing::info!("Session 事件已刷盘: {} 条", buffer.len());
                         }
                         tracing::info!("Session 批量写入任务已停止");
@@ -95,20 +117,20 @@ impl SessionManager {
 
                         // 如果缓冲区达到 10 条，立即写入
                         if buffer.len() >= 10 {
-                            Self::flush_events(&manager, &db_path, &mut buffer);
+                            Self::flush_events(&manager, &db_path, &mut buffer, &mut transcript_totals);
                         }
                     }
                     // 每 100ms 刷新一次
                     _ = tick_interval.tick() => {
                         if !buffer.is_empty() {
-                            Self::flush_events(&manager, &db_path, &mut buffer);
+                            Self::flush_events(&manager, &db_path, &mut buffer, &mut transcript_totals);
                         }
                     }
                 }
             }
         });
 
-        // 定期清理任务（每 1 小时）
+        // 定期清理任务（每 1 小时，每次按最新的保留策略执行）
         let manager_clone = self.manager.clone();
         let db_path_clone = self.db_path.clone();
         tokio::spawn(async move {
@@ -121,15 +143,26 @@ impl SessionManager {
                         break;
                     }
                     _ = cleanup_interval.tick() => {
-                        // 清理三个工具的过期会话
-                        for tool_id in &["claude-code", "codex", "gemini-cli"] {
-                            let _ = Self::cleanup_old_sessions_internal(
-                                &manager_clone,
-                                &db_path_clone,
-                                tool_id,
-                                1000,
-                                30,
-                            );
+                        let policy = Self::retention_policy();
+                        let result = Self::apply_retention_internal(
+                            manager_clone.clone(),
+                            db_path_clone.clone(),
+                            policy,
+                            false,
+                        )
+                        .await;
+                        match result {
+                            Ok(report) if !report.session_ids.is_empty() || report.usage_records > 0 => {
+                                tracing::info!(
+                                    sessions = report.session_ids.len(),
+                                    transcript_turns = report.transcript_turns,
+                                    usage_records = report.usage_records,
+                                    vacuumed = report.vacuumed,
+                                    "已按保留策略清理会话"
+                                );
+                            }
+                            Ok(_) => {}
+                            Err(e) => tracing::warn!("会话清理失败: {}", e),
                         }
                     }
                 }
@@ -138,7 +171,12 @@ impl SessionManager {
     }
 
     /// 批量写入事件到数据库
-    fn flush_events(manager: &Arc<DataManager>, db_path: &Path, buffer: &mut Vec<SessionEvent>) {
+    fn flush_events(
+        manager: &Arc<DataManager>,
+        db_path: &Path,
+        buffer: &mut Vec<SessionEvent>,
+        transcript_totals: &mut transcript::TranscriptTotals,
+    ) {
         for event in buffer.drain(..) {
             match event {
                 SessionEvent::NewRequest {
@@ -165,51 +203,99 @@ impl SessionManager {
                         }
                     }
                 }
+                SessionEvent::KeyLabel {
+                    session_id,
+                    key_label,
+                } => {
+                    if let Ok(db) = manager.sqlite(db_path) {
+                        let _ = db.execute(
+                            "UPDATE claude_proxy_sessions SET key_label = ? WHERE session_id = ?",
+                            &[&key_label, &session_id],
+                        );
+                    }
+                }
+                SessionEvent::RequestUsage(record) => {
+                    if let Ok(db) = manager.sqlite(db_path) {
+                        if let Err(e) = Self::insert_usage_record(&db, &record) {
+                            tracing::warn!("写入用量记录失败: {}", e);
+                        }
+                    }
+                }
+                SessionEvent::Transcript {
+                    turns,
+                    max_total_bytes,
+                } => {
+                    let Some(tool_id) = turns.first().map(|turn| turn.tool_id.clone()) else {
+                        continue;
+                    };
+                    if let Ok(db) = manager.sqlite(db_path) {
+                        let result = transcript::insert_turns(&db, &turns).and_then(|stored| {
+                            transcript_totals.add(&db, &tool_id, stored, max_total_bytes)
+                        });
+                        if let Err(e) = result {
+                            tracing::warn!("写入对话记录失败: {}", e);
+                        }
+                    }
+                }
             }
         }
     }
 
-    /// 内部清理方法（用于后台任务）
-    fn cleanup_old_sessions_internal(
-        manager: &Arc<DataManager>,
-        db_path: &Path,
-        tool_id: &str,
-        max_count: usize,
-        max_age_days: i64,
-    ) -> Result<usize> {
-        let db = manager.sqlite(db_path)?;
-        let now = chrono::Utc::now().timestamp();
-        let cutoff_time = now - (max_age_days * 24 * 3600);
-
-        // 1. 删除超过 30 天的会话
-        let deleted_by_age = db.execute(
-            "DELETE FROM claude_proxy_sessions WHERE tool_id = ? AND last_seen_at < ?",
-            &[tool_id, &cutoff_time.to_string()],
+    /// 写入单条用量记录
+    fn insert_usage_record(
+        db: &crate::data::managers::SqliteManager,
+        record: &UsageRecord,
+    ) -> Result<()> {
+        db.execute(
+            "INSERT INTO proxy_usage_records (
+                session_id, tool_id, profile_name, model,
+                input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
+                status_code, created_at, attempts, key_label
+            ) VALUES (NULLIF(?1, ''), ?2, NULLIF(?3, ''), NULLIF(?4, ''), ?5, ?6, ?7, ?8, ?9, ?10, ?11,
+                      NULLIF(?12, ''))",
+            &[
+                record.session_id.as_deref().unwrap_or(""),
+                &record.tool_id,
+                record.profile_name.as_deref().unwrap_or(""),
+                record.model.as_deref().unwrap_or(""),
+                &record.input_tokens.to_string(),
+                &record.output_tokens.to_string(),
+                &record.cache_read_tokens.to_string(),
+                &record.cache_creation_tokens.to_string(),
+                &record.status_code.to_string(),
+                &record.created_at.to_string(),
+                &record.attempts.max(1).to_string(),
+                record.key_label.as_deref().unwrap_or(""),
+            ],
         )?;
+        Ok(())
+    }
 
-        // 2. 如果超过 1000 条，删除最旧的会话
-        let count_rows = db.query(
-            "SELECT COUNT(*) FROM claude_proxy_sessions WHERE tool_id = ?",
-            &[tool_id],
-        )?;
-        let current_count = parse_count(&count_rows[0])?;
-
-        let deleted_by_count = if current_count > max_count {
-            let to_delete = current_count - max_count;
-            db.execute(
-                "DELETE FROM claude_proxy_sessions WHERE session_id IN (
-                    SELECT session_id FROM claude_proxy_sessions
-                    WHERE tool_id = ?
-                    ORDER BY last_seen_at ASC
-                    LIMIT ?
-                )",
-                &[tool_id, &to_delete.to_string()],
-            )?
-        } else {
-            0
-        };
+    /// 读取全局配置中的会话保留策略（未配置时使用默认策略）
+    pub fn retention_policy() -> SessionRetentionConfig {
+        match crate::utils::config::read_global_config() {
+            Ok(Some(config)) => config.session_retention,
+            Ok(None) => SessionRetentionConfig::default(),
+            Err(e) => {
+                tracing::warn!("读取会话保留策略失败，使用默认策略: {}", e);
+                SessionRetentionConfig::default()
+            }
+        }
+    }
 
-        Ok(deleted_by_age + deleted_by_count)
+    /// 按保留策略清理会话（在阻塞线程池中执行，删除与 VACUUM 期间不占用异步运行时线程）
+    async fn apply_retention_internal(
+        manager: Arc<DataManager>,
+        db_path: PathBuf,
+        policy: SessionRetentionConfig,
+        dry_run: bool,
+    ) -> Result<RetentionReport> {
+        tokio::task::spawn_blocking(move || {
+            let db = manager.sqlite(&db_path)?;
+            retention::apply(&db, &policy, chrono::Utc::now().timestamp(), dry_run)
+        })
+        .await
+        .unwrap_or_else(|e| Err(anyhow!(e)))
     }
 
     /// 发送会话事件（公共 API）
@@ -268,6 +354,7 @@ impl SessionManager {
             "DELETE FROM claude_proxy_sessions WHERE session_id = ?",
             &[session_id],
         )?;
+        transcript::delete_where(&db, "session_id = ?", &[session_id])?;
         Ok(())
     }
 
@@ -278,6 +365,7 @@ impl SessionManager {
             "DELETE FROM claude_proxy_sessions WHERE tool_id = ?",
             &[tool_id],
         )?;
+        transcript::delete_where(&db, "tool_id = ?", &[tool_id])?;
         Ok(())
     }
 
@@ -297,12 +385,12 @@ impl SessionManager {
         }
     }
 
-    /// 获取会话配置（公共 API，用于请求处理）
-    /// 返回 (config_name, url, api_key)
-    pub fn get_session_config(&self, session_id: &str) -> Result<Option<(String, String, String)>> {
+    /// 获取会话端点配置（公共 API，用于请求处理）
+    pub fn get_session_config(&self, session_id: &str) -> Result<Option<SessionEndpoint>> {
         let db = self.manager.sqlite(&self.db_path)?;
         let rows = db.query(
-            "SELECT config_name, url, api_key FROM claude_proxy_sessions WHERE session_id = ?",
+            "SELECT config_name, custom_profile_name, url, api_key
+             FROM claude_proxy_sessions WHERE session_id = ?",
             &[session_id],
         )?;
 
@@ -314,26 +402,24 @@ impl SessionManager {
     }
 
     /// 更新会话配置（公共 API）
+    ///
+    /// 会话只记录绑定的 Profile 名称，不复制 URL 与 Key，请求时再通过 ProfileManager 解析
     pub fn update_session_config(
         &self,
         session_id: &str,
         config_name: &str,
         custom_profile_name: Option<&str>,
-        url: &str,
-        api_key: &str,
     ) -> Result<()> {
         let db = self.manager.sqlite(&self.db_path)?;
         let now = chrono::Utc::now().timestamp();
 
         db.execute(
             "UPDATE claude_proxy_sessions
-             SET config_name = ?, custom_profile_name = ?, url = ?, api_key = ?, updated_at = ?
+             SET config_name = ?, custom_profile_name = ?, url = '', api_key = '', updated_at = ?
              WHERE session_id = ?",
             &[
                 config_name,
                 custom_profile_name.unwrap_or(""),
-                url,
-                api_key,
                 &now.to_string(),
                 session_id,
             ],
@@ -342,6 +428,195 @@ impl SessionManager {
         Ok(())
     }
 
+    /// 获取会话的用量明细（公共 API，按时间升序）
+    pub fn get_session_usage_records(&self, session_id: &str) -> Result<Vec<UsageRecord>> {
+        let db = self.manager.sqlite(&self.db_path)?;
+        let sql = format!(
+            "SELECT {} FROM proxy_usage_records WHERE session_id = ? ORDER BY created_at ASC, id ASC",
+            SELECT_USAGE_FIELDS
+        );
+        let rows = db.query(&sql, &[session_id])?;
+        rows.iter().map(parse_usage_record).collect()
+    }
+
+    /// 获取会话的用量汇总（公共 API）
+    pub fn get_session_usage_summary(&self, session_id: &str) -> Result<UsageSummary> {
+        let db = self.manager.sqlite(&self.db_path)?;
+        let sql = format!(
+            "SELECT {} FROM proxy_usage_records WHERE session_id = ?",
+            SELECT_USAGE_SUMMARY_FIELDS
+        );
+        let rows = db.query(&sql, &[session_id])?;
+        parse_usage_summary(&rows[0])
+    }
+
+    /// 查询时间范围内的用量明细（公共 API，按时间降序）
+    ///
+    /// `tool_id` 为 None 时返回所有工具的记录
+    pub fn get_usage_records(
+        &self,
+        tool_id: Option<&str>,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<UsageRecord>> {
+        let db = self.manager.sqlite(&self.db_path)?;
+        let sql = format!(
+            "SELECT {} FROM proxy_usage_records
+             WHERE (?1 = '' OR tool_id = ?1) AND created_at >= ?2 AND created_at < ?3
+             ORDER BY created_at DESC, id DESC",
+            SELECT_USAGE_FIELDS
+        );
+        let rows = db.query(
+            &sql,
+            &[
+                tool_id.unwrap_or(""),
+                &start_time.to_string(),
+                &end_time.to_string(),
+            ],
+        )?;
+        rows.iter().map(parse_usage_record).collect()
+    }
+
+    /// 查询本地 Key 累计消耗的 Token（输入 + 输出 + 缓存，用于配额校验）
+    ///
+    /// 只统计本机记录，导入的记录不计入
+    pub fn get_key_token_usage(&self, tool_id: &str, key_label: &str) -> Result<u64> {
+        let db = self.manager.sqlite(&self.db_path)?;
+        let rows = db.query(
+            "SELECT COALESCE(SUM(input_tokens + output_tokens + cache_read_tokens + cache_creation_tokens), 0)
+             FROM proxy_usage_records WHERE tool_id = ? AND key_label = ? AND origin IS NULL",
+            &[tool_id, key_label],
+        )?;
+        Ok(parse_count(&rows[0])? as u64)
+    }
+
+    /// 按本地 Key 标签汇总用量（公共 API，未使用签发 Key 的请求不计入）
+    pub fn get_usage_summary_by_key(
+        &self,
+        tool_id: &str,
+    ) -> Result<BTreeMap<String, UsageSummary>> {
+        let db = self.manager.sqlite(&self.db_path)?;
+        let sql = format!(
+            "SELECT key_label, {} FROM proxy_usage_records
+             WHERE tool_id = ? AND key_label IS NOT NULL
+             GROUP BY key_label",
+            SELECT_USAGE_SUMMARY_FIELDS
+        );
+        let rows = db.query(&sql, &[tool_id])?;
+        rows.iter()
+            .map(|row| {
+                let label = row.values[0]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("key_label is not a string"))?
+                    .to_string();
+                let summary_row = QueryRow {
+                    columns: row.columns[1..].to_vec(),
+                    values: row.values[1..].to_vec(),
+                };
+                Ok((label, parse_usage_summary(&summary_row)?))
+            })
+            .collect()
+    }
+
+    /// 获取会话的对话记录（公共 API，按时间升序）
+    pub fn get_session_transcript(&self, session_id: &str) -> Result<Vec<TranscriptTurn>> {
+        let db = self.manager.sqlite(&self.db_path)?;
+        transcript::load_session(&db, session_id)
+    }
+
+    /// 全文搜索对话记录（公共 API，按相关度排序）
+    ///
+    /// `tool_id` 为 None 时搜索所有工具的记录
+    pub fn search_transcripts(
+        &self,
+        query: &str,
+        tool_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<TranscriptSearchHit>> {
+        let db = self.manager.sqlite(&self.db_path)?;
+        transcript::search(&db, query, tool_id, limit)
+    }
+
+    /// 按保留策略清理会话（公共 API，`dry_run` 为 true 时只返回将要清理的会话）
+    pub async fn apply_retention(
+        &self,
+        policy: SessionRetentionConfig,
+        dry_run: bool,
+    ) -> Result<RetentionReport> {
+        Self::apply_retention_internal(self.manager.clone(), self.db_path.clone(), policy, dry_run)
+            .await
+    }
+
+    /// 导出会话及其用量明细与对话记录（公共 API）
+    ///
+    /// `session_ids` 为 None 时按 `tool_id` 批量导出，`tool_id` 也为 None 时导出全部会话
+    pub fn export_bundle(
+        &self,
+        session_ids: Option<&[String]>,
+        tool_id: Option<&str>,
+    ) -> Result<SessionBundle> {
+        let db = self.manager.sqlite(&self.db_path)?;
+
+        let sessions = match session_ids {
+            Some(ids) => {
+                let sql = format!(
+                    "SELECT {} FROM claude_proxy_sessions WHERE session_id = ?",
+                    SELECT_SESSION_FIELDS
+                );
+                let mut sessions = Vec::with_capacity(ids.len());
+                for id in ids {
+                    if let Some(row) = db.query(&sql, &[id.as_str()])?.first() {
+                        sessions.push(parse_proxy_session(row)?);
+                    }
+                }
+                sessions
+            }
+            None => {
+                let sql = format!(
+                    "SELECT {} FROM claude_proxy_sessions
+                     WHERE (?1 = '' OR tool_id = ?1) ORDER BY last_seen_at DESC",
+                    SELECT_SESSION_FIELDS
+                );
+                db.query(&sql, &[tool_id.unwrap_or("")])?
+                    .iter()
+                    .map(parse_proxy_session)
+                    .collect::<Result<Vec<_>>>()?
+            }
+        };
+
+        let usage_sql = format!(
+            "SELECT {} FROM proxy_usage_records WHERE session_id = ? ORDER BY created_at ASC, id ASC",
+            SELECT_USAGE_FIELDS
+        );
+        let instance_id = db.transaction(|tx| Ok(bundle::instance_id(tx)?))?;
+        let mut usage = Vec::new();
+        let mut transcripts = Vec::new();
+        for session in &sessions {
+            for row in db.query(&usage_sql, &[session.session_id.as_str()])? {
+                let mut record = parse_usage_record(&row)?;
+                // 本机记录写入来源标识，导入过的记录保留原标识
+                if record.origin.is_none() {
+                    record.origin = Some(format!("{}:{}", instance_id, record.id));
+                }
+                usage.push(record);
+            }
+            transcripts.extend(transcript::load_session(&db, &session.session_id)?);
+        }
+
+        Ok(SessionBundle {
+            exported_at: chrono::Utc::now().timestamp(),
+            sessions,
+            usage,
+            transcripts,
+        })
+    }
+
+    /// 导入会话包并合并到本机数据库（公共 API，重复导入不会产生重复记录）
+    pub fn import_bundle(&self, bundle: &SessionBundle) -> Result<ImportSummary> {
+        let db = self.manager.sqlite(&self.db_path)?;
+        bundle::import(&db, bundle)
+    }
+
     /// 更新会话备注（公共 API）
     pub fn update_session_note(&self, session_id: &str, note: Option<&str>) -> Result<()> {
         let db = self.manager.sqlite(&self.db_path)?;
@@ -370,6 +645,7 @@ pub fn shutdown_session_manager() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::session::models::TranscriptRole;
     use serial_test::serial;
     use tempfile::TempDir;
 
@@ -381,7 +657,14 @@ mod tests {
         // 初始化数据库
         let db = manager_instance.sqlite(&db_path).unwrap();
         db.execute_raw(CREATE_TABLE_SQL).unwrap();
+        db.execute_raw(CREATE_USAGE_TABLE_SQL).unwrap();
+        db.execute_raw(CREATE_TRANSCRIPT_TABLE_SQL).unwrap();
         let _ = db.execute_raw(ALTER_TABLE_SQL);
+        let _ = db.execute_raw(ALTER_USAGE_TABLE_SQL);
+        for sql in ALTER_KEY_LABEL_SQL.iter().chain(&ALTER_USAGE_ORIGIN_SQL) {
+            let _ = db.execute_raw(sql);
+        }
+        let _ = db.execute_raw(ALTER_TRANSCRIPT_HASH_SQL);
 
         let (event_sender, event_receiver) = mpsc::unbounded_channel();
 
@@ -482,20 +765,285 @@ mod tests {
 
         // 更新配置
         manager
-            .update_session_config(
-                "test_session_update",
-                "custom",
-                Some("my-profile"),
-                "https://api.test.com",
-                "sk-test",
-            )
+            .update_session_config("test_session_update", "custom", Some("my-profile"))
             .unwrap();
 
-        // 验证更新
+        // 验证更新：只记录 Profile 名称，不复制凭证
         let session = manager.get_session("test_session_update").unwrap().unwrap();
         assert_eq!(session.config_name, "custom");
         assert_eq!(session.custom_profile_name, Some("my-profile".to_string()));
-        assert_eq!(session.url, "https://api.test.com");
-        assert_eq!(session.api_key, "sk-test");
+        assert_eq!(session.url, "");
+        assert_eq!(session.api_key, "");
+        assert_eq!(
+            manager.get_session_config("test_session_update").unwrap(),
+            Some(SessionEndpoint::Profile("my-profile".to_string()))
+        );
+
+        manager
+            .update_session_config("test_session_update", "global", None)
+            .unwrap();
+        assert_eq!(
+            manager.get_session_config("test_session_update").unwrap(),
+            Some(SessionEndpoint::Global)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usage_record_roundtrip() {
+        let temp = TempDir::new().expect("create temp dir");
+        let manager = create_test_manager(&temp);
+
+        let now = chrono::Utc::now().timestamp();
+        for output_tokens in [10, 20] {
+            manager
+                .send_event(SessionEvent::RequestUsage(UsageRecord {
+                    session_id: Some("usage_session".to_string()),
+                    tool_id: "claude-code".to_string(),
+                    model: Some("claude-sonnet-4-5".to_string()),
+                    input_tokens: 100,
+                    output_tokens,
+                    cache_read_tokens: 5,
+                    status_code: 200,
+                    created_at: now,
+                    ..Default::default()
+                }))
+                .unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let records = manager.get_session_usage_records("usage_session").unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].profile_name, None);
+
+        let summary = manager.get_session_usage_summary("usage_session").unwrap();
+        assert_eq!(summary.request_count, 2);
+        assert_eq!(summary.input_tokens, 200);
+        assert_eq!(summary.output_tokens, 30);
+        assert_eq!(summary.cache_read_tokens, 10);
+
+        let by_tool = manager
+            .get_usage_records(Some("codex"), now - 10, now + 10)
+            .unwrap();
+        assert!(by_tool.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_key_label_usage() {
+        let temp = TempDir::new().expect("create temp dir");
+        let manager = create_test_manager(&temp);
+
+        let now = chrono::Utc::now().timestamp();
+        let session_id = "user_x_account__session_key-label-1".to_string();
+        manager
+            .send_event(SessionEvent::NewRequest {
+                session_id: session_id.clone(),
+                tool_id: "claude-code".to_string(),
+                timestamp: now,
+            })
+            .unwrap();
+        manager
+            .send_event(SessionEvent::KeyLabel {
+                session_id: session_id.clone(),
+                key_label: "alice".to_string(),
+            })
+            .unwrap();
+        for key_label in [Some("alice"), Some("alice"), None] {
+            manager
+                .send_event(SessionEvent::RequestUsage(UsageRecord {
+                    session_id: Some(session_id.clone()),
+                    tool_id: "claude-code".to_string(),
+                    input_tokens: 100,
+                    output_tokens: 20,
+                    cache_read_tokens: 5,
+                    status_code: 200,
+                    created_at: now,
+                    key_label: key_label.map(str::to_string),
+                    ..Default::default()
+                }))
+                .unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let session = manager.get_session(&session_id).unwrap().unwrap();
+        assert_eq!(session.key_label.as_deref(), Some("alice"));
+        assert_eq!(
+            manager.get_key_token_usage("claude-code", "alice").unwrap(),
+            250
+        );
+        assert_eq!(manager.get_key_token_usage("codex", "alice").unwrap(), 0);
+
+        let by_key = manager.get_usage_summary_by_key("claude-code").unwrap();
+        assert_eq!(by_key.len(), 1);
+        assert_eq!(by_key["alice"].request_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_transcript_roundtrip() {
+        let temp = TempDir::new().expect("create temp dir");
+        let manager = create_test_manager(&temp);
+
+        let now = chrono::Utc::now().timestamp();
+        let session_id = "codex_session_transcript-1".to_string();
+        manager
+            .send_event(SessionEvent::NewRequest {
+                session_id: session_id.clone(),
+                tool_id: "codex".to_string(),
+                timestamp: now,
+            })
+            .unwrap();
+        let turn = |role, content: &str| TranscriptTurn {
+            id: 0,
+            session_id: session_id.clone(),
+            tool_id: "codex".to_string(),
+            role,
+            content: content.to_string(),
+            truncated: false,
+            created_at: now,
+        };
+        manager
+            .send_event(SessionEvent::Transcript {
+                turns: vec![
+                    turn(TranscriptRole::User, "fix the migration bug"),
+                    turn(TranscriptRole::Assistant, "The migration now runs once."),
+                ],
+                max_total_bytes: 1024 * 1024,
+            })
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let turns = manager.get_session_transcript(&session_id).unwrap();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[1].role, TranscriptRole::Assistant);
+
+        let hits = manager
+            .search_transcripts("migration", Some("codex"), 10)
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].display_id.as_deref(), Some("transcript-1"));
+
+        manager.delete_session(&session_id).unwrap();
+        assert!(manager
+            .get_session_transcript(&session_id)
+            .unwrap()
+            .is_empty());
+        assert!(manager
+            .search_transcripts("migration", None, 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_import_bundle() {
+        let source_dir = TempDir::new().expect("create temp dir");
+        let source = create_test_manager(&source_dir);
+        let target_dir = TempDir::new().expect("create temp dir");
+        let target = create_test_manager(&target_dir);
+
+        let now = chrono::Utc::now().timestamp();
+        let session_id = "codex_session_bundle-1".to_string();
+        source
+            .send_event(SessionEvent::NewRequest {
+                session_id: session_id.clone(),
+                tool_id: "codex".to_string(),
+                timestamp: now,
+            })
+            .unwrap();
+        // 同一秒内两次相同的请求是两条独立记录
+        for _ in 0..2 {
+            source
+                .send_event(SessionEvent::RequestUsage(UsageRecord {
+                    session_id: Some(session_id.clone()),
+                    tool_id: "codex".to_string(),
+                    input_tokens: 100,
+                    output_tokens: 20,
+                    status_code: 200,
+                    created_at: now,
+                    ..Default::default()
+                }))
+                .unwrap();
+        }
+        source
+            .send_event(SessionEvent::Transcript {
+                turns: vec![TranscriptTurn {
+                    id: 0,
+                    session_id: session_id.clone(),
+                    tool_id: "codex".to_string(),
+                    role: TranscriptRole::User,
+                    content: "rename the bundle module".to_string(),
+                    truncated: false,
+                    created_at: now,
+                }],
+                max_total_bytes: 1024 * 1024,
+            })
+            .unwrap();
+        // 目标机器上已有同一会话（较早开始、已有备注）
+        target
+            .send_event(SessionEvent::NewRequest {
+                session_id: session_id.clone(),
+                tool_id: "codex".to_string(),
+                timestamp: now - 60,
+            })
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        target
+            .update_session_note(&session_id, Some("local note"))
+            .unwrap();
+
+        let bundle = source.export_bundle(None, Some("codex")).unwrap();
+        assert_eq!(bundle.sessions.len(), 1);
+        assert_eq!(bundle.usage.len(), 2);
+        assert!(bundle.usage.iter().all(|r| r.origin.is_some()));
+        assert_eq!(bundle.transcripts.len(), 1);
+        assert!(source
+            .export_bundle(None, Some("claude-code"))
+            .unwrap()
+            .sessions
+            .is_empty());
+
+        let summary = target.import_bundle(&bundle).unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                sessions_added: 0,
+                sessions_merged: 1,
+                usage_records: 2,
+                transcript_turns: 1,
+            }
+        );
+
+        // 重复导入只合并会话，不产生重复记录
+        let summary = target.import_bundle(&bundle).unwrap();
+        assert_eq!(summary.sessions_merged, 1);
+        assert_eq!(summary.usage_records, 0);
+        assert_eq!(summary.transcript_turns, 0);
+
+        let session = target.get_session(&session_id).unwrap().unwrap();
+        assert_eq!(session.first_seen_at, now - 60);
+        assert_eq!(session.last_seen_at, now);
+        assert_eq!(session.note.as_deref(), Some("local note"));
+        assert_eq!(
+            target.get_session_usage_records(&session_id).unwrap().len(),
+            2
+        );
+
+        // 导回来源机器时跳过本机仍保留的记录
+        assert_eq!(source.import_bundle(&bundle).unwrap().usage_records, 0);
+
+        // 从导入后的机器再次导出，来源标识保持不变
+        let reexported = target
+            .export_bundle(Some(std::slice::from_ref(&session_id)), None)
+            .unwrap();
+        assert_eq!(source.import_bundle(&reexported).unwrap().usage_records, 0);
+        assert_eq!(
+            target
+                .search_transcripts("bundle", Some("codex"), 10)
+                .unwrap()
+                .len(),
+            1
+        );
     }
 }
@@ -1,8 +1,16 @@
 // 会话管理服务模块
 
+pub mod bundle;
 mod db_utils;
 pub mod manager;
 pub mod models;
+pub mod retention;
+mod transcript;
 
+pub use bundle::{ExportFormat, ImportSummary, SessionBundle};
 pub use manager::SESSION_MANAGER;
-pub use models::{ProxySession, SessionEvent, SessionListResponse};
+pub use models::{
+    ProxySession, SessionEndpoint, SessionEvent, SessionListResponse, SessionUsageResponse,
+    TranscriptRole, TranscriptSearchHit, TranscriptTurn, UsageRecord, UsageSummary,
+};
+pub use retention::RetentionReport;
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 /// 代理会话记录（数据库模型）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxySession {
-    /// 完整会话ID（主键，来自 metadata.user_id）
+    /// 完整会话ID（主键，Claude Code 为 metadata.user_id，其他工具为 `<tool_id>_session_<id>`）
     pub session_id: String,
     /// 显示ID（_session_ 后的 UUID 部分）
     pub display_id: String,
@@ -13,14 +13,17 @@ pub struct ProxySession {
     pub tool_id: String,
     /// 配置名称（"global" 或 "custom"）
     pub config_name: String,
-    /// 自定义配置名称（config_name 为 custom 时记录）
+    /// 绑定的 Profile 名称（config_name 为 custom 时记录，请求时按名称解析凭证）
     pub custom_profile_name: Option<String>,
-    /// API Base URL（当 config_name 为 custom 时使用）
+    /// API Base URL（仅未绑定 Profile 的旧版自定义会话使用）
     pub url: String,
-    /// API Key（当 config_name 为 custom 时使用）
+    /// API Key（仅未绑定 Profile 的旧版自定义会话使用）
     pub api_key: String,
     /// 会话备注
     pub note: Option<String>,
+    /// 发起会话的本地 Key 标签（使用签发的 Key 时记录）
+    #[serde(default)]
+    pub key_label: Option<String>,
     /// 首次记录时间（Unix 时间戳，秒）
     pub first_seen_at: i64,
     /// 最后活跃时间（Unix 时间戳，秒）
@@ -33,6 +36,54 @@ pub struct ProxySession {
     pub updated_at: i64,
 }
 
+/// 会话级端点配置（由 `get_session_config()` 返回）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEndpoint {
+    /// 使用代理的全局配置
+    Global,
+    /// 绑定 Profile，请求时通过 ProfileManager 解析凭证
+    Profile(String),
+    /// 旧版自定义会话直接保存的端点（未绑定 Profile）
+    Custom { url: String, api_key: String },
+}
+
+/// 单次请求的用量记录（数据库模型）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UsageRecord {
+    /// 自增主键（写入前为 0）
+    #[serde(default)]
+    pub id: i64,
+    /// 关联的会话ID（无法识别会话时为空）
+    pub session_id: Option<String>,
+    /// 工具ID（"claude-code", "codex", "gemini-cli"）
+    pub tool_id: String,
+    /// 实际使用的 Profile 名称
+    pub profile_name: Option<String>,
+    /// 模型名称（优先取响应中的模型，缺失时取请求中的模型）
+    pub model: Option<String>,
+    /// 输入 Token（不含缓存命中部分）
+    pub input_tokens: i64,
+    /// 输出 Token
+    pub output_tokens: i64,
+    /// 缓存读取 Token
+    pub cache_read_tokens: i64,
+    /// 缓存写入 Token
+    pub cache_creation_tokens: i64,
+    /// 上游响应状态码
+    pub status_code: u16,
+    /// 发送到上游的次数（含重试与故障转移，1 表示一次成功）
+    #[serde(default)]
+    pub attempts: i64,
+    /// 请求使用的本地 Key 标签（使用签发的 Key 时记录）
+    #[serde(default)]
+    pub key_label: Option<String>,
+    /// 来源标识（导出时写入 `<实例 ID>:<记录 ID>`，导入时据此去重；本机记录为空）
+    #[serde(default)]
+    pub origin: Option<String>,
+    /// 记录时间（Unix 时间戳，秒）
+    pub created_at: i64,
+}
+
 /// 会话事件（异步队列传递）
 #[derive(Debug, Clone)]
 pub enum SessionEvent {
@@ -42,6 +93,106 @@ pub enum SessionEvent {
         tool_id: String,
         timestamp: i64,
     },
+    /// 请求用量事件（响应结束后发送）
+    RequestUsage(UsageRecord),
+    /// 记录会话使用的本地 Key 标签（在 NewRequest 之后发送）
+    KeyLabel {
+        session_id: String,
+        key_label: String,
+    },
+    /// 会话对话记录（响应结束后发送，写入后按工具存储上限淘汰最旧的记录）
+    Transcript {
+        turns: Vec<TranscriptTurn>,
+        max_total_bytes: u64,
+    },
+}
+
+/// 对话记录类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptRole {
+    /// 用户输入
+    User,
+    /// 助手回复文本
+    Assistant,
+    /// 助手发起的工具调用（`<工具名> <参数>`）
+    ToolCall,
+}
+
+impl TranscriptRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::ToolCall => "tool_call",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "user" => Some(Self::User),
+            "assistant" => Some(Self::Assistant),
+            "tool_call" => Some(Self::ToolCall),
+            _ => None,
+        }
+    }
+}
+
+/// 会话对话记录中的一条（数据库中压缩存储）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranscriptTurn {
+    /// 自增主键（写入前为 0）
+    #[serde(default)]
+    pub id: i64,
+    pub session_id: String,
+    pub tool_id: String,
+    pub role: TranscriptRole,
+    pub content: String,
+    /// 内容是否因超出单条上限被截断
+    #[serde(default)]
+    pub truncated: bool,
+    /// 记录时间（Unix 时间戳，秒）
+    pub created_at: i64,
+}
+
+/// 对话记录搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranscriptSearchHit {
+    pub session_id: String,
+    /// 会话显示ID（会话已被清理时为空）
+    pub display_id: Option<String>,
+    pub tool_id: String,
+    /// 会话备注
+    pub note: Option<String>,
+    /// 命中的记录 ID
+    pub turn_id: i64,
+    pub role: TranscriptRole,
+    /// 命中位置附近的内容片段
+    pub snippet: String,
+    pub created_at: i64,
+}
+
+/// 用量汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UsageSummary {
+    /// 请求次数
+    pub request_count: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    /// 重试总次数（各请求 attempts - 1 之和，用于发现不稳定的中转）
+    #[serde(default)]
+    pub retry_count: i64,
+}
+
+/// 会话用量响应
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionUsageResponse {
+    /// 用量明细（按时间升序）
+    pub records: Vec<UsageRecord>,
+    /// 用量汇总
+    pub summary: UsageSummary,
 }
 
 /// 会话列表响应
@@ -0,0 +1,333 @@
+//! 会话保留策略
+//!
+//! 按 [`SessionRetentionConfig`] 选出需要清理的会话：最后活跃时间超过保留天数，
+//! 或超出工具的会话数量上限（保留最近活跃的会话）。开启 `keep_annotated` 时，
+//! 有备注或自定义端点配置的会话不会被清理，也不计入数量上限。
+//!
+//! 清理会话时一并删除其对话记录；用量明细按记录时间单独清理（本地 Key 的用量用于额度统计，
+//! 始终保留）。清理后空闲页占比较高时执行 `VACUUM` 回收空间。
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::data::managers::SqliteManager;
+use crate::models::config::SessionRetentionConfig;
+use crate::services::session::transcript;
+
+/// 空闲页占总页数的比例达到该百分比时执行 VACUUM
+const VACUUM_FREE_PERCENT: i64 = 25;
+/// 空闲页少于该数量时不执行 VACUUM（避免小数据库频繁整理）
+const VACUUM_MIN_FREE_PAGES: i64 = 1024;
+
+/// 超过保留天数的用量明细（参数：?1 截止时间）
+const EXPIRED_USAGE_CONDITION: &str = "created_at < CAST(?1 AS INTEGER) AND key_label IS NULL";
+
+/// 待清理会话（CTE，参数：?1 截止时间、?2 数量上限、?3 是否保留有备注或自定义配置的会话）
+const EXPIRED_SESSIONS_CTE: &str = "
+WITH candidates AS (
+    SELECT session_id, tool_id, last_seen_at,
+        CAST(?3 AS INTEGER) != 0
+            AND (COALESCE(note, '') != '' OR config_name != 'global') AS protected
+    FROM claude_proxy_sessions
+),
+ranked AS (
+    SELECT session_id, tool_id, last_seen_at, protected,
+        ROW_NUMBER() OVER (
+            PARTITION BY tool_id, protected ORDER BY last_seen_at DESC, session_id
+        ) AS position
+    FROM candidates
+),
+expired AS (
+    SELECT session_id, tool_id FROM ranked
+    WHERE NOT protected
+      AND (last_seen_at < CAST(?1 AS INTEGER)
+           OR (CAST(?2 AS INTEGER) > 0 AND position > CAST(?2 AS INTEGER)))
+)";
+
+/// 清理结果（试运行时为将要清理的内容）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// 是否为试运行（未实际删除）
+    pub dry_run: bool,
+    /// 清理的会话 ID
+    pub session_ids: Vec<String>,
+    /// 按工具统计的清理会话数
+    pub by_tool: BTreeMap<String, usize>,
+    /// 随会话一并清理的对话记录数
+    pub transcript_turns: usize,
+    /// 超过保留天数的用量明细数
+    pub usage_records: usize,
+    /// 是否执行了 VACUUM
+    pub vacuumed: bool,
+}
+
+/// 按保留策略清理会话，`dry_run` 为 true 时只统计不删除
+pub fn apply(
+    db: &SqliteManager,
+    policy: &SessionRetentionConfig,
+    now: i64,
+    dry_run: bool,
+) -> Result<RetentionReport> {
+    let cutoff = if policy.max_age_days > 0 {
+        now - i64::from(policy.max_age_days) * 24 * 3600
+    } else {
+        i64::MIN
+    };
+    let params = [
+        cutoff.to_string(),
+        policy.max_sessions_per_tool.to_string(),
+        u8::from(policy.keep_annotated).to_string(),
+    ];
+    let params: Vec<&str> = params.iter().map(String::as_str).collect();
+
+    let expired: Vec<(String, String)> = db.query_map(
+        &format!(
+            "{EXPIRED_SESSIONS_CTE}
+             SELECT session_id, tool_id FROM expired ORDER BY tool_id, session_id"
+        ),
+        &params,
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let mut report = RetentionReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    if !expired.is_empty() {
+        let turns: Vec<i64> = db.query_map(
+            &format!(
+                "{EXPIRED_SESSIONS_CTE}
+                 SELECT COUNT(*) FROM session_transcripts
+                 WHERE session_id IN (SELECT session_id FROM expired)"
+            ),
+            &params,
+            |row| row.get(0),
+        )?;
+        report.transcript_turns = turns.first().copied().unwrap_or(0) as usize;
+    }
+    for (session_id, tool_id) in expired {
+        *report.by_tool.entry(tool_id).or_default() += 1;
+        report.session_ids.push(session_id);
+    }
+
+    let usage_cutoff = (policy.usage_max_age_days > 0)
+        .then(|| (now - i64::from(policy.usage_max_age_days) * 24 * 3600).to_string());
+    if let Some(usage_cutoff) = &usage_cutoff {
+        let count: Vec<i64> = db.query_map(
+            &format!("SELECT COUNT(*) FROM proxy_usage_records WHERE {EXPIRED_USAGE_CONDITION}"),
+            &[usage_cutoff.as_str()],
+            |row| row.get(0),
+        )?;
+        report.usage_records = count.first().copied().unwrap_or(0) as usize;
+    }
+
+    if dry_run || (report.session_ids.is_empty() && report.usage_records == 0) {
+        return Ok(report);
+    }
+
+    if !report.session_ids.is_empty() {
+        db.execute(
+            &format!(
+                "{EXPIRED_SESSIONS_CTE}
+                 DELETE FROM claude_proxy_sessions
+                 WHERE session_id IN (SELECT session_id FROM expired)"
+            ),
+            &params,
+        )?;
+        transcript::delete_orphans(db)?;
+    }
+    if let Some(usage_cutoff) = &usage_cutoff {
+        db.execute(
+            &format!("DELETE FROM proxy_usage_records WHERE {EXPIRED_USAGE_CONDITION}"),
+            &[usage_cutoff.as_str()],
+        )?;
+    }
+
+    let pragma = |name: &str| -> Result<i64> {
+        let values: Vec<i64> = db.query_map(&format!("PRAGMA {name}"), &[], |row| row.get(0))?;
+        Ok(values.first().copied().unwrap_or(0))
+    };
+    if should_vacuum(pragma("freelist_count")?, pragma("page_count")?) {
+        db.execute_raw("VACUUM")?;
+        report.vacuumed = true;
+    }
+
+    Ok(report)
+}
+
+/// 按空闲页数量与占比判断是否需要 VACUUM
+fn should_vacuum(free_pages: i64, page_count: i64) -> bool {
+    free_pages >= VACUUM_MIN_FREE_PAGES && free_pages * 100 >= page_count * VACUUM_FREE_PERCENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::session::db_utils::{CREATE_TABLE_SQL, CREATE_USAGE_TABLE_SQL};
+    use crate::services::session::models::{TranscriptRole, TranscriptTurn};
+    use crate::services::session::transcript::CREATE_TRANSCRIPT_TABLE_SQL;
+
+    const DAY: i64 = 24 * 3600;
+    const NOW: i64 = 1_700_000_000;
+
+    fn setup() -> SqliteManager {
+        let db = SqliteManager::default();
+        db.execute_raw(CREATE_TABLE_SQL).unwrap();
+        db.execute_raw(CREATE_TRANSCRIPT_TABLE_SQL).unwrap();
+        db.execute_raw(CREATE_USAGE_TABLE_SQL).unwrap();
+        db
+    }
+
+    fn insert_usage(db: &SqliteManager, session_id: &str, age_days: i64, key_label: Option<&str>) {
+        let created_at = (NOW - age_days * DAY).to_string();
+        db.execute(
+            "INSERT INTO proxy_usage_records (session_id, tool_id, created_at, key_label)
+             VALUES (?1, 'codex', ?2, NULLIF(?3, ''))",
+            &[session_id, &created_at, key_label.unwrap_or_default()],
+        )
+        .unwrap();
+    }
+
+    fn usage_count(db: &SqliteManager) -> i64 {
+        db.query_map("SELECT COUNT(*) FROM proxy_usage_records", &[], |row| {
+            row.get(0)
+        })
+        .unwrap()[0]
+    }
+
+    fn insert_session(db: &SqliteManager, id: &str, tool_id: &str, age_days: i64, note: &str) {
+        let last_seen = (NOW - age_days * DAY).to_string();
+        db.execute(
+            "INSERT INTO claude_proxy_sessions (
+                session_id, display_id, tool_id, config_name, url, api_key, note,
+                first_seen_at, last_seen_at, request_count, created_at, updated_at
+            ) VALUES (?1, ?1, ?2, 'global', '', '', ?3, ?4, ?4, 1, ?4, ?4)",
+            &[id, tool_id, note, &last_seen],
+        )
+        .unwrap();
+    }
+
+    fn session_count(db: &SqliteManager) -> i64 {
+        db.query_map("SELECT COUNT(*) FROM claude_proxy_sessions", &[], |row| {
+            row.get(0)
+        })
+        .unwrap()[0]
+    }
+
+    #[test]
+    fn test_retention_by_age_and_count() {
+        let db = setup();
+        insert_session(&db, "old", "codex", 40, "");
+        insert_session(&db, "old_with_note", "codex", 40, "keep me");
+        for (index, id) in ["c1", "c2", "c3"].iter().enumerate() {
+            insert_session(&db, id, "codex", index as i64, "");
+        }
+        insert_session(&db, "claude", "claude-code", 1, "");
+        transcript::insert_turns(
+            &db,
+            &[TranscriptTurn {
+                id: 0,
+                session_id: "old".to_string(),
+                tool_id: "codex".to_string(),
+                role: TranscriptRole::User,
+                content: "hello".to_string(),
+                truncated: false,
+                created_at: NOW - 40 * DAY,
+            }],
+        )
+        .unwrap();
+
+        let policy = SessionRetentionConfig {
+            max_age_days: 30,
+            max_sessions_per_tool: 2,
+            keep_annotated: true,
+            usage_max_age_days: 0,
+        };
+
+        // 试运行不删除
+        let preview = apply(&db, &policy, NOW, true).unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(preview.session_ids, vec!["c3", "old"]);
+        assert_eq!(preview.by_tool.get("codex"), Some(&2));
+        assert_eq!(preview.transcript_turns, 1);
+        assert_eq!(session_count(&db), 6);
+
+        let report = apply(&db, &policy, NOW, false).unwrap();
+        assert_eq!(report.session_ids, preview.session_ids);
+        assert!(!report.vacuumed);
+        assert_eq!(session_count(&db), 4);
+        assert!(transcript::load_session(&db, "old").unwrap().is_empty());
+
+        // 不保留有备注的会话时，备注会话同样按规则清理
+        let report = apply(
+            &db,
+            &SessionRetentionConfig {
+                keep_annotated: false,
+                ..policy
+            },
+            NOW,
+            false,
+        )
+        .unwrap();
+        assert_eq!(report.session_ids, vec!["old_with_note"]);
+    }
+
+    #[test]
+    fn test_retention_disabled_limits() {
+        let db = setup();
+        insert_session(&db, "ancient", "codex", 400, "");
+        insert_session(&db, "recent", "codex", 0, "");
+
+        insert_usage(&db, "ancient", 400, None);
+
+        let policy = SessionRetentionConfig {
+            max_age_days: 0,
+            max_sessions_per_tool: 0,
+            keep_annotated: true,
+            usage_max_age_days: 0,
+        };
+        let report = apply(&db, &policy, NOW, false).unwrap();
+        assert!(report.session_ids.is_empty());
+        assert_eq!(report.usage_records, 0);
+        assert_eq!(session_count(&db), 2);
+        assert_eq!(usage_count(&db), 1);
+    }
+
+    #[test]
+    fn test_usage_retention_keeps_key_usage() {
+        let db = setup();
+        insert_session(&db, "recent", "codex", 0, "");
+        insert_usage(&db, "recent", 200, None);
+        insert_usage(&db, "recent", 200, Some("team-a"));
+        insert_usage(&db, "recent", 10, None);
+
+        let policy = SessionRetentionConfig {
+            usage_max_age_days: 90,
+            ..Default::default()
+        };
+        let preview = apply(&db, &policy, NOW, true).unwrap();
+        assert_eq!(preview.usage_records, 1);
+        assert_eq!(usage_count(&db), 3);
+
+        let report = apply(&db, &policy, NOW, false).unwrap();
+        assert!(report.session_ids.is_empty());
+        assert_eq!(report.usage_records, 1);
+        assert_eq!(usage_count(&db), 2);
+    }
+
+    #[test]
+    fn test_should_vacuum() {
+        assert!(!should_vacuum(10, 20));
+        assert!(!should_vacuum(
+            VACUUM_MIN_FREE_PAGES,
+            VACUUM_MIN_FREE_PAGES * 10
+        ));
+        assert!(should_vacuum(
+            VACUUM_MIN_FREE_PAGES,
+            VACUUM_MIN_FREE_PAGES * 4
+        ));
+    }
+}
@@ -0,0 +1,572 @@
+//! 会话对话记录存储
+//!
+//! 对话内容以 deflate 压缩后存入 `session_transcripts`（同时记录原文的 SHA-256 摘要，
+//! 用于导入时去重），全文索引使用 FTS5 无内容表
+//! `session_transcripts_fts`（只保存索引，不重复保存原文），两张表通过 rowid 关联。
+//!
+//! unicode61 分词器会把连续的中日韩文字视为一个词，写入索引和搜索前会在这些字符之间插入空格，
+//! 使中文按单字索引、按短语匹配。
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use anyhow::{Context, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use rusqlite::{params_from_iter, Transaction};
+use sha2::{Digest, Sha256};
+
+use crate::data::managers::SqliteManager;
+use crate::services::session::models::{TranscriptRole, TranscriptSearchHit, TranscriptTurn};
+
+/// 创建对话记录表与全文索引
+pub const CREATE_TRANSCRIPT_TABLE_SQL: &str = "
+CREATE TABLE IF NOT EXISTS session_transcripts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    tool_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    content BLOB NOT NULL,
+    content_bytes INTEGER NOT NULL,
+    stored_bytes INTEGER NOT NULL,
+    truncated INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    content_hash TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_transcripts_session ON session_transcripts(session_id, id);
+CREATE INDEX IF NOT EXISTS idx_transcripts_tool ON session_transcripts(tool_id, id);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS session_transcripts_fts USING fts5(
+    content,
+    content = '',
+    contentless_delete = 1,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+";
+
+/// 兼容旧数据库：补充原文摘要列（旧记录为 NULL）
+pub const ALTER_TRANSCRIPT_HASH_SQL: &str =
+    "ALTER TABLE session_transcripts ADD COLUMN content_hash TEXT";
+
+/// 搜索结果片段中命中位置前后保留的字符数
+const SNIPPET_CONTEXT: usize = 60;
+
+/// 写入对话记录（同一事务内写入原文与索引），返回写入的压缩后字节数
+pub fn insert_turns(db: &SqliteManager, turns: &[TranscriptTurn]) -> Result<u64> {
+    let prepared = turns
+        .iter()
+        .map(|turn| Ok((turn, compress(&turn.content)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let stored_bytes = prepared.iter().map(|(_, c)| c.len() as u64).sum();
+
+    db.transaction(|tx| {
+        for (turn, compressed) in &prepared {
+            insert_turn(tx, turn, compressed)?;
+        }
+        Ok(())
+    })?;
+    Ok(stored_bytes)
+}
+
+/// 在事务内写入一条已压缩的对话记录及其索引
+pub fn insert_turn(
+    tx: &Transaction,
+    turn: &TranscriptTurn,
+    compressed: &[u8],
+) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO session_transcripts (
+            session_id, tool_id, role, content, content_bytes, stored_bytes,
+            truncated, created_at, content_hash
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        rusqlite::params![
+            turn.session_id,
+            turn.tool_id,
+            turn.role.as_str(),
+            compressed,
+            turn.content.len() as i64,
+            compressed.len() as i64,
+            turn.truncated,
+            turn.created_at,
+            content_hash(&turn.content),
+        ],
+    )?;
+    tx.execute(
+        "INSERT INTO session_transcripts_fts (rowid, content) VALUES (?1, ?2)",
+        rusqlite::params![tx.last_insert_rowid(), segment(&turn.content)],
+    )?;
+    Ok(())
+}
+
+/// 是否已存在相同的对话记录（会话、角色、时间与原文均相同）
+///
+/// 比较原文摘要；旧记录没有摘要时解压后比较原文
+pub fn contains_turn(tx: &Transaction, turn: &TranscriptTurn) -> rusqlite::Result<bool> {
+    let mut stmt = tx.prepare_cached(
+        "SELECT content_hash, content FROM session_transcripts
+         WHERE session_id = ?1 AND role = ?2 AND created_at = ?3 AND content_bytes = ?4",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![
+        turn.session_id,
+        turn.role.as_str(),
+        turn.created_at,
+        turn.content.len() as i64,
+    ])?;
+
+    let hash = content_hash(&turn.content);
+    while let Some(row) = rows.next()? {
+        let same = match row.get::<_, Option<String>>(0)? {
+            Some(stored) => stored == hash,
+            None => decompress(&row.get::<_, Vec<u8>>(1)?).is_ok_and(|c| c == turn.content),
+        };
+        if same {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// 按条件删除对话记录（同时删除索引），返回删除的记录数
+///
+/// `condition` 为作用于 `session_transcripts` 的 WHERE 子句
+pub fn delete_where(db: &SqliteManager, condition: &str, params: &[&str]) -> Result<usize> {
+    let deleted = db.transaction(|tx| {
+        tx.execute(
+            &format!(
+                "DELETE FROM session_transcripts_fts WHERE rowid IN (
+                    SELECT id FROM session_transcripts WHERE {condition}
+                )"
+            ),
+            params_from_iter(params.iter()),
+        )?;
+        Ok(tx.execute(
+            &format!("DELETE FROM session_transcripts WHERE {condition}"),
+            params_from_iter(params.iter()),
+        )?)
+    })?;
+    Ok(deleted)
+}
+
+/// 删除已不存在的会话的对话记录
+pub fn delete_orphans(db: &SqliteManager) -> Result<usize> {
+    delete_where(
+        db,
+        "session_id NOT IN (SELECT session_id FROM claude_proxy_sessions)",
+        &[],
+    )
+}
+
+/// 工具对话记录的压缩后总大小
+pub fn total_bytes(db: &SqliteManager, tool_id: &str) -> Result<u64> {
+    let total: Vec<i64> = db.query_map(
+        "SELECT COALESCE(SUM(stored_bytes), 0) FROM session_transcripts WHERE tool_id = ?1",
+        &[tool_id],
+        |row| row.get(0),
+    )?;
+    Ok(total.first().copied().unwrap_or(0).max(0) as u64)
+}
+
+/// 各工具对话记录大小的累计值
+///
+/// 首次写入时从数据库读取总大小，之后按写入量累加，只有累计值超出上限时才执行清理；
+/// 清理后重新读取，以纳入其他途径（保留策略、删除、导入）带来的变化
+#[derive(Debug, Default)]
+pub struct TranscriptTotals {
+    bytes: HashMap<String, u64>,
+}
+
+impl TranscriptTotals {
+    /// 记录新写入的字节数，必要时清理最旧的记录，返回删除的记录数
+    pub fn add(
+        &mut self,
+        db: &SqliteManager,
+        tool_id: &str,
+        stored_bytes: u64,
+        max_total_bytes: u64,
+    ) -> Result<usize> {
+        let total = match self.bytes.get_mut(tool_id) {
+            Some(total) => {
+                *total += stored_bytes;
+                *total
+            }
+            None => {
+                let total = total_bytes(db, tool_id)?;
+                self.bytes.insert(tool_id.to_string(), total);
+                total
+            }
+        };
+        if total <= max_total_bytes {
+            return Ok(0);
+        }
+
+        let deleted = enforce_limit(db, tool_id, max_total_bytes)?;
+        self.bytes
+            .insert(tool_id.to_string(), total_bytes(db, tool_id)?);
+        Ok(deleted)
+    }
+}
+
+/// 工具的对话记录超出存储上限时，从最旧的记录开始删除
+pub fn enforce_limit(db: &SqliteManager, tool_id: &str, max_total_bytes: u64) -> Result<usize> {
+    // 从最新的记录向前累计压缩后大小，累计值超出上限的记录全部删除
+    let cutoff: Vec<i64> = db.query_map(
+        "SELECT COALESCE(MAX(id), 0) FROM (
+            SELECT id, SUM(stored_bytes) OVER (ORDER BY id DESC) AS kept
+            FROM session_transcripts WHERE tool_id = ?1
+        ) WHERE kept > CAST(?2 AS INTEGER)",
+        &[tool_id, &max_total_bytes.to_string()],
+        |row| row.get(0),
+    )?;
+
+    match cutoff.first() {
+        Some(&id) if id > 0 => {
+            delete_where(db, "tool_id = ? AND id <= ?", &[tool_id, &id.to_string()])
+        }
+        _ => Ok(0),
+    }
+}
+
+/// 读取会话的全部对话记录（按写入顺序）
+pub fn load_session(db: &SqliteManager, session_id: &str) -> Result<Vec<TranscriptTurn>> {
+    let rows = db.query_map(
+        "SELECT id, session_id, tool_id, role, content, truncated, created_at
+         FROM session_transcripts WHERE session_id = ? ORDER BY id ASC",
+        &[session_id],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Vec<u8>>(4)?,
+                row.get::<_, bool>(5)?,
+                row.get::<_, i64>(6)?,
+            ))
+        },
+    )?;
+
+    rows.into_iter()
+        .filter_map(
+            |(id, session_id, tool_id, role, content, truncated, created_at)| {
+                let role = TranscriptRole::parse(&role)?;
+                Some(decompress(&content).map(|content| TranscriptTurn {
+                    id,
+                    session_id,
+                    tool_id,
+                    role,
+                    content,
+                    truncated,
+                    created_at,
+                }))
+            },
+        )
+        .collect()
+}
+
+/// 全文搜索对话记录（按相关度排序）
+///
+/// 查询按空白拆分为多个词，所有词都需命中；每个词按前缀匹配
+pub fn search(
+    db: &SqliteManager,
+    query: &str,
+    tool_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<TranscriptSearchHit>> {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows = db.query_map(
+        "WITH hits AS (
+            SELECT rowid AS id, rank FROM session_transcripts_fts
+            WHERE session_transcripts_fts MATCH ?1
+        )
+        SELECT t.id, t.session_id, t.tool_id, t.role, t.content, t.created_at,
+               s.display_id, NULLIF(s.note, '')
+        FROM hits
+        JOIN session_transcripts t ON t.id = hits.id
+        LEFT JOIN claude_proxy_sessions s ON s.session_id = t.session_id
+        WHERE ?2 = '' OR t.tool_id = ?2
+        ORDER BY hits.rank, t.id DESC
+        LIMIT ?3",
+        &[
+            &match_expression(&terms),
+            tool_id.unwrap_or(""),
+            &limit.to_string(),
+        ],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Vec<u8>>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, Option<String>>(7)?,
+            ))
+        },
+    )?;
+
+    rows.into_iter()
+        .filter_map(
+            |(turn_id, session_id, tool_id, role, content, created_at, display_id, note)| {
+                let role = TranscriptRole::parse(&role)?;
+                Some(decompress(&content).map(|content| TranscriptSearchHit {
+                    session_id,
+                    display_id,
+                    tool_id,
+                    note,
+                    turn_id,
+                    role,
+                    snippet: snippet(&content, &terms),
+                    created_at,
+                }))
+            },
+        )
+        .collect()
+}
+
+pub fn compress(content: &str) -> Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content.as_bytes())?;
+    encoder.finish().context("压缩对话记录失败")
+}
+
+/// 原文的 SHA-256 摘要（十六进制）
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn decompress(data: &[u8]) -> Result<String> {
+    let mut content = String::new();
+    DeflateDecoder::new(data)
+        .read_to_string(&mut content)
+        .context("解压对话记录失败")?;
+    Ok(content)
+}
+
+/// 是否为需要按单字索引的中日韩文字
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF     // 平假名、片假名
+        | 0x3400..=0x4DBF   // CJK 扩展 A
+        | 0x4E00..=0x9FFF   // CJK 统一汉字
+        | 0xAC00..=0xD7AF   // 韩文音节
+        | 0xF900..=0xFAFF   // CJK 兼容汉字
+        | 0x20000..=0x2FFFF // CJK 扩展 B 及以后
+    )
+}
+
+/// 在中日韩文字两侧插入空格，使其按单字分词
+fn segment(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        if is_cjk(c) {
+            result.push(' ');
+            result.push(c);
+            result.push(' ');
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// 构造 FTS5 查询：每个词作为短语并按前缀匹配，词之间为 AND
+///
+/// 用户输入整体放在引号内，避免被解析为 FTS5 查询语法
+fn match_expression(terms: &[&str]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("\"{}\" *", segment(term).replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 截取首个命中位置附近的内容（忽略大小写，换行替换为空格）
+fn snippet(content: &str, terms: &[&str]) -> String {
+    let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let chars: Vec<char> = content.chars().collect();
+    let folded: Vec<char> = chars.iter().map(|&c| lower(c)).collect();
+
+    let position = terms
+        .iter()
+        .filter_map(|term| {
+            let term: Vec<char> = term.chars().map(lower).collect();
+            folded.windows(term.len()).position(|window| window == term)
+        })
+        .min()
+        .unwrap_or(0);
+
+    let start = position.saturating_sub(SNIPPET_CONTEXT);
+    let end = (position + SNIPPET_CONTEXT * 2).min(chars.len());
+
+    let mut result = String::new();
+    if start > 0 {
+        result.push('…');
+    }
+    result.extend(
+        chars[start..end]
+            .iter()
+            .map(|&c| if c.is_whitespace() { ' ' } else { c }),
+    );
+    if end < chars.len() {
+        result.push('…');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_test_db(temp_dir: &TempDir) -> SqliteManager {
+        let db = SqliteManager::without_cache(&temp_dir.path().join("sessions.db")).unwrap();
+        db.execute_raw(crate::services::session::db_utils::CREATE_TABLE_SQL)
+            .unwrap();
+        db.execute_raw(CREATE_TRANSCRIPT_TABLE_SQL).unwrap();
+        db
+    }
+
+    fn turn(session_id: &str, role: TranscriptRole, content: &str) -> TranscriptTurn {
+        TranscriptTurn {
+            id: 0,
+            session_id: session_id.to_string(),
+            tool_id: "claude-code".to_string(),
+            role,
+            content: content.to_string(),
+            truncated: false,
+            created_at: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_insert_load_and_search() {
+        let temp = TempDir::new().unwrap();
+        let db = create_test_db(&temp);
+
+        insert_turns(
+            &db,
+            &[
+                turn("s1", TranscriptRole::User, "帮我修复数据库迁移的 bug"),
+                turn(
+                    "s1",
+                    TranscriptRole::ToolCall,
+                    r#"Bash {"command":"cargo test migration"}"#,
+                ),
+                turn(
+                    "s2",
+                    TranscriptRole::Assistant,
+                    "Refactored the Parser module.",
+                ),
+            ],
+        )
+        .unwrap();
+
+        let turns = load_session(&db, "s1").unwrap();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].role, TranscriptRole::User);
+        assert_eq!(turns[0].content, "帮我修复数据库迁移的 bug");
+
+        // 中文按短语匹配
+        let hits = search(&db, "迁移", None, 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session_id, "s1");
+        assert!(hits[0].snippet.contains("迁移"));
+        assert!(search(&db, "移迁", None, 10).unwrap().is_empty());
+
+        // 英文忽略大小写、按前缀匹配，多个词需同时命中
+        assert_eq!(search(&db, "pars", None, 10).unwrap()[0].session_id, "s2");
+        assert_eq!(search(&db, "cargo migration", None, 10).unwrap().len(), 1);
+        assert!(search(&db, "cargo parser", None, 10).unwrap().is_empty());
+        assert!(search(&db, "parser", Some("codex"), 10).unwrap().is_empty());
+
+        // 查询语法字符按普通文本处理
+        assert!(search(&db, "\"bug OR", None, 10).is_ok());
+        assert!(search(&db, "   ", None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_contains_turn() {
+        let temp = TempDir::new().unwrap();
+        let db = create_test_db(&temp);
+        let saved = turn("s1", TranscriptRole::User, "hello world");
+        insert_turns(&db, std::slice::from_ref(&saved)).unwrap();
+
+        let same_length = turn("s1", TranscriptRole::User, "hello there");
+        let check = |t: &TranscriptTurn| db.transaction(|tx| Ok(contains_turn(tx, t)?)).unwrap();
+        assert!(check(&saved));
+        assert!(!check(&same_length));
+
+        // 旧记录没有摘要时比较原文
+        db.execute_raw("UPDATE session_transcripts SET content_hash = NULL")
+            .unwrap();
+        assert!(check(&saved));
+        assert!(!check(&same_length));
+    }
+
+    #[test]
+    fn test_delete_and_enforce_limit() {
+        let temp = TempDir::new().unwrap();
+        let db = create_test_db(&temp);
+
+        let turns: Vec<_> = (0..10)
+            .map(|i| turn("s1", TranscriptRole::Assistant, &format!("reply number{i}")))
+            .collect();
+        insert_turns(&db, &turns).unwrap();
+
+        // 上限只够保留最新的几条
+        let deleted = enforce_limit(&db, "claude-code", 60).unwrap();
+        assert!(deleted > 0);
+        let remaining = load_session(&db, "s1").unwrap();
+        assert_eq!(remaining.len(), 10 - deleted);
+        assert_eq!(remaining.last().unwrap().content, "reply number9");
+        assert!(search(&db, "number0", None, 10).unwrap().is_empty());
+        assert_eq!(enforce_limit(&db, "claude-code", 60).unwrap(), 0);
+
+        // 会话已不存在时清理孤立记录
+        assert_eq!(delete_orphans(&db).unwrap(), remaining.len());
+        assert!(search(&db, "reply", None, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_totals_enforce_only_over_limit() {
+        let temp = TempDir::new().unwrap();
+        let db = create_test_db(&temp);
+        let mut totals = TranscriptTotals::default();
+
+        let mut added = 0;
+        for i in 0..10 {
+            let turn = turn("s1", TranscriptRole::User, &format!("message number{i}"));
+            let stored = insert_turns(&db, &[turn]).unwrap();
+            added += stored;
+            totals.add(&db, "claude-code", stored, 60).unwrap();
+        }
+        assert!(total_bytes(&db, "claude-code").unwrap() < added);
+        assert!(total_bytes(&db, "claude-code").unwrap() <= 60);
+        assert_eq!(
+            totals.bytes["claude-code"],
+            total_bytes(&db, "claude-code").unwrap()
+        );
+
+        // 未超出上限时不清理
+        let mut roomy = TranscriptTotals::default();
+        let stored = insert_turns(&db, &[turn("s2", TranscriptRole::User, "hi")]).unwrap();
+        assert_eq!(roomy.add(&db, "claude-code", stored, u64::MAX).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_snippet() {
+        let content = format!("{}Migration fixed{}", "a".repeat(100), "b".repeat(200));
+        let snippet = snippet(&content, &["migration"]);
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("Migration fixed"));
+
+        assert_eq!(super::snippet("line1\nline2", &["x"]), "line1 line2");
+    }
+}
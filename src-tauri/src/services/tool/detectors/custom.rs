@@ -0,0 +1,204 @@
+// Custom Tool Detector
+//
+// 基于自定义工具描述文件（~/.duckcoding/tools/*.json）的检测、安装、配置管理实现
+
+use super::super::detector_trait::ToolDetector;
+use crate::data::DataManager;
+use crate::models::tool_descriptor::{ConfigFormat, ToolDescriptor};
+use crate::models::InstallMethod;
+use crate::utils::CommandExecutor;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// 自定义工具检测器
+pub struct DescriptorDetector {
+    descriptor: ToolDescriptor,
+    check_command: String,
+    config_dir: PathBuf,
+}
+
+impl DescriptorDetector {
+    pub fn new(descriptor: ToolDescriptor) -> Self {
+        Self {
+            check_command: descriptor.check_command(),
+            config_dir: descriptor.config_dir_path(),
+            descriptor,
+        }
+    }
+
+    fn primary_format(&self) -> ConfigFormat {
+        self.descriptor
+            .config_files
+            .first()
+            .map(|f| f.format)
+            .unwrap_or(ConfigFormat::Json)
+    }
+}
+
+#[async_trait]
+impl ToolDetector for DescriptorDetector {
+    // ==================== 基础信息 ====================
+
+    fn tool_id(&self) -> &str {
+        &self.descriptor.id
+    }
+
+    fn tool_name(&self) -> &str {
+        &self.descriptor.name
+    }
+
+    fn config_dir(&self) -> PathBuf {
+        self.config_dir.clone()
+    }
+
+    fn config_file(&self) -> &str {
+        self.descriptor
+            .config_files
+            .first()
+            .map(|f| f.path.as_str())
+            .unwrap_or_default()
+    }
+
+    fn npm_package(&self) -> &str {
+        &self.descriptor.npm_package
+    }
+
+    fn check_command(&self) -> &str {
+        &self.check_command
+    }
+
+    fn use_proxy_for_version_check(&self) -> bool {
+        self.descriptor.use_proxy_for_version_check
+    }
+
+    // ==================== 检测逻辑 ====================
+
+    async fn detect_install_method(&self, _executor: &CommandExecutor) -> Option<InstallMethod> {
+        // 自定义工具仅支持 npm 安装
+        if self.descriptor.npm_package.is_empty() {
+            Some(InstallMethod::Other)
+        } else {
+            Some(InstallMethod::Npm)
+        }
+    }
+
+    // ==================== 安装逻辑 ====================
+
+    async fn install(
+        &self,
+        executor: &CommandExecutor,
+        method: &InstallMethod,
+        _force: bool,
+    ) -> Result<()> {
+        match method {
+            InstallMethod::Npm => self.run_npm(executor, "install", "@latest").await,
+            _ => anyhow::bail!("{} 仅支持 npm 安装", self.descriptor.name),
+        }
+    }
+
+    async fn update(&self, executor: &CommandExecutor, _force: bool) -> Result<()> {
+        self.run_npm(executor, "update", "").await
+    }
+
+    // ==================== 配置管理 ====================
+
+    async fn read_config(&self, manager: &DataManager) -> Result<Value> {
+        let config_path = self.config_dir.join(self.config_file());
+
+        match self.primary_format() {
+            ConfigFormat::Json => Ok(manager.json_uncached().read(&config_path)?),
+            ConfigFormat::Toml => {
+                let value = manager.toml().read(&config_path)?;
+                serde_json::to_value(value).context("转换 TOML 配置失败")
+            }
+            ConfigFormat::Env => {
+                let pairs = manager.env().read(&config_path)?;
+                Ok(serde_json::to_value(pairs)?)
+            }
+        }
+    }
+
+    async fn save_config(&self, manager: &DataManager, config: Value) -> Result<()> {
+        let config_path = self.config_dir.join(self.config_file());
+
+        match self.primary_format() {
+            ConfigFormat::Json => manager.json_uncached().write(&config_path, &config)?,
+            ConfigFormat::Toml => {
+                let content = toml::to_string_pretty(&config).context("序列化 TOML 配置失败")?;
+                let doc = content
+                    .parse::<toml_edit::DocumentMut>()
+                    .context("解析 TOML 配置失败")?;
+                manager.toml().write(&config_path, &doc)?;
+            }
+            ConfigFormat::Env => {
+                let pairs: HashMap<String, String> =
+                    serde_json::from_value(config).context("ENV 配置只支持字符串键值")?;
+                manager.env().write(&config_path, &pairs)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ==================== 私有实现方法 ====================
+
+impl DescriptorDetector {
+    /// 执行 npm install / update
+    async fn run_npm(&self, executor: &CommandExecutor, action: &str, suffix: &str) -> Result<()> {
+        let package = &self.descriptor.npm_package;
+        if package.is_empty() {
+            anyhow::bail!("{} 未配置 npm 包名", self.descriptor.name);
+        }
+        if !executor.command_exists_async("npm").await {
+            anyhow::bail!("npm 未安装");
+        }
+
+        let command =
+            format!("npm {action} -g {package}{suffix} --registry https://registry.npmmirror.com");
+        let result = executor.execute_async(&command).await;
+
+        if result.success {
+            Ok(())
+        } else {
+            anyhow::bail!("❌ npm {action} 失败\n\n{}", result.stderr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_env_config_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let descriptor: ToolDescriptor = serde_json::from_value(serde_json::json!({
+            "id": "qwen-code",
+            "name": "Qwen Code",
+            "command": "qwen",
+            "config_dir": dir.path(),
+            "config_files": [{ "path": ".env", "format": "env" }],
+            "default_port": 8791
+        }))
+        .unwrap();
+        let detector = DescriptorDetector::new(descriptor);
+        assert_eq!(detector.check_command(), "qwen --version");
+        assert_eq!(
+            detector
+                .detect_install_method(&CommandExecutor::new())
+                .await,
+            Some(InstallMethod::Other)
+        );
+
+        let manager = DataManager::new();
+        detector
+            .save_config(&manager, serde_json::json!({ "OPENAI_API_KEY": "sk-test" }))
+            .await
+            .unwrap();
+        let config = detector.read_config(&manager).await.unwrap();
+        assert_eq!(config["OPENAI_API_KEY"], "sk-test");
+    }
+}
@@ -4,13 +4,16 @@
 
 mod claude_code;
 mod codex;
+mod custom;
 mod gemini_cli;
 
 pub use claude_code::ClaudeCodeDetector;
 pub use codex::CodeXDetector;
+pub use custom::DescriptorDetector;
 pub use gemini_cli::GeminiCLIDetector;
 
 use super::detector_trait::ToolDetector;
+use crate::models::ToolDescriptor;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -22,7 +25,7 @@ pub struct DetectorRegistry {
 }
 
 impl DetectorRegistry {
-    /// 创建新的注册表并注册所有内置工具
+    /// 创建新的注册表并注册所有内置工具与自定义工具
     pub fn new() -> Self {
         let mut registry = Self {
             detectors: HashMap::new(),
@@ -33,6 +36,11 @@ impl DetectorRegistry {
         registry.register(Arc::new(CodeXDetector::new()));
         registry.register(Arc::new(GeminiCLIDetector::new()));
 
+        // 注册自定义工具描述
+        for descriptor in ToolDescriptor::load_all() {
+            registry.register(Arc::new(DescriptorDetector::new(descriptor)));
+        }
+
         tracing::debug!(
             "Detector 注册表初始化完成，已注册 {} 个工具",
             registry.detectors.len()
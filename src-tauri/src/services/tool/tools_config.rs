@@ -2,7 +2,7 @@
 //
 // 用于版本控制和多端同步的工具配置文件
 
-use crate::models::{InstallMethod, SSHConfig, ToolInstance, ToolType};
+use crate::models::{InstallMethod, SSHConfig, ToolDescriptor, ToolInstance, ToolType};
 use serde::{Deserialize, Serialize};
 
 /// tools.json 根配置
@@ -79,7 +79,7 @@ pub struct SSHToolInstance {
 
 impl Default for ToolsConfig {
     fn default() -> Self {
-        ToolsConfig {
+        let mut config = ToolsConfig {
             version: "1.0.0".to_string(),
             updated_at: chrono::Utc::now().to_rfc3339(),
             tools: vec![
@@ -105,7 +105,19 @@ impl Default for ToolsConfig {
                     ssh_tools: vec![],
                 },
             ],
-        }
+        };
+
+        // 自定义工具描述
+        config
+            .tools
+            .extend(ToolDescriptor::load_all().into_iter().map(|d| ToolGroup {
+                id: d.id,
+                name: d.name,
+                local_tools: vec![],
+                wsl_tools: vec![],
+                ssh_tools: vec![],
+            }));
+        config
     }
 }
 
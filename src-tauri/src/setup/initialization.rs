@@ -1,4 +1,5 @@
 use duckcoding::core::init_logger;
+use duckcoding::models::Tool;
 use duckcoding::services::profile_manager::ProfileManager;
 use duckcoding::services::proxy_config_manager::ProxyConfigManager;
 use duckcoding::utils::config::read_global_config;
@@ -42,19 +43,17 @@ fn initialize_proxy_profiles() -> Result<(), Box<dyn std::error::Error>> {
     let proxy_mgr = ProxyConfigManager::new()?;
     let profile_mgr = ProfileManager::new()?;
 
-    for tool_id in &["claude-code", "codex", "gemini-cli"] {
+    for tool in Tool::all() {
+        let tool_id = tool.id.as_str();
         if let Ok(Some(config)) = proxy_mgr.get_config(tool_id) {
             // 检查配置完整性并解构
-            if let (true, Some(proxy_key), Some(_real_key), Some(_real_url)) = (
-                config.enabled,
-                &config.local_api_key,
-                &config.real_api_key,
-                &config.real_base_url,
-            ) {
+            if let (true, Some(proxy_key), true) =
+                (config.enabled, &config.local_api_key, config.has_upstream())
+            {
                 let proxy_profile_name = format!("dc_proxy_{}", tool_id.replace("-", "_"));
                 let proxy_endpoint = format!("http://127.0.0.1:{}", config.port);
 
-                let result = match *tool_id {
+                let result = match tool_id {
                     "claude-code" => profile_mgr.save_claude_profile_internal(
                         &proxy_profile_name,
                         proxy_key.clone(),
@@ -72,7 +71,12 @@ fn initialize_proxy_profiles() -> Result<(), Box<dyn std::error::Error>> {
                         proxy_endpoint,
                         None, // 不设置 model，保留用户原有配置
                     ),
-                    _ => continue,
+                    _ => profile_mgr.save_custom_profile_internal(
+                        tool_id,
+                        &proxy_profile_name,
+                        proxy_key.clone(),
+                        proxy_endpoint,
+                    ),
                 };
 
                 if let Err(e) = result {
@@ -152,7 +156,13 @@ pub async fn initialize_app() -> Result<InitializationContext, Box<dyn std::erro
     ));
 
     // 6. 创建代理管理器并异步启动自启动代理
-    let proxy_manager = Arc::new(ProxyManager::new());
+    let gateway_config = ProxyConfigManager::new()
+        .and_then(|mgr| mgr.get_gateway_config())
+        .unwrap_or_else(|e| {
+            tracing::warn!(error = ?e, "读取统一网关配置失败，使用默认配置");
+            Default::default()
+        });
+    let proxy_manager = Arc::new(ProxyManager::with_gateway_config(gateway_config));
     let proxy_manager_for_auto_start = proxy_manager.clone();
     let profile_manager_for_auto_start = profile_manager.clone();
     tauri::async_runtime::spawn(async move {
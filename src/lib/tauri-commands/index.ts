@@ -22,6 +22,9 @@ export * from './session';
 // 余额监控
 export * from './balance';
 
+// 费用估算
+export * from './pricing';
+
 // 更新管理
 export * from './update';
 
@@ -0,0 +1,78 @@
+// 费用估算命令模块
+// 负责模型价格表管理和本地代理用量的费用统计
+
+import { invoke } from '@tauri-apps/api/core';
+import type { CostGroupBy, CostStats, ModelPrice, PricingStore } from './types';
+
+/**
+ * 获取价格表（未自定义时返回内置价格表）
+ */
+export async function getPricingTable(): Promise<PricingStore> {
+  return await invoke<PricingStore>('get_pricing_table');
+}
+
+/**
+ * 保存完整价格表
+ * @param store - 价格表
+ */
+export async function savePricingTable(store: PricingStore): Promise<void> {
+  return await invoke<void>('save_pricing_table', { store });
+}
+
+/**
+ * 设置模型价格
+ * @param model - 模型名称（支持前缀匹配，如 "claude-sonnet-4-5"）
+ * @param price - 价格（货币 / 百万 Token）
+ */
+export async function setModelPrice(model: string, price: ModelPrice): Promise<void> {
+  return await invoke<void>('set_model_price', { model, price });
+}
+
+/**
+ * 删除模型价格
+ * @param model - 模型名称
+ */
+export async function deleteModelPrice(model: string): Promise<void> {
+  return await invoke<void>('delete_model_price', { model });
+}
+
+/**
+ * 设置 Profile 价格倍率（中转站按官方价格比例计费）
+ * @param toolId - 工具 ID
+ * @param profileName - Profile 名称
+ * @param multiplier - 倍率（null 表示恢复为 1.0）
+ */
+export async function setProfilePriceMultiplier(
+  toolId: string,
+  profileName: string,
+  multiplier: number | null,
+): Promise<void> {
+  return await invoke<void>('set_profile_price_multiplier', {
+    toolId,
+    profileName,
+    multiplier,
+  });
+}
+
+/**
+ * 恢复内置默认模型价格（保留 Profile 倍率）
+ */
+export async function resetModelPrices(): Promise<PricingStore> {
+  return await invoke<PricingStore>('reset_model_prices');
+}
+
+/**
+ * 获取费用统计
+ * @param toolId - 工具 ID（null 表示全部工具）
+ * @param startTime - 开始时间（Unix 时间戳，秒）
+ * @param endTime - 结束时间（Unix 时间戳，秒）
+ * @param groupBy - 分组维度
+ */
+export async function getCostStats(
+  toolId: string | null,
+  startTime: number,
+  endTime: number,
+  groupBy: CostGroupBy,
+): Promise<CostStats> {
+  return await invoke<CostStats>('get_cost_stats', { toolId, startTime, endTime, groupBy });
+}
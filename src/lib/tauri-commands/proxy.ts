@@ -2,7 +2,19 @@
 // 负责透明代理的启动、停止、状态查询和配置管理
 
 import { invoke } from '@tauri-apps/api/core';
-import type { AllProxyStatus, ToolProxyConfig, ToolId } from './types';
+import type {
+  AllProxyStatus,
+  CaptureEntry,
+  CaptureSummary,
+  GatewayConfig,
+  GatewayStatus,
+  LocalApiKey,
+  ProxyMetricsSnapshot,
+  ReplayResult,
+  ToolProxyConfig,
+  ToolId,
+  UsageSummary,
+} from './types';
 
 // ==================== 多工具透明代理 API（新架构）====================
 
@@ -30,6 +42,92 @@ export async function getAllProxyStatus(): Promise<AllProxyStatus> {
   return await invoke<AllProxyStatus>('get_all_proxy_status');
 }
 
+/**
+ * 获取运行中透明代理的实时指标
+ * @returns 工具 ID 到指标快照的映射（仅包含运行中的代理）
+ */
+export async function getProxyMetrics(): Promise<Record<string, ProxyMetricsSnapshot>> {
+  return await invoke<Record<string, ProxyMetricsSnapshot>>('get_proxy_metrics');
+}
+
+/**
+ * 导出透明代理 HTTPS 监听使用的本地 CA 证书（PEM）
+ * @param targetPath - 导出路径
+ * @returns 导出后的证书路径，可用于 NODE_EXTRA_CA_CERTS 等信任配置
+ */
+export async function exportProxyCaCertificate(targetPath: string): Promise<string> {
+  return await invoke<string>('export_proxy_ca_certificate', { targetPath });
+}
+
+/**
+ * 签发本地 API Key
+ * @returns 包含明文 Key 的记录
+ */
+export async function issueProxyApiKey(
+  toolId: ToolId,
+  label: string,
+  options: { expiresAt?: number; tokenQuota?: number; allowedProfiles?: string[] } = {},
+): Promise<LocalApiKey> {
+  return await invoke<LocalApiKey>('issue_proxy_api_key', {
+    toolId,
+    label,
+    expiresAt: options.expiresAt ?? null,
+    tokenQuota: options.tokenQuota ?? null,
+    allowedProfiles: options.allowedProfiles ?? null,
+  });
+}
+
+/**
+ * 吊销本地 API Key（运行中的代理立即生效）
+ */
+export async function revokeProxyApiKey(toolId: ToolId, label: string): Promise<void> {
+  return await invoke<void>('revoke_proxy_api_key', { toolId, label });
+}
+
+/**
+ * 按本地 Key 标签汇总用量
+ */
+export async function getProxyApiKeyUsage(toolId: ToolId): Promise<Record<string, UsageSummary>> {
+  return await invoke<Record<string, UsageSummary>>('get_proxy_api_key_usage', { toolId });
+}
+
+// ==================== 统一网关 ====================
+
+/**
+ * 启动统一网关（仅转发到已启动的工具代理）
+ */
+export async function startProxyGateway(): Promise<GatewayStatus> {
+  return await invoke<GatewayStatus>('start_proxy_gateway');
+}
+
+/**
+ * 停止统一网关（各工具代理继续运行）
+ */
+export async function stopProxyGateway(): Promise<void> {
+  return await invoke<void>('stop_proxy_gateway');
+}
+
+/**
+ * 获取统一网关状态
+ */
+export async function getProxyGatewayStatus(): Promise<GatewayStatus> {
+  return await invoke<GatewayStatus>('get_proxy_gateway_status');
+}
+
+/**
+ * 获取统一网关配置
+ */
+export async function getProxyGatewayConfig(): Promise<GatewayConfig> {
+  return await invoke<GatewayConfig>('get_proxy_gateway_config');
+}
+
+/**
+ * 更新统一网关配置（运行中立即生效）
+ */
+export async function updateProxyGatewayConfig(config: GatewayConfig): Promise<void> {
+  return await invoke<void>('update_proxy_gateway_config', { config });
+}
+
 /**
  * 获取指定工具的代理配置
  */
@@ -50,3 +148,38 @@ export async function updateProxyConfig(toolId: ToolId, config: ToolProxyConfig)
 export async function getAllProxyConfigs(): Promise<Record<string, ToolProxyConfig>> {
   return await invoke<Record<string, ToolProxyConfig>>('get_all_proxy_configs');
 }
+
+// ==================== 抓包与重放 ====================
+
+/**
+ * 列出抓包记录摘要（最新的在前）
+ * @param toolId - 工具 ID
+ * @param limit - 最多返回条数（默认 200）
+ */
+export async function listProxyCaptures(toolId: ToolId, limit?: number): Promise<CaptureSummary[]> {
+  return await invoke<CaptureSummary[]>('list_proxy_captures', { toolId, limit });
+}
+
+/**
+ * 获取单条抓包记录详情
+ */
+export async function getProxyCapture(
+  toolId: ToolId,
+  captureId: string,
+): Promise<CaptureEntry | null> {
+  return await invoke<CaptureEntry | null>('get_proxy_capture', { toolId, captureId });
+}
+
+/**
+ * 清空指定工具的抓包记录
+ */
+export async function clearProxyCaptures(toolId: ToolId): Promise<void> {
+  return await invoke<void>('clear_proxy_captures', { toolId });
+}
+
+/**
+ * 使用当前上游重放抓包记录
+ */
+export async function replayProxyCapture(toolId: ToolId, captureId: string): Promise<ReplayResult> {
+  return await invoke<ReplayResult>('replay_proxy_capture', { toolId, captureId });
+}
@@ -2,7 +2,17 @@
 // 负责透明代理会话的 CRUD 和配置管理
 
 import { invoke } from '@tauri-apps/api/core';
-import type { SessionListResponse } from './types';
+import type {
+  SessionExportFormat,
+  SessionImportSummary,
+  SessionListResponse,
+  SessionRetentionConfig,
+  SessionRetentionReport,
+  SessionUsageResponse,
+  TranscriptSearchHit,
+  TranscriptTurn,
+  UsageRecord,
+} from './types';
 
 /**
  * 获取会话列表
@@ -42,23 +52,17 @@ export async function clearAllSessions(toolId: string): Promise<void> {
  * 更新会话配置
  * @param sessionId - 会话 ID
  * @param configName - 配置名称 ("global" 或 "custom")
- * @param customProfileName - 自定义配置名称 (global 时为 null)
- * @param url - API Base URL (global 时为空字符串)
- * @param apiKey - API Key (global 时为空字符串)
+ * @param customProfileName - 绑定的 Profile 名称 (global 时为 null)，请求时按名称解析凭证
  */
 export async function updateSessionConfig(
   sessionId: string,
   configName: string,
   customProfileName: string | null,
-  url: string,
-  apiKey: string,
 ): Promise<void> {
   return await invoke<void>('update_session_config', {
     sessionId,
     configName,
     customProfileName,
-    url,
-    apiKey,
   });
 }
 
@@ -73,3 +77,88 @@ export async function updateSessionNote(sessionId: string, note: string | null):
     note,
   });
 }
+
+/**
+ * 获取会话的用量明细与汇总
+ * @param sessionId - 会话 ID
+ */
+export async function getSessionUsage(sessionId: string): Promise<SessionUsageResponse> {
+  return await invoke<SessionUsageResponse>('get_session_usage', { sessionId });
+}
+
+/**
+ * 按工具和时间范围查询用量记录
+ * @param toolId - 工具 ID（null 表示全部工具）
+ * @param startTime - 开始时间（Unix 时间戳，秒）
+ * @param endTime - 结束时间（Unix 时间戳，秒）
+ */
+export async function getUsageRecords(
+  toolId: string | null,
+  startTime: number,
+  endTime: number,
+): Promise<UsageRecord[]> {
+  return await invoke<UsageRecord[]>('get_usage_records', { toolId, startTime, endTime });
+}
+
+/**
+ * 获取会话的对话记录（需在代理配置中开启对话记录）
+ * @param sessionId - 会话 ID
+ */
+export async function getSessionTranscript(sessionId: string): Promise<TranscriptTurn[]> {
+  return await invoke<TranscriptTurn[]>('get_session_transcript', { sessionId });
+}
+
+/**
+ * 全文搜索对话记录（按相关度排序，多个词需同时命中）
+ * @param query - 搜索内容
+ * @param toolId - 工具 ID（null 表示全部工具）
+ * @param limit - 最多返回条数（默认 50）
+ */
+export async function searchSessionTranscripts(
+  query: string,
+  toolId: string | null,
+  limit?: number,
+): Promise<TranscriptSearchHit[]> {
+  return await invoke<TranscriptSearchHit[]>('search_session_transcripts', {
+    query,
+    toolId,
+    limit,
+  });
+}
+
+/**
+ * 导出会话（含用量明细与对话记录），返回导出路径
+ * @param sessionIds - 指定导出的会话，为空时按 toolId 批量导出（均为空时导出全部）
+ */
+export async function exportSessions(
+  targetPath: string,
+  format: SessionExportFormat,
+  sessionIds: string[] | null,
+  toolId: string | null,
+): Promise<string> {
+  return await invoke<string>('export_sessions', { targetPath, format, sessionIds, toolId });
+}
+
+/**
+ * 导入 JSON Lines 会话包，合并到本机会话数据库
+ */
+export async function importSessions(sourcePath: string): Promise<SessionImportSummary> {
+  return await invoke<SessionImportSummary>('import_sessions', { sourcePath });
+}
+
+/**
+ * 预览保留策略将清理的会话（不删除）
+ * @param policy - 待预览的策略（null 表示使用已保存的策略）
+ */
+export async function previewSessionRetention(
+  policy: SessionRetentionConfig | null,
+): Promise<SessionRetentionReport> {
+  return await invoke<SessionRetentionReport>('preview_session_retention', { policy });
+}
+
+/**
+ * 立即按已保存的保留策略清理会话
+ */
+export async function applySessionRetention(): Promise<SessionRetentionReport> {
+  return await invoke<SessionRetentionReport>('apply_session_retention');
+}
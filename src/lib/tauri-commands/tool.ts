@@ -10,6 +10,7 @@ import type {
   ToolCandidate,
   InstallerCandidate,
   SSHConfig,
+  ToolDescriptor,
 } from './types';
 import type { ToolInstance } from '@/types/tool-management';
 
@@ -214,6 +215,14 @@ export async function addManualToolInstance(
   });
 }
 
+/**
+ * 列出自定义工具描述（~/.duckcoding/tools/*.json）
+ * @returns 有效的工具描述列表（新增描述后需重启应用才能检测与安装）
+ */
+export async function listCustomTools(): Promise<ToolDescriptor[]> {
+  return await invoke<ToolDescriptor[]>('list_custom_tools');
+}
+
 /**
  * 检测单个工具但不保存（仅用于预览）
  * @param toolId - 工具ID
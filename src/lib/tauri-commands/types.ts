@@ -64,6 +64,8 @@ export interface GlobalConfig {
   hide_session_config_hint?: boolean;
   // 日志系统配置
   log_config?: LogConfig;
+  // 会话保留策略
+  session_retention?: SessionRetentionConfig;
   // 配置监听
   external_watch_enabled?: boolean;
   external_poll_interval_ms?: number;
@@ -71,6 +73,14 @@ export interface GlobalConfig {
   single_instance_enabled?: boolean;
 }
 
+// 会话保留策略（后台每小时按该策略清理）
+export interface SessionRetentionConfig {
+  max_age_days: number; // 按最后活跃时间计算，0 表示不限
+  max_sessions_per_tool: number; // 保留最近活跃的会话，0 表示不限
+  keep_annotated: boolean; // 保留有备注或自定义端点配置的会话
+  usage_max_age_days: number; // 用量明细保留天数，0 表示不限（本地 Key 的用量不清理）
+}
+
 export type LogLevel = 'trace' | 'debug' | 'info' | 'warn' | 'error';
 export type LogFormat = 'json' | 'text';
 export type LogOutput = 'console' | 'file' | 'both';
@@ -219,18 +229,202 @@ export interface ProxyTestConfig {
   password?: string;
 }
 
+// 连接来源地址过滤（CIDR 或单个 IP；拒绝优先，允许列表非空时本机回环地址仍始终允许）
+export interface IpFilterConfig {
+  allow?: string[];
+  deny?: string[];
+}
+
+// HTTPS 监听配置（使用本地 CA 签发的证书）
+export interface TlsListenerConfig {
+  enabled: boolean;
+  port?: number; // 未设置时为 HTTP 端口 + 1000
+  extra_hosts?: string[]; // 证书额外包含的主机名或 IP（如局域网地址）
+}
+
+// 自定义工具描述（~/.duckcoding/tools/*.json）
+export interface ToolDescriptor {
+  id: string;
+  name: string;
+  command: string;
+  version_args: string;
+  npm_package: string;
+  config_dir: string;
+  config_files: ToolConfigFileDescriptor[];
+  auth:
+    | { style: 'bearer' }
+    | { style: 'x_api_key' }
+    | { style: 'x_goog_api_key' }
+    | { style: 'header'; name: string; prefix: string };
+  url: {
+    base_path?: string; // Base URL 缺少时追加，如 /v1
+    strip_prefix?: string; // 转发前从请求路径去掉的前缀
+  };
+  default_port: number;
+  use_proxy_for_version_check: boolean;
+}
+
+export interface ToolConfigFileDescriptor {
+  path: string; // 相对于 config_dir
+  format: 'json' | 'toml' | 'env';
+  api_key_field?: string;
+  base_url_field?: string;
+}
+
+// 统一网关配置：单一端口按路径前缀（/claude-code、/codex、/gemini-cli）或请求特征分发到各工具代理
+export interface GatewayConfig {
+  port: number; // 默认 8790
+  allow_public: boolean;
+  ip_filter?: IpFilterConfig;
+  tls?: TlsListenerConfig;
+  auto_start: boolean; // 应用启动时自动启动网关
+  drain_timeout_secs?: number;
+}
+
+// 统一网关运行状态
+export interface GatewayStatus {
+  running: boolean;
+  port: number;
+  tls_port?: number | null;
+  tools: string[]; // 当前可转发的工具（对应工具代理已启动）
+}
+
+// 签发给团队成员的本地 API Key
+export interface LocalApiKey {
+  key: string;
+  label: string; // 使用者标签（同一工具内唯一）
+  expires_at?: number; // 过期时间（Unix 秒）
+  token_quota?: number; // Token 配额（累计输入 + 输出 + 缓存）
+  allowed_profiles?: string[]; // 允许使用的 Profile（为空时不限制）
+  revoked: boolean;
+  created_at: number;
+}
+
 // 单个工具的代理配置
 export interface ToolProxyConfig {
   enabled: boolean;
   port: number;
   local_api_key: string | null;
+  local_api_keys?: LocalApiKey[]; // 签发的本地 Key（与 local_api_key 同时有效）
   real_api_key: string | null;
   real_base_url: string | null;
   real_model_provider: string | null; // Codex 专用：备份的 model_provider
   real_profile_name: string | null; // 备份的配置名称
   allow_public: boolean;
+  ip_filter?: IpFilterConfig; // 来源地址过滤（默认不限制）
   session_endpoint_config_enabled: boolean; // 工具级：是否允许会话自定义端点
   auto_start: boolean; // 应用启动时自动运行代理（默认关闭）
+  upstreams?: UpstreamConfig[]; // 上游池（为空时使用 real_* 配置）
+  load_balance?: LoadBalanceStrategy; // 上游选择策略
+  capture?: CaptureConfig; // 请求/响应抓包（默认关闭）
+  retry?: RetryConfig; // 瞬时错误重试
+  rate_limit?: RateLimitConfig; // 本地限流（默认不限制）
+  metrics_endpoint?: boolean; // 在代理端口暴露 Prometheus 指标 /__duckcoding/metrics
+  drain_timeout_secs?: number; // 停止/重启时等待进行中请求完成的最长时间（秒），默认 30
+  tls?: TlsListenerConfig; // HTTPS 监听（默认关闭）
+  upstream_protocol?: UpstreamProtocol; // 上游 API 协议（非 native 时进行协议转换）
+  model_rules?: ModelRule[]; // 模型映射与路由规则（按顺序匹配）
+  transcript?: TranscriptConfig; // 会话对话记录（默认关闭）
+}
+
+// 上游 API 协议：native 为透明转发，其余为上游实际使用的 API（OpenAI Chat / Responses、Anthropic Messages）
+// Codex 使用 Profile 的 wire_api 时会按上游自动桥接 Responses 与 Chat Completions
+export type UpstreamProtocol =
+  | 'native'
+  | 'openai_chat'
+  | 'openai_responses'
+  | 'anthropic_messages';
+
+// 模型映射与路由规则
+export interface ModelRule {
+  pattern: string; // 支持 * 通配符，如 "claude-haiku-*"
+  rewrite_to?: string | null; // 改写后的模型名称
+  profile_name?: string | null; // 路由到的 Profile（优先于 base_url）
+  base_url?: string | null; // 路由到的 Base URL
+  api_key?: string | null; // 路由到 Base URL 时使用的 Key（必填，为空时跳过该路由）
+  enabled: boolean;
+}
+
+// 上游选择策略：故障转移 / 加权轮询
+export type LoadBalanceStrategy = 'failover' | 'weighted_round_robin';
+
+// 上游池中的单个上游（引用 Profile）
+export interface UpstreamConfig {
+  profile_name: string;
+  weight: number; // 加权轮询权重（0 表示仅作为故障转移备用）
+  enabled: boolean;
+}
+
+// 请求/响应抓包配置
+export interface CaptureConfig {
+  enabled: boolean;
+  max_body_bytes: number; // 单个请求体/响应体的记录上限
+  max_file_bytes: number; // 单个抓包文件大小上限（超出后轮转）
+  max_files: number; // 保留的抓包文件数量
+}
+
+// 会话对话记录配置（压缩存储，支持全文搜索）
+export interface TranscriptConfig {
+  enabled: boolean;
+  max_turn_bytes: number; // 单条记录上限（超出部分截断）
+  max_total_bytes: number; // 该工具对话记录的存储上限（压缩后，超出后删除最旧的记录）
+}
+
+// 瞬时错误重试配置（连接重置、502/503/504、429）
+export interface RetryConfig {
+  max_retries: number; // 单个上游的最大重试次数（0 表示不重试）
+  base_delay_ms: number; // 首次重试等待时间，之后指数增长并附加随机抖动
+  max_delay_ms: number; // 单次等待上限（429 的 Retry-After 超出时不重试）
+}
+
+// 限流阈值（未设置的项不限制）
+export interface RateLimits {
+  requests_per_minute?: number | null; // 每分钟请求数
+  tokens_per_minute?: number | null; // 每分钟 Token 数（按上游返回的用量计算）
+  max_concurrent?: number | null; // 最大并发请求数
+}
+
+// 本地限流配置：顶层阈值作用于整个工具，profiles 按 Profile 名称单独限制
+export interface RateLimitConfig extends RateLimits {
+  profiles?: Record<string, RateLimits>;
+  queue_timeout_secs: number; // 排队超时（秒），超时返回 429
+}
+
+// 抓包记录摘要
+export interface CaptureSummary {
+  id: string;
+  tool_id: string;
+  timestamp: number; // 毫秒
+  method: string;
+  path: string;
+  profile_name: string | null;
+  response_status: number | null;
+  error: string | null;
+  duration_ms: number;
+}
+
+// 抓包记录详情（认证信息已脱敏）
+export interface CaptureEntry extends CaptureSummary {
+  query: string | null;
+  target_url: string;
+  request_headers: Record<string, string>;
+  /** 客户端原始请求体（协议转换前） */
+  request_body: string;
+  request_body_truncated: boolean;
+  response_headers: Record<string, string>;
+  response_body: string | null;
+  sse_chunks?: string[];
+  response_truncated: boolean;
+}
+
+// 抓包重放结果
+export interface ReplayResult {
+  status: number;
+  headers: Record<string, string>;
+  body: string;
+  target_url: string;
+  profile_name: string | null;
+  duration_ms: number;
 }
 
 export interface TransparentProxyStatus {
@@ -241,14 +435,57 @@ export interface TransparentProxyStatus {
 // 多工具代理状态映射
 export type AllProxyStatus = Record<string, TransparentProxyStatus>;
 
+// 代理错误类别
+export type ProxyErrorClass =
+  | 'unauthorized'
+  | 'rate_limited'
+  | 'configuration'
+  | 'proxy_loop'
+  | 'upstream_connect'
+  | 'upstream_timeout'
+  | 'upstream_4xx'
+  | 'upstream_5xx'
+  | 'stream_interrupted'
+  | 'internal';
+
+// 直方图桶（累计计数）
+export interface HistogramBucket {
+  le_ms: number | null; // 桶上界（毫秒），null 表示 +Inf
+  count: number;
+}
+
+// 耗时直方图快照
+export interface HistogramSnapshot {
+  buckets: HistogramBucket[];
+  count: number;
+  sum_ms: number;
+  avg_ms: number | null;
+  p50_ms: number | null; // 按桶上界估算
+  p95_ms: number | null; // 按桶上界估算
+}
+
+// 代理实例实时指标（自实例启动起累计）
+export interface ProxyMetricsSnapshot {
+  started_at: number; // Unix 秒
+  requests_total: number;
+  in_flight: number;
+  errors_total: number;
+  errors_by_class: Record<ProxyErrorClass, number>;
+  bytes_in: number; // 客户端请求体字节数
+  bytes_out: number; // 返回给客户端的响应体字节数
+  latency: HistogramSnapshot; // 请求总耗时（到响应体传输结束）
+  ttfb: HistogramSnapshot; // SSE 首字节时间
+}
+
 // 会话记录（后端数据模型）
 export interface SessionRecord {
   session_id: string;
   display_id: string;
   tool_id: string;
   config_name: string;
-  /** 自定义配置名称（config_name 为 "custom" 时记录） */
+  /** 绑定的 Profile 名称（config_name 为 "custom" 时记录，请求时解析凭证） */
   custom_profile_name: string | null;
+  /** 仅未绑定 Profile 的旧版自定义会话使用 */
   url: string;
   api_key: string;
   /** 会话备注 */
@@ -258,6 +495,8 @@ export interface SessionRecord {
   request_count: number;
   created_at: number;
   updated_at: number;
+  /** 发起会话的本地 Key 标签（使用签发的 Key 时记录） */
+  key_label: string | null;
 }
 
 // 会话列表响应
@@ -268,6 +507,126 @@ export interface SessionListResponse {
   page_size: number;
 }
 
+// 单次请求用量记录
+export interface UsageRecord {
+  id: number;
+  session_id: string | null;
+  tool_id: string;
+  profile_name: string | null;
+  model: string | null;
+  input_tokens: number; // 不含缓存命中部分
+  output_tokens: number;
+  cache_read_tokens: number;
+  cache_creation_tokens: number;
+  status_code: number;
+  attempts: number; // 发送到上游的次数（含重试与故障转移）
+  key_label: string | null; // 请求使用的本地 Key 标签
+  origin: string | null; // 导入记录的来源标识（本机记录为 null）
+  created_at: number;
+}
+
+// 用量汇总
+export interface UsageSummary {
+  request_count: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_read_tokens: number;
+  cache_creation_tokens: number;
+  retry_count: number; // 重试总次数
+}
+
+// 会话用量响应
+export interface SessionUsageResponse {
+  records: UsageRecord[];
+  summary: UsageSummary;
+}
+
+// 对话记录类型：用户输入 / 助手回复 / 工具调用
+export type TranscriptRole = 'user' | 'assistant' | 'tool_call';
+
+// 会话对话记录中的一条
+export interface TranscriptTurn {
+  id: number;
+  session_id: string;
+  tool_id: string;
+  role: TranscriptRole;
+  content: string;
+  truncated: boolean; // 是否因超出单条上限被截断
+  created_at: number;
+}
+
+// 对话记录搜索结果
+export interface TranscriptSearchHit {
+  session_id: string;
+  display_id: string | null; // 会话已被清理时为空
+  tool_id: string;
+  note: string | null;
+  turn_id: number;
+  role: TranscriptRole;
+  snippet: string; // 命中位置附近的内容片段
+  created_at: number;
+}
+
+// 会话导出格式（markdown 仅用于阅读，不支持导入）
+export type SessionExportFormat = 'jsonl' | 'markdown';
+
+// 会话清理结果（试运行时为将要清理的内容）
+export interface SessionRetentionReport {
+  dry_run: boolean;
+  session_ids: string[];
+  by_tool: Record<string, number>;
+  transcript_turns: number; // 随会话一并清理的对话记录数
+  usage_records: number; // 超过保留天数的用量明细数
+  vacuumed: boolean;
+}
+
+// 会话导入结果
+export interface SessionImportSummary {
+  sessions_added: number;
+  sessions_merged: number; // 与本机已有会话合并的数量
+  usage_records: number;
+  transcript_turns: number;
+}
+
+// 模型价格（货币 / 百万 Token）
+export interface ModelPrice {
+  input: number;
+  output: number;
+  cache_read: number;
+  cache_write: number;
+}
+
+// 价格表
+export interface PricingStore {
+  version: number;
+  currency: string;
+  models: Record<string, ModelPrice>;
+  profile_multipliers: Record<string, Record<string, number>>; // tool_id -> profile -> 倍率
+}
+
+// 费用统计分组维度
+export type CostGroupBy = 'request' | 'session' | 'profile' | 'model' | 'day';
+
+// 单个分组的费用汇总
+export interface CostSummary {
+  key: string;
+  request_count: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_read_tokens: number;
+  cache_creation_tokens: number;
+  cost: number;
+  unpriced_count: number; // 模型未定价的请求数
+}
+
+// 费用统计结果
+export interface CostStats {
+  currency: string;
+  group_by: CostGroupBy;
+  total: CostSummary;
+  groups: CostSummary[];
+}
+
 // 工具候选结果
 export interface ToolCandidate {
   tool_path: string;
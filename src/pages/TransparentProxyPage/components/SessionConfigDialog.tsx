@@ -15,8 +15,11 @@ import { Label } from '@/components/ui/label';
 import { RadioGroup, RadioGroupItem } from '@/components/ui/radio-group';
 import { useToast } from '@/hooks/use-toast';
 import { useSessionConfigManagement } from '../hooks/useSessionConfigManagement';
+import type { ToolId } from '../types/proxy-history';
 
 interface SessionConfigDialogProps {
+  /** 会话所属工具 ID */
+  toolId: ToolId;
   /** 弹窗开关状态 */
   open: boolean;
   /** 开关状态变更回调 */
@@ -40,6 +43,7 @@ interface SessionConfigDialogProps {
  * - 保存成功后刷新会话列表
  */
 export function SessionConfigDialog({
+  toolId,
   open,
   onOpenChange,
   sessionId,
@@ -53,7 +57,7 @@ export function SessionConfigDialog({
       ? currentCustomProfileName
       : currentConfig,
   );
-  const { profiles, loading, loadProfiles, applyConfig } = useSessionConfigManagement();
+  const { profiles, loading, loadProfiles, applyConfig } = useSessionConfigManagement(toolId);
   const { toast } = useToast();
 
   // 打开弹窗时加载配置列表和重置状态
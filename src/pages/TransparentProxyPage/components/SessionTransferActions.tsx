@@ -0,0 +1,89 @@
+// 会话导出 / 导入按钮组件
+// 导出当前工具的会话（JSON Lines 或 Markdown），导入 JSON Lines 会话包
+
+import { useState } from 'react';
+import { open, save } from '@tauri-apps/plugin-dialog';
+import { Download, Upload } from 'lucide-react';
+import { Button } from '@/components/ui/button';
+import { useToast } from '@/hooks/use-toast';
+import { exportSessions, importSessions } from '@/lib/tauri-commands';
+import type { ToolId } from '../types/proxy-history';
+
+interface SessionTransferActionsProps {
+  /** 工具 ID（导出该工具的全部会话） */
+  toolId: ToolId;
+  /** 导入成功回调 */
+  onImported: () => void;
+}
+
+/**
+ * 会话导出 / 导入按钮组件
+ *
+ * 功能：
+ * - 导出：选择保存路径，扩展名为 .md 时导出 Markdown，否则导出 JSON Lines 会话包
+ * - 导入：选择 JSON Lines 会话包，合并到本机会话数据库（重复记录自动跳过）
+ */
+export function SessionTransferActions({ toolId, onImported }: SessionTransferActionsProps) {
+  const [busy, setBusy] = useState(false);
+  const { toast } = useToast();
+
+  const handleExport = async () => {
+    const target = await save({
+      title: '导出会话',
+      defaultPath: `${toolId}-sessions.jsonl`,
+      filters: [
+        { name: '会话包', extensions: ['jsonl'] },
+        { name: 'Markdown', extensions: ['md'] },
+      ],
+    });
+    if (!target) return;
+
+    setBusy(true);
+    try {
+      const format = target.toLowerCase().endsWith('.md') ? 'markdown' : 'jsonl';
+      const path = await exportSessions(target, format, null, toolId);
+      toast({ title: '导出成功', description: path });
+    } catch (error) {
+      toast({ title: '导出失败', description: String(error), variant: 'destructive' });
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const handleImport = async () => {
+    const source = await open({
+      title: '导入会话',
+      directory: false,
+      multiple: false,
+      filters: [{ name: '会话包', extensions: ['jsonl'] }],
+    });
+    if (!source || typeof source !== 'string') return;
+
+    setBusy(true);
+    try {
+      const summary = await importSessions(source);
+      toast({
+        title: '导入成功',
+        description: `新增 ${summary.sessions_added} 个会话，合并 ${summary.sessions_merged} 个，用量 ${summary.usage_records} 条，对话 ${summary.transcript_turns} 条`,
+      });
+      onImported();
+    } catch (error) {
+      toast({ title: '导入失败', description: String(error), variant: 'destructive' });
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  return (
+    <>
+      <Button variant="outline" size="sm" onClick={handleExport} disabled={busy}>
+        <Download className="h-3 w-3 mr-1" />
+        导出
+      </Button>
+      <Button variant="outline" size="sm" onClick={handleImport} disabled={busy}>
+        <Upload className="h-3 w-3 mr-1" />
+        导入
+      </Button>
+    </>
+  );
+}
@@ -2,17 +2,20 @@
 // 提供配置列表加载和应用配置到会话的功能
 
 import { useState, useCallback } from 'react';
-import { pmListToolProfiles, pmGetProfile, updateSessionConfig } from '@/lib/tauri-commands';
+import { pmListToolProfiles, updateSessionConfig } from '@/lib/tauri-commands';
+import type { ToolId } from '../types/proxy-history';
 
 /**
  * 会话配置管理 Hook
  *
  * 功能：
- * - 加载 Claude Code 配置列表（使用新的 ProfileManager API）
+ * - 加载指定工具的配置列表（使用新的 ProfileManager API）
  * - 应用选中的配置到指定会话
  * - 处理配置切换逻辑（global vs custom）
+ *
+ * @param toolId - 会话所属工具 ID
  */
-export function useSessionConfigManagement() {
+export function useSessionConfigManagement(toolId: ToolId) {
   const [profiles, setProfiles] = useState<string[]>([]);
   const [loading, setLoading] = useState(false);
 
@@ -23,13 +26,13 @@ export function useSessionConfigManagement() {
    */
   const loadProfiles = useCallback(async () => {
     try {
-      const profileList = await pmListToolProfiles('claude-code');
+      const profileList = await pmListToolProfiles(toolId);
       setProfiles(['global', ...profileList]);
     } catch (error) {
       console.error('Failed to load profiles:', error);
       setProfiles(['global']); // 降级到只显示 global
     }
-  }, []);
+  }, [toolId]);
 
   /**
    * 应用配置到会话
@@ -41,19 +44,11 @@ export function useSessionConfigManagement() {
     setLoading(true);
     try {
       if (selectedProfile === 'global') {
-        // 切换到全局配置：config_name="global", custom_profile_name=null, url="", api_key=""
-        await updateSessionConfig(sessionId, 'global', null, '', '');
+        // 切换到全局配置：config_name="global", custom_profile_name=null
+        await updateSessionConfig(sessionId, 'global', null);
       } else {
-        // 切换到自定义配置：读取指定配置文件的详情（不激活）
-        const profileData = await pmGetProfile('claude-code', selectedProfile);
-        // 保存 custom_profile_name 以便显示
-        await updateSessionConfig(
-          sessionId,
-          'custom',
-          selectedProfile,
-          profileData.base_url,
-          profileData.api_key,
-        );
+        // 绑定 Profile：只记录名称，代理在每次请求时解析最新凭证
+        await updateSessionConfig(sessionId, 'custom', selectedProfile);
       }
       return { success: true };
     } catch (error) {
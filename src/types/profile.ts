@@ -30,6 +30,14 @@ export interface GeminiProfilePayload {
   model?: string; // 可选，不填则不修改原生配置
 }
 
+/**
+ * 自定义工具 Profile Payload（工具由 ~/.duckcoding/tools/*.json 描述）
+ */
+export interface CustomProfilePayload {
+  api_key: string;
+  base_url: string;
+}
+
 /**
  * Profile Payload 联合类型（前端传递给后端）
  *
@@ -38,7 +46,8 @@ export interface GeminiProfilePayload {
 export type ProfilePayload =
   | ({ type: 'claude-code' } & ClaudeProfilePayload)
   | ({ type: 'codex' } & CodexProfilePayload)
-  | ({ type: 'gemini-cli' } & GeminiProfilePayload);
+  | ({ type: 'gemini-cli' } & GeminiProfilePayload)
+  | ({ type: 'custom' } & CustomProfilePayload);
 
 /**
  * Profile 完整数据（包含时间戳）